@@ -65,7 +65,7 @@ fn agent_collides_with_edge() {
   };
 
   let vertices =
-    vec![Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
+    [Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
 
   let line = get_line_for_agent_to_edge(
     &agent,
@@ -93,7 +93,7 @@ fn agent_collides_with_left_vertex() {
   };
 
   let vertices =
-    vec![Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
+    [Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
 
   let line = get_line_for_agent_to_edge(
     &agent,
@@ -120,7 +120,7 @@ fn agent_collides_with_right_vertex_with_line() {
     avoidance_responsibility: 1.0,
   };
 
-  let vertices = vec![Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0)];
+  let vertices = [Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0)];
 
   let line = get_line_for_agent_to_edge(
     &agent,
@@ -148,7 +148,7 @@ fn agent_collides_with_right_vertex_handled_by_next_edge() {
   };
 
   let vertices =
-    vec![Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
+    [Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
 
   let line = get_line_for_agent_to_edge(
     &agent,
@@ -185,7 +185,7 @@ fn agent_velocity_projects_to_cutoff_line() {
   };
 
   let vertices =
-    vec![Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
+    [Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
 
   let line = get_line_for_agent_to_edge(
     &agent,
@@ -213,7 +213,7 @@ fn agent_velocity_projects_to_shadows() {
   };
 
   let vertices =
-    vec![Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
+    [Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
 
   let line = get_line_for_agent_to_edge(
     &agent,
@@ -257,7 +257,7 @@ fn agent_velocity_projects_to_covered_shadows_creates_no_lines() {
     avoidance_responsibility: 1.0,
   };
 
-  let vertices = vec![
+  let vertices = [
     Vec2::new(-2.0, 0.5),
     Vec2::new(-1.0, 0.0),
     Vec2::new(1.0, 0.0),
@@ -324,7 +324,7 @@ fn backwards_edges_are_ignored() {
     avoidance_responsibility: 1.0,
   };
 
-  let vertices = vec![Vec2::new(-1.0, -1.0), Vec2::new(-1.0, 1.0)];
+  let vertices = [Vec2::new(-1.0, -1.0), Vec2::new(-1.0, 1.0)];
 
   let line = get_line_for_agent_to_edge(
     &agent,
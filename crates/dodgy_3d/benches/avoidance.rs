@@ -0,0 +1,171 @@
+//! Benchmarks for each stage of computing avoidance velocities.
+//!
+//! Use `cargo bench --features debug -- --save-baseline <name>` to record a
+//! baseline, and `cargo bench --features debug -- --baseline <name>` to compare
+//! against it. Changes smaller than the noise threshold are not reported as
+//! regressions.
+
+use std::{borrow::Cow, time::Duration};
+
+use criterion::{
+  criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion,
+  Throughput,
+};
+use dodgy_3d::{
+  scenarios::{random_soup, Scenario},
+  solve_linear_program, Agent, AvoidanceOptions, ObjectiveWeights, Plane,
+  Simulator, SolverLimits, Tolerances,
+};
+
+const AGENT_COUNTS: [usize; 3] = [100, 1_000, 10_000];
+const TIME_STEP: f32 = 0.1;
+
+struct Setup {
+  scenario: Scenario,
+  simulator: Simulator,
+  neighbours: Vec<Vec<usize>>,
+  planes: Vec<Vec<Plane>>,
+  avoidance_options: AvoidanceOptions,
+}
+
+fn setup(agent_count: usize) -> Setup {
+  let scenario = random_soup(agent_count, /* seed= */ 1);
+  let simulator = scenario.clone().into_simulator();
+  let avoidance_options = AvoidanceOptions {
+    obstacle_margin: 0.0,
+    time_horizon: scenario.time_horizon,
+    obstacle_time_horizon: scenario.time_horizon,
+    tolerances: Tolerances::default(),
+    strict_rvo2_compatibility: false,
+    objective_weights: ObjectiveWeights::default(),
+    solver_limits: SolverLimits::default(),
+    responsibility_override: None,
+  };
+
+  let neighbours = (0..agent_count)
+    .map(|index| simulator.get_agent_neighbours(index))
+    .collect::<Vec<_>>();
+  let planes = neighbours
+    .iter()
+    .enumerate()
+    .map(|(index, neighbours)| {
+      scenario.agents[index].compute_planes_for_neighbours(
+        &borrow_neighbours(&scenario.agents, neighbours),
+        TIME_STEP,
+        &avoidance_options,
+      )
+    })
+    .collect();
+
+  Setup { scenario, simulator, neighbours, planes, avoidance_options }
+}
+
+fn borrow_neighbours<'a>(
+  agents: &'a [Agent],
+  neighbours: &[usize],
+) -> Vec<Cow<'a, Agent>> {
+  neighbours.iter().map(|&index| Cow::Borrowed(&agents[index])).collect()
+}
+
+fn configure_group<M: criterion::measurement::Measurement>(
+  group: &mut criterion::BenchmarkGroup<'_, M>,
+  agent_count: usize,
+) {
+  group.throughput(Throughput::Elements(agent_count as u64));
+  if agent_count >= 10_000 {
+    // Large crowds are slow enough that the default sample size takes too
+    // long.
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(20));
+  }
+}
+
+fn plane_construction(c: &mut Criterion) {
+  let mut group = c.benchmark_group("plane_construction");
+  for agent_count in AGENT_COUNTS {
+    let setup = setup(agent_count);
+    configure_group(&mut group, agent_count);
+    group.bench_function(BenchmarkId::from_parameter(agent_count), |b| {
+      b.iter(|| {
+        for (agent, neighbours) in
+          setup.scenario.agents.iter().zip(&setup.neighbours)
+        {
+          std::hint::black_box(agent.compute_planes_for_neighbours(
+            &borrow_neighbours(&setup.scenario.agents, neighbours),
+            TIME_STEP,
+            &setup.avoidance_options,
+          ));
+        }
+      })
+    });
+  }
+  group.finish();
+}
+
+fn linear_program(c: &mut Criterion) {
+  let mut group = c.benchmark_group("linear_program");
+  for agent_count in AGENT_COUNTS {
+    let setup = setup(agent_count);
+    configure_group(&mut group, agent_count);
+    group.bench_function(BenchmarkId::from_parameter(agent_count), |b| {
+      b.iter(|| {
+        for (index, planes) in setup.planes.iter().enumerate() {
+          let agent = &setup.scenario.agents[index];
+          std::hint::black_box(solve_linear_program(
+            planes,
+            /* rigid_constraint_count= */ 0,
+            setup.scenario.max_speed,
+            setup.scenario.goal_points[index] - agent.position,
+            setup.avoidance_options.tolerances.solver_epsilon,
+          ));
+        }
+      })
+    });
+  }
+  group.finish();
+}
+
+fn neighbour_search(c: &mut Criterion) {
+  let mut group = c.benchmark_group("neighbour_search");
+  for agent_count in AGENT_COUNTS {
+    let setup = setup(agent_count);
+    configure_group(&mut group, agent_count);
+    group.bench_function(BenchmarkId::from_parameter(agent_count), |b| {
+      b.iter(|| {
+        for index in 0..agent_count {
+          std::hint::black_box(setup.simulator.get_agent_neighbours(index));
+        }
+      })
+    });
+  }
+  group.finish();
+}
+
+fn simulator_step(c: &mut Criterion) {
+  let mut group = c.benchmark_group("simulator_step");
+  for agent_count in AGENT_COUNTS {
+    let setup = setup(agent_count);
+    configure_group(&mut group, agent_count);
+    group.bench_function(BenchmarkId::from_parameter(agent_count), |b| {
+      b.iter_batched(
+        || setup.scenario.clone().into_simulator(),
+        |mut simulator| simulator.step(TIME_STEP),
+        BatchSize::LargeInput,
+      )
+    });
+  }
+  group.finish();
+}
+
+fn regression_config() -> Criterion {
+  // Only report changes that are both statistically significant and larger
+  // than typical run-to-run noise.
+  Criterion::default().significance_level(0.01).noise_threshold(0.03)
+}
+
+criterion_group! {
+  name = benches;
+  config = regression_config();
+  targets = plane_construction, linear_program, neighbour_search, simulator_step
+}
+criterion_main!(benches);
@@ -0,0 +1,72 @@
+// Builds planes for arbitrary agents and solves them, checking that no input
+// (however malformed) produces a non-finite velocity.
+#![no_main]
+
+use std::borrow::Cow;
+
+use dodgy_3d::{
+  Agent, AvoidanceOptions, ObjectiveWeights, SolverLimits, Tolerances,
+};
+use glam::Vec3;
+use libfuzzer_sys::fuzz_target;
+
+/// The position, velocity, radius and avoidance responsibility of an agent.
+type AgentInput = ([f32; 3], [f32; 3], f32, f32);
+
+fn to_agent((position, velocity, radius, responsibility): AgentInput) -> Agent {
+  Agent {
+    position: Vec3::from_array(position),
+    velocity: Vec3::from_array(velocity),
+    radius,
+    avoidance_responsibility: responsibility,
+  }
+}
+
+fuzz_target!(|input: (
+  AgentInput,
+  Vec<AgentInput>,
+  [f32; 3],
+  f32,
+  f32,
+  f32,
+  bool,
+)| {
+  let (
+    agent,
+    neighbours,
+    preferred_velocity,
+    max_speed,
+    time_step,
+    time_horizon,
+    strict_rvo2_compatibility,
+  ) = input;
+  // These are documented preconditions rather than malformed data.
+  let valid = time_step > 0.0 && time_horizon > 0.0 && max_speed >= 0.0;
+  if !valid {
+    return;
+  }
+
+  let agent = to_agent(agent);
+  let neighbours = neighbours
+    .into_iter()
+    .map(|neighbour| Cow::Owned(to_agent(neighbour)))
+    .collect::<Vec<_>>();
+  let velocity = agent.compute_avoiding_velocity(
+    &neighbours,
+    &[],
+    Vec3::from_array(preferred_velocity),
+    max_speed,
+    time_step,
+    &AvoidanceOptions {
+      obstacle_margin: 0.0,
+      time_horizon,
+      obstacle_time_horizon: time_horizon,
+      tolerances: Tolerances::default(),
+      strict_rvo2_compatibility,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: SolverLimits::default(),
+      responsibility_override: None,
+    },
+  );
+  assert!(velocity.is_finite(), "{velocity}");
+});
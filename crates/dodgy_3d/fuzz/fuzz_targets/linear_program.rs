@@ -0,0 +1,35 @@
+// Solves arbitrary linear programs, checking that no input (however
+// malformed) produces a non-finite value.
+#![no_main]
+
+use dodgy_3d::{solve_linear_program, Plane, Tolerances};
+use glam::Vec3;
+use libfuzzer_sys::fuzz_target;
+
+/// The point and normal of a plane.
+type PlaneInput = ([f32; 3], [f32; 3]);
+
+fuzz_target!(|input: (Vec<PlaneInput>, usize, f32, [f32; 3])| {
+  let (planes, rigid_plane_count, radius, preferred_value) = input;
+  let valid = radius >= 0.0;
+  if !valid {
+    return;
+  }
+
+  let planes = planes
+    .into_iter()
+    .map(|(point, normal)| Plane {
+      point: Vec3::from_array(point),
+      // Planes must have unit normals, so only the direction is arbitrary.
+      normal: Vec3::from_array(normal).normalize_or(Vec3::X),
+    })
+    .collect::<Vec<_>>();
+  let value = solve_linear_program(
+    &planes,
+    rigid_plane_count.min(planes.len()),
+    radius,
+    Vec3::from_array(preferred_value),
+    Tolerances::default().solver_epsilon,
+  );
+  assert!(value.is_finite(), "{value}");
+});
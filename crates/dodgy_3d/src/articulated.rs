@@ -0,0 +1,97 @@
+use std::borrow::Cow;
+
+use glam::{Quat, Vec3};
+
+use crate::{Agent, AvoidanceOptions, CompoundAgent, CompoundSphere, Obstacle};
+
+/// A chain of linked agents (e.g. a truck with trailers, or a snake-like
+/// creature). The head of the chain avoids collisions for the whole body,
+/// and every other link is dragged along behind the link in front of it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ArticulatedAgent {
+  /// The links of the chain, starting with the head. Other agents should
+  /// avoid each link as a neighbour. Must not be empty.
+  pub links: Vec<Agent>,
+  /// The distance between the center of each link and the link in front of
+  /// it. Must have one less element than `links`.
+  pub link_lengths: Vec<f32>,
+}
+
+impl ArticulatedAgent {
+  /// Creates a chain from its `links` (starting with the head), keeping the
+  /// current distances between consecutive links.
+  pub fn new(links: Vec<Agent>) -> Self {
+    let link_lengths =
+      links.windows(2).map(|pair| pair[0].position.distance(pair[1].position));
+    Self { link_lengths: link_lengths.collect(), links }
+  }
+
+  /// Same as [`Agent::compute_avoiding_velocity`], but computes the velocity
+  /// of the head so that the whole chain avoids collisions. Each link is
+  /// treated as moving with the head's velocity (which is close to the
+  /// links' actual motion over short time horizons, except in tight turns).
+  /// See [`CompoundAgent::compute_avoiding_velocity`] for how obstacles and
+  /// neighbours are avoided.
+  pub fn compute_avoiding_velocity(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec3 {
+    let head = &self.links[0];
+    CompoundAgent {
+      position: head.position,
+      orientation: Quat::IDENTITY,
+      velocity: head.velocity,
+      spheres: self
+        .links
+        .iter()
+        .map(|link| CompoundSphere {
+          offset: link.position - head.position,
+          radius: link.radius,
+        })
+        .collect(),
+      avoidance_responsibility: head.avoidance_responsibility,
+    }
+    .compute_avoiding_velocity(
+      neighbours,
+      obstacles,
+      preferred_velocity,
+      max_speed,
+      time_step,
+      avoidance_options,
+    )
+  }
+
+  /// Moves the head with `head_velocity` for `time_step`, and drags every
+  /// other link behind the link in front of it. Each link's velocity is set
+  /// to how it moved.
+  pub fn step(&mut self, head_velocity: Vec3, time_step: f32) {
+    let head = &mut self.links[0];
+    head.velocity = head_velocity;
+    head.position += head_velocity * time_step;
+
+    for link_index in 1..self.links.len() {
+      let leader_position = self.links[link_index - 1].position;
+      let link = &mut self.links[link_index];
+      // A link on top of its leader has no direction to trail in, so it
+      // stays where it is.
+      let Some(direction) = (link.position - leader_position).try_normalize()
+      else {
+        link.velocity = Vec3::ZERO;
+        continue;
+      };
+      let new_position =
+        leader_position + direction * self.link_lengths[link_index - 1];
+      link.velocity = (new_position - link.position) / time_step;
+      link.position = new_position;
+    }
+  }
+}
+
+#[cfg(test)]
+#[path = "articulated_test.rs"]
+mod test;
@@ -0,0 +1,75 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{
+  Agent, AvoidanceOptions, ObjectiveWeights, SolverLimits, Tolerances,
+};
+
+use super::*;
+
+fn link(position: Vec3) -> Agent {
+  Agent {
+    position,
+    velocity: Vec3::ZERO,
+    radius: 0.5,
+    avoidance_responsibility: 1.0,
+  }
+}
+
+/// A chain along the -X axis, with its head at the origin.
+fn chain() -> ArticulatedAgent {
+  ArticulatedAgent::new(
+    [0.0, -1.0, -2.0]
+      .into_iter()
+      .map(|x| link(Vec3::new(x, 0.0, 0.0)))
+      .collect(),
+  )
+}
+
+#[test]
+fn followers_trail_head() {
+  let mut chain = chain();
+  assert_eq!(chain.link_lengths, [1.0, 1.0]);
+
+  // Drive in a quarter circle.
+  for step in 0..20 {
+    let angle = step as f32 / 20.0 * std::f32::consts::FRAC_PI_2;
+    chain.step(Vec3::new(angle.cos(), 0.0, angle.sin()), 0.1);
+    for pair in chain.links.windows(2) {
+      let length = pair[0].position.distance(pair[1].position);
+      assert!((length - 1.0).abs() < 1e-4, "{length}");
+    }
+  }
+
+  // Every link moved forward along the path.
+  for link in chain.links.iter() {
+    assert!(link.velocity.length() > 0.5, "{}", link.velocity);
+    assert!(link.velocity.length() < 1.0 + 1e-4, "{}", link.velocity);
+  }
+}
+
+#[test]
+fn head_avoids_neighbours_of_tail() {
+  let chain = chain();
+  // The neighbour is only in the tail's way.
+  let neighbour = link(Vec3::new(-2.0, 0.0, 1.5));
+  let velocity = chain.compute_avoiding_velocity(
+    &[Cow::Borrowed(&neighbour)],
+    &[],
+    Vec3::Z,
+    1.0,
+    0.1,
+    &AvoidanceOptions {
+      obstacle_margin: 0.0,
+      time_horizon: 2.0,
+      obstacle_time_horizon: 2.0,
+      tolerances: Tolerances::default(),
+      strict_rvo2_compatibility: false,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: SolverLimits::default(),
+      responsibility_override: None,
+    },
+  );
+  assert!(velocity.distance(Vec3::Z) > 0.1, "{velocity}");
+}
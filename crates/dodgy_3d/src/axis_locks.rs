@@ -0,0 +1,27 @@
+use glam::{BVec3, Vec3};
+
+use crate::{linear_programming::Plane, terrain};
+
+/// Creates the (rigid) planes that keep the components of the velocity for
+/// the `locked_axes` at zero. Each locked axis is an equality constraint (a
+/// very thin slab of valid velocities), so the velocity is still solved for
+/// with the other constraints instead of being zeroed afterwards.
+pub(crate) fn get_planes_for_locked_axes(locked_axes: BVec3) -> Vec<Plane> {
+  [Vec3::X, Vec3::Y, Vec3::Z]
+    .into_iter()
+    .zip(<[bool; 3]>::from(locked_axes))
+    .filter(|&(_, locked)| locked)
+    .flat_map(|(axis, _)| terrain::get_planes_for_tangent(axis))
+    .collect()
+}
+
+/// Zeroes the components of `velocity` for the `locked_axes`. The slabs from
+/// [`get_planes_for_locked_axes`] leave a tiny bit of slack, which would
+/// otherwise let agents slowly drift along locked axes.
+pub(crate) fn zero_locked_axes(velocity: Vec3, locked_axes: BVec3) -> Vec3 {
+  Vec3::select(locked_axes, Vec3::ZERO, velocity)
+}
+
+#[cfg(test)]
+#[path = "axis_locks_test.rs"]
+mod test;
@@ -0,0 +1,29 @@
+use glam::{BVec3, Vec3};
+
+use super::*;
+
+#[test]
+fn locks_only_selected_axes() {
+  assert!(get_planes_for_locked_axes(BVec3::FALSE).is_empty());
+
+  let planes = get_planes_for_locked_axes(BVec3::new(true, false, true));
+  assert_eq!(planes.len(), 4);
+  for plane in planes.iter() {
+    assert_eq!(plane.normal.y, 0.0);
+  }
+  // Velocities along the free axis are valid, but not along locked axes.
+  let is_valid = |velocity: Vec3| {
+    planes.iter().all(|plane| (velocity - plane.point).dot(plane.normal) >= 0.0)
+  };
+  assert!(is_valid(Vec3::new(0.0, 3.0, 0.0)));
+  assert!(!is_valid(Vec3::new(0.1, 3.0, 0.0)));
+  assert!(!is_valid(Vec3::new(0.0, 3.0, -0.1)));
+}
+
+#[test]
+fn zeroes_locked_components() {
+  assert_eq!(
+    zero_locked_axes(Vec3::new(1.0, 2.0, 3.0), BVec3::new(false, true, false)),
+    Vec3::new(1.0, 0.0, 3.0)
+  );
+}
@@ -0,0 +1,44 @@
+use glam::{Vec3, Vec3Swizzles};
+
+use crate::Obstacle;
+
+/// A source of obstacles describing the edge of the area agents can move in,
+/// such as the border of a navigation mesh. The boundary is queried for each
+/// agent every step, so only the obstacles near each agent need to be
+/// generated. Any `Fn(Vec3, f32) -> Vec<Obstacle>` taking the `position` and
+/// `distance` is a boundary.
+pub trait Boundary {
+  /// The obstacles of the boundary within `distance` of `position`. Returning
+  /// obstacles further away is allowed, but wasteful.
+  fn get_obstacles_near(&self, position: Vec3, distance: f32) -> Vec<Obstacle>;
+}
+
+impl<F: Fn(Vec3, f32) -> Vec<Obstacle>> Boundary for F {
+  fn get_obstacles_near(&self, position: Vec3, distance: f32) -> Vec<Obstacle> {
+    self(position, distance)
+  }
+}
+
+impl Obstacle {
+  /// Creates an obstacle from a chain of boundary edges in 3D, as produced by
+  /// a navigation mesh. The walkable area must be to the right of the edges
+  /// (as in [`Obstacle::Open`]). The wall spans from `height` below the lowest
+  /// vertex to `height` above the highest vertex, so agents walking along a
+  /// sloped border still collide with it.
+  pub fn from_boundary_edges(vertices: &[Vec3], height: f32) -> Obstacle {
+    let (bottom, top) = vertices
+      .iter()
+      .fold((f32::INFINITY, f32::NEG_INFINITY), |(bottom, top), vertex| {
+        (bottom.min(vertex.y), top.max(vertex.y))
+      });
+    Obstacle::Open {
+      vertices: vertices.iter().map(|vertex| vertex.xz()).collect(),
+      bottom: bottom - height,
+      top: top + height,
+    }
+  }
+}
+
+#[cfg(test)]
+#[path = "boundary_test.rs"]
+mod test;
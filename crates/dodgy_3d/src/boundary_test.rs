@@ -0,0 +1,47 @@
+use glam::{Vec2, Vec3};
+
+use super::*;
+
+#[test]
+fn boundary_edges_become_open_obstacle() {
+  let obstacle = Obstacle::from_boundary_edges(
+    &[
+      Vec3::new(0.0, 1.0, 0.0),
+      Vec3::new(2.0, 3.0, 0.0),
+      Vec3::new(2.0, 2.0, 5.0),
+    ],
+    /* height= */ 0.5,
+  );
+
+  assert_eq!(
+    obstacle,
+    Obstacle::Open {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(2.0, 5.0)
+      ],
+      bottom: 0.5,
+      top: 3.5,
+    }
+  );
+}
+
+#[test]
+fn closures_are_boundaries() {
+  let boundary = |position: Vec3, distance: f32| {
+    vec![Obstacle::from_boundary_edges(
+      &[position, position + Vec3::new(distance, 0.0, 0.0)],
+      /* height= */ 1.0,
+    )]
+  };
+
+  assert_eq!(
+    boundary.get_obstacles_near(Vec3::new(1.0, 2.0, 3.0), 4.0),
+    [Obstacle::Open {
+      vertices: vec![Vec2::new(1.0, 3.0), Vec2::new(5.0, 3.0)],
+      bottom: 1.0,
+      top: 3.0,
+    }]
+  );
+}
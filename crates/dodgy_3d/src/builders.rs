@@ -0,0 +1,370 @@
+use glam::{BVec3, Vec3};
+
+use crate::{
+  Agent, AgentParameters, AvoidanceOptions, LeaderFollowing, ObjectiveWeights,
+  ResponsibilityOverride, SimulatorMargin, SocialForce, SolverLimits,
+  Tolerances, TrafficLane, Yielding,
+};
+
+impl Agent {
+  /// Starts building an agent from the defaults of [`AgentBuilder`].
+  pub fn builder() -> AgentBuilder {
+    AgentBuilder::default()
+  }
+}
+
+impl AgentParameters {
+  /// Starts building agent parameters from the defaults of
+  /// [`AgentParametersBuilder`].
+  pub fn builder() -> AgentParametersBuilder {
+    AgentParametersBuilder::default()
+  }
+}
+
+impl AvoidanceOptions {
+  /// Starts building avoidance options from the defaults of
+  /// [`AvoidanceOptionsBuilder`].
+  pub fn builder() -> AvoidanceOptionsBuilder {
+    AvoidanceOptionsBuilder::default()
+  }
+}
+
+impl Default for AvoidanceOptions {
+  /// The defaults of [`AvoidanceOptionsBuilder`].
+  fn default() -> Self {
+    AvoidanceOptionsBuilder::default().build()
+  }
+}
+
+/// Builds an [`Agent`], validating it in [`AgentBuilder::build`]. By default,
+/// the agent is a person-sized agent standing still at the origin: its radius
+/// is 0.5, and it takes full responsibility for avoidance.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AgentBuilder {
+  agent: Agent,
+}
+
+impl Default for AgentBuilder {
+  fn default() -> Self {
+    Self {
+      agent: Agent {
+        position: Vec3::ZERO,
+        velocity: Vec3::ZERO,
+        radius: 0.5,
+        avoidance_responsibility: 1.0,
+      },
+    }
+  }
+}
+
+impl AgentBuilder {
+  pub fn position(mut self, position: Vec3) -> Self {
+    self.agent.position = position;
+    self
+  }
+
+  pub fn velocity(mut self, velocity: Vec3) -> Self {
+    self.agent.velocity = velocity;
+    self
+  }
+
+  pub fn radius(mut self, radius: f32) -> Self {
+    self.agent.radius = radius;
+    self
+  }
+
+  pub fn avoidance_responsibility(
+    mut self,
+    avoidance_responsibility: f32,
+  ) -> Self {
+    self.agent.avoidance_responsibility = avoidance_responsibility;
+    self
+  }
+
+  /// Builds the agent. Panics if the position or velocity is not finite, the
+  /// radius is not positive, or the avoidance responsibility is negative.
+  pub fn build(self) -> Agent {
+    let agent = self.agent;
+    assert!(
+      agent.position.is_finite(),
+      "position must be finite, was {}",
+      agent.position
+    );
+    assert!(
+      agent.velocity.is_finite(),
+      "velocity must be finite, was {}",
+      agent.velocity
+    );
+    assert_positive("radius", agent.radius);
+    assert_non_negative(
+      "avoidance_responsibility",
+      agent.avoidance_responsibility,
+    );
+    agent
+  }
+}
+
+/// Builds [`AgentParameters`], validating them in
+/// [`AgentParametersBuilder::build`]. By default, the agent heads for the
+/// origin at up to 1 unit per second, keeps its radius from obstacles, avoids
+/// agents 3 seconds ahead and obstacles 1 second ahead, and uses none of the
+/// optional behaviours (traffic lanes, social forces, following, or yielding).
+#[derive(Clone, PartialEq, Debug)]
+pub struct AgentParametersBuilder {
+  parameters: AgentParameters,
+}
+
+impl Default for AgentParametersBuilder {
+  fn default() -> Self {
+    Self {
+      parameters: AgentParameters {
+        goal_point: Vec3::ZERO,
+        max_speed: 1.0,
+        obstacle_margin: SimulatorMargin::AgentRadius,
+        time_horizon: 3.0,
+        obstacle_time_horizon: 1.0,
+        traffic_lane: None,
+        social_force: None,
+        leader_following: None,
+        yielding: None,
+        urgency: 0.0,
+        standing_height: None,
+        locked_axes: BVec3::FALSE,
+      },
+    }
+  }
+}
+
+impl AgentParametersBuilder {
+  pub fn goal_point(mut self, goal_point: Vec3) -> Self {
+    self.parameters.goal_point = goal_point;
+    self
+  }
+
+  pub fn max_speed(mut self, max_speed: f32) -> Self {
+    self.parameters.max_speed = max_speed;
+    self
+  }
+
+  pub fn obstacle_margin(mut self, obstacle_margin: SimulatorMargin) -> Self {
+    self.parameters.obstacle_margin = obstacle_margin;
+    self
+  }
+
+  pub fn time_horizon(mut self, time_horizon: f32) -> Self {
+    self.parameters.time_horizon = time_horizon;
+    self
+  }
+
+  pub fn obstacle_time_horizon(mut self, obstacle_time_horizon: f32) -> Self {
+    self.parameters.obstacle_time_horizon = obstacle_time_horizon;
+    self
+  }
+
+  pub fn traffic_lane(mut self, traffic_lane: TrafficLane) -> Self {
+    self.parameters.traffic_lane = Some(traffic_lane);
+    self
+  }
+
+  pub fn social_force(mut self, social_force: SocialForce) -> Self {
+    self.parameters.social_force = Some(social_force);
+    self
+  }
+
+  pub fn leader_following(mut self, leader_following: LeaderFollowing) -> Self {
+    self.parameters.leader_following = Some(leader_following);
+    self
+  }
+
+  pub fn yielding(mut self, yielding: Yielding) -> Self {
+    self.parameters.yielding = Some(yielding);
+    self
+  }
+
+  pub fn urgency(mut self, urgency: f32) -> Self {
+    self.parameters.urgency = urgency;
+    self
+  }
+
+  /// Makes the agent a ground agent standing `standing_height` tall. See
+  /// [`AgentParameters::standing_height`].
+  pub fn standing_height(mut self, standing_height: f32) -> Self {
+    self.parameters.standing_height = Some(standing_height);
+    self
+  }
+
+  pub fn locked_axes(mut self, locked_axes: BVec3) -> Self {
+    self.parameters.locked_axes = locked_axes;
+    self
+  }
+
+  /// Builds the parameters. Panics if the goal point is not finite, the max
+  /// speed or obstacle margin is negative, an obstacle margin curve is empty, a
+  /// time horizon, the social force range, or the standing height is not
+  /// positive, the yielding standing distance or follow distance is negative,
+  /// or the urgency is outside 0 to 1.
+  pub fn build(self) -> AgentParameters {
+    let parameters = self.parameters;
+    assert!(
+      parameters.goal_point.is_finite(),
+      "goal_point must be finite, was {}",
+      parameters.goal_point
+    );
+    assert_non_negative("max_speed", parameters.max_speed);
+    match &parameters.obstacle_margin {
+      SimulatorMargin::AgentRadius => {}
+      SimulatorMargin::Distance(margin) => {
+        assert_non_negative("obstacle_margin", *margin);
+      }
+      SimulatorMargin::SpeedCurve(curve)
+      | SimulatorMargin::ObstacleDistanceCurve(curve) => assert!(
+        !curve.points.is_empty(),
+        "obstacle_margin curve must not be empty"
+      ),
+    }
+    assert_positive("time_horizon", parameters.time_horizon);
+    assert_positive("obstacle_time_horizon", parameters.obstacle_time_horizon);
+    if let Some(social_force) = &parameters.social_force {
+      assert_positive("social_force.range", social_force.range);
+    }
+    if let Some(leader_following) = &parameters.leader_following {
+      assert_non_negative(
+        "leader_following.options.distance",
+        leader_following.options.distance,
+      );
+    }
+    if let Some(yielding) = &parameters.yielding {
+      assert_non_negative(
+        "yielding.standing_distance",
+        yielding.standing_distance,
+      );
+    }
+    assert!(
+      (0.0..=1.0).contains(&parameters.urgency),
+      "urgency must be between 0 and 1, was {}",
+      parameters.urgency
+    );
+    if let Some(standing_height) = parameters.standing_height {
+      assert_positive("standing_height", standing_height);
+    }
+    parameters
+  }
+}
+
+/// Builds [`AvoidanceOptions`], validating them in
+/// [`AvoidanceOptionsBuilder::build`]. By default, agents keep no margin from
+/// obstacles, avoid agents 3 seconds ahead and obstacles 1 second ahead, and
+/// use the default tolerances, objective weights, and solver limits (without
+/// strict RVO2-3D compatibility or a responsibility override).
+#[derive(Clone, PartialEq, Debug)]
+pub struct AvoidanceOptionsBuilder {
+  options: AvoidanceOptions,
+}
+
+impl Default for AvoidanceOptionsBuilder {
+  fn default() -> Self {
+    Self {
+      options: AvoidanceOptions {
+        obstacle_margin: 0.0,
+        time_horizon: 3.0,
+        obstacle_time_horizon: 1.0,
+        tolerances: Tolerances::default(),
+        strict_rvo2_compatibility: false,
+        objective_weights: ObjectiveWeights::default(),
+        solver_limits: SolverLimits::default(),
+        responsibility_override: None,
+      },
+    }
+  }
+}
+
+impl AvoidanceOptionsBuilder {
+  pub fn obstacle_margin(mut self, obstacle_margin: f32) -> Self {
+    self.options.obstacle_margin = obstacle_margin;
+    self
+  }
+
+  pub fn time_horizon(mut self, time_horizon: f32) -> Self {
+    self.options.time_horizon = time_horizon;
+    self
+  }
+
+  pub fn obstacle_time_horizon(mut self, obstacle_time_horizon: f32) -> Self {
+    self.options.obstacle_time_horizon = obstacle_time_horizon;
+    self
+  }
+
+  pub fn tolerances(mut self, tolerances: Tolerances) -> Self {
+    self.options.tolerances = tolerances;
+    self
+  }
+
+  pub fn strict_rvo2_compatibility(
+    mut self,
+    strict_rvo2_compatibility: bool,
+  ) -> Self {
+    self.options.strict_rvo2_compatibility = strict_rvo2_compatibility;
+    self
+  }
+
+  pub fn objective_weights(
+    mut self,
+    objective_weights: ObjectiveWeights,
+  ) -> Self {
+    self.options.objective_weights = objective_weights;
+    self
+  }
+
+  pub fn solver_limits(mut self, solver_limits: SolverLimits) -> Self {
+    self.options.solver_limits = solver_limits;
+    self
+  }
+
+  pub fn responsibility_override(
+    mut self,
+    responsibility_override: ResponsibilityOverride,
+  ) -> Self {
+    self.options.responsibility_override = Some(responsibility_override);
+    self
+  }
+
+  /// Builds the options. Panics if the obstacle margin, a tolerance, or an
+  /// objective weight is negative, or a time horizon is not positive.
+  pub fn build(self) -> AvoidanceOptions {
+    let options = self.options;
+    assert_non_negative("obstacle_margin", options.obstacle_margin);
+    assert_positive("time_horizon", options.time_horizon);
+    assert_positive("obstacle_time_horizon", options.obstacle_time_horizon);
+    let tolerances = &options.tolerances;
+    assert_non_negative("tangent_epsilon", tolerances.tangent_epsilon);
+    assert_non_negative("touching_epsilon", tolerances.touching_epsilon);
+    assert_non_negative(
+      "normalization_epsilon",
+      tolerances.normalization_epsilon,
+    );
+    assert_non_negative("solver_epsilon", tolerances.solver_epsilon);
+    assert_non_negative(
+      "vertical_motion",
+      options.objective_weights.vertical_motion,
+    );
+    assert_non_negative(
+      "velocity_change",
+      options.objective_weights.velocity_change,
+    );
+    options
+  }
+}
+
+/// Panics if `value` (named `name`) is not positive (including NaN).
+fn assert_positive(name: &str, value: f32) {
+  assert!(value > 0.0, "{} must be positive, was {}", name, value);
+}
+
+/// Panics if `value` (named `name`) is negative (or NaN).
+fn assert_non_negative(name: &str, value: f32) {
+  assert!(value >= 0.0, "{} must not be negative, was {}", name, value);
+}
+
+#[cfg(test)]
+#[path = "builders_test.rs"]
+mod test;
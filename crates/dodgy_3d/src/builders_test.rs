@@ -0,0 +1,163 @@
+use glam::{BVec3, Vec3};
+
+use crate::{
+  Agent, AgentParameters, AvoidanceOptions, FollowOptions, LeaderFollowing,
+  MarginCurve, ObjectiveWeights, SimulatorMargin, SocialForce, SolverLimits,
+  Tolerances, Yielding,
+};
+
+#[test]
+fn builds_with_defaults() {
+  assert_eq!(
+    Agent::builder().build(),
+    Agent {
+      position: Vec3::ZERO,
+      velocity: Vec3::ZERO,
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    }
+  );
+  assert_eq!(
+    AgentParameters::builder().build(),
+    AgentParameters {
+      goal_point: Vec3::ZERO,
+      max_speed: 1.0,
+      obstacle_margin: SimulatorMargin::AgentRadius,
+      time_horizon: 3.0,
+      obstacle_time_horizon: 1.0,
+      traffic_lane: None,
+      social_force: None,
+      leader_following: None,
+      yielding: None,
+      urgency: 0.0,
+      standing_height: None,
+      locked_axes: BVec3::FALSE,
+    }
+  );
+  assert_eq!(
+    AvoidanceOptions::builder().build(),
+    AvoidanceOptions {
+      obstacle_margin: 0.0,
+      time_horizon: 3.0,
+      obstacle_time_horizon: 1.0,
+      tolerances: Tolerances::default(),
+      strict_rvo2_compatibility: false,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: SolverLimits::default(),
+      responsibility_override: None,
+    }
+  );
+  assert_eq!(AvoidanceOptions::default(), AvoidanceOptions::builder().build());
+}
+
+#[test]
+fn builds_with_overrides() {
+  let agent = Agent::builder()
+    .position(Vec3::new(1.0, 2.0, 3.0))
+    .velocity(Vec3::X)
+    .radius(2.0)
+    .avoidance_responsibility(0.5)
+    .build();
+  assert_eq!(agent.position, Vec3::new(1.0, 2.0, 3.0));
+  assert_eq!(agent.velocity, Vec3::X);
+  assert_eq!(agent.radius, 2.0);
+  assert_eq!(agent.avoidance_responsibility, 0.5);
+
+  let parameters = AgentParameters::builder()
+    .goal_point(Vec3::new(10.0, 0.0, 0.0))
+    .max_speed(2.0)
+    .obstacle_margin(SimulatorMargin::Distance(0.1))
+    .time_horizon(5.0)
+    .urgency(1.0)
+    .standing_height(1.8)
+    .locked_axes(BVec3::new(false, true, false))
+    .build();
+  assert_eq!(parameters.goal_point, Vec3::new(10.0, 0.0, 0.0));
+  assert_eq!(parameters.max_speed, 2.0);
+  assert_eq!(parameters.obstacle_margin, SimulatorMargin::Distance(0.1));
+  assert_eq!(parameters.time_horizon, 5.0);
+  assert_eq!(parameters.obstacle_time_horizon, 1.0);
+  assert_eq!(parameters.urgency, 1.0);
+  assert_eq!(parameters.standing_height, Some(1.8));
+  assert_eq!(parameters.locked_axes, BVec3::new(false, true, false));
+
+  let options = AvoidanceOptions::builder()
+    .obstacle_margin(0.5)
+    .time_horizon(1.0)
+    .strict_rvo2_compatibility(true)
+    .tolerances(Tolerances::rvo2())
+    .build();
+  assert_eq!(options.obstacle_margin, 0.5);
+  assert_eq!(options.time_horizon, 1.0);
+  assert!(options.strict_rvo2_compatibility);
+  assert_eq!(options.tolerances, Tolerances::rvo2());
+}
+
+#[test]
+#[should_panic(expected = "radius must be positive, was 0")]
+fn zero_radius_panics() {
+  Agent::builder().radius(0.0).build();
+}
+
+#[test]
+#[should_panic(expected = "time_horizon must be positive, was NaN")]
+fn nan_time_horizon_panics() {
+  AgentParameters::builder().time_horizon(f32::NAN).build();
+}
+
+#[test]
+#[should_panic(expected = "urgency must be between 0 and 1, was 2")]
+fn excessive_urgency_panics() {
+  AgentParameters::builder().urgency(2.0).build();
+}
+
+#[test]
+#[should_panic(expected = "obstacle_time_horizon must be positive, was -1")]
+fn negative_obstacle_time_horizon_panics() {
+  AvoidanceOptions::builder().obstacle_time_horizon(-1.0).build();
+}
+
+#[test]
+#[should_panic(expected = "obstacle_margin curve must not be empty")]
+fn empty_margin_curve_panics() {
+  AgentParameters::builder()
+    .obstacle_margin(SimulatorMargin::SpeedCurve(MarginCurve {
+      points: vec![],
+    }))
+    .build();
+}
+
+#[test]
+#[should_panic(expected = "social_force.range must be positive, was 0")]
+fn zero_social_force_range_panics() {
+  AgentParameters::builder()
+    .social_force(SocialForce { strength: 1.0, range: 0.0 })
+    .build();
+}
+
+#[test]
+#[should_panic(
+  expected = "leader_following.options.distance must not be negative, was -1"
+)]
+fn negative_follow_distance_panics() {
+  AgentParameters::builder()
+    .leader_following(LeaderFollowing {
+      leader_index: 0,
+      options: FollowOptions { distance: -1.0, angle: 0.0 },
+    })
+    .build();
+}
+
+#[test]
+#[should_panic(
+  expected = "yielding.standing_distance must not be negative, was -1"
+)]
+fn negative_yielding_standing_distance_panics() {
+  AgentParameters::builder()
+    .yielding(Yielding {
+      speed: 1.0,
+      look_ahead_time: 1.0,
+      standing_distance: -1.0,
+    })
+    .build();
+}
@@ -0,0 +1,268 @@
+//! Tools for calibrating avoidance against empirical pedestrian data.
+//!
+//! The fundamental diagram of pedestrian flow relates the density of a crowd
+//! to how fast it walks. [`measure_fundamental_diagram`] measures dodgy's
+//! fundamental diagram by running agents along a periodic corridor at several
+//! densities, and [`calibrate_time_horizon`] picks the time horizon that best
+//! matches an empirical curve (e.g. [`weidmann_speed`]).
+
+use glam::{BVec3, Vec2, Vec3};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{Agent, AgentParameters, Obstacle, Simulator, SimulatorMargin};
+
+/// The parameters of the agents walking in the corridor.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PedestrianParameters {
+  pub radius: f32,
+  /// The speed agents walk at when unobstructed (their free-flow speed).
+  pub max_speed: f32,
+  pub time_horizon: f32,
+  pub obstacle_margin: SimulatorMargin,
+}
+
+/// The corridor scenario to measure the fundamental diagram with. Agents walk
+/// along the X axis through a corridor with walls along both sides. The ends
+/// of the corridor wrap around, so the density stays constant.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CorridorOptions {
+  /// The length of the corridor (along the X axis).
+  pub length: f32,
+  /// The width of the corridor (along the Z axis).
+  pub width: f32,
+  /// The densities to measure, in agents per unit area.
+  pub densities: Vec<f32>,
+  pub time_step: f32,
+  /// The number of steps to run before measuring, so the crowd can settle.
+  pub warmup_steps: u32,
+  /// The number of steps to measure speeds over.
+  pub measure_steps: u32,
+  /// The seed for the initial placement of agents. The same seed always
+  /// produces the same initial placement.
+  pub seed: u64,
+}
+
+/// A point on a fundamental diagram.
+#[derive(Clone, PartialEq, Debug)]
+pub struct FundamentalDiagramPoint {
+  /// The density of the crowd, in agents per unit area.
+  pub density: f32,
+  /// The mean speed of agents along the corridor.
+  pub mean_speed: f32,
+  /// The specific flow (agents per unit time per unit width), which is the
+  /// density times the mean speed.
+  pub flow: f32,
+}
+
+/// The result of [`calibrate_time_horizon`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct CalibrationResult {
+  /// The time horizon that best matched the reference speeds.
+  pub time_horizon: f32,
+  /// The root mean squared difference between the measured and reference
+  /// speeds for `time_horizon`.
+  pub speed_error: f32,
+  /// The fundamental diagram measured with `time_horizon`.
+  pub diagram: Vec<FundamentalDiagramPoint>,
+}
+
+/// The empirical speed of pedestrians at `density` (in pedestrians per square
+/// metre), from Weidmann (1993). This is a common reference for calibrating
+/// pedestrian models.
+pub fn weidmann_speed(density: f32) -> f32 {
+  const FREE_FLOW_SPEED: f32 = 1.34;
+  const GAMMA: f32 = 1.913;
+  const MAX_DENSITY: f32 = 5.4;
+
+  if density <= 0.0 {
+    return FREE_FLOW_SPEED;
+  }
+  if density >= MAX_DENSITY {
+    return 0.0;
+  }
+  FREE_FLOW_SPEED * (1.0 - (-GAMMA * (1.0 / density - 1.0 / MAX_DENSITY)).exp())
+}
+
+/// Measures the fundamental diagram of agents with `parameters` in the
+/// corridor described by `options`, with a point for each density.
+pub fn measure_fundamental_diagram(
+  parameters: &PedestrianParameters,
+  options: &CorridorOptions,
+) -> Vec<FundamentalDiagramPoint> {
+  options
+    .densities
+    .iter()
+    .map(|&density| {
+      let mean_speed = measure_mean_speed(parameters, options, density);
+      FundamentalDiagramPoint {
+        density,
+        mean_speed,
+        flow: density * mean_speed,
+      }
+    })
+    .collect()
+}
+
+/// Measures the fundamental diagram for each of the
+/// `candidate_time_horizons`, and picks the time horizon whose speeds best
+/// match `reference_speed` (e.g. [`weidmann_speed`]). The `time_horizon` of
+/// `parameters` is ignored. `candidate_time_horizons` must not be empty.
+pub fn calibrate_time_horizon(
+  parameters: &PedestrianParameters,
+  options: &CorridorOptions,
+  candidate_time_horizons: &[f32],
+  reference_speed: impl Fn(f32) -> f32,
+) -> CalibrationResult {
+  candidate_time_horizons
+    .iter()
+    .map(|&time_horizon| {
+      let diagram = measure_fundamental_diagram(
+        &PedestrianParameters { time_horizon, ..parameters.clone() },
+        options,
+      );
+      let squared_error = diagram
+        .iter()
+        .map(|point| {
+          (point.mean_speed - reference_speed(point.density)).powi(2)
+        })
+        .sum::<f32>();
+      CalibrationResult {
+        time_horizon,
+        speed_error: (squared_error / diagram.len().max(1) as f32).sqrt(),
+        diagram,
+      }
+    })
+    .min_by(|left, right| left.speed_error.total_cmp(&right.speed_error))
+    .expect("candidate_time_horizons must not be empty")
+}
+
+/// Runs the corridor at `density` and measures the mean speed along the
+/// corridor.
+fn measure_mean_speed(
+  parameters: &PedestrianParameters,
+  options: &CorridorOptions,
+  density: f32,
+) -> f32 {
+  let mut simulator = create_corridor(parameters, options, density);
+  let agent_count = simulator.get_agent_count();
+  let mut speed_sum = 0.0;
+  for step in 0..options.warmup_steps + options.measure_steps {
+    step_corridor(&mut simulator, options.length, options.time_step);
+    if step >= options.warmup_steps {
+      speed_sum += (0..agent_count)
+        .map(|agent_index| simulator.get_agent(agent_index).velocity.x)
+        .sum::<f32>();
+    }
+  }
+  speed_sum / (options.measure_steps.max(1) as usize * agent_count) as f32
+}
+
+/// Creates a simulator with the corridor's walls, and agents filling the
+/// corridor at `density`.
+fn create_corridor(
+  parameters: &PedestrianParameters,
+  options: &CorridorOptions,
+  density: f32,
+) -> Simulator {
+  let mut simulator = Simulator::new();
+  // Keep agents on the ground.
+  simulator.set_terrain(Some(Box::new(|_: f32, _: f32| 0.0)));
+  // The walls extend past the ends of the corridor, so agents near the ends
+  // still avoid them. The solid side of each wall faces out of the corridor.
+  let wall_overhang = options.length;
+  for (start_x, end_x, z) in [
+    (options.length + wall_overhang, -wall_overhang, 0.0),
+    (-wall_overhang, options.length + wall_overhang, options.width),
+  ] {
+    simulator.add_obstacle(Obstacle::Open {
+      vertices: vec![Vec2::new(start_x, z), Vec2::new(end_x, z)],
+      bottom: -1.0,
+      top: 1.0,
+    });
+  }
+
+  let agent_count =
+    (density * options.length * options.width).round().max(1.0) as usize;
+  // Spread the agents out in a jittered grid so they start out roughly evenly
+  // spaced.
+  let columns = ((agent_count as f32 * options.length / options.width)
+    .sqrt()
+    .ceil() as usize)
+    .max(1);
+  let rows = agent_count.div_ceil(columns);
+  let spacing =
+    Vec2::new(options.length / columns as f32, options.width / rows as f32);
+  let mut rng = StdRng::seed_from_u64(options.seed);
+  for index in 0..agent_count {
+    let jitter =
+      Vec2::new(rng.random_range(-0.1..0.1), rng.random_range(-0.1..0.1));
+    let cell = Vec2::new((index % columns) as f32, (index / columns) as f32);
+    let position = (cell + Vec2::splat(0.5) + jitter) * spacing;
+    let position = Vec3::new(position.x, 0.0, position.y);
+    simulator.add_agent(
+      Agent {
+        position,
+        velocity: Vec3::ZERO,
+        radius: parameters.radius,
+        avoidance_responsibility: 1.0,
+      },
+      AgentParameters {
+        goal_point: position,
+        max_speed: parameters.max_speed,
+        obstacle_margin: parameters.obstacle_margin.clone(),
+        time_horizon: parameters.time_horizon,
+        obstacle_time_horizon: parameters.time_horizon,
+        traffic_lane: None,
+        social_force: None,
+        leader_following: None,
+        yielding: None,
+        urgency: 0.0,
+        standing_height: None,
+        locked_axes: BVec3::FALSE,
+      },
+    );
+  }
+
+  simulator
+}
+
+/// Steps the periodic corridor of `length`. Agents near either end avoid the
+/// agents near the other end (as if the ends were joined), and agents that
+/// walk off the end wrap around to the start.
+fn step_corridor(simulator: &mut Simulator, length: f32, time_step: f32) {
+  // Keep the goal ahead of each agent, so agents always walk down the
+  // corridor.
+  for agent_index in 0..simulator.get_agent_count() {
+    let position = simulator.get_agent(agent_index).position;
+    simulator.get_agent_parameters_mut(agent_index).goal_point =
+      position + Vec3::new(length, 0.0, 0.0);
+  }
+
+  let ghost_distance = simulator.get_max_neighbour_query_distance();
+  let ghosts = (0..simulator.get_agent_count())
+    .map(|agent_index| simulator.get_agent(agent_index))
+    .filter_map(|agent| {
+      let offset = if agent.position.x < ghost_distance {
+        length
+      } else if agent.position.x > length - ghost_distance {
+        -length
+      } else {
+        return None;
+      };
+      Some(Agent {
+        position: agent.position + Vec3::new(offset, 0.0, 0.0),
+        ..agent.clone()
+      })
+    })
+    .collect::<Vec<_>>();
+  simulator.step_with_ghosts(time_step, &ghosts);
+
+  for agent_index in 0..simulator.get_agent_count() {
+    let agent = simulator.get_agent_mut(agent_index);
+    agent.position.x = agent.position.x.rem_euclid(length);
+  }
+}
+
+#[cfg(test)]
+#[path = "calibration_test.rs"]
+mod test;
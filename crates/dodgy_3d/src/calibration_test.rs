@@ -0,0 +1,122 @@
+use glam::Vec3;
+
+use super::*;
+
+fn parameters() -> PedestrianParameters {
+  PedestrianParameters {
+    radius: 0.2,
+    max_speed: 1.34,
+    time_horizon: 1.0,
+    obstacle_margin: SimulatorMargin::AgentRadius,
+  }
+}
+
+fn options(densities: Vec<f32>) -> CorridorOptions {
+  CorridorOptions {
+    length: 8.0,
+    width: 2.0,
+    densities,
+    time_step: 0.1,
+    warmup_steps: 20,
+    measure_steps: 20,
+    seed: 1,
+  }
+}
+
+#[test]
+fn weidmann_speed_falls_with_density() {
+  assert_eq!(weidmann_speed(0.0), 1.34);
+  assert_eq!(weidmann_speed(5.4), 0.0);
+  assert_eq!(weidmann_speed(10.0), 0.0);
+  assert!((weidmann_speed(1.0) - 1.0581).abs() < 1e-3);
+  assert!(weidmann_speed(1.0) > weidmann_speed(2.0));
+  assert!(weidmann_speed(2.0) > weidmann_speed(4.0));
+}
+
+#[test]
+fn sparse_crowd_walks_at_free_flow_speed() {
+  let diagram = measure_fundamental_diagram(&parameters(), &options(vec![0.1]));
+  assert_eq!(diagram.len(), 1);
+  assert_eq!(diagram[0].density, 0.1);
+  assert!((diagram[0].mean_speed - 1.34).abs() < 0.05, "{:?}", diagram);
+  assert!((diagram[0].flow - diagram[0].mean_speed * 0.1).abs() < 1e-5);
+}
+
+#[test]
+fn dense_crowd_walks_slower() {
+  let diagram =
+    measure_fundamental_diagram(&parameters(), &options(vec![0.5, 4.0]));
+  assert_eq!(diagram.len(), 2);
+  assert!(diagram[0].mean_speed > diagram[1].mean_speed, "{:?}", diagram);
+  assert!(diagram[1].mean_speed <= 1.34 + 1e-4, "{:?}", diagram);
+}
+
+#[test]
+fn measurement_is_deterministic() {
+  let options = options(vec![2.0]);
+  assert_eq!(
+    measure_fundamental_diagram(&parameters(), &options),
+    measure_fundamental_diagram(&parameters(), &options)
+  );
+}
+
+#[test]
+fn calibration_picks_best_matching_time_horizon() {
+  let options = options(vec![0.5, 3.0]);
+  let result = calibrate_time_horizon(
+    &parameters(),
+    &options,
+    &[0.5, 2.0],
+    weidmann_speed,
+  );
+  assert!(result.time_horizon == 0.5 || result.time_horizon == 2.0);
+  assert_eq!(result.diagram.len(), 2);
+
+  // The chosen time horizon matches the reference at least as well as any
+  // other candidate.
+  for time_horizon in [0.5, 2.0] {
+    let other = calibrate_time_horizon(
+      &parameters(),
+      &options,
+      &[time_horizon],
+      weidmann_speed,
+    );
+    assert!(result.speed_error <= other.speed_error);
+  }
+
+  // A reference that matches the measured speeds exactly has no error.
+  let exact = calibrate_time_horizon(
+    &parameters(),
+    &options,
+    &[result.time_horizon],
+    |density| {
+      result
+        .diagram
+        .iter()
+        .find(|point| point.density == density)
+        .unwrap()
+        .mean_speed
+    },
+  );
+  assert_eq!(exact.speed_error, 0.0);
+}
+
+#[test]
+fn agents_stay_in_corridor() {
+  // A packed crowd that starts out moving into the walls.
+  let options = CorridorOptions { width: 1.0, ..options(vec![]) };
+  let mut simulator = create_corridor(&parameters(), &options, 4.0);
+  for agent_index in 0..simulator.get_agent_count() {
+    let agent = simulator.get_agent_mut(agent_index);
+    agent.velocity =
+      Vec3::new(0.0, 0.0, if agent.position.z < 0.5 { -1.0 } else { 1.0 });
+  }
+  for _ in 0..30 {
+    step_corridor(&mut simulator, options.length, options.time_step);
+    for agent_index in 0..simulator.get_agent_count() {
+      let position = simulator.get_agent(agent_index).position;
+      assert!((0.0..=options.length).contains(&position.x), "{position}");
+      assert!((0.0..=options.width).contains(&position.z), "{position}");
+    }
+  }
+}
@@ -0,0 +1,143 @@
+use std::{
+  ops::{Index, IndexMut},
+  sync::Arc,
+};
+
+/// The number of elements in each chunk of a [`ChunkedVec`].
+const CHUNK_SIZE: usize = 64;
+
+/// A vector stored as shared, copy-on-write chunks. Cloning only copies the
+/// pointers to the chunks, and modifying an element only copies the chunk it is
+/// in (if that chunk is shared). This makes cloning a simulator every frame
+/// (e.g. for rollback netcode) proportional to the number of changed elements
+/// rather than the number of elements.
+#[derive(Clone, Debug)]
+pub(crate) struct ChunkedVec<T> {
+  /// The chunks of elements. Every chunk except the last is full.
+  chunks: Vec<Arc<Vec<T>>>,
+  len: usize,
+}
+
+impl<T> Default for ChunkedVec<T> {
+  fn default() -> Self {
+    Self { chunks: Vec::new(), len: 0 }
+  }
+}
+
+impl<T: Clone> ChunkedVec<T> {
+  pub(crate) fn new() -> Self {
+    Self::default()
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    self.len
+  }
+
+  pub(crate) fn push(&mut self, value: T) {
+    match self.chunks.last_mut() {
+      Some(chunk) if chunk.len() < CHUNK_SIZE => {
+        Arc::make_mut(chunk).push(value)
+      }
+      _ => {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        chunk.push(value);
+        self.chunks.push(Arc::new(chunk));
+      }
+    }
+    self.len += 1;
+  }
+
+  /// Removes the element at `index`, shifting all later elements down. Panics
+  /// if `index` is out of bounds.
+  pub(crate) fn remove(&mut self, index: usize) -> T {
+    assert!(
+      index < self.len,
+      "index {} out of bounds for length {}",
+      index,
+      self.len
+    );
+    let chunk_index = index / CHUNK_SIZE;
+    let value =
+      Arc::make_mut(&mut self.chunks[chunk_index]).remove(index % CHUNK_SIZE);
+    // Refill each chunk with the first element of the next chunk, so every
+    // chunk except the last stays full.
+    for next_chunk_index in chunk_index + 1..self.chunks.len() {
+      let moved = Arc::make_mut(&mut self.chunks[next_chunk_index]).remove(0);
+      Arc::make_mut(&mut self.chunks[next_chunk_index - 1]).push(moved);
+    }
+    if self.chunks.last().is_some_and(|chunk| chunk.is_empty()) {
+      self.chunks.pop();
+    }
+    self.len -= 1;
+    value
+  }
+
+  /// Keeps only the elements whose entry in `keep` is true, preserving their
+  /// order. This is much faster than removing the elements one at a time.
+  /// Chunks before the first removed element are untouched (so stay shared).
+  /// Panics if `keep` is not the same length as the vector.
+  pub(crate) fn retain(&mut self, keep: &[bool]) {
+    assert_eq!(keep.len(), self.len);
+    let Some(first_removed_index) = keep.iter().position(|&keep| !keep) else {
+      return;
+    };
+    let first_chunk_index = first_removed_index / CHUNK_SIZE;
+    let rest = self
+      .chunks
+      .drain(first_chunk_index..)
+      .flat_map(Arc::unwrap_or_clone)
+      .collect::<Vec<_>>();
+    self.len = first_chunk_index * CHUNK_SIZE;
+    let rest_keep = &keep[self.len..];
+    for (value, &keep) in rest.into_iter().zip(rest_keep) {
+      if keep {
+        self.push(value);
+      }
+    }
+  }
+
+  pub(crate) fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+    self.chunks.iter().flat_map(|chunk| chunk.iter())
+  }
+}
+
+impl<T: PartialEq> PartialEq for ChunkedVec<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.len == other.len
+      && self
+        .chunks
+        .iter()
+        .zip(other.chunks.iter())
+        .all(|(left, right)| Arc::ptr_eq(left, right) || left == right)
+  }
+}
+
+impl<T> Index<usize> for ChunkedVec<T> {
+  type Output = T;
+
+  fn index(&self, index: usize) -> &T {
+    assert!(
+      index < self.len,
+      "index {} out of bounds for length {}",
+      index,
+      self.len
+    );
+    &self.chunks[index / CHUNK_SIZE][index % CHUNK_SIZE]
+  }
+}
+
+impl<T: Clone> IndexMut<usize> for ChunkedVec<T> {
+  fn index_mut(&mut self, index: usize) -> &mut T {
+    assert!(
+      index < self.len,
+      "index {} out of bounds for length {}",
+      index,
+      self.len
+    );
+    &mut Arc::make_mut(&mut self.chunks[index / CHUNK_SIZE])[index % CHUNK_SIZE]
+  }
+}
+
+#[cfg(test)]
+#[path = "chunked_vec_test.rs"]
+mod test;
@@ -0,0 +1,73 @@
+use super::*;
+
+#[test]
+fn behaves_like_a_vec() {
+  let mut chunked = ChunkedVec::new();
+  let mut expected = Vec::new();
+  for value in 0..200 {
+    chunked.push(value);
+    expected.push(value);
+  }
+  assert_eq!(chunked.len(), 200);
+  assert_eq!(chunked.chunks.len(), 4);
+
+  for index in [150, 0, 63, 64, 100] {
+    assert_eq!(chunked.remove(index), expected.remove(index));
+  }
+  chunked[10] = -1;
+  expected[10] = -1;
+
+  assert_eq!(chunked.len(), expected.len());
+  assert_eq!(chunked.iter().copied().collect::<Vec<_>>(), expected);
+  for (index, value) in expected.iter().enumerate() {
+    assert_eq!(chunked[index], *value);
+  }
+}
+
+#[test]
+fn clones_only_copy_changed_chunks() {
+  let mut original = ChunkedVec::new();
+  for value in 0..200 {
+    original.push(value);
+  }
+
+  let mut clone = original.clone();
+  assert_eq!(clone, original);
+  clone[70] = -1;
+  assert_ne!(clone, original);
+  assert_eq!(original[70], 70);
+
+  let shared_chunk_count = original
+    .chunks
+    .iter()
+    .zip(clone.chunks.iter())
+    .filter(|(left, right)| Arc::ptr_eq(left, right))
+    .count();
+  assert_eq!(shared_chunk_count, 3);
+}
+
+#[test]
+fn retain_keeps_elements_in_order() {
+  let mut original = ChunkedVec::new();
+  for value in 0..200 {
+    original.push(value);
+  }
+
+  let mut retained = original.clone();
+  let keep =
+    (0..200).map(|value| value < 70 || value % 3 == 0).collect::<Vec<_>>();
+  retained.retain(&keep);
+  let expected =
+    (0..200).filter(|value| value < &70 || value % 3 == 0).collect::<Vec<_>>();
+  assert_eq!(retained.len(), expected.len());
+  assert_eq!(retained.iter().copied().collect::<Vec<_>>(), expected);
+  // Only chunks from the first removed element onwards are copied.
+  assert!(Arc::ptr_eq(&original.chunks[0], &retained.chunks[0]));
+  assert!(retained.chunks[..retained.chunks.len() - 1]
+    .iter()
+    .all(|chunk| chunk.len() == CHUNK_SIZE));
+
+  retained.retain(&vec![false; retained.len()]);
+  assert_eq!(retained.len(), 0);
+  assert!(retained.chunks.is_empty());
+}
@@ -0,0 +1,129 @@
+use std::borrow::Cow;
+
+use glam::{Quat, Vec3};
+
+use crate::{
+  get_plane_tolerances, obstacles::get_planes_for_agent_to_obstacle, Agent,
+  AvoidanceOptions, Obstacle,
+};
+
+/// One of the spheres making up a [`CompoundAgent`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct CompoundSphere {
+  /// The offset of the sphere's center from the agent's position, in the
+  /// agent's local space.
+  pub offset: Vec3,
+  pub radius: f32,
+}
+
+/// An agent made up of several spheres with fixed offsets, for agents that
+/// are poorly approximated by a single sphere (e.g. long vehicles or large
+/// bosses). All the spheres move together with a single shared velocity.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CompoundAgent {
+  /// The position of the agent. The spheres are offset from this position.
+  pub position: Vec3,
+  /// The orientation of the agent, which rotates the offsets of the spheres.
+  pub orientation: Quat,
+  /// The current velocity of the agent (shared by all spheres).
+  pub velocity: Vec3,
+  /// The spheres making up the agent. Must not be empty.
+  pub spheres: Vec<CompoundSphere>,
+  /// The amount of responsibility the agent has to avoid other agents. See
+  /// [`Agent::avoidance_responsibility`].
+  pub avoidance_responsibility: f32,
+}
+
+impl CompoundAgent {
+  /// Creates an [`Agent`] for each sphere of this agent. Other agents should
+  /// avoid these spheres as neighbours.
+  pub fn get_sphere_agents(&self) -> Vec<Agent> {
+    self
+      .spheres
+      .iter()
+      .map(|sphere| Agent {
+        position: self.position + self.orientation * sphere.offset,
+        velocity: self.velocity,
+        radius: sphere.radius,
+        avoidance_responsibility: self.avoidance_responsibility,
+      })
+      .collect()
+  }
+
+  /// Same as [`Agent::compute_avoiding_velocity`], but for a compound agent.
+  /// Each neighbour is avoided by the sphere closest to it, and every sphere
+  /// avoids the obstacles. Unlike for agents, `obstacle_margin` is the
+  /// distance each sphere must keep from obstacles in addition to its radius.
+  pub fn compute_avoiding_velocity(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec3 {
+    assert!(time_step > 0.0, "time_step must be positive, was {}", time_step);
+    assert!(
+      !self.spheres.is_empty(),
+      "compound agents must have at least one sphere"
+    );
+
+    let sphere_agents = self.get_sphere_agents();
+    let obstacle_planes = sphere_agents
+      .iter()
+      .flat_map(|sphere_agent| {
+        obstacles.iter().flat_map(move |obstacle| {
+          get_planes_for_agent_to_obstacle(
+            sphere_agent,
+            obstacle,
+            sphere_agent.radius + avoidance_options.obstacle_margin,
+            avoidance_options.obstacle_time_horizon,
+          )
+        })
+      })
+      .collect();
+
+    let plane_tolerances = get_plane_tolerances(avoidance_options);
+    let neighbour_planes = neighbours
+      .iter()
+      .map(|neighbour| {
+        let closest_sphere = sphere_agents
+          .iter()
+          .min_by(|left, right| {
+            let gap = |sphere: &Agent| {
+              sphere.position.distance(neighbour.position) - sphere.radius
+            };
+            gap(left).total_cmp(&gap(right))
+          })
+          .unwrap();
+        closest_sphere.get_plane_for_neighbour(
+          neighbour,
+          avoidance_options.time_horizon,
+          time_step,
+          &plane_tolerances,
+          avoidance_options.strict_rvo2_compatibility,
+          avoidance_options
+            .get_responsibility_override(closest_sphere, neighbour),
+        )
+      })
+      .collect::<Vec<_>>();
+
+    // The obstacle planes were already computed per sphere, so they are passed
+    // as rigid planes.
+    sphere_agents[0]
+      .solve_for_planes(
+        &[],
+        obstacle_planes,
+        &neighbour_planes,
+        preferred_velocity,
+        max_speed,
+        avoidance_options,
+      )
+      .0
+  }
+}
+
+#[cfg(test)]
+#[path = "compound_test.rs"]
+mod test;
@@ -0,0 +1,126 @@
+use std::borrow::Cow;
+
+use glam::{Quat, Vec2, Vec3};
+
+use crate::{Agent, AvoidanceOptions, Obstacle};
+
+use super::*;
+
+macro_rules! assert_vec_near {
+  ($left: expr, $right: expr, $eps: expr) => {{
+    let left = $left;
+    let right = $right;
+    let eps = $eps;
+    assert!(
+      left.distance(right) < eps,
+      "left: {}, right: {}, epsilon: {}",
+      left,
+      right,
+      eps
+    );
+  }};
+}
+
+/// A vehicle that is long along its local X axis.
+fn vehicle() -> CompoundAgent {
+  CompoundAgent {
+    position: Vec3::ZERO,
+    orientation: Quat::IDENTITY,
+    velocity: Vec3::new(0.0, 0.0, 1.0),
+    spheres: [-2.0, 0.0, 2.0]
+      .into_iter()
+      .map(|x| CompoundSphere { offset: Vec3::new(x, 0.0, 0.0), radius: 0.5 })
+      .collect(),
+    avoidance_responsibility: 1.0,
+  }
+}
+
+fn avoidance_options() -> AvoidanceOptions {
+  AvoidanceOptions::builder()
+    .time_horizon(2.0)
+    .obstacle_time_horizon(2.0)
+    .build()
+}
+
+#[test]
+fn sphere_agents_follow_orientation() {
+  let vehicle = CompoundAgent {
+    position: Vec3::new(1.0, 0.0, 0.0),
+    orientation: Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+    ..vehicle()
+  };
+  let spheres = vehicle.get_sphere_agents();
+  assert_eq!(spheres.len(), 3);
+  assert_vec_near!(spheres[0].position, Vec3::new(1.0, 0.0, 2.0), 1e-5);
+  assert_vec_near!(spheres[1].position, Vec3::new(1.0, 0.0, 0.0), 1e-5);
+  assert_vec_near!(spheres[2].position, Vec3::new(1.0, 0.0, -2.0), 1e-5);
+  for sphere in spheres {
+    assert_eq!(sphere.velocity, vehicle.velocity);
+    assert_eq!(sphere.radius, 0.5);
+  }
+}
+
+#[test]
+fn avoids_neighbour_in_front_of_any_sphere() {
+  let vehicle = vehicle();
+  // The neighbour is only in the way of the rear sphere.
+  let neighbour = Agent {
+    position: Vec3::new(-2.0, 0.0, 1.5),
+    velocity: Vec3::ZERO,
+    radius: 0.5,
+    avoidance_responsibility: 1.0,
+  };
+  let preferred_velocity = Vec3::new(0.0, 0.0, 1.0);
+
+  // A single sphere at the vehicle's position would drive right past.
+  let single_sphere = Agent {
+    position: vehicle.position,
+    velocity: vehicle.velocity,
+    radius: 0.5,
+    avoidance_responsibility: 1.0,
+  };
+  assert_vec_near!(
+    single_sphere.compute_avoiding_velocity(
+      &[Cow::Borrowed(&neighbour)],
+      &[],
+      preferred_velocity,
+      1.0,
+      0.1,
+      &avoidance_options()
+    ),
+    preferred_velocity,
+    1e-5
+  );
+
+  let velocity = vehicle.compute_avoiding_velocity(
+    &[Cow::Borrowed(&neighbour)],
+    &[],
+    preferred_velocity,
+    1.0,
+    0.1,
+    &avoidance_options(),
+  );
+  assert!(velocity.distance(preferred_velocity) > 0.1, "{velocity}");
+}
+
+#[test]
+fn every_sphere_avoids_obstacles() {
+  let vehicle = vehicle();
+  // A wall that only the front sphere would run into.
+  let wall = Obstacle::Open {
+    vertices: vec![Vec2::new(1.5, 1.0), Vec2::new(2.5, 1.0)],
+    bottom: -1.0,
+    top: 1.0,
+  };
+  let velocity = vehicle.compute_avoiding_velocity(
+    &[],
+    &[Cow::Borrowed(&wall)],
+    Vec3::new(0.0, 0.0, 1.0),
+    1.0,
+    0.1,
+    &avoidance_options(),
+  );
+  // The front sphere is 0.5 from the wall, and can only get there after the
+  // obstacle time horizon.
+  assert!(velocity.z <= 0.25 + 1e-4, "{velocity}");
+}
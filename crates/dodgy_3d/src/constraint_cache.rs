@@ -0,0 +1,188 @@
+use std::{borrow::Cow, collections::HashMap, hash::Hash};
+
+use glam::Vec3;
+
+use crate::{
+  get_plane_tolerances, linear_programming::Plane, Agent, AvoidanceOptions,
+  Tolerances,
+};
+
+/// Caches the planes induced by an agent's neighbours between frames. In a
+/// steady-state crowd, most neighbours move in nearly the same way relative to
+/// the agent every frame, so their planes can be reused instead of recomputed.
+/// See [`Agent::build_constraints_with_cache`]. Each agent needs its own cache.
+#[derive(Clone, Debug)]
+pub struct ConstraintCache<K> {
+  /// How much a neighbour's relative position or relative velocity can change
+  /// before its plane is recomputed.
+  threshold: f32,
+  /// The settings the cached planes were computed with. If these change, all
+  /// planes are recomputed.
+  settings: Option<CacheSettings>,
+  /// The cached plane of each neighbour seen in the last frame.
+  entries: HashMap<K, CachedPlane>,
+  /// The number of planes reused in the last frame.
+  reused_count: usize,
+}
+
+/// The settings that affect every plane.
+#[derive(Clone, PartialEq, Debug)]
+struct CacheSettings {
+  time_horizon: f32,
+  time_step: f32,
+  tolerances: Tolerances,
+  strict_rvo2_compatibility: bool,
+}
+
+/// The plane of a neighbour, along with the state it was computed from.
+#[derive(Clone, Debug)]
+struct CachedPlane {
+  relative_position: Vec3,
+  relative_velocity: Vec3,
+  radius: f32,
+  neighbour_radius: f32,
+  avoidance_responsibility: f32,
+  neighbour_avoidance_responsibility: f32,
+  /// The overridden responsibility (see
+  /// [`AvoidanceOptions::responsibility_override`]), if any.
+  responsibility_override: Option<f32>,
+  /// The plane, relative to the agent's velocity. The plane's point is offset
+  /// from the agent's velocity, so storing it relative to the agent's velocity
+  /// allows reusing it when both agents change velocity together.
+  relative_plane: Plane,
+}
+
+impl<K: Hash + Eq + Clone> ConstraintCache<K> {
+  /// Creates an empty cache. Planes are reused while the relative position
+  /// and relative velocity of a neighbour each change by less than
+  /// `threshold`. Larger thresholds are cheaper but less accurate. A
+  /// threshold of zero only reuses planes for neighbours whose relative state
+  /// is unchanged.
+  pub fn new(threshold: f32) -> Self {
+    Self { threshold, settings: None, entries: HashMap::new(), reused_count: 0 }
+  }
+
+  /// Forgets all cached planes.
+  pub fn clear(&mut self) {
+    self.settings = None;
+    self.entries.clear();
+    self.reused_count = 0;
+  }
+
+  /// The number of neighbours with cached planes.
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  /// Whether no neighbours have cached planes.
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// The number of planes that were reused (rather than recomputed) the last
+  /// time the cache was used.
+  pub fn reused_count(&self) -> usize {
+    self.reused_count
+  }
+
+  /// Computes the planes for `neighbours` (identified by `neighbour_keys`),
+  /// reusing cached planes where possible. Neighbours that are no longer
+  /// present are evicted from the cache.
+  pub(crate) fn get_planes_for_neighbours(
+    &mut self,
+    agent: &Agent,
+    neighbours: &[Cow<'_, Agent>],
+    neighbour_keys: &[K],
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec<Plane> {
+    assert_eq!(
+      neighbours.len(),
+      neighbour_keys.len(),
+      "every neighbour must have a key"
+    );
+    assert!(time_step > 0.0, "time_step must be positive, was {}", time_step);
+
+    let tolerances = get_plane_tolerances(avoidance_options);
+    let settings = CacheSettings {
+      time_horizon: avoidance_options.time_horizon,
+      time_step,
+      tolerances,
+      strict_rvo2_compatibility: avoidance_options.strict_rvo2_compatibility,
+    };
+    let mut old_entries = if self.settings.as_ref() == Some(&settings) {
+      std::mem::take(&mut self.entries)
+    } else {
+      HashMap::new()
+    };
+    self.entries.clear();
+    self.reused_count = 0;
+
+    let planes = neighbours
+      .iter()
+      .zip(neighbour_keys)
+      .map(|(neighbour, key)| {
+        let relative_position = neighbour.position - agent.position;
+        let relative_velocity = agent.velocity - neighbour.velocity;
+
+        let responsibility_override =
+          avoidance_options.get_responsibility_override(agent, neighbour);
+        let cached_plane = old_entries.remove(key).filter(|entry| {
+          entry.relative_position.distance(relative_position) <= self.threshold
+            && entry.relative_velocity.distance(relative_velocity)
+              <= self.threshold
+            && entry.radius == agent.radius
+            && entry.neighbour_radius == neighbour.radius
+            && entry.avoidance_responsibility == agent.avoidance_responsibility
+            && entry.neighbour_avoidance_responsibility
+              == neighbour.avoidance_responsibility
+            && entry.responsibility_override == responsibility_override
+        });
+        let entry = match cached_plane {
+          Some(entry) => {
+            self.reused_count += 1;
+            entry
+          }
+          None => {
+            let plane = agent.get_plane_for_neighbour(
+              neighbour,
+              settings.time_horizon,
+              time_step,
+              &settings.tolerances,
+              settings.strict_rvo2_compatibility,
+              responsibility_override,
+            );
+            CachedPlane {
+              relative_position,
+              relative_velocity,
+              radius: agent.radius,
+              neighbour_radius: neighbour.radius,
+              avoidance_responsibility: agent.avoidance_responsibility,
+              neighbour_avoidance_responsibility: neighbour
+                .avoidance_responsibility,
+              responsibility_override,
+              relative_plane: Plane {
+                point: plane.point - agent.velocity,
+                normal: plane.normal,
+              },
+            }
+          }
+        };
+
+        let plane = Plane {
+          point: entry.relative_plane.point + agent.velocity,
+          normal: entry.relative_plane.normal,
+        };
+        self.entries.insert(key.clone(), entry);
+        plane
+      })
+      .collect();
+
+    self.settings = Some(settings);
+    planes
+  }
+}
+
+#[cfg(test)]
+#[path = "constraint_cache_test.rs"]
+mod test;
@@ -0,0 +1,109 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use super::*;
+
+fn avoidance_options() -> AvoidanceOptions {
+  AvoidanceOptions::builder().time_horizon(2.0).build()
+}
+
+fn agent(position: Vec3, velocity: Vec3) -> Agent {
+  Agent { position, velocity, radius: 1.0, avoidance_responsibility: 1.0 }
+}
+
+#[test]
+fn reuses_planes_for_unchanged_neighbours() {
+  let mut cache = ConstraintCache::new(/* threshold= */ 0.1);
+  let neighbours = [
+    Cow::Owned(agent(Vec3::new(4.0, 0.0, 0.0), Vec3::ZERO)),
+    Cow::Owned(agent(Vec3::new(0.0, 0.0, 4.0), Vec3::ZERO)),
+  ];
+  let keys = ["a", "b"];
+  let preferred_velocity = Vec3::new(1.0, 0.0, 1.0);
+
+  let first_agent = agent(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0));
+  let first = first_agent
+    .build_constraints_with_cache(
+      &neighbours,
+      &keys,
+      &[],
+      0.1,
+      &avoidance_options(),
+      &mut cache,
+    )
+    .solve(preferred_velocity, 2.0);
+  assert_eq!(cache.reused_count(), 0);
+  assert_eq!(cache.len(), 2);
+  assert_eq!(
+    first,
+    first_agent.compute_avoiding_velocity(
+      &neighbours,
+      &[],
+      preferred_velocity,
+      2.0,
+      0.1,
+      &avoidance_options(),
+    )
+  );
+
+  // Everything moved together, so the relative state is unchanged.
+  let offset = Vec3::new(0.05, 0.0, 0.0);
+  let moved_agent = agent(first_agent.position + offset, first_agent.velocity);
+  let moved_neighbours = neighbours
+    .iter()
+    .map(|neighbour| {
+      Cow::Owned(agent(neighbour.position + offset, neighbour.velocity))
+    })
+    .collect::<Vec<_>>();
+  let second = moved_agent
+    .build_constraints_with_cache(
+      &moved_neighbours,
+      &keys,
+      &[],
+      0.1,
+      &avoidance_options(),
+      &mut cache,
+    )
+    .solve(preferred_velocity, 2.0);
+  assert_eq!(cache.reused_count(), 2);
+  assert!((first - second).length() < 1e-5, "{} {}", first, second);
+
+  // Only "a" changed significantly.
+  let changed_neighbours = [
+    Cow::Owned(agent(Vec3::new(3.0, 0.0, 0.0), Vec3::ZERO)),
+    neighbours[1].clone(),
+  ];
+  first_agent.build_constraints_with_cache(
+    &changed_neighbours,
+    &keys,
+    &[],
+    0.1,
+    &avoidance_options(),
+    &mut cache,
+  );
+  assert_eq!(cache.reused_count(), 1);
+
+  // "b" is gone, so it is evicted.
+  first_agent.build_constraints_with_cache(
+    &changed_neighbours[..1],
+    &keys[..1],
+    &[],
+    0.1,
+    &avoidance_options(),
+    &mut cache,
+  );
+  assert_eq!(cache.reused_count(), 1);
+  assert_eq!(cache.len(), 1);
+
+  // Changing the settings recomputes everything.
+  first_agent.build_constraints_with_cache(
+    &changed_neighbours[..1],
+    &keys[..1],
+    &[],
+    0.2,
+    &avoidance_options(),
+    &mut cache,
+  );
+  assert_eq!(cache.reused_count(), 0);
+}
@@ -0,0 +1,89 @@
+use glam::Vec3;
+
+use crate::{
+  linear_programming::{LinearProgramOutcome, Plane, SolverLimits},
+  objective, ObjectiveWeights,
+};
+
+/// The constraints on an agent's velocity induced by its neighbours and
+/// obstacles, created by [`crate::Agent::build_constraints`]. Building the
+/// constraints is the expensive part of computing an avoiding velocity, so a
+/// `ConstraintSet` can be reused to cheaply evaluate several preferred
+/// velocities or max speeds for the same agent in the same frame.
+#[derive(Clone, Debug)]
+pub struct ConstraintSet {
+  /// The planes of the linear program, starting with the rigid planes.
+  pub(crate) planes: Vec<Plane>,
+  /// The number of rigid planes at the start of `planes`.
+  pub(crate) rigid_plane_count: usize,
+  /// The velocity of the agent the constraints were built for.
+  pub(crate) current_velocity: Vec3,
+  pub(crate) objective_weights: ObjectiveWeights,
+  pub(crate) solver_epsilon: f32,
+  pub(crate) solver_limits: SolverLimits,
+}
+
+impl ConstraintSet {
+  /// Computes the avoiding velocity for `preferred_velocity`. This is the same
+  /// as the result of [`crate::Agent::compute_avoiding_velocity`] with the
+  /// same arguments that built these constraints. The resulting vector will be
+  /// at most `max_speed` in length.
+  pub fn solve(&self, preferred_velocity: Vec3, max_speed: f32) -> Vec3 {
+    self.solve_within_limits(preferred_velocity, max_speed).0
+  }
+
+  /// Same as [`Self::solve`], but also returns whether the solver reached its
+  /// limits (see [`crate::SolverLimits`]) and stopped early.
+  pub fn solve_within_limits(
+    &self,
+    preferred_velocity: Vec3,
+    max_speed: f32,
+  ) -> (Vec3, bool) {
+    let (velocity, limit_reached, _) =
+      self.solve_with_outcome(preferred_velocity, max_speed);
+    (velocity, limit_reached)
+  }
+
+  /// Same as [`Self::solve_within_limits`], but also returns how the linear
+  /// program was solved.
+  pub(crate) fn solve_with_outcome(
+    &self,
+    preferred_velocity: Vec3,
+    max_speed: f32,
+  ) -> (Vec3, bool, LinearProgramOutcome) {
+    objective::solve_weighted_linear_program(
+      &self.planes,
+      self.rigid_plane_count,
+      max_speed,
+      preferred_velocity,
+      self.current_velocity,
+      &self.objective_weights,
+      self.solver_epsilon,
+      &self.solver_limits,
+    )
+  }
+
+  /// Returns whether `velocity` satisfies all the constraints (within the
+  /// solver's tolerance).
+  pub fn is_valid(&self, velocity: Vec3) -> bool {
+    self.planes.iter().all(|plane| {
+      plane.signed_distance_to_plane(velocity) >= -self.solver_epsilon
+    })
+  }
+
+  /// The planes of the constraints, starting with the rigid planes (for
+  /// obstacles), followed by the planes for neighbours.
+  pub fn planes(&self) -> &[Plane] {
+    &self.planes
+  }
+
+  /// The number of rigid planes (for obstacles) at the start of
+  /// [`Self::planes`], which are never relaxed.
+  pub fn rigid_plane_count(&self) -> usize {
+    self.rigid_plane_count
+  }
+}
+
+#[cfg(test)]
+#[path = "constraint_set_test.rs"]
+mod test;
@@ -0,0 +1,50 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{Agent, AvoidanceOptions};
+
+fn avoidance_options() -> AvoidanceOptions {
+  AvoidanceOptions::builder().time_horizon(2.0).build()
+}
+
+#[test]
+fn solving_matches_compute_avoiding_velocity() {
+  let agent = Agent {
+    position: Vec3::ZERO,
+    velocity: Vec3::new(1.0, 0.0, 0.0),
+    radius: 1.0,
+    avoidance_responsibility: 1.0,
+  };
+  let neighbours = [Cow::Owned(Agent {
+    position: Vec3::new(4.0, 0.5, 0.0),
+    velocity: Vec3::new(-1.0, 0.0, 0.0),
+    radius: 1.0,
+    avoidance_responsibility: 1.0,
+  })];
+
+  let constraints =
+    agent.build_constraints(&neighbours, &[], 0.1, &avoidance_options());
+  for (preferred_velocity, max_speed) in [
+    (Vec3::new(1.0, 0.0, 0.0), 2.0),
+    (Vec3::new(0.0, 0.0, 1.0), 2.0),
+    (Vec3::new(3.0, 0.0, 0.0), 1.5),
+  ] {
+    let velocity = constraints.solve(preferred_velocity, max_speed);
+    assert_eq!(
+      velocity,
+      agent.compute_avoiding_velocity(
+        &neighbours,
+        &[],
+        preferred_velocity,
+        max_speed,
+        0.1,
+        &avoidance_options(),
+      )
+    );
+    assert!(constraints.is_valid(velocity), "{}", velocity);
+  }
+
+  // Heading straight at the neighbour is not valid.
+  assert!(!constraints.is_valid(Vec3::new(1.0, 0.0, 0.0)));
+}
@@ -0,0 +1,92 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::Agent;
+
+/// Parameters for coordinating flows of agents that cross each other (e.g.
+/// at a 90 degree intersection of corridors). Plain ORCA makes agents at a
+/// crossing dodge in every direction, so the flows jam into clumps. Instead,
+/// agents turn towards the direction the crossing flow is moving. Since both
+/// flows do this, they cross at a shallower angle, so agents meet crossing
+/// agents at lower relative speeds, and the flows interleave instead of
+/// jamming. See [`crate::Simulator::set_crossing_flow`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct CrossingFlow {
+  /// The smallest angle (in radians) between the agent's preferred direction
+  /// and a neighbour's direction for the neighbour to be crossing. Neighbours
+  /// heading in (nearly) the same or the opposite direction are not crossing,
+  /// since ORCA already handles counter flows well.
+  pub min_crossing_angle: f32,
+  /// The number of crossing neighbours needed before the agent turns, so
+  /// agents only coordinate in dense crossings.
+  pub min_crossing_neighbours: usize,
+  /// How strongly the agent turns towards the crossing flow. At 1, the agent
+  /// turns by the full velocity of the crossing flow (across its preferred
+  /// direction), and larger values turn further. Weak biases are drowned out
+  /// by the avoidance itself, so they barely help.
+  pub bias: f32,
+}
+
+impl Default for CrossingFlow {
+  fn default() -> Self {
+    Self {
+      min_crossing_angle: std::f32::consts::FRAC_PI_4,
+      min_crossing_neighbours: 3,
+      bias: 1.5,
+    }
+  }
+}
+
+/// The speed below which neighbours have no direction, and so are never
+/// crossing.
+const MIN_CROSSING_SPEED: f32 = 1e-3;
+
+/// Turns the `preferred_velocity` of `agent` towards the flow of the
+/// `neighbours` crossing ahead of it, keeping its speed. Returns the
+/// `preferred_velocity` unchanged if there are too few crossing neighbours.
+pub(crate) fn bias_for_crossing_flow(
+  agent: &Agent,
+  neighbours: &[Cow<'_, Agent>],
+  preferred_velocity: Vec3,
+  crossing_flow: &CrossingFlow,
+) -> Vec3 {
+  let Some(preferred_direction) = preferred_velocity.try_normalize() else {
+    return preferred_velocity;
+  };
+  // Crossing neighbours are those ahead of the agent whose direction is far
+  // enough from both the preferred direction and its opposite. Neighbours
+  // behind the agent will never be in its way.
+  let max_alignment = crossing_flow.min_crossing_angle.cos();
+  let mut crossing_velocity = Vec3::ZERO;
+  let mut crossing_neighbours = 0;
+  for neighbour in neighbours {
+    if neighbour.velocity.length_squared()
+      < MIN_CROSSING_SPEED * MIN_CROSSING_SPEED
+      || (neighbour.position - agent.position).dot(preferred_direction) <= 0.0
+    {
+      continue;
+    }
+    let alignment =
+      neighbour.velocity.normalize().dot(preferred_direction).abs();
+    if alignment < max_alignment {
+      crossing_velocity += neighbour.velocity;
+      crossing_neighbours += 1;
+    }
+  }
+  if crossing_neighbours == 0
+    || crossing_neighbours < crossing_flow.min_crossing_neighbours
+  {
+    return preferred_velocity;
+  }
+
+  crossing_velocity /= crossing_neighbours as f32;
+  let across = crossing_velocity
+    - preferred_direction * crossing_velocity.dot(preferred_direction);
+  (preferred_velocity + across * crossing_flow.bias).normalize_or_zero()
+    * preferred_velocity.length()
+}
+
+#[cfg(test)]
+#[path = "crossing_test.rs"]
+mod test;
@@ -0,0 +1,74 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{crossing::bias_for_crossing_flow, Agent, CrossingFlow};
+
+fn moving_agent(position: Vec3, velocity: Vec3) -> Cow<'static, Agent> {
+  Cow::Owned(Agent::builder().position(position).velocity(velocity).build())
+}
+
+#[test]
+fn turns_towards_crossing_flow() {
+  let agent = Agent::builder().build();
+  let neighbours = [
+    moving_agent(Vec3::new(2.0, 0.0, -1.0), Vec3::Z),
+    moving_agent(Vec3::new(3.0, 0.0, 0.0), Vec3::Z),
+    moving_agent(Vec3::new(4.0, 0.0, 1.0), Vec3::Z),
+  ];
+
+  let velocity = bias_for_crossing_flow(
+    &agent,
+    &neighbours,
+    Vec3::new(2.0, 0.0, 0.0),
+    &CrossingFlow { bias: 1.0, ..Default::default() },
+  );
+  // The agent turns along with the flow, keeping its speed.
+  assert!((velocity.length() - 2.0).abs() < 1e-5, "{velocity}");
+  assert!(
+    (velocity.normalize() - Vec3::new(2.0, 0.0, 1.0).normalize()).length()
+      < 1e-5,
+    "{velocity}"
+  );
+}
+
+#[test]
+fn ignores_aligned_and_opposing_flows() {
+  let agent = Agent::builder().build();
+  let neighbours = [
+    moving_agent(Vec3::new(2.0, 0.0, 0.0), Vec3::X),
+    moving_agent(Vec3::new(3.0, 0.0, 0.0), -Vec3::X),
+    moving_agent(Vec3::new(4.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.1)),
+  ];
+
+  assert_eq!(
+    bias_for_crossing_flow(
+      &agent,
+      &neighbours,
+      Vec3::X,
+      &CrossingFlow { min_crossing_neighbours: 1, ..Default::default() },
+    ),
+    Vec3::X
+  );
+}
+
+#[test]
+fn ignores_sparse_crossings_and_agents_behind() {
+  let agent = Agent::builder().build();
+  let neighbours = [
+    moving_agent(Vec3::new(2.0, 0.0, 0.0), Vec3::Z),
+    moving_agent(Vec3::new(3.0, 0.0, 0.0), Vec3::Z),
+    // Behind the agent.
+    moving_agent(Vec3::new(-2.0, 0.0, 0.0), Vec3::Z),
+  ];
+
+  assert_eq!(
+    bias_for_crossing_flow(
+      &agent,
+      &neighbours,
+      Vec3::X,
+      &CrossingFlow { min_crossing_neighbours: 3, ..Default::default() },
+    ),
+    Vec3::X
+  );
+}
@@ -0,0 +1,113 @@
+use glam::{UVec3, Vec3};
+
+/// A coarse grid of crowd density, from [`crate::Simulator::get_density_grid`].
+/// The density of each cell is the number of agents whose center is in the
+/// cell, divided by the volume of the cell.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DensityGrid {
+  /// The minimum corner of the grid.
+  pub min: Vec3,
+  /// The width of each (cubic) cell.
+  pub cell_size: f32,
+  /// The number of cells along each axis.
+  pub size: UVec3,
+  /// The density of each cell, indexed by `x + size.x * (y + size.y * z)`.
+  pub densities: Vec<f32>,
+}
+
+impl DensityGrid {
+  /// Creates a grid of zero density covering the box from `min` to `max`.
+  pub(crate) fn new(min: Vec3, max: Vec3, cell_size: f32) -> Self {
+    let layout = GridLayout::new(min, max, cell_size);
+    Self {
+      min,
+      cell_size,
+      size: layout.size,
+      densities: vec![0.0; layout.cell_count()],
+    }
+  }
+
+  /// The cell containing `point`, if it is in the grid.
+  pub fn get_cell(&self, point: Vec3) -> Option<UVec3> {
+    self.layout().get_cell(point)
+  }
+
+  /// The density of the cell at `cell`. Panics if `cell` is outside the grid.
+  pub fn get_density(&self, cell: UVec3) -> f32 {
+    self.densities[self.layout().get_index(cell)]
+  }
+
+  /// The center of the cell at `cell`.
+  pub fn get_cell_center(&self, cell: UVec3) -> Vec3 {
+    self.layout().get_cell_center(cell)
+  }
+
+  /// Counts an agent at `point`, if it is in the grid.
+  pub(crate) fn add_agent(&mut self, point: Vec3) {
+    let layout = self.layout();
+    if let Some(cell) = layout.get_cell(point) {
+      self.densities[layout.get_index(cell)] += 1.0 / self.cell_size.powi(3);
+    }
+  }
+
+  fn layout(&self) -> GridLayout {
+    GridLayout { min: self.min, cell_size: self.cell_size, size: self.size }
+  }
+}
+
+/// The cells of a grid of cubes covering a box, shared by the grids that bin
+/// agents by position.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct GridLayout {
+  /// The minimum corner of the grid.
+  pub(crate) min: Vec3,
+  /// The width of each (cubic) cell.
+  pub(crate) cell_size: f32,
+  /// The number of cells along each axis.
+  pub(crate) size: UVec3,
+}
+
+impl GridLayout {
+  /// Creates a layout covering the box from `min` to `max`, with at least one
+  /// cell along each axis.
+  pub(crate) fn new(min: Vec3, max: Vec3, cell_size: f32) -> Self {
+    assert!(cell_size > 0.0, "cell_size must be positive, was {}", cell_size);
+    let size = ((max - min) / cell_size).ceil().max(Vec3::ONE).as_uvec3();
+    Self { min, cell_size, size }
+  }
+
+  /// The total number of cells.
+  pub(crate) fn cell_count(&self) -> usize {
+    (self.size.x * self.size.y * self.size.z) as usize
+  }
+
+  /// The cell containing `point`, if it is in the grid.
+  pub(crate) fn get_cell(&self, point: Vec3) -> Option<UVec3> {
+    let cell = ((point - self.min) / self.cell_size).floor();
+    if cell.cmplt(Vec3::ZERO).any() || cell.cmpge(self.size.as_vec3()).any() {
+      return None;
+    }
+    Some(cell.as_uvec3())
+  }
+
+  /// The center of the cell at `cell`.
+  pub(crate) fn get_cell_center(&self, cell: UVec3) -> Vec3 {
+    self.min + (cell.as_vec3() + 0.5) * self.cell_size
+  }
+
+  /// The index of `cell` in a grid's cell data, which is ordered by
+  /// `x + size.x * (y + size.y * z)`. Panics if `cell` is outside the grid.
+  pub(crate) fn get_index(&self, cell: UVec3) -> usize {
+    assert!(
+      cell.cmplt(self.size).all(),
+      "cell {} is outside the grid of size {}",
+      cell,
+      self.size
+    );
+    (cell.x + self.size.x * (cell.y + self.size.y * cell.z)) as usize
+  }
+}
+
+#[cfg(test)]
+#[path = "density_test.rs"]
+mod test;
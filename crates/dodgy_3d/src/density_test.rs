@@ -0,0 +1,28 @@
+use glam::{UVec3, Vec3};
+
+use super::*;
+
+#[test]
+fn counts_agents_per_cell() {
+  let mut grid = DensityGrid::new(Vec3::ZERO, Vec3::new(4.0, 1.0, 3.0), 2.0);
+  assert_eq!(grid.size, UVec3::new(2, 1, 2));
+
+  grid.add_agent(Vec3::new(1.0, 0.5, 1.0));
+  grid.add_agent(Vec3::new(1.5, 0.5, 0.5));
+  grid.add_agent(Vec3::new(3.0, 0.5, 3.5));
+  // Outside the grid.
+  grid.add_agent(Vec3::new(-1.0, 0.5, 1.0));
+
+  assert_eq!(grid.get_density(UVec3::new(0, 0, 0)), 2.0 / 8.0);
+  assert_eq!(grid.get_density(UVec3::new(1, 0, 1)), 1.0 / 8.0);
+  assert_eq!(grid.get_density(UVec3::new(1, 0, 0)), 0.0);
+  assert_eq!(
+    grid.get_cell(Vec3::new(3.0, 0.0, 1.0)),
+    Some(UVec3::new(1, 0, 0))
+  );
+  assert_eq!(grid.get_cell(Vec3::new(5.0, 0.0, 1.0)), None);
+  assert_eq!(
+    grid.get_cell_center(UVec3::new(1, 0, 1)),
+    Vec3::new(3.0, 1.0, 3.0)
+  );
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+use glam::Vec3;
+
+use crate::linear_programming::{LinearProgramOutcome, Plane};
+
+/// A record of the decisions made while computing an avoiding velocity for a
+/// single agent, from [`crate::Agent::explain_avoiding_velocity`]. This is
+/// meant for bug reports and for learning how the algorithm works. The
+/// [`fmt::Display`] implementation narrates the decisions step by step.
+#[derive(Clone, Debug)]
+pub struct Explanation {
+  /// How the plane for each neighbour was constructed, in the same order as
+  /// the neighbours.
+  pub neighbours: Vec<NeighbourExplanation>,
+  /// The number of (rigid) planes created for obstacles. These come before the
+  /// neighbour planes in the linear program.
+  pub obstacle_plane_count: usize,
+  /// How the linear program was solved.
+  pub outcome: LinearProgramOutcome,
+  /// Whether the solver reached its limits (see [`crate::SolverLimits`]) and
+  /// stopped early.
+  pub solver_limit_reached: bool,
+  /// The resulting avoiding velocity.
+  pub velocity: Vec3,
+}
+
+/// How the plane for a single neighbour was constructed.
+#[derive(Clone, Debug)]
+pub struct NeighbourExplanation {
+  /// The resulting plane.
+  pub plane: Plane,
+  /// The part of the velocity obstacle the relative velocity was projected
+  /// onto.
+  pub branch: PlaneBranch,
+  /// Whether the relative velocity was inside the velocity obstacle (i.e. the
+  /// agents would collide within the time horizon).
+  pub inside_velocity_obstacle: bool,
+  /// The fraction of the avoidance the agent takes on, from the split of
+  /// `avoidance_responsibility` between the agent and the neighbour. This is 1
+  /// when the agent is outside the velocity obstacle.
+  pub responsibility: f32,
+}
+
+/// The part of a velocity obstacle the relative velocity was projected onto to
+/// construct a plane.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlaneBranch {
+  /// The cut-off sphere: the velocities that collide within the time horizon.
+  CutoffSphere,
+  /// The cut-off shadow: the cone of velocities that collide after the time
+  /// horizon.
+  CutoffShadow,
+  /// The agents are nearly touching, so the cut-off shadow was treated as the
+  /// half-space facing away from the neighbour.
+  NearlyTouching,
+  /// The agents already collide, so the relative velocity was projected onto
+  /// the cut-off sphere of the time step to push them apart.
+  Colliding,
+}
+
+impl fmt::Display for Explanation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "{} obstacle plane(s) (rigid).", self.obstacle_plane_count)?;
+    for (index, neighbour) in self.neighbours.iter().enumerate() {
+      let branch = match neighbour.branch {
+        PlaneBranch::CutoffSphere => "projected onto the cut-off sphere",
+        PlaneBranch::CutoffShadow => "projected onto the cut-off shadow",
+        PlaneBranch::NearlyTouching => {
+          "nearly touching, so pushed directly away"
+        }
+        PlaneBranch::Colliding => "colliding, so pushed apart within the step",
+      };
+      let inside =
+        if neighbour.inside_velocity_obstacle { "inside" } else { "outside" };
+      writeln!(
+        f,
+        "Neighbour {}: {} ({} the velocity obstacle), taking {}% of the \
+         responsibility. Plane point {}, normal {}.",
+        index,
+        branch,
+        inside,
+        neighbour.responsibility * 100.0,
+        neighbour.plane.point,
+        neighbour.plane.normal
+      )?;
+    }
+    match self.outcome {
+      LinearProgramOutcome::Feasible => {
+        writeln!(f, "3D linear program satisfied every plane.")?
+      }
+      LinearProgramOutcome::Relaxed { index_of_failed_constraint } => writeln!(
+        f,
+        "3D linear program failed at plane {}, so the 4D linear program \
+         minimized the violation of the neighbour planes.",
+        index_of_failed_constraint
+      )?,
+      LinearProgramOutcome::RigidInfeasible { index_of_failed_constraint } => {
+        writeln!(
+          f,
+          "3D linear program failed at rigid plane {}, so only the planes \
+           before it are satisfied.",
+          index_of_failed_constraint
+        )?
+      }
+      LinearProgramOutcome::Interrupted { index_of_failed_constraint } => {
+        writeln!(
+          f,
+          "3D linear program stopped at plane {} after reaching the solver \
+           limits.",
+          index_of_failed_constraint
+        )?
+      }
+    }
+    if self.solver_limit_reached {
+      writeln!(f, "The solver reached its limits and stopped early.")?;
+    }
+    write!(f, "Resulting velocity: {}.", self.velocity)
+  }
+}
+
+#[cfg(test)]
+#[path = "explain_test.rs"]
+mod test;
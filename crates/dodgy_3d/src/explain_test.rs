@@ -0,0 +1,131 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{Agent, AvoidanceOptions, LinearProgramOutcome, PlaneBranch};
+
+fn avoidance_options() -> AvoidanceOptions {
+  AvoidanceOptions::builder().time_horizon(2.0).build()
+}
+
+fn agent(position: Vec3, velocity: Vec3) -> Agent {
+  Agent { position, velocity, radius: 0.5, avoidance_responsibility: 1.0 }
+}
+
+#[test]
+fn explains_plane_construction() {
+  let agent = agent(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0));
+  let neighbours = [
+    // Heading straight for the agent.
+    Cow::Owned(self::agent(
+      Vec3::new(4.0, 0.0, 0.0),
+      Vec3::new(-1.0, 0.0, 0.0),
+    )),
+    // Far off to the side and moving away, so it will not collide.
+    Cow::Owned(self::agent(
+      Vec3::new(0.0, 0.0, 20.0),
+      Vec3::new(0.0, 0.0, 1.0),
+    )),
+    // Overlapping the agent.
+    Cow::Owned(self::agent(Vec3::new(0.0, 0.0, -0.5), Vec3::ZERO)),
+  ];
+  let explanation = agent.explain_avoiding_velocity(
+    &neighbours,
+    &[],
+    Vec3::new(1.0, 0.0, 0.0),
+    /* max_speed= */ 2.0,
+    /* time_step= */ 0.1,
+    &avoidance_options(),
+  );
+
+  assert_eq!(
+    explanation
+      .neighbours
+      .iter()
+      .map(|neighbour| (
+        neighbour.branch,
+        neighbour.inside_velocity_obstacle,
+        neighbour.responsibility
+      ))
+      .collect::<Vec<_>>(),
+    [
+      (PlaneBranch::CutoffShadow, true, 0.5),
+      (PlaneBranch::CutoffSphere, false, 1.0),
+      (PlaneBranch::Colliding, true, 0.5),
+    ]
+  );
+  assert_eq!(explanation.obstacle_plane_count, 0);
+  assert_eq!(
+    explanation.velocity,
+    agent.compute_avoiding_velocity(
+      &neighbours,
+      &[],
+      Vec3::new(1.0, 0.0, 0.0),
+      /* max_speed= */ 2.0,
+      /* time_step= */ 0.1,
+      &avoidance_options(),
+    )
+  );
+
+  let narrative = explanation.to_string();
+  assert!(
+    narrative.contains(
+      "Neighbour 0: projected onto the cut-off shadow (inside the velocity \
+       obstacle), taking 50% of the responsibility."
+    ),
+    "{narrative}"
+  );
+  assert!(narrative.contains("Neighbour 2: colliding"), "{narrative}");
+  assert!(narrative
+    .ends_with(&format!("Resulting velocity: {}.", explanation.velocity)));
+}
+
+#[test]
+fn explains_relaxed_linear_program() {
+  let agent = agent(Vec3::ZERO, Vec3::ZERO);
+  // Colliding on both sides, so the agent cannot get away from both.
+  let neighbours = [
+    Cow::Owned(self::agent(Vec3::new(0.5, 0.0, 0.0), Vec3::ZERO)),
+    Cow::Owned(self::agent(Vec3::new(-0.5, 0.0, 0.0), Vec3::ZERO)),
+  ];
+  let explanation = agent.explain_avoiding_velocity(
+    &neighbours,
+    &[],
+    Vec3::ZERO,
+    /* max_speed= */ 1.0,
+    /* time_step= */ 0.1,
+    &avoidance_options(),
+  );
+
+  assert_eq!(
+    explanation.outcome,
+    LinearProgramOutcome::Relaxed { index_of_failed_constraint: 0 }
+  );
+  assert!(!explanation.solver_limit_reached);
+  assert!(
+    explanation.to_string().contains(
+      "3D linear program failed at plane 0, so the 4D linear program \
+       minimized the violation of the neighbour planes."
+    ),
+    "{explanation}"
+  );
+}
+
+#[test]
+fn explains_feasible_linear_program() {
+  let explanation = agent(Vec3::ZERO, Vec3::ZERO).explain_avoiding_velocity(
+    &[],
+    &[],
+    Vec3::new(1.0, 0.0, 0.0),
+    /* max_speed= */ 2.0,
+    /* time_step= */ 0.1,
+    &avoidance_options(),
+  );
+  assert_eq!(explanation.outcome, LinearProgramOutcome::Feasible);
+  assert_eq!(explanation.velocity, Vec3::new(1.0, 0.0, 0.0));
+  assert_eq!(
+    explanation.to_string(),
+    "0 obstacle plane(s) (rigid).\n3D linear program satisfied every \
+     plane.\nResulting velocity: [1, 0, 0]."
+  );
+}
@@ -0,0 +1,31 @@
+use glam::Vec3;
+
+use super::*;
+
+/// The documented bound on the relative error of the approximations.
+const MAX_RELATIVE_ERROR: f32 = 5e-6;
+
+#[test]
+fn inverse_sqrt_is_within_error_bound() {
+  // Sweep across many orders of magnitude, with many mantissas in each.
+  let mut x = 1e-30f32;
+  while x < 1e30 {
+    let expected = 1.0 / (x as f64).sqrt();
+    let relative_error =
+      ((approximate_inverse_sqrt(x) as f64 - expected) / expected).abs();
+    assert!(
+      relative_error <= MAX_RELATIVE_ERROR as f64,
+      "error for {x} was {relative_error}"
+    );
+    x *= 1.001;
+  }
+}
+
+#[test]
+fn normalizes_vectors() {
+  let vector = Vec3::new(3.0, -4.0, 12.0);
+  let normalized =
+    normalize_with_length_squared(vector, vector.length_squared());
+  assert!((normalized.length() - 1.0).abs() <= MAX_RELATIVE_ERROR);
+  assert!(normalized.distance(vector.normalize()) <= MAX_RELATIVE_ERROR);
+}
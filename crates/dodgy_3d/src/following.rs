@@ -0,0 +1,70 @@
+use glam::{Quat, Vec3};
+
+use crate::Agent;
+
+/// Parameters for [`suggest_follow_point`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct FollowOptions {
+  /// The distance from the leader's center to trail at.
+  pub distance: f32,
+  /// The angle (in radians) around the vertical axis from directly behind the
+  /// leader to trail at. Zero is directly behind, and positive angles rotate
+  /// around `Vec3::Y` by the right-hand rule. Several followers can trail at
+  /// different angles to form an arc behind the leader.
+  pub angle: f32,
+}
+
+/// Makes an agent in a [`crate::Simulator`] follow another agent. While
+/// following, the agent's goal point is ignored, and the agent instead heads
+/// for the point suggested by [`suggest_follow_point`] while matching the
+/// leader's velocity.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LeaderFollowing {
+  /// The index of the agent to follow. This is kept up to date when agents
+  /// are removed from the simulator, and following stops if the leader is
+  /// removed.
+  pub leader_index: usize,
+  pub options: FollowOptions,
+}
+
+/// Suggests the point a `follower` should head for to trail the `leader`, for
+/// example for escorts or pets. The follower trails behind the leader's
+/// direction of travel. If the follower is ahead of the leader, it first
+/// heads to the leader's side, so it gets out of the leader's path instead of
+/// walking straight through it.
+pub fn suggest_follow_point(
+  leader: &Agent,
+  follower: &Agent,
+  options: &FollowOptions,
+) -> Vec3 {
+  const MIN_SPEED: f32 = 1e-3;
+
+  let offset = follower.position - leader.position;
+  // A (nearly) stopped leader has no direction of travel, so trail behind it
+  // from wherever the follower already is.
+  let Some(forward) =
+    (if leader.velocity.length_squared() > MIN_SPEED * MIN_SPEED {
+      leader.velocity.try_normalize()
+    } else {
+      (-offset).try_normalize()
+    })
+  else {
+    return follower.position;
+  };
+
+  let distance_ahead = offset.dot(forward);
+  if distance_ahead > 0.0 {
+    let side = (offset - forward * distance_ahead)
+      .try_normalize()
+      .unwrap_or_else(|| forward.any_orthonormal_vector());
+    let distance = options.distance.max(leader.radius + follower.radius);
+    return leader.position + side * distance;
+  }
+
+  let behind = Quat::from_rotation_y(options.angle) * -forward;
+  leader.position + behind * options.distance
+}
+
+#[cfg(test)]
+#[path = "following_test.rs"]
+mod test;
@@ -0,0 +1,90 @@
+use glam::Vec3;
+
+use crate::Agent;
+
+use super::*;
+
+macro_rules! assert_vec_near {
+  ($left: expr, $right: expr, $eps: expr) => {{
+    let left = $left;
+    let right = $right;
+    let eps = $eps;
+    assert!(
+      left.distance(right) < eps,
+      "left: {}, right: {}, epsilon: {}",
+      left,
+      right,
+      eps
+    );
+  }};
+}
+
+fn agent(position: Vec3, velocity: Vec3) -> Agent {
+  Agent { position, velocity, radius: 0.5, avoidance_responsibility: 1.0 }
+}
+
+#[test]
+fn trails_behind_moving_leader() {
+  let leader = agent(Vec3::ZERO, Vec3::new(2.0, 0.0, 0.0));
+  let follower = agent(Vec3::new(-5.0, 0.0, 3.0), Vec3::ZERO);
+  assert_vec_near!(
+    suggest_follow_point(
+      &leader,
+      &follower,
+      &FollowOptions { distance: 2.0, angle: 0.0 }
+    ),
+    Vec3::new(-2.0, 0.0, 0.0),
+    1e-5
+  );
+  // Rotating a quarter turn around Y moves the point from -X to +Z.
+  assert_vec_near!(
+    suggest_follow_point(
+      &leader,
+      &follower,
+      &FollowOptions { distance: 2.0, angle: std::f32::consts::FRAC_PI_2 }
+    ),
+    Vec3::new(0.0, 0.0, 2.0),
+    1e-5
+  );
+}
+
+#[test]
+fn follower_ahead_of_leader_moves_aside() {
+  let leader = agent(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0));
+  let follower = agent(Vec3::new(3.0, 0.0, -0.5), Vec3::ZERO);
+  assert_vec_near!(
+    suggest_follow_point(
+      &leader,
+      &follower,
+      &FollowOptions { distance: 2.0, angle: 0.0 }
+    ),
+    Vec3::new(0.0, 0.0, -2.0),
+    1e-5
+  );
+}
+
+#[test]
+fn stopped_leader_keeps_follower_on_its_side() {
+  let leader = agent(Vec3::ZERO, Vec3::ZERO);
+  let follower = agent(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO);
+  assert_vec_near!(
+    suggest_follow_point(
+      &leader,
+      &follower,
+      &FollowOptions { distance: 2.0, angle: 0.0 }
+    ),
+    Vec3::new(0.0, 0.0, 2.0),
+    1e-5
+  );
+
+  // A follower on top of a stopped leader stays put.
+  let follower = agent(Vec3::ZERO, Vec3::ZERO);
+  assert_eq!(
+    suggest_follow_point(
+      &leader,
+      &follower,
+      &FollowOptions { distance: 2.0, angle: 0.0 }
+    ),
+    Vec3::ZERO
+  );
+}
@@ -0,0 +1,376 @@
+use std::{
+  borrow::Cow,
+  f32::consts::TAU,
+  io::{self, Write},
+};
+
+use glam::{Vec2, Vec3, Vec3Swizzles};
+
+use crate::{Agent, AvoidanceOptions, Plane};
+
+/// The number of segments around circles (e.g. of spheres and cones) in
+/// exported meshes.
+const CIRCLE_SEGMENTS: usize = 16;
+/// The number of rings from pole to pole of spheres in exported meshes.
+const SPHERE_RINGS: usize = 8;
+
+/// The velocity obstacles and planes an agent avoids its neighbours with, for
+/// inspecting the geometry visually (e.g. to track down a regression in plane
+/// construction) instead of deciphering normals by hand. Everything is in the
+/// agent's velocity space. Export with [`GeometryDump::write_obj`] (e.g. for
+/// Blender) or [`GeometryDump::write_svg`] (for a quick look in a browser).
+#[derive(Clone, Debug)]
+pub struct GeometryDump {
+  /// The current velocity of the agent.
+  pub velocity: Vec3,
+  /// The velocity obstacle of each neighbour, in the same order as the
+  /// neighbours.
+  pub velocity_obstacles: Vec<VelocityObstacle>,
+  /// The plane constructed for each neighbour, in the same order as the
+  /// neighbours.
+  pub planes: Vec<Plane>,
+}
+
+/// The velocities of an agent that collide with a neighbour (assuming the
+/// neighbour keeps its velocity).
+#[derive(Clone, PartialEq, Debug)]
+pub struct VelocityObstacle {
+  /// The velocities in this sphere collide with the neighbour within the time
+  /// horizon (or within the time step if the agents already collide).
+  pub cutoff_sphere_center: Vec3,
+  pub cutoff_sphere_radius: f32,
+  /// The cone of velocities that (eventually) collide with the neighbour, or
+  /// None if the agents already collide. The cut-off sphere cuts off the tip
+  /// of this cone.
+  pub shadow: Option<Shadow>,
+}
+
+/// A cone of velocities, starting at the `apex` and opening towards `axis`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Shadow {
+  /// The velocity of the neighbour.
+  pub apex: Vec3,
+  /// The direction of the neighbour. Always unit length.
+  pub axis: Vec3,
+  /// The angle between the axis and the sides of the cone, in radians.
+  pub half_angle: f32,
+}
+
+impl GeometryDump {
+  /// Computes the geometry for `agent` avoiding `neighbours`, using the same
+  /// planes as [`Agent::compute_avoiding_velocity`].
+  pub fn new(
+    agent: &Agent,
+    neighbours: &[Cow<'_, Agent>],
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Self {
+    let velocity_obstacles = neighbours
+      .iter()
+      .map(|neighbour| {
+        VelocityObstacle::new(
+          agent,
+          neighbour,
+          avoidance_options.time_horizon,
+          time_step,
+        )
+      })
+      .collect();
+    Self {
+      velocity: agent.velocity,
+      velocity_obstacles,
+      planes: agent.get_planes_for_neighbours(
+        neighbours,
+        time_step,
+        avoidance_options,
+      ),
+    }
+  }
+
+  /// Writes the geometry as a Wavefront OBJ file, with one object per part:
+  /// the agent's velocity (as a point), the cut-off sphere and shadow of each
+  /// velocity obstacle, and each plane (as a square `extent` wide, with a line
+  /// along its normal). Shadows are `extent` long.
+  pub fn write_obj(
+    &self,
+    extent: f32,
+    writer: &mut impl Write,
+  ) -> io::Result<()> {
+    let mut obj = ObjWriter { writer, vertex_count: 0 };
+    obj.begin_object("velocity")?;
+    let index = obj.write_vertex(self.velocity)?;
+    writeln!(obj.writer, "p {}", index)?;
+
+    for (index, velocity_obstacle) in self.velocity_obstacles.iter().enumerate()
+    {
+      obj.begin_object(&format!("cutoff_sphere_{}", index))?;
+      obj.write_sphere(
+        velocity_obstacle.cutoff_sphere_center,
+        velocity_obstacle.cutoff_sphere_radius,
+      )?;
+      if let Some(shadow) = &velocity_obstacle.shadow {
+        obj.begin_object(&format!("shadow_{}", index))?;
+        obj.write_cone(shadow, extent)?;
+      }
+    }
+
+    for (index, plane) in self.planes.iter().enumerate() {
+      obj.begin_object(&format!("plane_{}", index))?;
+      obj.write_plane(plane, extent)?;
+    }
+    Ok(())
+  }
+
+  /// Writes a top-down (XZ) slice of the geometry at the height of the
+  /// agent's velocity as an SVG file, covering `extent` around the agent's
+  /// velocity in each direction. The X axis points right, and the Z axis
+  /// points down. Cut-off spheres are drawn as circles, shadows as the lines
+  /// along their sides, and planes as lines with a short tick on their valid
+  /// side. Planes that are parallel to the slice are skipped.
+  pub fn write_svg(
+    &self,
+    extent: f32,
+    writer: &mut impl Write,
+  ) -> io::Result<()> {
+    let center = self.velocity.xz();
+    let height = self.velocity.y;
+    let stroke_width = extent / 200.0;
+    writeln!(
+      writer,
+      r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+      center.x - extent,
+      center.y - extent,
+      extent * 2.0,
+      extent * 2.0
+    )?;
+    writeln!(writer, r#"<g fill="none" stroke-width="{}">"#, stroke_width)?;
+
+    for velocity_obstacle in self.velocity_obstacles.iter() {
+      // The slice through the cut-off sphere is a smaller circle.
+      let height_offset = height - velocity_obstacle.cutoff_sphere_center.y;
+      let radius_squared = velocity_obstacle.cutoff_sphere_radius.powi(2)
+        - height_offset * height_offset;
+      if radius_squared > 0.0 {
+        let sphere_center = velocity_obstacle.cutoff_sphere_center.xz();
+        writeln!(
+          writer,
+          r#"<circle cx="{}" cy="{}" r="{}" stroke="red"/>"#,
+          sphere_center.x,
+          sphere_center.y,
+          radius_squared.sqrt()
+        )?;
+      }
+      if let Some(shadow) = &velocity_obstacle.shadow {
+        // Draw the sides of the cone as seen from above, which is exact when
+        // the neighbour is level with the agent.
+        let apex = shadow.apex.xz();
+        let axis = shadow.axis.xz().normalize_or_zero();
+        for side in [-1.0, 1.0] {
+          let direction =
+            Vec2::from_angle(shadow.half_angle * side).rotate(axis);
+          let end = apex + direction * extent * 2.0;
+          write_svg_line(writer, apex, end, "orange")?;
+        }
+      }
+    }
+
+    for plane in self.planes.iter() {
+      // Find where the plane crosses the slice: the points where
+      // `normal_xz.dot(point) = offset`.
+      let normal_xz = plane.normal.xz();
+      let normal_xz_length_squared = normal_xz.length_squared();
+      if normal_xz_length_squared < 1e-6 {
+        continue;
+      }
+      let offset = normal_xz.dot(plane.point.xz())
+        - plane.normal.y * (height - plane.point.y);
+      let closest_point = normal_xz * (offset / normal_xz_length_squared);
+      // Start from the point on the line closest to the velocity, so the line
+      // covers the view.
+      let direction = normal_xz.perp().normalize();
+      let middle =
+        closest_point + direction * direction.dot(center - closest_point);
+      write_svg_line(
+        writer,
+        middle - direction * extent * 2.0,
+        middle + direction * extent * 2.0,
+        "blue",
+      )?;
+      write_svg_line(
+        writer,
+        middle,
+        middle + normal_xz.normalize() * extent * 0.05,
+        "blue",
+      )?;
+    }
+
+    writeln!(
+      writer,
+      r#"<circle cx="{}" cy="{}" r="{}" fill="black"/>"#,
+      center.x,
+      center.y,
+      stroke_width * 3.0
+    )?;
+    writeln!(writer, "</g>")?;
+    writeln!(writer, "</svg>")
+  }
+}
+
+impl VelocityObstacle {
+  /// Computes the velocity obstacle of `neighbour` for `agent`, in the same
+  /// way planes are constructed.
+  fn new(
+    agent: &Agent,
+    neighbour: &Agent,
+    time_horizon: f32,
+    time_step: f32,
+  ) -> Self {
+    let relative_neighbour_position = neighbour.position - agent.position;
+    let distance = relative_neighbour_position.length();
+    let sum_radius = agent.radius + neighbour.radius;
+    if distance <= sum_radius {
+      // The agents already collide, so they must separate within the time
+      // step.
+      return Self {
+        cutoff_sphere_center: neighbour.velocity
+          + relative_neighbour_position / time_step,
+        cutoff_sphere_radius: sum_radius / time_step,
+        shadow: None,
+      };
+    }
+    Self {
+      cutoff_sphere_center: neighbour.velocity
+        + relative_neighbour_position / time_horizon,
+      cutoff_sphere_radius: sum_radius / time_horizon,
+      shadow: Some(Shadow {
+        apex: neighbour.velocity,
+        axis: relative_neighbour_position / distance,
+        half_angle: (sum_radius / distance).asin(),
+      }),
+    }
+  }
+}
+
+/// Writes a line from `start` to `end` to an SVG file.
+fn write_svg_line(
+  writer: &mut impl Write,
+  start: Vec2,
+  end: Vec2,
+  stroke: &str,
+) -> io::Result<()> {
+  writeln!(
+    writer,
+    r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}"/>"#,
+    start.x, start.y, end.x, end.y, stroke
+  )
+}
+
+/// Writes objects to an OBJ file, keeping track of vertex indices (which are
+/// shared between all objects in the file).
+struct ObjWriter<'a, W: Write> {
+  writer: &'a mut W,
+  vertex_count: usize,
+}
+
+impl<W: Write> ObjWriter<'_, W> {
+  fn begin_object(&mut self, name: &str) -> io::Result<()> {
+    writeln!(self.writer, "o {}", name)
+  }
+
+  /// Writes a vertex, returning its (1-based) index.
+  fn write_vertex(&mut self, vertex: Vec3) -> io::Result<usize> {
+    writeln!(self.writer, "v {} {} {}", vertex.x, vertex.y, vertex.z)?;
+    self.vertex_count += 1;
+    Ok(self.vertex_count)
+  }
+
+  fn write_sphere(&mut self, center: Vec3, radius: f32) -> io::Result<()> {
+    let first_index = self.vertex_count + 1;
+    for ring in 0..=SPHERE_RINGS {
+      let (ring_sin, ring_cos) =
+        (ring as f32 / SPHERE_RINGS as f32 * TAU / 2.0).sin_cos();
+      for segment in 0..CIRCLE_SEGMENTS {
+        let (segment_sin, segment_cos) =
+          (segment as f32 / CIRCLE_SEGMENTS as f32 * TAU).sin_cos();
+        self.write_vertex(
+          center
+            + Vec3::new(
+              ring_sin * segment_cos,
+              ring_cos,
+              ring_sin * segment_sin,
+            ) * radius,
+        )?;
+      }
+    }
+    for ring in 0..SPHERE_RINGS {
+      for segment in 0..CIRCLE_SEGMENTS {
+        let next_segment = (segment + 1) % CIRCLE_SEGMENTS;
+        let index =
+          |ring: usize, segment| first_index + ring * CIRCLE_SEGMENTS + segment;
+        writeln!(
+          self.writer,
+          "f {} {} {} {}",
+          index(ring, segment),
+          index(ring, next_segment),
+          index(ring + 1, next_segment),
+          index(ring + 1, segment)
+        )?;
+      }
+    }
+    Ok(())
+  }
+
+  fn write_cone(&mut self, shadow: &Shadow, length: f32) -> io::Result<()> {
+    let apex_index = self.write_vertex(shadow.apex)?;
+    let (side_a, side_b) = shadow.axis.any_orthonormal_pair();
+    let (half_angle_sin, half_angle_cos) = shadow.half_angle.sin_cos();
+    for segment in 0..CIRCLE_SEGMENTS {
+      let (segment_sin, segment_cos) =
+        (segment as f32 / CIRCLE_SEGMENTS as f32 * TAU).sin_cos();
+      let direction = shadow.axis * half_angle_cos
+        + (side_a * segment_cos + side_b * segment_sin) * half_angle_sin;
+      self.write_vertex(shadow.apex + direction * length)?;
+    }
+    for segment in 0..CIRCLE_SEGMENTS {
+      let next_segment = (segment + 1) % CIRCLE_SEGMENTS;
+      writeln!(
+        self.writer,
+        "f {} {} {}",
+        apex_index,
+        apex_index + 1 + segment,
+        apex_index + 1 + next_segment
+      )?;
+    }
+    Ok(())
+  }
+
+  fn write_plane(&mut self, plane: &Plane, extent: f32) -> io::Result<()> {
+    let (side_a, side_b) = plane.normal.any_orthonormal_pair();
+    let half_extent = extent * 0.5;
+    let mut corner_indices = [0; 4];
+    for (corner_index, (a, b)) in
+      [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+        .into_iter()
+        .enumerate()
+    {
+      corner_indices[corner_index] = self
+        .write_vertex(plane.point + (side_a * a + side_b * b) * half_extent)?;
+    }
+    writeln!(
+      self.writer,
+      "f {} {} {} {}",
+      corner_indices[0],
+      corner_indices[1],
+      corner_indices[2],
+      corner_indices[3]
+    )?;
+    let start_index = self.write_vertex(plane.point)?;
+    let end_index =
+      self.write_vertex(plane.point + plane.normal * half_extent)?;
+    writeln!(self.writer, "l {} {}", start_index, end_index)
+  }
+}
+
+#[cfg(test)]
+#[path = "geometry_dump_test.rs"]
+mod test;
@@ -0,0 +1,132 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{Agent, AvoidanceOptions};
+
+use super::{GeometryDump, Shadow, VelocityObstacle, CIRCLE_SEGMENTS};
+
+fn avoidance_options() -> AvoidanceOptions {
+  AvoidanceOptions::builder().time_horizon(2.0).build()
+}
+
+fn dump() -> GeometryDump {
+  let agent = Agent {
+    position: Vec3::ZERO,
+    velocity: Vec3::new(1.0, 0.0, 0.0),
+    radius: 0.5,
+    avoidance_responsibility: 1.0,
+  };
+  let neighbours = [
+    // Far away, so the agent is outside its velocity obstacle.
+    Cow::Owned(Agent {
+      position: Vec3::new(4.0, 0.0, 0.0),
+      velocity: Vec3::new(-1.0, 0.0, 0.0),
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    }),
+    // Overlapping the agent.
+    Cow::Owned(Agent {
+      position: Vec3::new(0.0, 0.0, 0.5),
+      velocity: Vec3::ZERO,
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    }),
+  ];
+  GeometryDump::new(&agent, &neighbours, 0.1, &avoidance_options())
+}
+
+#[test]
+fn computes_velocity_obstacles() {
+  let dump = dump();
+  assert_eq!(dump.velocity, Vec3::new(1.0, 0.0, 0.0));
+  assert_eq!(dump.planes.len(), 2);
+  assert_eq!(
+    dump.velocity_obstacles,
+    [
+      VelocityObstacle {
+        cutoff_sphere_center: Vec3::new(1.0, 0.0, 0.0),
+        cutoff_sphere_radius: 0.5,
+        shadow: Some(Shadow {
+          apex: Vec3::new(-1.0, 0.0, 0.0),
+          axis: Vec3::X,
+          half_angle: 0.25f32.asin(),
+        }),
+      },
+      VelocityObstacle {
+        cutoff_sphere_center: Vec3::new(0.0, 0.0, 5.0),
+        cutoff_sphere_radius: 10.0,
+        shadow: None,
+      },
+    ]
+  );
+}
+
+#[test]
+fn writes_obj_file() {
+  let mut obj = Vec::new();
+  dump().write_obj(3.0, &mut obj).unwrap();
+  let obj = String::from_utf8(obj).unwrap();
+
+  let objects =
+    obj.lines().filter(|line| line.starts_with("o ")).collect::<Vec<_>>();
+  assert_eq!(
+    objects,
+    [
+      "o velocity",
+      "o cutoff_sphere_0",
+      "o shadow_0",
+      "o cutoff_sphere_1",
+      "o plane_0",
+      "o plane_1"
+    ]
+  );
+  assert_eq!(obj.lines().next(), Some("o velocity"));
+  assert!(obj.contains("\nv 1 0 0\np 1\n"));
+
+  // Every face and line must refer to a vertex in the file.
+  let vertex_count = obj.lines().filter(|line| line.starts_with("v ")).count();
+  for line in obj.lines() {
+    if line.starts_with("f ") || line.starts_with("l ") {
+      for index in line.split(' ').skip(1) {
+        let index = index.parse::<usize>().unwrap();
+        assert!((1..=vertex_count).contains(&index), "{line}");
+      }
+    }
+  }
+  let cone_face_count = obj
+    .lines()
+    .skip_while(|line| *line != "o shadow_0")
+    .skip(1)
+    .take_while(|line| !line.starts_with("o "))
+    .filter(|line| line.starts_with("f "))
+    .count();
+  assert_eq!(cone_face_count, CIRCLE_SEGMENTS);
+}
+
+#[test]
+fn writes_svg_file() {
+  let mut svg = Vec::new();
+  dump().write_svg(3.0, &mut svg).unwrap();
+  let svg = String::from_utf8(svg).unwrap();
+
+  assert!(svg.starts_with(
+    r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-2 -3 6 6">"#
+  ));
+  assert!(svg.ends_with("</svg>\n"));
+  // A circle for each cut-off sphere, and one for the velocity.
+  assert_eq!(svg.matches("<circle").count(), 3);
+  // Two sides of the one shadow, and a line and tick for each plane.
+  assert_eq!(svg.matches(r#"stroke="orange""#).count(), 2);
+  assert_eq!(svg.matches(r#"stroke="blue""#).count(), 4);
+}
+
+#[test]
+fn svg_skips_planes_parallel_to_slice() {
+  let mut dump = dump();
+  dump.planes[0].normal = Vec3::Y;
+  let mut svg = Vec::new();
+  dump.write_svg(3.0, &mut svg).unwrap();
+  let svg = String::from_utf8(svg).unwrap();
+  assert_eq!(svg.matches(r#"stroke="blue""#).count(), 2);
+}
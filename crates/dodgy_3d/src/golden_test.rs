@@ -0,0 +1,169 @@
+// Checks the resolved velocities of every agent in the canonical scenarios
+// against traces recorded in `golden/`, so that any change in behaviour (even
+// one that still passes every other test) is caught and has to be deliberate.
+// After an intended change, re-record the traces with:
+//
+//   DODGY_UPDATE_GOLDEN=1 cargo test -p dodgy_3d golden
+use std::path::PathBuf;
+
+use glam::Vec3;
+
+use crate::scenarios::{self, Scenario};
+
+/// The largest difference in any velocity component that is not considered a
+/// change. This allows for differences in floating point math between
+/// platforms.
+const TOLERANCE: f32 = 1e-3;
+
+const STEP_COUNT: usize = 40;
+const TIME_STEP: f32 = 0.1;
+
+/// The environment variable that re-records the traces instead of checking
+/// them.
+const UPDATE_VARIABLE: &str = "DODGY_UPDATE_GOLDEN";
+
+/// The velocity of each agent after each step.
+type Trace = Vec<Vec<Vec3>>;
+
+fn record_trace(scenario: Scenario) -> Trace {
+  let mut simulator = scenario.into_simulator();
+  (0..STEP_COUNT)
+    .map(|_| {
+      simulator.step(TIME_STEP);
+      (0..simulator.get_agent_count())
+        .map(|agent_index| simulator.get_agent(agent_index).velocity)
+        .collect()
+    })
+    .collect()
+}
+
+/// Formats the trace with one line per agent per step, so changes show up
+/// clearly in diffs.
+fn format_trace(name: &str, trace: &Trace) -> String {
+  let mut contents = format!(
+    "# The golden trace for {name}. Re-record with `{UPDATE_VARIABLE}=1 cargo \
+     test -p dodgy_3d golden`.\n# step agent velocity.x velocity.y \
+     velocity.z\n"
+  );
+  for (step, velocities) in trace.iter().enumerate() {
+    for (agent_index, velocity) in velocities.iter().enumerate() {
+      // The debug format of floats round-trips exactly.
+      contents.push_str(&format!(
+        "{step} {agent_index} {:?} {:?} {:?}\n",
+        velocity.x, velocity.y, velocity.z
+      ));
+    }
+  }
+  contents
+}
+
+fn parse_trace(contents: &str) -> Trace {
+  let mut trace = Trace::new();
+  for line in contents.lines().filter(|line| !line.starts_with('#')) {
+    let values = line.split(' ').collect::<Vec<_>>();
+    let [step, agent_index, x, y, z] = values[..] else {
+      panic!("malformed golden trace line: {line}");
+    };
+    let step = step.parse::<usize>().unwrap();
+    let agent_index = agent_index.parse::<usize>().unwrap();
+    if step == trace.len() {
+      trace.push(Vec::new());
+    }
+    assert_eq!(
+      (step, agent_index),
+      (trace.len() - 1, trace[step].len()),
+      "golden trace lines are out of order"
+    );
+    trace[step].push(Vec3::new(
+      x.parse().unwrap(),
+      y.parse().unwrap(),
+      z.parse().unwrap(),
+    ));
+  }
+  trace
+}
+
+fn check_golden_trace(name: &str, scenario: Scenario) {
+  let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    .join("golden")
+    .join(format!("{name}.txt"));
+  let trace = record_trace(scenario);
+  if std::env::var_os(UPDATE_VARIABLE).is_some() {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, format_trace(name, &trace)).unwrap();
+    return;
+  }
+
+  let update_hint = format!(
+    "If the change is intended, re-record the traces with \
+     `{UPDATE_VARIABLE}=1 cargo test -p dodgy_3d golden`."
+  );
+  let contents = std::fs::read_to_string(&path).unwrap_or_else(|error| {
+    panic!("Failed to read {}: {error}. {update_hint}", path.display())
+  });
+  let expected_trace = parse_trace(&contents);
+  assert_eq!(
+    trace.len(),
+    expected_trace.len(),
+    "{name} has a different number of steps. {update_hint}"
+  );
+  for (step, (velocities, expected_velocities)) in
+    trace.iter().zip(expected_trace.iter()).enumerate()
+  {
+    assert_eq!(
+      velocities.len(),
+      expected_velocities.len(),
+      "{name} has a different number of agents. {update_hint}"
+    );
+    for (agent_index, (velocity, expected_velocity)) in
+      velocities.iter().zip(expected_velocities.iter()).enumerate()
+    {
+      assert!(
+        (*velocity - *expected_velocity).abs().max_element() <= TOLERANCE,
+        "{name} drifted at step {step} for agent {agent_index}: expected \
+         {expected_velocity}, got {velocity}. {update_hint}"
+      );
+    }
+  }
+}
+
+#[test]
+fn sphere_antipodal_swap() {
+  check_golden_trace(
+    "sphere_antipodal_swap",
+    scenarios::sphere_antipodal_swap(/* agent_count= */ 16),
+  );
+}
+
+#[test]
+fn corridor_crossing() {
+  check_golden_trace(
+    "corridor_crossing",
+    scenarios::corridor_crossing(/* agent_count= */ 16),
+  );
+}
+
+#[test]
+fn random_soup() {
+  check_golden_trace(
+    "random_soup",
+    scenarios::random_soup(/* agent_count= */ 16, /* seed= */ 7),
+  );
+}
+
+#[test]
+fn dense_grid() {
+  check_golden_trace(
+    "dense_grid",
+    scenarios::dense_grid(/* agent_count= */ 27),
+  );
+}
+
+#[test]
+fn traces_round_trip() {
+  let trace = vec![
+    vec![Vec3::new(0.1, -2.0, 1.0 / 3.0), Vec3::ZERO],
+    vec![Vec3::new(f32::MIN_POSITIVE, 1e10, -0.0), Vec3::X],
+  ];
+  assert_eq!(parse_trace(&format_trace("test", &trace)), trace);
+}
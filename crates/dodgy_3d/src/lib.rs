@@ -19,22 +19,117 @@
 // United States of America
 //
 // <https://gamma.cs.unc.edu/RVO2/>
+mod articulated;
+mod axis_locks;
+mod boundary;
+mod builders;
+pub mod calibration;
+mod chunked_vec;
+mod compound;
+mod constraint_cache;
+mod constraint_set;
+mod crossing;
+mod density;
+mod explain;
+mod fast_math;
+mod following;
+#[cfg(feature = "debug")]
+mod geometry_dump;
 mod linear_programming;
+mod lookahead;
+mod mixed;
+mod neighbour_provider;
+mod objective;
+mod obstacle_bvh;
+mod obstacle_nudge;
+mod obstacles;
+mod orientation;
+mod presets;
+#[cfg(feature = "dodgy_2d")]
+pub mod projection;
+mod quantization;
+mod queueing;
+mod recording;
+pub mod repro;
+mod responsibility;
+mod sampling;
+pub mod scenarios;
+mod sharding;
 mod simulator;
+pub mod soak;
+mod social_force;
+#[cfg(feature = "metrics")]
+mod telemetry;
+mod terrain;
+mod threat;
+mod time_horizon;
+mod traffic;
+mod uncertainty;
+mod unit_scale;
+mod yielding;
+
+use std::{borrow::Cow, hash::Hash};
 
-use std::borrow::Cow;
+use rand::{Rng, RngCore};
 
-use crate::linear_programming::solve_linear_program;
+use crate::{
+  fast_math::normalize_with_length_squared, linear_programming::RVO_EPSILON,
+};
+use obstacles::get_planes_for_agent_to_obstacle;
 
-pub use glam::Vec3;
-pub use simulator::{AgentParameters, Simulator, SimulatorMargin};
+pub use articulated::ArticulatedAgent;
+pub use boundary::Boundary;
+pub use builders::{
+  AgentBuilder, AgentParametersBuilder, AvoidanceOptionsBuilder,
+};
+pub use compound::{CompoundAgent, CompoundSphere};
+pub use constraint_cache::ConstraintCache;
+pub use constraint_set::ConstraintSet;
+pub use crossing::CrossingFlow;
+pub use density::DensityGrid;
+pub use explain::{Explanation, NeighbourExplanation, PlaneBranch};
+pub use following::{suggest_follow_point, FollowOptions, LeaderFollowing};
+pub use glam::{BVec3, Quat, UVec3, Vec2, Vec3};
+pub use linear_programming::{LinearProgramOutcome, Plane, SolverLimits};
+pub use lookahead::LookaheadOptions;
+pub use neighbour_provider::NeighbourProvider;
+pub use objective::ObjectiveWeights;
+pub use obstacle_nudge::{nudge_away_from_obstacles, ObstacleNudge};
+pub use obstacles::Obstacle;
+pub use orientation::{suggest_orientation, OrientationOptions};
+pub use quantization::{AgentQuantization, QuantizedAgentState, Quantizer};
+pub use queueing::Queue;
+pub use recording::FlowRecorder;
+pub use responsibility::{ResponsibilityOverride, ResponsibilityOverrideFn};
+pub use sharding::{ShardedAgentId, ShardedSimulator};
+pub use simulator::{
+  AgentParameters, Importance, ImportanceClassifier, MarginCurve, Simulator,
+  SimulatorMargin, SimulatorSnapshot, SplitAgentIndex, StepScratch,
+  MAX_URGENCY_RADIUS_SHRINK, MAX_URGENCY_TIME_HORIZON_SHRINK,
+  MAX_URGENCY_VIOLATION,
+};
+pub use social_force::SocialForce;
+#[cfg(feature = "metrics")]
+pub use telemetry::{
+  describe_metrics, AGENTS_METRIC, COLLISIONS_METRIC, SOLVER_FALLBACKS_METRIC,
+  SOLVER_LIMITED_METRIC, STEPS_METRIC, STEP_SECONDS_METRIC,
+};
+pub use terrain::Terrain;
+pub use threat::{get_threat_score, ThreatFilter};
+pub use time_horizon::{suggest_time_horizon, TimeHorizonOptions};
+pub use traffic::TrafficLane;
+pub use uncertainty::NeighbourUncertainty;
+pub use unit_scale::UnitScale;
+pub use yielding::Yielding;
 
-// Re-export Plane so we can use it to provide debug data.
+// Re-export the solver so we can use it to provide debug data.
+#[cfg(feature = "debug")]
+pub use crate::linear_programming::{
+  solve_linear_program, solve_linear_program_with_limits, verify_solution,
+  SolutionViolation,
+};
 #[cfg(feature = "debug")]
-pub use crate::linear_programming::Plane;
-// Otherwise, just import it privately.
-#[cfg(not(feature = "debug"))]
-use crate::linear_programming::Plane;
+pub use geometry_dump::{GeometryDump, Shadow, VelocityObstacle};
 
 /// A single agent in the simulation.
 #[derive(Clone, PartialEq, Debug)]
@@ -49,7 +144,8 @@ pub struct Agent {
   pub radius: f32,
   /// The amount of responsibility an agent has to avoid other agents. The
   /// amount of avoidance between two agents is then dependent on the ratio of
-  /// the responsibility between the agents. Note this does not affect
+  /// the responsibility between the agents (unless overridden by
+  /// [`AvoidanceOptions::responsibility_override`]). Note this does not affect
   /// avoidance of obstacles.
   pub avoidance_responsibility: f32,
 }
@@ -57,30 +153,253 @@ pub struct Agent {
 /// Parameters for computing the avoidance vector.
 #[derive(Clone, PartialEq, Debug)]
 pub struct AvoidanceOptions {
+  /// The distance that the agent must be from any obstacle. This is commonly
+  /// the agent's radius to ensure the agent never intersects the obstacle (for
+  /// example a wall). An alternative is to set this to a small value to treat
+  /// obstacles as the edge of something (like a cliff).
+  pub obstacle_margin: f32,
   /// How long in the future should collisions be considered between agents.
   pub time_horizon: f32,
+  /// How long in the future should collisions be considered for obstacles.
+  pub obstacle_time_horizon: f32,
+  /// The numerical tolerances used when computing the avoidance vector.
+  pub tolerances: Tolerances,
+  /// Whether to disable the deviations dodgy makes from RVO2-3D, for users
+  /// that need to match the behaviour of the original library. In this mode,
+  /// `avoidance_responsibility` is ignored (every agent takes half the
+  /// responsibility, even when outside the velocity obstacle), the distance to
+  /// the cut-off shadow is measured as in RVO2-3D, and only
+  /// `tolerances.solver_epsilon` is used. Degenerate inputs (where RVO2-3D
+  /// would produce NaN) are still guarded against.
+  pub strict_rvo2_compatibility: bool,
+  /// Weights for secondary terms of the objective, for agents that should
+  /// prefer some valid velocities over others (e.g. to save energy).
+  pub objective_weights: ObjectiveWeights,
+  /// Limits on the work the solver may do for each avoiding velocity, so
+  /// worst-case inputs cannot blow the frame budget.
+  pub solver_limits: SolverLimits,
+  /// Overrides the split of responsibility for specific pairs of agents. If
+  /// None, responsibility is always split by `avoidance_responsibility`.
+  pub responsibility_override: Option<ResponsibilityOverride>,
+}
+
+impl AvoidanceOptions {
+  /// The share of responsibility `agent` takes for avoiding `neighbour`, if
+  /// overridden by `responsibility_override`.
+  pub(crate) fn get_responsibility_override(
+    &self,
+    agent: &Agent,
+    neighbour: &Agent,
+  ) -> Option<f32> {
+    self.responsibility_override.as_ref().and_then(|responsibility_override| {
+      responsibility_override.get(agent, neighbour)
+    })
+  }
+}
+
+/// Numerical tolerances used to guard against floating point error when
+/// computing the avoidance vector. The defaults are tuned for agents with
+/// radii and speeds around 1 unit. For worlds in other units (e.g.
+/// centimeters), see [`Tolerances::scaled_to_units`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tolerances {
+  /// The relative tolerance when deciding whether an agent's velocity should
+  /// be projected onto the cut-off sphere or the cut-off shadow. Velocities
+  /// within this tolerance of the tangent ring are projected onto the cut-off
+  /// sphere, since both projections agree near the ring.
+  pub tangent_epsilon: f32,
+  /// The relative tolerance for agents that are nearly touching. If the gap
+  /// between the squared distance and the squared sum of radii is within this
+  /// fraction of the squared sum of radii, the cut-off shadow is treated as
+  /// the half-space facing away from the neighbour, instead of computing a
+  /// (numerically unstable) tangent cone.
+  pub touching_epsilon: f32,
+  /// The squared length below which a vector is considered too short to
+  /// normalize.
+  pub normalization_epsilon: f32,
+  /// The epsilon used by the linear program to determine whether constraints
+  /// are parallel or violated.
+  pub solver_epsilon: f32,
+}
+
+impl Default for Tolerances {
+  fn default() -> Self {
+    Self {
+      tangent_epsilon: 1e-5,
+      touching_epsilon: 1e-4,
+      normalization_epsilon: 1e-12,
+      solver_epsilon: RVO_EPSILON,
+    }
+  }
 }
 
+impl Tolerances {
+  /// The tolerances matching RVO2-3D, which performs exact comparisons when
+  /// constructing planes.
+  pub fn rvo2() -> Self {
+    Self {
+      tangent_epsilon: 0.0,
+      touching_epsilon: 0.0,
+      normalization_epsilon: 0.0,
+      solver_epsilon: RVO_EPSILON,
+    }
+  }
+}
+
+/// The result of [`Agent::compute_avoiding_velocity_with_info`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct AvoidanceResult {
+  /// The avoiding velocity.
+  pub velocity: Vec3,
+  /// Whether the preferred velocity was longer than the max speed, and so had
+  /// to be clamped.
+  pub speed_clamped: bool,
+  /// Whether the preferred velocity (after clamping to the max speed) was
+  /// valid. If true, `velocity` is the clamped preferred velocity.
+  pub preferred_velocity_feasible: bool,
+  /// The distance from the preferred velocity to `velocity`. Large values mean
+  /// the agent is being forced off course, and may want to replan.
+  pub deviation: f32,
+  /// Whether the solver reached its limits (see
+  /// [`AvoidanceOptions::solver_limits`]), so `velocity` is only the best
+  /// velocity found before stopping.
+  pub solver_limit_reached: bool,
+}
+
+/// The result of [`Agent::compute_avoiding_velocity_for_candidates`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct CandidateChoice {
+  /// The avoiding velocity for the chosen candidate.
+  pub velocity: Vec3,
+  /// The index of the chosen candidate in the preferred velocities.
+  pub candidate_index: usize,
+}
+
+/// How close the avoiding velocity must be to a candidate preferred velocity
+/// for the candidate to be considered unobstructed.
+const UNOBSTRUCTED_CANDIDATE_TOLERANCE: f32 = 1e-4;
+
 impl Agent {
   /// Computes a velocity based off the agent's preferred velocity (usually the
   /// direction to its current goal/waypoint). This new velocity is intended to
-  /// avoid running into the agent's `neighbours`. This is not always possible,
-  /// but agents will attempt to resolve any collisions in a reasonable fashion.
-  /// The `max_speed` is the maximum magnitude of the returned velocity. Even if
-  /// the `preferred_velocity` is larger than `max_speed`, the resulting vector
-  /// will be at most `max_speed` in length. The `time_step` helps determine the
-  /// velocity in cases of existing collisions, and must be positive.
+  /// avoid running into the agent's `neighbours` and `obstacles`. This is not
+  /// always possible, but agents will attempt to resolve any collisions in a
+  /// reasonable fashion. The `max_speed` is the maximum magnitude of the
+  /// returned velocity. Even if the `preferred_velocity` is larger than
+  /// `max_speed`, the resulting vector will be at most `max_speed` in length.
+  /// The `time_step` helps determine the velocity in cases of existing
+  /// collisions, and must be positive.
   pub fn compute_avoiding_velocity(
     &self,
     neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec3 {
+    self
+      .compute_avoiding_velocity_internal(
+        neighbours,
+        obstacles,
+        Vec::new(),
+        preferred_velocity,
+        max_speed,
+        time_step,
+        avoidance_options,
+      )
+      .0
+  }
+
+  /// Same as [`Self::compute_avoiding_velocity`], but additionally describes
+  /// how the avoiding velocity relates to the `preferred_velocity`. This can be
+  /// used to detect when the agent is being forced off course.
+  pub fn compute_avoiding_velocity_with_info(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> AvoidanceResult {
+    let constraints = self.build_constraints(
+      neighbours,
+      obstacles,
+      time_step,
+      avoidance_options,
+    );
+    let (velocity, solver_limit_reached) =
+      constraints.solve_within_limits(preferred_velocity, max_speed);
+
+    let clamped_preferred_velocity =
+      preferred_velocity.clamp_length_max(max_speed);
+    AvoidanceResult {
+      velocity,
+      speed_clamped: preferred_velocity.length_squared()
+        > max_speed * max_speed,
+      preferred_velocity_feasible: constraints
+        .is_valid(clamped_preferred_velocity),
+      deviation: preferred_velocity.distance(velocity),
+      solver_limit_reached,
+    }
+  }
+
+  /// Same as [`Self::compute_avoiding_velocity`], but for an agent walking on
+  /// `terrain`. The `preferred_velocity` is projected onto the terrain's
+  /// tangent plane at the agent's position, and the resulting velocity is
+  /// restricted to that tangent plane, so the agent never tries to dodge
+  /// vertically off the ground.
+  #[allow(clippy::too_many_arguments)]
+  pub fn compute_avoiding_velocity_on_terrain(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    terrain: &dyn Terrain,
     preferred_velocity: Vec3,
     max_speed: f32,
     time_step: f32,
     avoidance_options: &AvoidanceOptions,
   ) -> Vec3 {
+    let ground_normal = terrain.normal_at(self.position.x, self.position.z);
+
     self
       .compute_avoiding_velocity_internal(
         neighbours,
+        obstacles,
+        terrain::get_planes_for_tangent(ground_normal).to_vec(),
+        terrain::project_onto_tangent(preferred_velocity, ground_normal),
+        max_speed,
+        time_step,
+        avoidance_options,
+      )
+      .0
+  }
+
+  /// Same as [`Self::compute_avoiding_velocity`], but for an agent assigned to
+  /// the traffic `lane`. The resulting velocity keeps the agent in the lane's
+  /// altitude band (using the obstacle time horizon) and never moves against
+  /// the lane's direction.
+  #[allow(clippy::too_many_arguments)]
+  pub fn compute_avoiding_velocity_in_lane(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    lane: &TrafficLane,
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec3 {
+    self
+      .compute_avoiding_velocity_internal(
+        neighbours,
+        obstacles,
+        traffic::get_planes_for_lane(
+          self,
+          lane,
+          avoidance_options.obstacle_time_horizon,
+        ),
         preferred_velocity,
         max_speed,
         time_step,
@@ -89,12 +408,313 @@ impl Agent {
       .0
   }
 
+  /// Same as [`Self::compute_avoiding_velocity`], but keeps the components of
+  /// the velocity for the `locked_axes` at zero (e.g. the Y axis for ground
+  /// units). The locked components are constraints of the linear program, so
+  /// the other components still avoid collisions correctly.
+  #[allow(clippy::too_many_arguments)]
+  pub fn compute_avoiding_velocity_with_locked_axes(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    locked_axes: BVec3,
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec3 {
+    let (velocity, _) = self.compute_avoiding_velocity_internal(
+      neighbours,
+      obstacles,
+      axis_locks::get_planes_for_locked_axes(locked_axes),
+      axis_locks::zero_locked_axes(preferred_velocity, locked_axes),
+      max_speed,
+      time_step,
+      avoidance_options,
+    );
+    axis_locks::zero_locked_axes(velocity, locked_axes)
+  }
+
+  /// Same as [`Self::compute_avoiding_velocity`], but accounts for uncertainty
+  /// in the state of the `neighbours`. Each neighbour has a corresponding
+  /// entry in `neighbour_uncertainties`. The plane induced by each neighbour is
+  /// offset so that (assuming Gaussian errors) the chance of the resulting
+  /// velocity violating it is at most `max_collision_probability`, which must
+  /// be positive. Smaller probabilities result in more conservative avoidance.
+  #[allow(clippy::too_many_arguments)]
+  pub fn compute_avoiding_velocity_with_uncertainty(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    neighbour_uncertainties: &[NeighbourUncertainty],
+    max_collision_probability: f32,
+    obstacles: &[Cow<'_, Obstacle>],
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec3 {
+    assert_eq!(
+      neighbours.len(),
+      neighbour_uncertainties.len(),
+      "every neighbour must have an uncertainty"
+    );
+    let confidence_factor =
+      uncertainty::get_confidence_factor(max_collision_probability);
+
+    let neighbour_planes = self
+      .get_planes_for_neighbours(neighbours, time_step, avoidance_options)
+      .into_iter()
+      .zip(neighbour_uncertainties)
+      .map(|(plane, uncertainty)| {
+        uncertainty::offset_plane_for_uncertainty(
+          plane,
+          uncertainty,
+          avoidance_options.time_horizon,
+          confidence_factor,
+        )
+      })
+      .collect::<Vec<_>>();
+
+    self
+      .solve_for_planes(
+        obstacles,
+        Vec::new(),
+        &neighbour_planes,
+        preferred_velocity,
+        max_speed,
+        avoidance_options,
+      )
+      .0
+  }
+
+  /// Same as [`Self::compute_avoiding_velocity`], but for an agent whose
+  /// extent changes over time as it rotates (e.g. a helicopter's rotor disc
+  /// tilting, or a swinging boom). `swept_radius(t)` is the agent's radius `t`
+  /// seconds from now. The agent avoids neighbours using the largest radius
+  /// it has over the time horizon, and keeps the obstacle margin plus however
+  /// much its largest radius over the obstacle time horizon exceeds its
+  /// `radius` from obstacles. The largest radius is found by sampling
+  /// `swept_radius` at [`SWEPT_RADIUS_SAMPLE_COUNT`] evenly spaced times.
+  #[allow(clippy::too_many_arguments)]
+  pub fn compute_avoiding_velocity_with_swept_radius(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    swept_radius: &dyn Fn(f32) -> f32,
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec3 {
+    let swept_agent = Agent {
+      radius: get_max_swept_radius(
+        swept_radius,
+        avoidance_options.time_horizon,
+      ),
+      ..self.clone()
+    };
+    let obstacle_radius = get_max_swept_radius(
+      swept_radius,
+      avoidance_options.obstacle_time_horizon,
+    );
+    let swept_avoidance_options = AvoidanceOptions {
+      obstacle_margin: avoidance_options.obstacle_margin
+        + (obstacle_radius - self.radius).max(0.0),
+      ..avoidance_options.clone()
+    };
+    swept_agent.compute_avoiding_velocity(
+      neighbours,
+      obstacles,
+      preferred_velocity,
+      max_speed,
+      time_step,
+      &swept_avoidance_options,
+    )
+  }
+
+  /// Same as [`Self::compute_avoiding_velocity`], but picks the valid velocity
+  /// with the lowest `cost` instead of the valid velocity closest to the
+  /// `preferred_velocity`. This allows arbitrary preferences (e.g. preferring
+  /// to pass on the right). Since `cost` can be any function, the valid
+  /// velocities are sampled: the candidates are the velocity closest to the
+  /// `preferred_velocity`, and `sample_count` velocities spread throughout the
+  /// valid region (along with their closest valid velocities). The
+  /// `objective_weights` of `avoidance_options` are ignored. If there are no
+  /// valid velocities, this falls back to the result of
+  /// [`Self::compute_avoiding_velocity`].
+  #[allow(clippy::too_many_arguments)]
+  pub fn compute_avoiding_velocity_with_cost(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    preferred_velocity: Vec3,
+    cost: &dyn Fn(Vec3) -> f32,
+    sample_count: usize,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec3 {
+    let neighbour_planes =
+      self.get_planes_for_neighbours(neighbours, time_step, avoidance_options);
+    let (planes, rigid_plane_count) = self.collect_planes(
+      obstacles,
+      Vec::new(),
+      &neighbour_planes,
+      avoidance_options,
+    );
+
+    sampling::select_lowest_cost_velocity(
+      &planes,
+      rigid_plane_count,
+      max_speed,
+      preferred_velocity,
+      cost,
+      sample_count,
+      avoidance_options.tolerances.solver_epsilon,
+      &avoidance_options.solver_limits,
+    )
+  }
+
+  /// Same as [`Self::compute_avoiding_velocity`], but chooses between several
+  /// `preferred_velocities` ranked from most to least preferred (e.g. going
+  /// straight, detouring left, then detouring right). The first candidate that
+  /// is unobstructed (i.e. its avoiding velocity is the candidate itself,
+  /// limited to `max_speed`) is chosen. If every candidate is obstructed, the
+  /// candidate whose avoiding velocity deviates the least from it is chosen.
+  /// `preferred_velocities` must not be empty.
+  pub fn compute_avoiding_velocity_for_candidates(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    preferred_velocities: &[Vec3],
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> CandidateChoice {
+    assert!(
+      !preferred_velocities.is_empty(),
+      "preferred_velocities must not be empty"
+    );
+
+    let constraints = self.build_constraints(
+      neighbours,
+      obstacles,
+      time_step,
+      avoidance_options,
+    );
+
+    let mut best_choice = None;
+    let mut best_deviation = f32::INFINITY;
+    for (candidate_index, &preferred_velocity) in
+      preferred_velocities.iter().enumerate()
+    {
+      let velocity = constraints.solve(preferred_velocity, max_speed);
+      let deviation =
+        velocity.distance(preferred_velocity.clamp_length_max(max_speed));
+      let choice = CandidateChoice { velocity, candidate_index };
+      if deviation <= UNOBSTRUCTED_CANDIDATE_TOLERANCE {
+        return choice;
+      }
+      if deviation < best_deviation {
+        best_deviation = deviation;
+        best_choice = Some(choice);
+      }
+    }
+
+    best_choice.expect("preferred_velocities is not empty")
+  }
+
+  /// Same as [`Self::compute_avoiding_velocity`], but looks a few steps ahead
+  /// instead of only avoiding the current situation. Several candidate first
+  /// velocities are each rolled out over a few internal steps (assuming
+  /// neighbours keep their current velocities), and the first velocity of the
+  /// rollout that stays closest to the `preferred_velocity` overall is
+  /// returned. This avoids some of the myopic choices of plain ORCA (e.g.
+  /// dodging into the path of a crossing agent at an intersection), at the
+  /// cost of `candidate_count * rollout_steps` solves. See
+  /// [`LookaheadOptions`].
+  #[allow(clippy::too_many_arguments)]
+  pub fn compute_avoiding_velocity_with_lookahead(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+    lookahead_options: &LookaheadOptions,
+  ) -> Vec3 {
+    lookahead::select_lookahead_velocity(
+      self,
+      neighbours,
+      obstacles,
+      preferred_velocity,
+      max_speed,
+      time_step,
+      avoidance_options,
+      lookahead_options,
+    )
+  }
+
+  /// Builds the constraints induced by `neighbours` and `obstacles`, without
+  /// solving for the avoiding velocity. The arguments match
+  /// [`Self::compute_avoiding_velocity`]. The avoiding velocity for any
+  /// preferred velocity and max speed can then be computed cheaply using
+  /// [`ConstraintSet::solve`].
+  pub fn build_constraints(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> ConstraintSet {
+    let neighbour_planes =
+      self.get_planes_for_neighbours(neighbours, time_step, avoidance_options);
+    self.build_constraints_for_planes(
+      obstacles,
+      Vec::new(),
+      &neighbour_planes,
+      avoidance_options,
+    )
+  }
+
+  /// Same as [`Self::build_constraints`], but reuses the planes in `cache` for
+  /// neighbours whose state relative to this agent barely changed since the
+  /// cache was last used. Each neighbour has a corresponding handle in
+  /// `neighbour_keys`, which identifies it between frames. The `cache` must
+  /// only be used for this agent.
+  pub fn build_constraints_with_cache<K: Hash + Eq + Clone>(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    neighbour_keys: &[K],
+    obstacles: &[Cow<'_, Obstacle>],
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+    cache: &mut ConstraintCache<K>,
+  ) -> ConstraintSet {
+    let neighbour_planes = cache.get_planes_for_neighbours(
+      self,
+      neighbours,
+      neighbour_keys,
+      time_step,
+      avoidance_options,
+    );
+    self.build_constraints_for_planes(
+      obstacles,
+      Vec::new(),
+      &neighbour_planes,
+      avoidance_options,
+    )
+  }
+
   #[cfg(feature = "debug")]
   /// Same as [`Self::compute_avoiding_velocity`], but additionally provides
-  /// debug data in the form of the plane constraints generated by each agent.
+  /// debug data in the form of the plane constraints generated by each agent
+  /// and obstacle.
   pub fn compute_avoiding_velocity_with_debug(
     &self,
     neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
     preferred_velocity: Vec3,
     max_speed: f32,
     time_step: f32,
@@ -102,6 +722,8 @@ impl Agent {
   ) -> (Vec3, Vec<Plane>) {
     self.compute_avoiding_velocity_internal(
       neighbours,
+      obstacles,
+      Vec::new(),
       preferred_velocity,
       max_speed,
       time_step,
@@ -109,39 +731,282 @@ impl Agent {
     )
   }
 
-  /// The implementation of [`Self::compute_avoiding_velocity`].
-  fn compute_avoiding_velocity_internal(
+  /// Same as [`Self::compute_avoiding_velocity`], but records the decisions
+  /// made along the way: how the plane for each neighbour was constructed
+  /// (including the split of responsibility), and how the linear program was
+  /// solved. This is slower, so it is only meant for investigating a single
+  /// agent (e.g. for a bug report). Print the result for a narrative log.
+  pub fn explain_avoiding_velocity(
     &self,
     neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
     preferred_velocity: Vec3,
     max_speed: f32,
     time_step: f32,
     avoidance_options: &AvoidanceOptions,
-  ) -> (Vec3, Vec<Plane>) {
+  ) -> Explanation {
     assert!(time_step > 0.0, "time_step must be positive, was {}", time_step);
-
-    let planes = neighbours
+    let plane_tolerances = get_plane_tolerances(avoidance_options);
+    let neighbour_explanations = neighbours
       .iter()
       .map(|neighbour| {
-        self.get_plane_for_neighbour(
+        self.explain_plane_for_neighbour(
           neighbour,
           avoidance_options.time_horizon,
           time_step,
+          &plane_tolerances,
+          avoidance_options.strict_rvo2_compatibility,
+          avoidance_options.get_responsibility_override(self, neighbour),
+          &mut rand::rng(),
         )
       })
-      .collect::<Vec<Plane>>();
+      .collect::<Vec<_>>();
+    let neighbour_planes = neighbour_explanations
+      .iter()
+      .map(|explanation| explanation.plane.clone())
+      .collect::<Vec<_>>();
+    let constraints = self.build_constraints_for_planes(
+      obstacles,
+      Vec::new(),
+      &neighbour_planes,
+      avoidance_options,
+    );
+    let (velocity, solver_limit_reached, outcome) =
+      constraints.solve_with_outcome(preferred_velocity, max_speed);
+    Explanation {
+      neighbours: neighbour_explanations,
+      obstacle_plane_count: constraints.rigid_plane_count,
+      outcome,
+      solver_limit_reached,
+      velocity,
+    }
+  }
+
+  /// The implementation of [`Self::compute_avoiding_velocity`]. The
+  /// `rigid_planes` must always be satisfied, and are never relaxed if the
+  /// problem is infeasible. These must be trivially satisfiable by a velocity
+  /// of zero. The returned planes start with the `rigid_planes`, followed by
+  /// the planes for `obstacles` (which are also rigid).
+  #[allow(clippy::too_many_arguments)]
+  fn compute_avoiding_velocity_internal(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, Obstacle>],
+    rigid_planes: Vec<Plane>,
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> (Vec3, Vec<Plane>) {
+    let neighbour_planes =
+      self.get_planes_for_neighbours(neighbours, time_step, avoidance_options);
+    self.solve_for_planes(
+      obstacles,
+      rigid_planes,
+      &neighbour_planes,
+      preferred_velocity,
+      max_speed,
+      avoidance_options,
+    )
+  }
+
+  /// Solves for the avoiding velocity given the already computed
+  /// `neighbour_planes`. See [`Self::compute_avoiding_velocity_internal`].
+  fn solve_for_planes(
+    &self,
+    obstacles: &[Cow<'_, Obstacle>],
+    rigid_planes: Vec<Plane>,
+    neighbour_planes: &[Plane],
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> (Vec3, Vec<Plane>) {
+    let constraints = self.build_constraints_for_planes(
+      obstacles,
+      rigid_planes,
+      neighbour_planes,
+      avoidance_options,
+    );
+    (constraints.solve(preferred_velocity, max_speed), constraints.planes)
+  }
+
+  /// Creates the [`ConstraintSet`] from the `rigid_planes`, the planes for
+  /// `obstacles`, and the `neighbour_planes`.
+  fn build_constraints_for_planes(
+    &self,
+    obstacles: &[Cow<'_, Obstacle>],
+    rigid_planes: Vec<Plane>,
+    neighbour_planes: &[Plane],
+    avoidance_options: &AvoidanceOptions,
+  ) -> ConstraintSet {
+    let (planes, rigid_plane_count) = self.collect_planes(
+      obstacles,
+      rigid_planes,
+      neighbour_planes,
+      avoidance_options,
+    );
+    ConstraintSet {
+      planes,
+      rigid_plane_count,
+      current_velocity: self.velocity,
+      objective_weights: avoidance_options.objective_weights.clone(),
+      solver_epsilon: avoidance_options.tolerances.solver_epsilon,
+      solver_limits: avoidance_options.solver_limits.clone(),
+    }
+  }
+
+  /// Combines the `rigid_planes`, the planes for `obstacles`, and the
+  /// `neighbour_planes` into the constraints of the linear program. Returns the
+  /// constraints and the number of rigid constraints at the start.
+  fn collect_planes(
+    &self,
+    obstacles: &[Cow<'_, Obstacle>],
+    rigid_planes: Vec<Plane>,
+    neighbour_planes: &[Plane],
+    avoidance_options: &AvoidanceOptions,
+  ) -> (Vec<Plane>, usize) {
+    let mut planes = rigid_planes;
+    // Obstacle planes never depend on the agent's velocity and are always
+    // satisfied by a velocity of zero, so they can be treated as rigid.
+    planes.extend(obstacles.iter().flat_map(|obstacle| {
+      get_planes_for_agent_to_obstacle(
+        self,
+        obstacle,
+        avoidance_options.obstacle_margin,
+        avoidance_options.obstacle_time_horizon,
+      )
+    }));
+    let rigid_plane_count = planes.len();
+    planes.extend_from_slice(neighbour_planes);
+    (planes, rigid_plane_count)
+  }
+
+  #[cfg(feature = "debug")]
+  /// Computes the plane constraints induced by `neighbours` without solving
+  /// for the avoiding velocity. The avoiding velocity can then be computed
+  /// using [`solve_linear_program`].
+  pub fn compute_planes_for_neighbours(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec<Plane> {
+    self.get_planes_for_neighbours(neighbours, time_step, avoidance_options)
+  }
 
-    (solve_linear_program(&planes, max_speed, preferred_velocity), planes)
+  /// Creates the planes describing the half-spaces of valid velocities that
+  /// should not collide with each of the `neighbours`.
+  fn get_planes_for_neighbours(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec<Plane> {
+    let mut planes = Vec::with_capacity(neighbours.len());
+    self.extend_planes_for_neighbours(
+      neighbours,
+      time_step,
+      avoidance_options,
+      &mut rand::rng(),
+      &mut planes,
+    );
+    planes
+  }
+
+  /// Same as [`Self::get_planes_for_neighbours`], but appends the planes to
+  /// `planes` (so callers can reuse a buffer), and uses `rng` to break ties
+  /// (see [`Self::get_plane_for_neighbour_with_rng`]).
+  fn extend_planes_for_neighbours(
+    &self,
+    neighbours: &[Cow<'_, Agent>],
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+    rng: &mut dyn RngCore,
+    planes: &mut Vec<Plane>,
+  ) {
+    assert!(time_step > 0.0, "time_step must be positive, was {}", time_step);
+
+    let plane_tolerances = get_plane_tolerances(avoidance_options);
+
+    planes.extend(neighbours.iter().map(|neighbour| {
+      self.get_plane_for_neighbour_with_rng(
+        neighbour,
+        avoidance_options.time_horizon,
+        time_step,
+        &plane_tolerances,
+        avoidance_options.strict_rvo2_compatibility,
+        avoidance_options.get_responsibility_override(self, neighbour),
+        rng,
+      )
+    }));
   }
 
   /// Creates a plane to describe the half-space of valid velocities that should
-  /// not collide with `neighbour`.
+  /// not collide with `neighbour`. If `strict_rvo2_compatibility` is true, the
+  /// responsibility is split evenly as in RVO2-3D. If `responsibility_override`
+  /// is set, the agent takes that share of the responsibility instead (see
+  /// [`ResponsibilityOverride`]).
   fn get_plane_for_neighbour(
     &self,
     neighbour: &Agent,
     time_horizon: f32,
     time_step: f32,
+    tolerances: &Tolerances,
+    strict_rvo2_compatibility: bool,
+    responsibility_override: Option<f32>,
+  ) -> Plane {
+    self.get_plane_for_neighbour_with_rng(
+      neighbour,
+      time_horizon,
+      time_step,
+      tolerances,
+      strict_rvo2_compatibility,
+      responsibility_override,
+      &mut rand::rng(),
+    )
+  }
+
+  /// Same as [`Self::get_plane_for_neighbour`], but uses `rng` to pick the
+  /// direction to push apart in when the agent and `neighbour` are colliding
+  /// and the direction is otherwise undefined (e.g. they exactly overlap with
+  /// the same velocity). This is the only use of randomness in avoidance.
+  #[allow(clippy::too_many_arguments)]
+  fn get_plane_for_neighbour_with_rng(
+    &self,
+    neighbour: &Agent,
+    time_horizon: f32,
+    time_step: f32,
+    tolerances: &Tolerances,
+    strict_rvo2_compatibility: bool,
+    responsibility_override: Option<f32>,
+    rng: &mut dyn RngCore,
   ) -> Plane {
+    self
+      .explain_plane_for_neighbour(
+        neighbour,
+        time_horizon,
+        time_step,
+        tolerances,
+        strict_rvo2_compatibility,
+        responsibility_override,
+        rng,
+      )
+      .plane
+  }
+
+  /// Same as [`Self::get_plane_for_neighbour_with_rng`], but also describes
+  /// how the plane was constructed.
+  #[allow(clippy::too_many_arguments)]
+  fn explain_plane_for_neighbour(
+    &self,
+    neighbour: &Agent,
+    time_horizon: f32,
+    time_step: f32,
+    tolerances: &Tolerances,
+    strict_rvo2_compatibility: bool,
+    responsibility_override: Option<f32>,
+    rng: &mut dyn RngCore,
+  ) -> NeighbourExplanation {
     // There are two parts to the velocity obstacle induced by `neighbour`.
     // 1) The cut-off sphere. This is where the agent collides with `neighbour`
     // after some time (either `time_horizon` or `time_step`).
@@ -162,6 +1027,18 @@ impl Agent {
     let vo_normal;
     let relative_velocity_projected_to_vo;
     let inside_vo;
+    let branch;
+
+    // The direction to use if a normal cannot be computed otherwise. Pushing
+    // away from the neighbour is always a reasonable choice.
+    let away_from_neighbour = {
+      let recip = relative_neighbour_position.length_recip();
+      if recip.is_finite() && recip > 0.0 {
+        -relative_neighbour_position * recip
+      } else {
+        Vec3::ZERO
+      }
+    };
 
     // Find out if the agent is inside the cut-off sphere. Note: since both the
     // distance to the cut-off sphere and the radius of the cut-off sphere is
@@ -190,9 +1067,12 @@ impl Agent {
       let dot = cutoff_sphere_center_to_relative_velocity
         .dot(relative_neighbour_position);
 
+      let tangent_ring_triangle_leg_squared =
+        distance_squared - sum_radius_squared;
+
       // TODO: Figure out why this works.
       if dot < 0.0
-        && dot * dot
+        && dot * dot * (1.0 + tolerances.tangent_epsilon)
           > sum_radius_squared
             * cutoff_sphere_center_to_relative_velocity_length_squared
       {
@@ -201,12 +1081,38 @@ impl Agent {
 
         let cutoff_sphere_radius = sum_radius / time_horizon;
 
-        vo_normal =
-          cutoff_sphere_center_to_relative_velocity.normalize_or_zero();
+        // The relative velocity can only be at the center of the cut-off
+        // sphere if it is deep inside the velocity obstacle, so pushing away
+        // from the neighbour is the natural choice.
+        vo_normal = if cutoff_sphere_center_to_relative_velocity_length_squared
+          > tolerances.normalization_epsilon
+        {
+          normalize_with_length_squared(
+            cutoff_sphere_center_to_relative_velocity,
+            cutoff_sphere_center_to_relative_velocity_length_squared,
+          )
+        } else {
+          away_from_neighbour
+        };
         relative_velocity_projected_to_vo =
           vo_normal * cutoff_sphere_radius + cutoff_sphere_center;
         inside_vo = cutoff_sphere_center_to_relative_velocity_length_squared
           < cutoff_sphere_radius * cutoff_sphere_radius;
+        branch = PlaneBranch::CutoffSphere;
+      } else if tangent_ring_triangle_leg_squared
+        <= tolerances.touching_epsilon * sum_radius_squared
+      {
+        // The agents are nearly touching, so the cut-off shadow is nearly a
+        // half-space through the origin facing away from the neighbour.
+        // Computing the tangent cone here would divide by nearly zero, causing
+        // the plane to flip between frames.
+        vo_normal = away_from_neighbour;
+        let distance_to_plane = Plane { normal: vo_normal, point: Vec3::ZERO }
+          .signed_distance_to_plane(relative_agent_velocity);
+        inside_vo = distance_to_plane < 0.0;
+        relative_velocity_projected_to_vo =
+          relative_agent_velocity - distance_to_plane * vo_normal;
+        branch = PlaneBranch::NearlyTouching;
       } else {
         // The relative velocity is past the cut-off sphere tangent ring, so
         // project onto the shadow (which is a cone). Note this means we can
@@ -227,8 +1133,6 @@ impl Agent {
         // distance between the rays, the intersection point of the tangent
         // plane's normal and the relative_agent_velocity ray, and the
         // projection of that point onto the relative_neighbour_position ray.
-        let tangent_ring_triangle_leg_squared =
-          distance_squared - sum_radius_squared;
 
         let squared_distance_between_rays = relative_neighbour_position
           .cross(relative_agent_velocity)
@@ -249,16 +1153,36 @@ impl Agent {
           - squared_distance_between_rays / tangent_ring_triangle_leg_squared;
         // Always choose the negative solution, since we know the intersection
         // point must be behind us (since if it was ahead, we should have
-        // projected to the cutoff sphere instead).
-        let t = (-b - (b * b - a * c).sqrt()) / a;
+        // projected to the cutoff sphere instead). The discriminant can only be
+        // negative due to floating point error, so clamp it.
+        let t = (-b - (b * b - a * c).max(0.0).sqrt()) / a;
 
-        vo_normal = (relative_agent_velocity + t * relative_neighbour_position)
-          .normalize_or_zero();
-        let distance_to_plane = Plane { normal: vo_normal, point: Vec3::ZERO }
-          .signed_distance_to_plane(relative_agent_velocity);
-        inside_vo = distance_to_plane < 0.0;
-        relative_velocity_projected_to_vo =
-          relative_agent_velocity - distance_to_plane * vo_normal;
+        let normal = relative_agent_velocity + t * relative_neighbour_position;
+        let normal_length_squared = normal.length_squared();
+        vo_normal = if normal_length_squared > tolerances.normalization_epsilon
+        {
+          normalize_with_length_squared(normal, normal_length_squared)
+        } else {
+          away_from_neighbour
+        };
+        if strict_rvo2_compatibility {
+          // RVO2-3D measures the distance to the cone along the normal from
+          // the cone's axis (at `-t * relative_neighbour_position`), instead of
+          // the distance to the tangent plane. These only agree when the
+          // relative velocity is on the cone.
+          let distance_to_cone = sum_radius * -t - normal_length_squared.sqrt();
+          inside_vo = distance_to_cone > 0.0;
+          relative_velocity_projected_to_vo =
+            relative_agent_velocity + distance_to_cone * vo_normal;
+        } else {
+          let distance_to_plane =
+            Plane { normal: vo_normal, point: Vec3::ZERO }
+              .signed_distance_to_plane(relative_agent_velocity);
+          inside_vo = distance_to_plane < 0.0;
+          relative_velocity_projected_to_vo =
+            relative_agent_velocity - distance_to_plane * vo_normal;
+        }
+        branch = PlaneBranch::CutoffShadow;
       }
     } else {
       // Collision. Project on cut-off sphere at time `time_step`.
@@ -276,14 +1200,17 @@ impl Agent {
         // If the vector has a length of zero, pick a random direction. Fork the
         // implementation of `normalize_or` so we only compute random
         // values if necessary (which should be very rare).
-        let recip = velocity_from_circle_center.length_recip();
-        if recip.is_finite() && recip > 0.0 {
-          velocity_from_circle_center * recip
+        let length_squared = velocity_from_circle_center.length_squared();
+        if length_squared > tolerances.normalization_epsilon {
+          normalize_with_length_squared(
+            velocity_from_circle_center,
+            length_squared,
+          )
         } else {
           // Generate uniform random point based on
           // https://math.stackexchange.com/a/1586015
-          let z: f32 = rand::random();
-          let longitude: f32 = rand::random();
+          let z: f32 = rng.random_range(-1.0..=1.0);
+          let longitude: f32 = rng.random_range(0.0..std::f32::consts::TAU);
 
           let z_normalize = (1.0 - z * z).sqrt();
           Vec3::new(
@@ -298,23 +1225,99 @@ impl Agent {
       relative_velocity_projected_to_vo =
         vo_normal * cutoff_sphere_radius + cutoff_sphere_center;
       inside_vo = true;
+      branch = PlaneBranch::Colliding;
     }
 
     // As in the paper, `u` is the vector from the relative velocity to the
     // nearest point outside the velocity obstacle.
     let u = relative_velocity_projected_to_vo - relative_agent_velocity;
 
-    let responsibility = if inside_vo {
-      self.avoidance_responsibility
-        / (self.avoidance_responsibility + neighbour.avoidance_responsibility)
+    let responsibility = if let Some(responsibility) = responsibility_override {
+      responsibility
+    } else if strict_rvo2_compatibility {
+      // RVO2-3D always assumes both agents share the responsibility evenly.
+      0.5
+    } else if inside_vo {
+      let total_responsibility =
+        self.avoidance_responsibility + neighbour.avoidance_responsibility;
+      if total_responsibility > 0.0 {
+        self.avoidance_responsibility / total_responsibility
+      } else {
+        // Neither agent wants to avoid, but someone has to.
+        0.5
+      }
     } else {
       1.0
     };
 
-    Plane { point: self.velocity + u * responsibility, normal: vo_normal }
+    NeighbourExplanation {
+      plane: Plane {
+        point: self.velocity + u * responsibility,
+        normal: vo_normal,
+      },
+      branch,
+      inside_velocity_obstacle: inside_vo,
+      responsibility,
+    }
   }
 }
 
+/// The number of times [`Agent::compute_avoiding_velocity_with_swept_radius`]
+/// samples the swept radius over the time horizon.
+pub const SWEPT_RADIUS_SAMPLE_COUNT: usize = 8;
+
+/// Finds the largest radius `swept_radius` produces over `time_horizon`, by
+/// sampling evenly spaced times (including now and the end of the horizon).
+fn get_max_swept_radius(
+  swept_radius: &dyn Fn(f32) -> f32,
+  time_horizon: f32,
+) -> f32 {
+  (0..SWEPT_RADIUS_SAMPLE_COUNT)
+    .map(|sample| {
+      swept_radius(
+        time_horizon * sample as f32 / (SWEPT_RADIUS_SAMPLE_COUNT - 1) as f32,
+      )
+    })
+    .fold(0.0, f32::max)
+}
+
+/// Determines the tolerances to use when creating planes for neighbours. In
+/// strict RVO2 compatibility mode, the RVO2 tolerances are used (except for
+/// the solver).
+fn get_plane_tolerances(avoidance_options: &AvoidanceOptions) -> Tolerances {
+  if avoidance_options.strict_rvo2_compatibility {
+    Tolerances {
+      solver_epsilon: avoidance_options.tolerances.solver_epsilon,
+      ..Tolerances::rvo2()
+    }
+  } else {
+    avoidance_options.tolerances.clone()
+  }
+}
+
+/// How much agents may overlap (as a fraction of their combined radii) before
+/// it counts as a collision. Tightly packed agents are often touching within
+/// floating point error, which is not a collision.
+pub(crate) const COLLISION_OVERLAP_TOLERANCE: f32 = 0.01;
+
+/// Whether `agent` and `other` overlap by more than
+/// [`COLLISION_OVERLAP_TOLERANCE`].
+pub(crate) fn are_colliding(agent: &Agent, other: &Agent) -> bool {
+  let radius =
+    (agent.radius + other.radius) * (1.0 - COLLISION_OVERLAP_TOLERANCE);
+  agent.position.distance_squared(other.position) < radius * radius
+}
+
 #[cfg(test)]
 #[path = "lib_test.rs"]
 mod test;
+
+// The traces are recorded without approximations, which change them enough to
+// drift in the scenarios with many symmetric conflicts.
+#[cfg(all(test, not(feature = "fast-math")))]
+#[path = "golden_test.rs"]
+mod golden_test;
+
+#[cfg(test)]
+#[path = "rvo2_conformance_test.rs"]
+mod rvo2_conformance_test;
@@ -38,14 +38,19 @@ mod get_plane_for_neighbour_tests {
     };
 
     let neighbour = Agent {
-      position: position,
+      position,
       velocity: Vec3::ZERO,
       radius: 1.0,
       avoidance_responsibility: 1.0,
     };
 
     let actual_plane = agent.get_plane_for_neighbour(
-      &neighbour, /* time_horizon= */ 1.0, /* time_step= */ 1.0,
+      &neighbour,
+      /* time_horizon= */ 1.0,
+      /* time_step= */ 1.0,
+      &Tolerances::default(),
+      /* strict_rvo2_compatibility= */ false,
+      /* responsibility_override= */ None,
     );
     // The agent's velocity projects directly onto the cut-off sphere.
     assert_plane_eq!(
@@ -74,7 +79,12 @@ mod get_plane_for_neighbour_tests {
     };
 
     let inside_shadow_plane = agent.get_plane_for_neighbour(
-      &neighbour, /* time_horizon= */ 1.0, /* time_step= */ 1.0,
+      &neighbour,
+      /* time_horizon= */ 1.0,
+      /* time_step= */ 1.0,
+      &Tolerances::default(),
+      /* strict_rvo2_compatibility= */ false,
+      /* responsibility_override= */ None,
     );
     assert_plane_eq!(
       inside_shadow_plane,
@@ -87,7 +97,12 @@ mod get_plane_for_neighbour_tests {
     agent.velocity = Vec3::new(10.0, -1.0, 0.0);
 
     let outside_shadow_plane = agent.get_plane_for_neighbour(
-      &neighbour, /* time_horizon= */ 1.0, /* time_step= */ 1.0,
+      &neighbour,
+      /* time_horizon= */ 1.0,
+      /* time_step= */ 1.0,
+      &Tolerances::default(),
+      /* strict_rvo2_compatibility= */ false,
+      /* responsibility_override= */ None,
     );
     assert_plane_eq!(
       outside_shadow_plane,
@@ -115,7 +130,12 @@ mod get_plane_for_neighbour_tests {
     };
 
     let collision_plane = agent.get_plane_for_neighbour(
-      &neighbour, /* time_horizon= */ 1.0, /* time_step= */ 0.5,
+      &neighbour,
+      /* time_horizon= */ 1.0,
+      /* time_step= */ 0.5,
+      &Tolerances::default(),
+      /* strict_rvo2_compatibility= */ false,
+      /* responsibility_override= */ None,
     );
     assert_plane_eq!(
       collision_plane,
@@ -145,7 +165,12 @@ mod get_plane_for_neighbour_tests {
     };
 
     let collision_plane = agent.get_plane_for_neighbour(
-      &neighbour, /* time_horizon= */ 2.0, /* time_step= */ 0.5,
+      &neighbour,
+      /* time_horizon= */ 2.0,
+      /* time_step= */ 0.5,
+      &Tolerances::default(),
+      /* strict_rvo2_compatibility= */ false,
+      /* responsibility_override= */ None,
     );
     assert_plane_eq!(
       collision_plane,
@@ -173,7 +198,12 @@ mod get_plane_for_neighbour_tests {
     };
 
     let actual_plane = agent.get_plane_for_neighbour(
-      &neighbour, /* time_horizon= */ 2.0, /* time_step= */ 0.5,
+      &neighbour,
+      /* time_horizon= */ 2.0,
+      /* time_step= */ 0.5,
+      &Tolerances::default(),
+      /* strict_rvo2_compatibility= */ false,
+      /* responsibility_override= */ None,
     );
     assert_plane_eq!(
       actual_plane,
@@ -201,7 +231,12 @@ mod get_plane_for_neighbour_tests {
     };
 
     let actual_plane = agent.get_plane_for_neighbour(
-      &neighbour, /* time_horizon= */ 2.0, /* time_step= */ 0.5,
+      &neighbour,
+      /* time_horizon= */ 2.0,
+      /* time_step= */ 0.5,
+      &Tolerances::default(),
+      /* strict_rvo2_compatibility= */ false,
+      /* responsibility_override= */ None,
     );
     assert_plane_eq!(
       actual_plane,
@@ -211,14 +246,54 @@ mod get_plane_for_neighbour_tests {
       }
     );
   }
+
+  #[test]
+  fn nearly_touching_agents_use_stable_half_space() {
+    let agent = Agent {
+      position: Vec3::ZERO,
+      velocity: Vec3::new(0.0, 1.0, 0.0),
+      radius: 1.0,
+      avoidance_responsibility: 1.0,
+    };
+
+    for gap in [1e-6, 1e-5, 1e-4] {
+      let neighbour = Agent {
+        position: Vec3::new(2.0 + gap, 0.0, 0.0),
+        velocity: Vec3::ZERO,
+        radius: 1.0,
+        avoidance_responsibility: 1.0,
+      };
+
+      let actual_plane = agent.get_plane_for_neighbour(
+        &neighbour,
+        /* time_horizon= */ 1.0,
+        /* time_step= */ 1.0,
+        &Tolerances::default(),
+        /* strict_rvo2_compatibility= */ false,
+        /* responsibility_override= */ None,
+      );
+      // The plane should not flip regardless of how close the agents are.
+      assert_plane_eq!(
+        actual_plane,
+        Plane {
+          point: Vec3::new(0.0, 1.0, 0.0),
+          normal: Vec3::new(-1.0, 0.0, 0.0),
+        }
+      );
+    }
+  }
 }
 
 mod compute_avoiding_velocity {
   use std::borrow::Cow;
 
-  use glam::Vec3;
+  use glam::{BVec3, Vec2, Vec3};
 
-  use crate::{Agent, AvoidanceOptions};
+  use crate::{
+    Agent, AvoidanceOptions, AvoidanceResult, CandidateChoice,
+    NeighbourUncertainty, ObjectiveWeights, Obstacle, SolverLimits, Tolerances,
+    TrafficLane,
+  };
 
   #[test]
   fn moves_apart_if_directly_on_top_of_each_other() {
@@ -231,14 +306,586 @@ mod compute_avoiding_velocity {
 
     let avoiding_velocity = agent.compute_avoiding_velocity(
       &[Cow::Owned(agent.clone())],
+      &[],
       /* preferred_velocity= */ Vec3::ZERO,
       /* max_speed= */ 2.0,
       /* time_step= */ 0.01,
-      &AvoidanceOptions { time_horizon: 1.0 },
+      &AvoidanceOptions {
+        obstacle_margin: 0.0,
+        time_horizon: 1.0,
+        obstacle_time_horizon: 1.0,
+        tolerances: Tolerances::default(),
+        strict_rvo2_compatibility: false,
+        objective_weights: ObjectiveWeights::default(),
+        solver_limits: SolverLimits::default(),
+        responsibility_override: None,
+      },
     );
 
     // Agents will move in a random direction if they are perfectly on top of
     // one another.
     assert_ne!(avoiding_velocity, Vec3::ZERO);
   }
+
+  #[test]
+  fn slows_down_for_walls() {
+    let agent = Agent {
+      position: Vec3::new(0.0, 0.0, -3.0),
+      velocity: Vec3::ZERO,
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    let wall = Obstacle::Open {
+      vertices: vec![Vec2::new(-5.0, 0.0), Vec2::new(5.0, 0.0)],
+      bottom: -5.0,
+      top: 5.0,
+    };
+
+    let avoiding_velocity = agent.compute_avoiding_velocity(
+      &[],
+      &[Cow::Owned(wall)],
+      /* preferred_velocity= */ Vec3::new(0.0, 0.0, 2.0),
+      /* max_speed= */ 2.0,
+      /* time_step= */ 0.01,
+      &AvoidanceOptions {
+        obstacle_margin: 1.0,
+        time_horizon: 1.0,
+        obstacle_time_horizon: 2.0,
+        tolerances: Tolerances::default(),
+        strict_rvo2_compatibility: false,
+        objective_weights: ObjectiveWeights::default(),
+        solver_limits: SolverLimits::default(),
+        responsibility_override: None,
+      },
+    );
+
+    // The agent can only cover the distance to the margin over the obstacle
+    // time horizon.
+    assert!(
+      avoiding_velocity.distance(Vec3::new(0.0, 0.0, 1.0)) < 1e-3,
+      "velocity {} should be slowed by the wall",
+      avoiding_velocity
+    );
+  }
+
+  #[test]
+  fn uncertain_neighbours_are_avoided_more() {
+    let agent = Agent {
+      position: Vec3::ZERO,
+      velocity: Vec3::new(1.0, 0.0, 0.0),
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    let neighbour = Agent {
+      position: Vec3::new(5.0, 0.0, 0.5),
+      velocity: Vec3::new(-1.0, 0.0, 0.0),
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    let avoidance_options = AvoidanceOptions {
+      obstacle_margin: 0.0,
+      time_horizon: 5.0,
+      obstacle_time_horizon: 1.0,
+      tolerances: Tolerances::default(),
+      strict_rvo2_compatibility: false,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: SolverLimits::default(),
+      responsibility_override: None,
+    };
+
+    let compute_velocity = |uncertainty: NeighbourUncertainty| {
+      agent.compute_avoiding_velocity_with_uncertainty(
+        &[Cow::Borrowed(&neighbour)],
+        &[uncertainty],
+        /* max_collision_probability= */ 0.01,
+        &[],
+        /* preferred_velocity= */ Vec3::new(1.0, 0.0, 0.0),
+        /* max_speed= */ 2.0,
+        /* time_step= */ 0.01,
+        &avoidance_options,
+      )
+    };
+
+    let certain_velocity = compute_velocity(NeighbourUncertainty::default());
+    let expected_velocity = agent.compute_avoiding_velocity(
+      &[Cow::Borrowed(&neighbour)],
+      &[],
+      /* preferred_velocity= */ Vec3::new(1.0, 0.0, 0.0),
+      /* max_speed= */ 2.0,
+      /* time_step= */ 0.01,
+      &avoidance_options,
+    );
+    assert!(certain_velocity.distance(expected_velocity) < 1e-5);
+
+    let uncertain_velocity = compute_velocity(NeighbourUncertainty {
+      position_covariance: glam::Mat3::from_diagonal(Vec3::splat(0.25)),
+      velocity_covariance: glam::Mat3::from_diagonal(Vec3::splat(0.01)),
+    });
+    // The neighbour is to the +z side, so more uncertainty means dodging
+    // further towards -z.
+    assert!(
+      uncertain_velocity.z < certain_velocity.z - 0.01,
+      "uncertain={} certain={}",
+      uncertain_velocity,
+      certain_velocity
+    );
+  }
+
+  #[test]
+  fn swept_radius_is_avoided_over_horizon() {
+    let agent = Agent {
+      position: Vec3::new(0.0, 0.0, -3.0),
+      velocity: Vec3::ZERO,
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    let wall = Obstacle::Open {
+      vertices: vec![Vec2::new(-5.0, 0.0), Vec2::new(5.0, 0.0)],
+      bottom: -5.0,
+      top: 5.0,
+    };
+    let avoidance_options = AvoidanceOptions {
+      obstacle_margin: 1.0,
+      time_horizon: 1.0,
+      obstacle_time_horizon: 2.0,
+      tolerances: Tolerances::default(),
+      strict_rvo2_compatibility: false,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: SolverLimits::default(),
+      responsibility_override: None,
+    };
+    let compute_velocity = |swept_radius: &dyn Fn(f32) -> f32| {
+      agent.compute_avoiding_velocity_with_swept_radius(
+        &[],
+        &[Cow::Owned(wall.clone())],
+        swept_radius,
+        /* preferred_velocity= */ Vec3::new(0.0, 0.0, 2.0),
+        /* max_speed= */ 2.0,
+        /* time_step= */ 0.01,
+        &avoidance_options,
+      )
+    };
+
+    // A constant radius behaves like a regular agent.
+    let velocity = compute_velocity(&|_| 0.5);
+    assert!(velocity.distance(Vec3::new(0.0, 0.0, 1.0)) < 1e-3, "{velocity}");
+
+    // A boom that swings out to a radius of 1.5 after 1 second keeps an extra
+    // unit away from the wall.
+    let velocity = compute_velocity(&|t| 0.5 + t.min(1.0));
+    assert!(velocity.distance(Vec3::new(0.0, 0.0, 0.5)) < 1e-3, "{velocity}");
+  }
+
+  #[test]
+  fn locked_axes_are_kept_at_zero() {
+    let agent = Agent {
+      position: Vec3::ZERO,
+      velocity: Vec3::new(0.0, 0.0, 1.0),
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    // Mostly above the agent, so the easiest way around it is down.
+    let neighbour = Agent {
+      position: Vec3::new(0.05, 0.15, 3.0),
+      velocity: Vec3::new(0.0, 0.0, -1.0),
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    let avoidance_options = AvoidanceOptions {
+      obstacle_margin: 0.0,
+      time_horizon: 3.0,
+      obstacle_time_horizon: 1.0,
+      tolerances: Tolerances::default(),
+      strict_rvo2_compatibility: false,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: SolverLimits::default(),
+      responsibility_override: None,
+    };
+    let compute_velocity = |locked_axes| {
+      agent.compute_avoiding_velocity_with_locked_axes(
+        &[Cow::Borrowed(&neighbour)],
+        &[],
+        locked_axes,
+        /* preferred_velocity= */ Vec3::new(0.0, 0.2, 1.0),
+        /* max_speed= */ 2.0,
+        /* time_step= */ 0.01,
+        &avoidance_options,
+      )
+    };
+
+    let velocity = compute_velocity(BVec3::FALSE);
+    assert!(velocity.y < -0.1, "{velocity}");
+
+    // With Y locked, the agent has to go around sideways instead.
+    let velocity = compute_velocity(BVec3::new(false, true, false));
+    assert_eq!(velocity.y, 0.0);
+    assert!(velocity.x < -0.1, "{velocity}");
+  }
+
+  #[test]
+  fn pathological_inputs_give_finite_velocities() {
+    let agent = |position, velocity, radius, avoidance_responsibility| Agent {
+      position,
+      velocity,
+      radius,
+      avoidance_responsibility,
+    };
+    let avoidance_options = AvoidanceOptions {
+      obstacle_margin: 0.1,
+      time_horizon: 2.0,
+      obstacle_time_horizon: 1.0,
+      tolerances: Tolerances::default(),
+      strict_rvo2_compatibility: false,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: SolverLimits::default(),
+      responsibility_override: None,
+    };
+    let cases = [
+      // Identical agents.
+      (
+        agent(Vec3::ZERO, Vec3::ZERO, 0.5, 1.0),
+        agent(Vec3::ZERO, Vec3::ZERO, 0.5, 1.0),
+        Vec3::X,
+        0.1,
+      ),
+      // Huge magnitudes.
+      (
+        agent(Vec3::splat(1e20), Vec3::splat(1e30), 1e30, 1.0),
+        agent(Vec3::splat(1e20) + Vec3::X, Vec3::splat(-1e30), 1e30, 1.0),
+        Vec3::splat(1e30),
+        0.1,
+      ),
+      // Denormals.
+      (
+        agent(Vec3::ZERO, Vec3::splat(1e-40), 1e-40, 1.0),
+        agent(Vec3::splat(1e-40), Vec3::ZERO, 1e-40, 1.0),
+        Vec3::X,
+        1e-40,
+      ),
+      // Colliding with a denormal time step.
+      (
+        agent(Vec3::ZERO, Vec3::X, 0.5, 1.0),
+        agent(Vec3::new(0.5, 0.0, 0.0), -Vec3::X, 0.5, 1.0),
+        Vec3::X,
+        1e-40,
+      ),
+      // Neither agent takes any responsibility.
+      (
+        agent(Vec3::ZERO, Vec3::X, 0.5, 0.0),
+        agent(Vec3::new(0.5, 0.0, 0.0), -Vec3::X, 0.5, 0.0),
+        Vec3::X,
+        0.1,
+      ),
+      // Non-finite neighbour and preferred velocity.
+      (
+        agent(Vec3::ZERO, Vec3::X, 0.5, 1.0),
+        agent(Vec3::NAN, Vec3::INFINITY, 0.5, 1.0),
+        Vec3::NAN,
+        0.1,
+      ),
+    ];
+    for (agent, neighbour, preferred_velocity, time_step) in cases {
+      let velocity = agent.compute_avoiding_velocity(
+        &[Cow::Borrowed(&neighbour)],
+        &[],
+        preferred_velocity,
+        /* max_speed= */ 1.0,
+        time_step,
+        &avoidance_options,
+      );
+      assert!(
+        velocity.is_finite(),
+        "{agent:?} avoiding {neighbour:?} gave {velocity}"
+      );
+    }
+  }
+
+  #[test]
+  fn picks_lowest_cost_velocity() {
+    let agent = Agent {
+      position: Vec3::ZERO,
+      velocity: Vec3::ZERO,
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    let wall = Obstacle::Open {
+      vertices: vec![Vec2::new(-5.0, 3.0), Vec2::new(5.0, 3.0)],
+      bottom: -5.0,
+      top: 5.0,
+    };
+
+    // Prefers moving along +z as fast as possible, but the wall limits that.
+    let avoiding_velocity = agent.compute_avoiding_velocity_with_cost(
+      &[],
+      &[Cow::Owned(wall)],
+      /* preferred_velocity= */ Vec3::ZERO,
+      &|velocity| -velocity.z,
+      /* sample_count= */ 32,
+      /* max_speed= */ 2.0,
+      /* time_step= */ 0.01,
+      &AvoidanceOptions {
+        obstacle_margin: 1.0,
+        time_horizon: 1.0,
+        obstacle_time_horizon: 2.0,
+        tolerances: Tolerances::default(),
+        strict_rvo2_compatibility: false,
+        objective_weights: ObjectiveWeights::default(),
+        solver_limits: SolverLimits::default(),
+        responsibility_override: None,
+      },
+    );
+    assert!(
+      (avoiding_velocity.z - 1.0).abs() < 1e-3,
+      "velocity {} should be as fast as the wall allows",
+      avoiding_velocity
+    );
+  }
+
+  #[test]
+  fn reports_info_about_avoiding_velocity() {
+    let agent = Agent {
+      position: Vec3::ZERO,
+      velocity: Vec3::ZERO,
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    let wall = Obstacle::Open {
+      vertices: vec![Vec2::new(-5.0, 1.0), Vec2::new(5.0, 1.0)],
+      bottom: -5.0,
+      top: 5.0,
+    };
+    let avoidance_options = AvoidanceOptions {
+      obstacle_margin: 0.5,
+      time_horizon: 1.0,
+      obstacle_time_horizon: 2.0,
+      tolerances: Tolerances::default(),
+      strict_rvo2_compatibility: false,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: SolverLimits::default(),
+      responsibility_override: None,
+    };
+
+    let result = agent.compute_avoiding_velocity_with_info(
+      &[],
+      &[Cow::Borrowed(&wall)],
+      /* preferred_velocity= */ Vec3::new(3.0, 0.0, 0.0),
+      /* max_speed= */ 2.0,
+      /* time_step= */ 0.01,
+      &avoidance_options,
+    );
+    assert_eq!(
+      result,
+      AvoidanceResult {
+        velocity: Vec3::new(2.0, 0.0, 0.0),
+        speed_clamped: true,
+        preferred_velocity_feasible: true,
+        deviation: 1.0,
+        solver_limit_reached: false,
+      }
+    );
+
+    // Moving towards the wall is limited to 0.25.
+    let result = agent.compute_avoiding_velocity_with_info(
+      &[],
+      &[Cow::Borrowed(&wall)],
+      /* preferred_velocity= */ Vec3::new(0.0, 0.0, 1.0),
+      /* max_speed= */ 2.0,
+      /* time_step= */ 0.01,
+      &avoidance_options,
+    );
+    assert!(!result.speed_clamped);
+    assert!(!result.preferred_velocity_feasible);
+    assert!((result.deviation - 0.75).abs() < 1e-4, "{:?}", result);
+    assert!(!result.solver_limit_reached);
+
+    // Without any iterations, the solver cannot satisfy the wall's planes, so
+    // it stops at zero.
+    let result = agent.compute_avoiding_velocity_with_info(
+      &[],
+      &[Cow::Borrowed(&wall)],
+      /* preferred_velocity= */ Vec3::new(0.0, 0.0, 1.0),
+      /* max_speed= */ 2.0,
+      /* time_step= */ 0.01,
+      &AvoidanceOptions {
+        solver_limits: SolverLimits {
+          max_iterations: Some(0),
+          time_budget: None,
+        },
+        ..avoidance_options
+      },
+    );
+    assert_eq!(result.velocity, Vec3::ZERO);
+    assert!(result.solver_limit_reached);
+  }
+
+  #[test]
+  fn chooses_first_unobstructed_candidate() {
+    let agent = Agent {
+      position: Vec3::ZERO,
+      velocity: Vec3::ZERO,
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    // A wall blocks moving along +z.
+    let wall = Obstacle::Open {
+      vertices: vec![Vec2::new(-5.0, 1.0), Vec2::new(5.0, 1.0)],
+      bottom: -5.0,
+      top: 5.0,
+    };
+    let avoidance_options = AvoidanceOptions {
+      obstacle_margin: 0.5,
+      time_horizon: 1.0,
+      obstacle_time_horizon: 2.0,
+      tolerances: Tolerances::default(),
+      strict_rvo2_compatibility: false,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: SolverLimits::default(),
+      responsibility_override: None,
+    };
+
+    let choice = agent.compute_avoiding_velocity_for_candidates(
+      &[],
+      &[Cow::Borrowed(&wall)],
+      &[
+        Vec3::new(0.0, 0.0, 2.0),
+        Vec3::new(-2.0, 0.0, 0.0),
+        Vec3::new(2.0, 0.0, 0.0),
+      ],
+      /* max_speed= */ 1.0,
+      /* time_step= */ 0.01,
+      &avoidance_options,
+    );
+    // Going straight is blocked, so the left detour (limited to the max speed)
+    // wins.
+    assert_eq!(choice.candidate_index, 1);
+    assert!(choice.velocity.distance(Vec3::new(-1.0, 0.0, 0.0)) < 1e-4);
+
+    // If all candidates are blocked, the least blocked candidate wins.
+    let CandidateChoice { velocity, candidate_index } = agent
+      .compute_avoiding_velocity_for_candidates(
+        &[],
+        &[Cow::Borrowed(&wall)],
+        &[Vec3::new(0.0, 0.0, 2.0), Vec3::new(0.5, 0.0, 0.5)],
+        /* max_speed= */ 1.0,
+        /* time_step= */ 0.01,
+        &avoidance_options,
+      );
+    assert_eq!(candidate_index, 1);
+    assert!(velocity.z <= 0.25 + 1e-4, "{}", velocity);
+  }
+
+  #[test]
+  fn follows_traffic_lane() {
+    let agent = Agent {
+      position: Vec3::new(0.0, 39.0, 0.0),
+      velocity: Vec3::ZERO,
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    let lane = TrafficLane {
+      min_altitude: 30.0,
+      max_altitude: 40.0,
+      direction: Some(Vec3::X),
+    };
+    let avoidance_options = AvoidanceOptions {
+      obstacle_margin: 0.0,
+      time_horizon: 1.0,
+      obstacle_time_horizon: 1.0,
+      tolerances: Tolerances::default(),
+      strict_rvo2_compatibility: false,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: SolverLimits::default(),
+      responsibility_override: None,
+    };
+
+    // Climbing is limited by the top of the altitude band.
+    let avoiding_velocity = agent.compute_avoiding_velocity_in_lane(
+      &[],
+      &[],
+      &lane,
+      /* preferred_velocity= */ Vec3::new(1.0, 5.0, 0.0),
+      /* max_speed= */ 10.0,
+      /* time_step= */ 0.01,
+      &avoidance_options,
+    );
+    assert!(
+      avoiding_velocity.distance(Vec3::new(1.0, 1.0, 0.0)) < 1e-3,
+      "velocity {} should be limited by the lane ceiling",
+      avoiding_velocity
+    );
+
+    // Moving against the lane is not allowed.
+    let avoiding_velocity = agent.compute_avoiding_velocity_in_lane(
+      &[],
+      &[],
+      &lane,
+      /* preferred_velocity= */ Vec3::new(-1.0, 0.0, 1.0),
+      /* max_speed= */ 10.0,
+      /* time_step= */ 0.01,
+      &avoidance_options,
+    );
+    assert!(
+      avoiding_velocity.distance(Vec3::new(0.0, 0.0, 1.0)) < 1e-3,
+      "velocity {} should not go against the lane",
+      avoiding_velocity
+    );
+  }
+
+  #[test]
+  fn stays_on_terrain_tangent_plane() {
+    // Rises one unit in y for every unit in x.
+    let terrain = |x: f32, _z: f32| x;
+    let ground_normal = Vec3::new(-1.0, 1.0, 0.0).normalize();
+
+    let agent = Agent {
+      position: Vec3::ZERO,
+      velocity: Vec3::ZERO,
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    let avoidance_options = AvoidanceOptions {
+      obstacle_margin: 0.0,
+      time_horizon: 1.0,
+      obstacle_time_horizon: 1.0,
+      tolerances: Tolerances::default(),
+      strict_rvo2_compatibility: false,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: SolverLimits::default(),
+      responsibility_override: None,
+    };
+
+    let unobstructed_velocity = agent.compute_avoiding_velocity_on_terrain(
+      &[],
+      &[],
+      &terrain,
+      /* preferred_velocity= */ Vec3::new(2.0, 0.0, 0.0),
+      /* max_speed= */ 2.0,
+      /* time_step= */ 0.01,
+      &avoidance_options,
+    );
+    // The preferred velocity is projected onto the slope.
+    assert!(unobstructed_velocity.distance(Vec3::new(1.0, 1.0, 0.0)) < 1e-3);
+
+    let neighbour = Agent {
+      position: Vec3::new(1.0, 1.0, 0.0),
+      velocity: Vec3::ZERO,
+      radius: 0.5,
+      avoidance_responsibility: 1.0,
+    };
+    let avoiding_velocity = agent.compute_avoiding_velocity_on_terrain(
+      &[Cow::Owned(neighbour)],
+      &[],
+      &terrain,
+      /* preferred_velocity= */ Vec3::new(2.0, 0.0, 0.0),
+      /* max_speed= */ 2.0,
+      /* time_step= */ 0.01,
+      &avoidance_options,
+    );
+    assert!(avoiding_velocity.distance(unobstructed_velocity) > 0.1);
+    assert!(
+      avoiding_velocity.dot(ground_normal).abs() < 1e-3,
+      "velocity {} is not along the ground",
+      avoiding_velocity
+    );
+  }
 }
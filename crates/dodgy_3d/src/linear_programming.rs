@@ -18,50 +18,326 @@
 // United States of America
 //
 // <https://gamma.cs.unc.edu/RVO2/>
-use glam::Vec3;
+use std::time::{Duration, Instant};
+
+use glam::{Vec3, Vec4, Vec4Swizzles};
 
 /// A half-space to act as a constraint on the linear program. This is
 /// represented as a point and a normal, where the valid half-space resides in
-/// the direction of the normal.
-#[derive(Clone, Debug)]
+/// the direction of the normal. Each neighbour and obstacle constrains an
+/// agent's velocity with planes (in velocity space), for example in
+/// [`crate::ConstraintSet::planes`].
+///
+/// With the `serde` feature, planes can be serialized (e.g. to dump the
+/// constraints for an external tool).
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Plane {
+  /// Any point on the plane.
   pub point: Vec3,
   /// Must always have length = 1
   pub normal: Vec3,
 }
 
 impl Plane {
+  /// Creates the plane through `point`, with the valid half-space in the
+  /// direction of `normal`. The normal is normalized, so it can have any
+  /// (non-zero) length.
+  pub fn from_point_normal(point: Vec3, normal: Vec3) -> Self {
+    Self { point, normal: normal.normalize() }
+  }
+
+  /// Creates the plane from its equation `a*x + b*y + c*z + d = 0` (with
+  /// `equation = (a, b, c, d)`), where the valid half-space is where
+  /// `a*x + b*y + c*z + d >= 0`. The equation does not need to be normalized,
+  /// but `(a, b, c)` must not be zero.
+  pub fn from_equation(equation: Vec4) -> Self {
+    let length = equation.xyz().length();
+    let normal = equation.xyz() / length;
+    Self { point: normal * (-equation.w / length), normal }
+  }
+
+  /// The equation `a*x + b*y + c*z + d = 0` of the plane as `(a, b, c, d)`,
+  /// where the valid half-space is where `a*x + b*y + c*z + d >= 0`. The
+  /// equation is normalized, so `(a, b, c)` is the normal and `d` is the
+  /// signed distance from the plane to the origin.
+  pub fn to_equation(&self) -> Vec4 {
+    self.normal.extend(-self.normal.dot(self.point))
+  }
+
+  /// The signed distance from the plane to `point`, which is positive on the
+  /// valid side of the plane.
   pub fn signed_distance_to_plane(&self, point: Vec3) -> f32 {
     (point - self.point).dot(self.normal)
   }
+
+  /// Whether the plane is made of only finite values.
+  pub fn is_finite(&self) -> bool {
+    self.point.is_finite() && self.normal.is_finite()
+  }
+}
+
+/// Limits on the work the linear program may do, so pathological inputs
+/// (e.g. hundreds of conflicting constraints) cannot blow the frame budget.
+/// When a limit is reached, the solver stops and returns the best value found
+/// so far, which still satisfies the rigid constraints (falling back to zero if
+/// the rigid constraints were not all processed yet). By default, there are no
+/// limits.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct SolverLimits {
+  /// The maximum number of times the solver may move the value to satisfy a
+  /// violated constraint. Each iteration costs time linear in the number of
+  /// constraints. If None, the number of iterations is unlimited.
+  pub max_iterations: Option<usize>,
+  /// The maximum time the solver may take for a single solve. This is checked
+  /// between iterations, so the solver can overrun it by one iteration. If
+  /// None, the time is unlimited.
+  pub time_budget: Option<Duration>,
+}
+
+/// Tracks the work done by the linear program against its [`SolverLimits`].
+struct SolverBudget<'limits> {
+  limits: &'limits SolverLimits,
+  /// When the solve started, if there is a time budget.
+  start: Option<Instant>,
+  iterations: usize,
+  /// Whether a limit was reached, after which the solver must stop.
+  limit_reached: bool,
+}
+
+impl<'limits> SolverBudget<'limits> {
+  fn new(limits: &'limits SolverLimits) -> Self {
+    Self {
+      limits,
+      start: limits.time_budget.map(|_| Instant::now()),
+      iterations: 0,
+      limit_reached: false,
+    }
+  }
+
+  /// Records the start of an iteration. Returns false if a limit has been
+  /// reached, in which case the iteration must not be performed.
+  fn try_iterate(&mut self) -> bool {
+    if self.limit_reached {
+      return false;
+    }
+    self.iterations += 1;
+    let iterations_reached = self
+      .limits
+      .max_iterations
+      .is_some_and(|max_iterations| self.iterations > max_iterations);
+    let time_reached = match (self.start, self.limits.time_budget) {
+      (Some(start), Some(time_budget)) => start.elapsed() > time_budget,
+      _ => false,
+    };
+    self.limit_reached = iterations_reached || time_reached;
+    !self.limit_reached
+  }
 }
 
 /// Solves the linear program defined as finding the value closest to
 /// `preferred_value` under the constraints that the value has a length less
 /// than `radius`, and is outside all half-spaces defined by `constraints`. If
-/// satisfying all constraints is infeasible, the constraints are relaxed and
-/// the least-penetrating value is returned.
+/// satisfying all constraints is infeasible, the non-rigid constraints (i.e.
+/// `constraints[rigid_constraint_count..]`) are relaxed and the
+/// least-penetrating value is returned. If the rigid constraints cannot be
+/// satisfied, returns the best value prior to that determination. `epsilon`
+/// determines when constraints are considered parallel or violated.
+///
+/// Malformed inputs (e.g. from agents with NaN positions) never produce a
+/// non-finite value: non-finite constraints are ignored, a non-finite
+/// `preferred_value` is treated as zero, and zero is returned if the solution
+/// still ends up non-finite.
+#[cfg(any(test, feature = "debug"))]
 pub fn solve_linear_program(
   constraints: &[Plane],
+  rigid_constraint_count: usize,
   radius: f32,
   preferred_value: Vec3,
+  epsilon: f32,
 ) -> Vec3 {
-  match solve_linear_program_3d(
+  solve_linear_program_with_limits(
+    constraints,
+    rigid_constraint_count,
+    radius,
+    preferred_value,
+    epsilon,
+    &SolverLimits::default(),
+  )
+  .0
+}
+
+/// Same as [`solve_linear_program`], but stops early once the `limits` are
+/// reached (see [`SolverLimits`]). Also returns whether a limit was reached.
+pub fn solve_linear_program_with_limits(
+  constraints: &[Plane],
+  rigid_constraint_count: usize,
+  radius: f32,
+  preferred_value: Vec3,
+  epsilon: f32,
+  limits: &SolverLimits,
+) -> (Vec3, bool) {
+  let (value, limit_reached, _) = solve_linear_program_with_outcome(
+    constraints,
+    rigid_constraint_count,
+    radius,
+    preferred_value,
+    epsilon,
+    limits,
+  );
+  (value, limit_reached)
+}
+
+/// How the linear program was solved, for explaining a solution (see
+/// [`crate::Explanation`]). Constraint indices skip any non-finite constraints,
+/// which are ignored.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LinearProgramOutcome {
+  /// Every constraint was satisfied by the 3D linear program.
+  Feasible,
+  /// The constraint at `index_of_failed_constraint` could not be satisfied
+  /// along with the constraints before it, so the 4D linear program found the
+  /// value that violates the (non-rigid) constraints the least.
+  Relaxed { index_of_failed_constraint: usize },
+  /// The rigid constraint at `index_of_failed_constraint` could not be
+  /// satisfied along with the (rigid) constraints before it, so the value only
+  /// satisfies those. This is only possible due to floating point error.
+  RigidInfeasible { index_of_failed_constraint: usize },
+  /// The solver reached its limits (see [`SolverLimits`]) while satisfying the
+  /// constraint at `index_of_failed_constraint` in the 3D linear program.
+  Interrupted { index_of_failed_constraint: usize },
+}
+
+/// Same as [`solve_linear_program_with_limits`], but also returns how the
+/// linear program was solved.
+pub(crate) fn solve_linear_program_with_outcome(
+  constraints: &[Plane],
+  rigid_constraint_count: usize,
+  radius: f32,
+  preferred_value: Vec3,
+  epsilon: f32,
+  limits: &SolverLimits,
+) -> (Vec3, bool, LinearProgramOutcome) {
+  if !constraints.iter().all(Plane::is_finite) {
+    let finite_constraints =
+      constraints.iter().filter(|plane| plane.is_finite()).cloned();
+    let rigid_constraint_count = constraints[..rigid_constraint_count]
+      .iter()
+      .filter(|plane| plane.is_finite())
+      .count();
+    return solve_linear_program_with_outcome(
+      &finite_constraints.collect::<Vec<_>>(),
+      rigid_constraint_count,
+      radius,
+      preferred_value,
+      epsilon,
+      limits,
+    );
+  }
+  let preferred_value =
+    if preferred_value.is_finite() { preferred_value } else { Vec3::ZERO };
+
+  let mut budget = SolverBudget::new(limits);
+  let (value, outcome) = match solve_linear_program_3d(
     constraints,
     radius,
     &OptimalValue::Point(preferred_value),
+    epsilon,
+    &mut budget,
   ) {
-    LinearProgram3DResult::Feasible(optimal_value) => optimal_value,
+    LinearProgram3DResult::Feasible(optimal_value) => {
+      (optimal_value, LinearProgramOutcome::Feasible)
+    }
+    // The partial value only satisfies the constraints before the failed
+    // line, so it is only usable if those include every rigid constraint.
+    LinearProgram3DResult::Interrupted {
+      index_of_failed_line,
+      partial_value,
+    } => (
+      if index_of_failed_line < rigid_constraint_count {
+        Vec3::ZERO
+      } else {
+        partial_value
+      },
+      LinearProgramOutcome::Interrupted {
+        index_of_failed_constraint: index_of_failed_line,
+      },
+    ),
     LinearProgram3DResult::Infeasible {
       index_of_failed_line,
       partial_value,
-    } => solve_linear_program_4d(
-      constraints,
-      radius,
+    } if index_of_failed_line < rigid_constraint_count => (
+      partial_value,
+      LinearProgramOutcome::RigidInfeasible {
+        index_of_failed_constraint: index_of_failed_line,
+      },
+    ),
+    LinearProgram3DResult::Infeasible {
       index_of_failed_line,
       partial_value,
+    } => (
+      solve_linear_program_4d(
+        constraints,
+        rigid_constraint_count,
+        radius,
+        index_of_failed_line,
+        partial_value,
+        epsilon,
+        &mut budget,
+      ),
+      LinearProgramOutcome::Relaxed {
+        index_of_failed_constraint: index_of_failed_line,
+      },
     ),
+  };
+  // Zero always satisfies the rigid constraints, so it is a safe fallback.
+  let value = if value.is_finite() { value } else { Vec3::ZERO };
+  (value, budget.limit_reached, outcome)
+}
+
+/// A way in which a solution of the linear program is invalid. See
+/// [`verify_solution`].
+#[cfg(any(test, feature = "debug"))]
+#[derive(Clone, PartialEq, Debug)]
+pub enum SolutionViolation {
+  /// The solution is faster than the max speed.
+  ExceedsMaxSpeed { speed: f32 },
+  /// The solution is outside the half-space of the plane at `plane_index`, by
+  /// `distance`.
+  OutsidePlane { plane_index: usize, distance: f32 },
+}
+
+/// The distance (relative to the max speed) that a solution may be outside a
+/// constraint by in [`verify_solution`], to allow for floating point error.
+#[cfg(any(test, feature = "debug"))]
+const VERIFY_TOLERANCE: f32 = 1e-4;
+
+/// Checks that `result` (e.g. from [`solve_linear_program`]) is within
+/// `max_speed` and inside every one of the `planes`, allowing for a small
+/// floating point error. Returns every violation found, so an empty result
+/// means the solution is valid. This is useful for validating outputs when a
+/// solver bug is suspected. Note that when the planes cannot all be satisfied,
+/// the solver relaxes the non-rigid planes, so violations are expected then.
+#[cfg(any(test, feature = "debug"))]
+pub fn verify_solution(
+  planes: &[Plane],
+  max_speed: f32,
+  result: Vec3,
+) -> Vec<SolutionViolation> {
+  let tolerance = VERIFY_TOLERANCE * max_speed.max(1.0);
+  let mut violations = Vec::new();
+  let speed = result.length();
+  if speed > max_speed + tolerance {
+    violations.push(SolutionViolation::ExceedsMaxSpeed { speed });
+  }
+  for (plane_index, plane) in planes.iter().enumerate() {
+    let distance = -plane.signed_distance_to_plane(result);
+    if distance > tolerance {
+      violations
+        .push(SolutionViolation::OutsidePlane { plane_index, distance });
+    }
   }
+  violations
 }
 
 #[derive(Clone, Debug)]
@@ -71,7 +347,8 @@ struct Line {
   direction: Vec3,
 }
 
-const RVO_EPSILON: f32 = 0.00001;
+/// The default epsilon used by the linear program, matching RVO2-3D.
+pub const RVO_EPSILON: f32 = 0.00001;
 
 /// The definition of the optimal value ignoring all constraints.
 enum OptimalValue {
@@ -91,6 +368,7 @@ fn solve_linear_program_along_line(
   radius: f32,
   constraints: &[Plane],
   optimal_value: &OptimalValue,
+  epsilon: f32,
 ) -> Result<Vec3, ()> {
   // Find the intersecting "times" of the line between `line` and the sphere
   // with `radius`. This is fairly straightforward by using the equation of a
@@ -123,10 +401,10 @@ fn solve_linear_program_along_line(
     let direction_dot = line.direction.dot(constraint.normal);
     let numerator = constraint.signed_distance_to_plane(line.point);
 
-    if direction_dot * direction_dot <= RVO_EPSILON {
+    if direction_dot * direction_dot <= epsilon {
       // `line` and `constraint` are nearly parallel.
 
-      if numerator < -RVO_EPSILON {
+      if numerator < -epsilon {
         // `line` is parallel to and on the invalid side of `constraint`, so all
         // `line` values are invalid, and so the result is infeasible.
         return Err(());
@@ -191,6 +469,7 @@ fn solve_linear_program_along_plane(
   radius: f32,
   constraints: &[Plane],
   optimal_value: &OptimalValue,
+  epsilon: f32,
 ) -> Result<Vec3, ()> {
   // We need to figure out the radius and center of the circle in `plane` that
   // intersects the sphere.
@@ -216,7 +495,7 @@ fn solve_linear_program_along_plane(
       let squared_length_of_projection = projected_optimal_direction_in_plane
         .dot(projected_optimal_direction_in_plane);
 
-      if squared_length_of_projection <= RVO_EPSILON {
+      if squared_length_of_projection <= epsilon {
         valid_plane_center
       } else {
         valid_plane_center
@@ -251,7 +530,7 @@ fn solve_linear_program_along_plane(
       continue;
     }
     let cross = constraint.normal.cross(plane.normal);
-    if cross.dot(cross) <= RVO_EPSILON {
+    if cross.dot(cross) <= epsilon {
       // `plane` is parallel to `constraint`, but the current best value (which
       // satisfies `plane`) is on the wrong side of `constraint`. Therefore, we
       // can't satisfy both `plane` and `constraint`.
@@ -273,6 +552,7 @@ fn solve_linear_program_along_plane(
       radius,
       &constraints[0..index],
       optimal_value,
+      epsilon,
     ) else {
       return Err(());
     };
@@ -297,6 +577,14 @@ enum LinearProgram3DResult {
     /// constrained by the lines prior to `index_of_failed_line`.
     partial_value: Vec3,
   },
+  /// The solver reached its limits before finishing.
+  Interrupted {
+    /// The index of the line the solver stopped at.
+    index_of_failed_line: usize,
+    /// The value at the time the solver stopped. This satisfies the lines
+    /// prior to `index_of_failed_line`.
+    partial_value: Vec3,
+  },
 }
 
 /// Solves the 3D linear program, restricted to the sphere defined by `radius`,
@@ -305,6 +593,8 @@ fn solve_linear_program_3d(
   constraints: &[Plane],
   radius: f32,
   optimal_value: &OptimalValue,
+  epsilon: f32,
+  budget: &mut SolverBudget,
 ) -> LinearProgram3DResult {
   let mut best_value = match *optimal_value {
     // If optimizing by a direction, the best value is just on the sphere in
@@ -325,6 +615,12 @@ fn solve_linear_program_3d(
       // half-plane defined by `constraint`, there is nothing to do.
       continue;
     }
+    if !budget.try_iterate() {
+      return LinearProgram3DResult::Interrupted {
+        index_of_failed_line: index,
+        partial_value: best_value,
+      };
+    }
 
     // Since the current `best_value` violates `constraint`, the new best value
     // must reside somewhere on the plane defined by `constraint`.
@@ -333,6 +629,7 @@ fn solve_linear_program_3d(
       radius,
       &constraints[0..index],
       optimal_value,
+      epsilon,
     ) {
       Ok(new_value) => best_value = new_value,
       Err(()) => {
@@ -350,15 +647,23 @@ fn solve_linear_program_3d(
 /// Solves the 4D linear program, after the 3D linear program was determined to
 /// be infeasible. This effectively finds the first valid value when moving all
 /// non-rigid half-spaces back at the same speed. `radius` limits the magnitude
-/// of the resulting value. `index_of_failed_plane` and `partial_value` are the
-/// results from the infeasible 3D program, where `partial_value` is assumed to
-/// satisfy all `constraints[0..index_of_failed_plane]`.
+/// of the resulting value. `rigid_constraint_count` determines the constraints
+/// that will not be moved. These are assumed to be trivially satisfiable (in
+/// practice these are satisfied by a value of 0). `index_of_failed_plane` and
+/// `partial_value` are the results from the infeasible 3D program, where
+/// `partial_value` is assumed to satisfy all
+/// `constraints[0..index_of_failed_plane]`.
 fn solve_linear_program_4d(
   constraints: &[Plane],
+  rigid_constraint_count: usize,
   radius: f32,
   index_of_failed_plane: usize,
   partial_value: Vec3,
+  epsilon: f32,
+  budget: &mut SolverBudget,
 ) -> Vec3 {
+  debug_assert!(rigid_constraint_count <= index_of_failed_plane);
+
   let mut penetration = 0.0;
   let mut best_value = partial_value;
 
@@ -373,6 +678,11 @@ fn solve_linear_program_4d(
     }
 
     let index = index + index_of_failed_plane;
+    // Every value found so far satisfies the rigid constraints, so stopping
+    // early still gives a valid (if more penetrating) value.
+    if !budget.try_iterate() {
+      break;
+    }
 
     // The goal is to find the value that penetrates all constraints the least.
     // While optimizing the value to penetrate `constraint` as little as
@@ -383,7 +693,12 @@ fn solve_linear_program_4d(
 
     // Start a new problem to find the least penetrating value for `constraint`.
     let mut penetration_constraints = Vec::with_capacity(index);
-    for previous_constraint in &constraints[0..index] {
+    // Copy over all the rigid constraints - these must always be satisfied
+    // without modification.
+    penetration_constraints
+      .extend_from_slice(&constraints[0..rigid_constraint_count]);
+
+    for previous_constraint in &constraints[rigid_constraint_count..index] {
       // The new constraint for `previous_constraint` is the half-space such
       // that `previous_constraint` is violated no more than `constraint`. This
       // half-space is defined by the plane through the intersection of both
@@ -393,7 +708,7 @@ fn solve_linear_program_4d(
 
       let cross = previous_constraint.normal.cross(constraint.normal);
 
-      let new_plane_point = if cross.dot(cross) <= RVO_EPSILON {
+      let new_plane_point = if cross.dot(cross) <= epsilon {
         // The constraint planes are parallel.
 
         if constraint.normal.dot(previous_constraint.normal) > 0.0 {
@@ -428,6 +743,8 @@ fn solve_linear_program_4d(
       // The optimal value is the furthest value in the direction of the valid
       // side of `constraint`'s half-space.
       &OptimalValue::Direction(constraint.normal),
+      epsilon,
+      budget,
     ) {
       best_value = result;
       penetration = -constraint.signed_distance_to_plane(best_value);
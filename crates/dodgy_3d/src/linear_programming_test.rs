@@ -14,12 +14,50 @@ macro_rules! assert_vec3_near {
   }};
 }
 
+mod plane_tests {
+  use glam::{Vec3, Vec4};
+
+  use super::Plane;
+
+  #[test]
+  fn converts_equation_form() {
+    // 2y - 4 >= 0, i.e. y >= 2.
+    let plane = Plane::from_equation(Vec4::new(0.0, 2.0, 0.0, -4.0));
+    assert_vec3_near!(plane.normal, Vec3::Y);
+    assert_eq!(plane.signed_distance_to_plane(Vec3::new(5.0, 3.0, 1.0)), 1.0);
+    assert_eq!(plane.to_equation(), Vec4::new(0.0, 1.0, 0.0, -2.0));
+
+    let plane = Plane::from_point_normal(
+      Vec3::new(1.0, 2.0, 3.0),
+      Vec3::new(3.0, 0.0, 4.0),
+    );
+    assert_eq!(plane.normal, Vec3::new(0.6, 0.0, 0.8));
+    let round_trip = Plane::from_equation(plane.to_equation());
+    assert_vec3_near!(round_trip.normal, plane.normal);
+    assert!(round_trip.signed_distance_to_plane(plane.point).abs() < 1e-5);
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn serializes_with_serde() {
+    let plane = Plane {
+      point: Vec3::new(1.0, 2.0, 3.0),
+      normal: Vec3::new(0.0, 1.0, 0.0),
+    };
+    let json = serde_json::to_string(&plane).unwrap();
+    assert_eq!(json, r#"{"point":[1.0,2.0,3.0],"normal":[0.0,1.0,0.0]}"#);
+    assert_eq!(serde_json::from_str::<Plane>(&json).unwrap(), plane);
+  }
+}
+
 mod solve_linear_program_along_line_tests {
   use glam::Vec3;
 
   use crate::linear_programming::Plane;
 
-  use super::{solve_linear_program_along_line, Line, OptimalValue};
+  use super::{
+    solve_linear_program_along_line, Line, OptimalValue, RVO_EPSILON,
+  };
 
   #[test]
   fn projects_optimal_point_with_no_constraints() {
@@ -37,6 +75,7 @@ mod solve_linear_program_along_line_tests {
         1.0,
         Default::default(),
         &OptimalValue::Point(Vec3::new(5.0, 0.25, 0.0)),
+        RVO_EPSILON,
       ),
       Ok(Vec3::new(0.5, 0.25, 0.0))
     );
@@ -47,6 +86,7 @@ mod solve_linear_program_along_line_tests {
         1.0,
         Default::default(),
         &OptimalValue::Point(Vec3::new(5.0, 2.0, 0.0)),
+        RVO_EPSILON,
       ),
       Ok(Vec3::new(0.5, circle_height_at_half, 0.0))
     );
@@ -57,6 +97,7 @@ mod solve_linear_program_along_line_tests {
         1.0,
         Default::default(),
         &OptimalValue::Point(Vec3::new(5.0, -100.0, 0.0)),
+        RVO_EPSILON,
       ),
       Ok(Vec3::new(0.5, -circle_height_at_half, 0.0))
     );
@@ -78,6 +119,7 @@ mod solve_linear_program_along_line_tests {
         1.0,
         Default::default(),
         &OptimalValue::Direction(Vec3::new(1.0, 0.0, 0.5).normalize()),
+        RVO_EPSILON,
       ),
       Ok(Vec3::new(0.5, 0.0, circle_height_at_half))
     );
@@ -88,6 +130,7 @@ mod solve_linear_program_along_line_tests {
         1.0,
         Default::default(),
         &OptimalValue::Direction(Vec3::new(1.0, 0.0, -0.5).normalize()),
+        RVO_EPSILON,
       ),
       Ok(Vec3::new(0.5, 0.0, -circle_height_at_half))
     );
@@ -119,6 +162,7 @@ mod solve_linear_program_along_line_tests {
         1.0,
         &constraints,
         &OptimalValue::Point(Vec3::new(0.0, -5.0, 0.25)),
+        RVO_EPSILON,
       ),
       Ok(Vec3::new(0.0, 0.5, 0.25))
     );
@@ -129,6 +173,7 @@ mod solve_linear_program_along_line_tests {
         1.0,
         &constraints,
         &OptimalValue::Point(Vec3::new(0.0, -5.0, 1.0)),
+        RVO_EPSILON,
       ),
       Ok(Vec3::new(0.0, 0.5, 0.5))
     );
@@ -139,6 +184,7 @@ mod solve_linear_program_along_line_tests {
         1.0,
         &constraints,
         &OptimalValue::Point(Vec3::new(0.0, -5.0, -1.0)),
+        RVO_EPSILON,
       ),
       Ok(Vec3::new(0.0, 0.5, -0.75))
     );
@@ -172,6 +218,7 @@ mod solve_linear_program_along_line_tests {
         1.0,
         &constraints,
         &OptimalValue::Point(Vec3::ZERO),
+        RVO_EPSILON,
       ),
       Err(())
     );
@@ -190,6 +237,7 @@ mod solve_linear_program_along_line_tests {
         1.0,
         Default::default(),
         &OptimalValue::Point(Vec3::ZERO),
+        RVO_EPSILON,
       ),
       Err(())
     );
@@ -210,7 +258,8 @@ mod solve_linear_program_along_line_tests {
         &valid_line,
         1.0,
         &feasible_constraint,
-        &OptimalValue::Direction(Vec3::X)
+        &OptimalValue::Direction(Vec3::X),
+        RVO_EPSILON,
       )
       .unwrap(),
       Vec3::new(1.0, 0.0, 0.0)
@@ -225,7 +274,8 @@ mod solve_linear_program_along_line_tests {
         &valid_line,
         1.0,
         &infeasible_constraint,
-        &OptimalValue::Direction(Vec3::X)
+        &OptimalValue::Direction(Vec3::X),
+        RVO_EPSILON,
       ),
       Err(())
     );
@@ -236,7 +286,7 @@ mod solve_linear_program_along_plane_tests {
   use glam::Vec3;
 
   use crate::linear_programming::{
-    solve_linear_program_along_plane, OptimalValue,
+    solve_linear_program_along_plane, OptimalValue, RVO_EPSILON,
   };
 
   use super::Plane;
@@ -254,6 +304,7 @@ mod solve_linear_program_along_plane_tests {
         10.0,
         &[],
         &OptimalValue::Point(Vec3::new(0.0, 1.0, 0.0)),
+        RVO_EPSILON,
       )
       .unwrap(),
       Vec3::new(2.0, 1.0, 2.0)
@@ -265,6 +316,7 @@ mod solve_linear_program_along_plane_tests {
         10.0,
         &[],
         &OptimalValue::Point(Vec3::new(0.0, 15.0, 0.0)),
+        RVO_EPSILON,
       )
       .unwrap(),
       // The radius of the sphere around the origin is 10.0. The distance of
@@ -288,6 +340,7 @@ mod solve_linear_program_along_plane_tests {
         10.0,
         &[],
         &OptimalValue::Direction(Vec3::new(0.0, 1.0, 0.0)),
+        RVO_EPSILON,
       )
       .unwrap(),
       // The radius of the sphere around the origin is 10.0. The distance of
@@ -304,6 +357,7 @@ mod solve_linear_program_along_plane_tests {
         10.0,
         &[],
         &OptimalValue::Direction(Vec3::new(1.0, 0.0, 1.0).normalize()),
+        RVO_EPSILON,
       )
       .unwrap(),
       Vec3::new(2.0, 0.0, 2.0)
@@ -331,7 +385,8 @@ mod solve_linear_program_along_plane_tests {
         &value_plane,
         1.0,
         &constraints,
-        &OptimalValue::Direction(Vec3::new(1.0, 0.0, 0.0))
+        &OptimalValue::Direction(Vec3::new(1.0, 0.0, 0.0)),
+        RVO_EPSILON,
       )
       .unwrap(),
       Vec3::new(0.6, -0.1, 0.0)
@@ -359,7 +414,8 @@ mod solve_linear_program_along_plane_tests {
         &value_plane,
         1.0,
         &constraints,
-        &OptimalValue::Point(Vec3::ZERO)
+        &OptimalValue::Point(Vec3::ZERO),
+        RVO_EPSILON,
       ),
       Err(())
     );
@@ -381,7 +437,8 @@ mod solve_linear_program_along_plane_tests {
         &value_plane,
         1.0,
         &constraints,
-        &OptimalValue::Point(Vec3::ZERO)
+        &OptimalValue::Point(Vec3::ZERO),
+        RVO_EPSILON,
       ),
       Err(())
     );
@@ -400,7 +457,8 @@ mod solve_linear_program_along_plane_tests {
         &value_plane,
         1.0,
         &[],
-        &OptimalValue::Point(Vec3::ZERO)
+        &OptimalValue::Point(Vec3::ZERO),
+        RVO_EPSILON,
       ),
       Err(())
     );
@@ -412,6 +470,7 @@ mod solve_linear_program_3d_tests {
 
   use crate::linear_programming::{
     solve_linear_program_3d, LinearProgram3DResult, OptimalValue, Plane,
+    SolverBudget, SolverLimits, RVO_EPSILON,
   };
 
   fn unwrap_feasible(result: LinearProgram3DResult) -> Vec3 {
@@ -440,6 +499,8 @@ mod solve_linear_program_3d_tests {
         Default::default(),
         1.0,
         &OptimalValue::Point(Vec3::new(0.5, 0.25, 0.0)),
+        RVO_EPSILON,
+        &mut SolverBudget::new(&SolverLimits::default()),
       )),
       Vec3::new(0.5, 0.25, 0.0)
     );
@@ -449,6 +510,8 @@ mod solve_linear_program_3d_tests {
         Default::default(),
         1.0,
         &OptimalValue::Point(Vec3::new(1.0, 1.0, 0.0)),
+        RVO_EPSILON,
+        &mut SolverBudget::new(&SolverLimits::default()),
       )),
       Vec3::new(one_over_root_2, one_over_root_2, 0.0)
     );
@@ -467,6 +530,8 @@ mod solve_linear_program_3d_tests {
           one_over_root_2,
           one_over_root_2
         )),
+        RVO_EPSILON,
+        &mut SolverBudget::new(&SolverLimits::default()),
       )),
       Vec3::new(0.0, one_over_root_2 * 3.0, one_over_root_2 * 3.0)
     );
@@ -480,6 +545,8 @@ mod solve_linear_program_3d_tests {
           one_over_root_2,
           -one_over_root_2
         )),
+        RVO_EPSILON,
+        &mut SolverBudget::new(&SolverLimits::default()),
       )),
       Vec3::new(0.0, one_over_root_2 * 5.0, one_over_root_2 * -5.0)
     );
@@ -497,6 +564,8 @@ mod solve_linear_program_3d_tests {
         &constraints,
         1.0,
         &OptimalValue::Point(Vec3::ZERO),
+        RVO_EPSILON,
+        &mut SolverBudget::new(&SolverLimits::default()),
       )),
       Vec3::new(0.5, 0.5, 0.5)
     );
@@ -525,6 +594,8 @@ mod solve_linear_program_3d_tests {
         &constraints,
         1.0,
         &OptimalValue::Point(Vec3::ZERO),
+        RVO_EPSILON,
+        &mut SolverBudget::new(&SolverLimits::default()),
       ));
 
     assert_eq!(index_of_failed_line, 2);
@@ -535,7 +606,9 @@ mod solve_linear_program_3d_tests {
 mod solve_linear_program_4d_tests {
   use glam::Vec3;
 
-  use super::{solve_linear_program_4d, Plane};
+  use super::{
+    solve_linear_program_4d, Plane, SolverBudget, SolverLimits, RVO_EPSILON,
+  };
 
   #[test]
   fn finds_least_penetrating_value() {
@@ -562,9 +635,12 @@ mod solve_linear_program_4d_tests {
     assert_vec3_near!(
       solve_linear_program_4d(
         &constraints,
+        /* rigid_constraint_count= */ 0,
         /* radius= */ 10.0,
         /* index_of_failed_line= */ 3,
-        /* partial_value= */ Vec3::new(1.0, 1.0, 0.0)
+        /* partial_value= */ Vec3::new(1.0, 1.0, 0.0),
+        RVO_EPSILON,
+        &mut SolverBudget::new(&SolverLimits::default()),
       ),
       Vec3::new(-0.75736, -0.75736, 9.94248)
     );
@@ -574,7 +650,7 @@ mod solve_linear_program_4d_tests {
 mod solve_linear_program_tests {
   use glam::Vec3;
 
-  use super::{solve_linear_program, Plane};
+  use super::{solve_linear_program, Plane, RVO_EPSILON};
 
   #[test]
   fn finds_valid_value_when_feasible() {
@@ -596,8 +672,10 @@ mod solve_linear_program_tests {
     assert_vec3_near!(
       solve_linear_program(
         &constraints,
+        /* rigid_constraint_count= */ 0,
         /* radius= */ 10.0,
         /* preferred_value= */ Vec3::ZERO,
+        RVO_EPSILON,
       ),
       Vec3::new(1.0, 1.0, 1.0)
     );
@@ -628,10 +706,309 @@ mod solve_linear_program_tests {
     assert_vec3_near!(
       solve_linear_program(
         &constraints,
+        /* rigid_constraint_count= */ 0,
         /* radius= */ 10.0,
         /* preferred_value= */ Vec3::ZERO,
+        RVO_EPSILON,
       ),
       Vec3::new(-0.75736, -0.75736, 9.94248)
     );
   }
+
+  #[test]
+  fn ignores_non_finite_constraints() {
+    let constraints = [
+      Plane { point: Vec3::new(0.0, 1.0, 0.0), normal: Vec3::Y },
+      Plane { point: Vec3::NAN, normal: Vec3::X },
+      Plane { point: Vec3::ZERO, normal: Vec3::INFINITY },
+    ];
+
+    assert_vec3_near!(
+      solve_linear_program(
+        &constraints,
+        /* rigid_constraint_count= */ 2,
+        /* radius= */ 10.0,
+        /* preferred_value= */ Vec3::ZERO,
+        RVO_EPSILON,
+      ),
+      Vec3::new(0.0, 1.0, 0.0)
+    );
+    // A non-finite preferred value is treated as zero.
+    assert_vec3_near!(
+      solve_linear_program(
+        &constraints,
+        /* rigid_constraint_count= */ 0,
+        /* radius= */ 10.0,
+        /* preferred_value= */ Vec3::NAN,
+        RVO_EPSILON,
+      ),
+      Vec3::new(0.0, 1.0, 0.0)
+    );
+  }
+
+  #[test]
+  fn rigid_constraints_are_not_relaxed() {
+    let constraints = [
+      Plane {
+        point: Vec3::new(0.0, 1.0, 0.0),
+        normal: Vec3::new(0.0, 1.0, 0.0),
+      },
+      Plane {
+        point: Vec3::new(0.0, -1.0, 0.0),
+        normal: Vec3::new(0.0, -1.0, 0.0),
+      },
+    ];
+
+    assert_vec3_near!(
+      solve_linear_program(
+        &constraints,
+        /* rigid_constraint_count= */ 1,
+        /* radius= */ 10.0,
+        /* preferred_value= */ Vec3::ZERO,
+        RVO_EPSILON,
+      ),
+      Vec3::new(0.0, 1.0, 0.0)
+    );
+  }
+}
+
+mod verify_solution_tests {
+  use glam::Vec3;
+
+  use crate::linear_programming::Plane;
+
+  use super::{verify_solution, SolutionViolation};
+
+  #[test]
+  fn reports_all_violations() {
+    let planes = [
+      Plane { point: Vec3::ZERO, normal: Vec3::X },
+      Plane { point: Vec3::new(0.0, 1.0, 0.0), normal: Vec3::Y },
+      Plane { point: Vec3::new(0.0, 0.0, 1.0), normal: Vec3::Z },
+    ];
+    assert_eq!(verify_solution(&planes, 2.0, Vec3::new(1.0, 1.0, 1.0)), []);
+    assert_eq!(
+      verify_solution(&planes, 2.0, Vec3::new(3.0, 0.5, 1.0)),
+      [
+        SolutionViolation::ExceedsMaxSpeed {
+          speed: Vec3::new(3.0, 0.5, 1.0).length()
+        },
+        SolutionViolation::OutsidePlane { plane_index: 1, distance: 0.5 },
+      ]
+    );
+  }
+}
+
+mod solve_linear_program_properties {
+  use glam::Vec3;
+  use proptest::prelude::*;
+
+  use crate::linear_programming::Plane;
+
+  use super::{
+    solve_linear_program, solve_linear_program_3d,
+    solve_linear_program_with_limits, verify_solution, LinearProgram3DResult,
+    OptimalValue, SolverBudget, SolverLimits, RVO_EPSILON,
+  };
+
+  fn vec3(range: f32) -> impl Strategy<Value = Vec3> {
+    (-range..range, -range..range, -range..range)
+      .prop_map(|(x, y, z)| Vec3::new(x, y, z))
+  }
+
+  fn plane() -> impl Strategy<Value = Plane> {
+    (
+      vec3(3.0),
+      vec3(1.0)
+        .prop_filter("normal must not be tiny", |normal| normal.length() > 0.1),
+    )
+      .prop_map(|(point, normal)| Plane { point, normal: normal.normalize() })
+  }
+
+  fn is_feasible(planes: &[Plane], max_speed: f32) -> bool {
+    matches!(
+      solve_linear_program_3d(
+        planes,
+        max_speed,
+        &OptimalValue::Point(Vec3::ZERO),
+        RVO_EPSILON,
+        &mut SolverBudget::new(&SolverLimits::default()),
+      ),
+      LinearProgram3DResult::Feasible(_)
+    )
+  }
+
+  proptest! {
+    #[test]
+    fn solution_is_valid_and_optimal(
+      planes in prop::collection::vec(plane(), 0..8),
+      max_speed in 0.5f32..5.0,
+      preferred_value in vec3(6.0),
+      samples in prop::collection::vec(vec3(5.0), 32),
+    ) {
+      let result = solve_linear_program(
+        &planes,
+        /* rigid_constraint_count= */ 0,
+        max_speed,
+        preferred_value,
+        RVO_EPSILON,
+      );
+      // Even when infeasible, the max speed is never exceeded.
+      prop_assert!(
+        verify_solution(&[], max_speed, result).is_empty(),
+        "{result} is faster than {max_speed}"
+      );
+      prop_assume!(is_feasible(&planes, max_speed));
+
+      let violations = verify_solution(&planes, max_speed, result);
+      prop_assert!(violations.is_empty(), "{violations:?}");
+      // No other valid value is closer to the preferred value.
+      let distance = result.distance(preferred_value);
+      for sample in samples {
+        if verify_solution(&planes, max_speed, sample).is_empty() {
+          prop_assert!(
+            distance <= sample.distance(preferred_value) + 1e-3,
+            "{sample} is closer to {preferred_value} than {result}"
+          );
+        }
+      }
+    }
+
+    #[test]
+    fn rigid_planes_are_satisfied(
+      rigid_planes in prop::collection::vec(plane(), 0..3),
+      planes in prop::collection::vec(plane(), 0..8),
+      max_speed in 0.5f32..5.0,
+      preferred_value in vec3(6.0),
+    ) {
+      prop_assume!(is_feasible(&rigid_planes, max_speed));
+      let rigid_plane_count = rigid_planes.len();
+      let all_planes = rigid_planes.into_iter().chain(planes).collect::<Vec<_>>();
+      let result = solve_linear_program(
+        &all_planes,
+        rigid_plane_count,
+        max_speed,
+        preferred_value,
+        RVO_EPSILON,
+      );
+      let violations =
+        verify_solution(&all_planes[..rigid_plane_count], max_speed, result);
+      prop_assert!(violations.is_empty(), "{violations:?}");
+    }
+
+    #[test]
+    fn limited_solutions_satisfy_rigid_planes(
+      // Stopping early can fall back to zero, so rigid planes must be
+      // satisfied by zero (as they are for avoidance).
+      rigid_planes in prop::collection::vec(
+        plane().prop_filter("zero must be valid", |plane| {
+          plane.signed_distance_to_plane(Vec3::ZERO) >= 0.0
+        }),
+        0..3,
+      ),
+      planes in prop::collection::vec(plane(), 0..8),
+      max_speed in 0.5f32..5.0,
+      preferred_value in vec3(6.0),
+      max_iterations in 0usize..6,
+    ) {
+      let rigid_plane_count = rigid_planes.len();
+      let all_planes = rigid_planes.into_iter().chain(planes).collect::<Vec<_>>();
+      let (result, _) = solve_linear_program_with_limits(
+        &all_planes,
+        rigid_plane_count,
+        max_speed,
+        preferred_value,
+        RVO_EPSILON,
+        &SolverLimits { max_iterations: Some(max_iterations), time_budget: None },
+      );
+      let violations =
+        verify_solution(&all_planes[..rigid_plane_count], max_speed, result);
+      prop_assert!(violations.is_empty(), "{violations:?}");
+    }
+  }
+}
+
+mod solve_linear_program_with_limits_tests {
+  use std::time::Duration;
+
+  use glam::Vec3;
+
+  use super::{
+    solve_linear_program, solve_linear_program_with_limits, Plane,
+    SolverLimits, RVO_EPSILON,
+  };
+
+  /// A rigid plane keeping y below 0.5, followed by (non-rigid) planes pushing
+  /// x and z above 1.
+  fn constraints() -> [Plane; 3] {
+    [
+      Plane { point: Vec3::new(0.0, 0.5, 0.0), normal: Vec3::NEG_Y },
+      Plane { point: Vec3::new(1.0, 0.0, 0.0), normal: Vec3::X },
+      Plane { point: Vec3::new(0.0, 0.0, 1.0), normal: Vec3::Z },
+    ]
+  }
+
+  #[test]
+  fn same_as_unlimited_within_limits() {
+    let constraints = constraints();
+    let unlimited = solve_linear_program(
+      &constraints,
+      /* rigid_constraint_count= */ 1,
+      /* radius= */ 10.0,
+      /* preferred_value= */ Vec3::new(0.0, 2.0, 0.0),
+      RVO_EPSILON,
+    );
+    assert_vec3_near!(unlimited, Vec3::new(1.0, 0.5, 1.0));
+
+    for limits in [
+      SolverLimits::default(),
+      SolverLimits { max_iterations: Some(3), time_budget: None },
+      SolverLimits {
+        max_iterations: None,
+        time_budget: Some(Duration::from_secs(60)),
+      },
+    ] {
+      assert_eq!(
+        solve_linear_program_with_limits(
+          &constraints,
+          /* rigid_constraint_count= */ 1,
+          /* radius= */ 10.0,
+          /* preferred_value= */ Vec3::new(0.0, 2.0, 0.0),
+          RVO_EPSILON,
+          &limits,
+        ),
+        (unlimited, false),
+        "{limits:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn returns_best_value_so_far() {
+    let (value, limit_reached) = solve_linear_program_with_limits(
+      &constraints(),
+      /* rigid_constraint_count= */ 1,
+      /* radius= */ 10.0,
+      /* preferred_value= */ Vec3::new(0.0, 2.0, 0.0),
+      RVO_EPSILON,
+      &SolverLimits { max_iterations: Some(2), time_budget: None },
+    );
+    assert!(limit_reached);
+    // Only the first two planes were satisfied.
+    assert_vec3_near!(value, Vec3::new(1.0, 0.5, 0.0));
+  }
+
+  #[test]
+  fn returns_zero_before_rigid_planes_are_satisfied() {
+    let (value, limit_reached) = solve_linear_program_with_limits(
+      &constraints(),
+      /* rigid_constraint_count= */ 1,
+      /* radius= */ 10.0,
+      /* preferred_value= */ Vec3::new(0.0, 2.0, 0.0),
+      RVO_EPSILON,
+      &SolverLimits { max_iterations: Some(0), time_budget: None },
+    );
+    assert!(limit_reached);
+    assert_eq!(value, Vec3::ZERO);
+  }
 }
@@ -0,0 +1,134 @@
+use std::borrow::Cow;
+
+use glam::{Quat, Vec3};
+
+use crate::{Agent, AvoidanceOptions, Obstacle};
+
+/// Parameters for [`Agent::compute_avoiding_velocity_with_lookahead`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct LookaheadOptions {
+  /// The number of avoiding velocities in each rollout (including the first).
+  /// More steps see further ahead, but cost more.
+  pub rollout_steps: usize,
+  /// The time (in seconds) between the steps of a rollout.
+  pub rollout_step_duration: f32,
+  /// The number of first velocities to roll out. The first candidate heads for
+  /// the preferred velocity, and the rest head for the preferred velocity
+  /// turned around the vertical axis by up to `max_candidate_angle` (evenly
+  /// alternating between left and right).
+  pub candidate_count: usize,
+  /// The largest angle (in radians) to turn the preferred velocity by for a
+  /// candidate.
+  pub max_candidate_angle: f32,
+}
+
+impl Default for LookaheadOptions {
+  fn default() -> Self {
+    Self {
+      rollout_steps: 3,
+      rollout_step_duration: 0.5,
+      candidate_count: 5,
+      max_candidate_angle: std::f32::consts::FRAC_PI_3,
+    }
+  }
+}
+
+/// Rolls out each candidate first velocity for `agent` (see
+/// [`LookaheadOptions`]), and returns the first velocity of the rollout that
+/// deviates the least from the `preferred_velocity` in total. Each rollout
+/// moves the agent along its velocity, and moves the neighbours along their
+/// current velocities (i.e. assuming they follow their current plan), then
+/// computes the next avoiding velocity for the `preferred_velocity`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn select_lookahead_velocity(
+  agent: &Agent,
+  neighbours: &[Cow<'_, Agent>],
+  obstacles: &[Cow<'_, Obstacle>],
+  preferred_velocity: Vec3,
+  max_speed: f32,
+  time_step: f32,
+  avoidance_options: &AvoidanceOptions,
+  lookahead_options: &LookaheadOptions,
+) -> Vec3 {
+  assert!(
+    lookahead_options.rollout_steps > 0,
+    "rollout_steps must be positive"
+  );
+  assert!(
+    lookahead_options.candidate_count > 0,
+    "candidate_count must be positive"
+  );
+  assert!(
+    lookahead_options.rollout_step_duration > 0.0,
+    "rollout_step_duration must be positive, was {}",
+    lookahead_options.rollout_step_duration
+  );
+
+  let clamped_preferred_velocity =
+    preferred_velocity.clamp_length_max(max_speed);
+  let constraints = agent.build_constraints(
+    neighbours,
+    obstacles,
+    time_step,
+    avoidance_options,
+  );
+
+  let mut best_velocity = Vec3::ZERO;
+  let mut best_cost = f32::INFINITY;
+  for target in get_candidate_targets(preferred_velocity, lookahead_options) {
+    let first_velocity = constraints.solve(target, max_speed);
+    let mut cost = first_velocity.distance(clamped_preferred_velocity);
+
+    let mut rollout_agent = agent.clone();
+    rollout_agent.velocity = first_velocity;
+    let mut rollout_neighbours = neighbours.to_vec();
+    for _ in 1..lookahead_options.rollout_steps {
+      rollout_agent.position +=
+        rollout_agent.velocity * lookahead_options.rollout_step_duration;
+      for neighbour in rollout_neighbours.iter_mut().map(Cow::to_mut) {
+        neighbour.position +=
+          neighbour.velocity * lookahead_options.rollout_step_duration;
+      }
+      rollout_agent.velocity = rollout_agent.compute_avoiding_velocity(
+        &rollout_neighbours,
+        obstacles,
+        preferred_velocity,
+        max_speed,
+        time_step,
+        avoidance_options,
+      );
+      cost += rollout_agent.velocity.distance(clamped_preferred_velocity);
+    }
+
+    // Only strictly better rollouts win, so earlier (more preferred)
+    // candidates win ties.
+    if cost < best_cost {
+      best_cost = cost;
+      best_velocity = first_velocity;
+    }
+  }
+  best_velocity
+}
+
+/// The velocities each candidate heads for initially. See
+/// [`LookaheadOptions::candidate_count`].
+fn get_candidate_targets(
+  preferred_velocity: Vec3,
+  lookahead_options: &LookaheadOptions,
+) -> impl Iterator<Item = Vec3> + '_ {
+  let max_turn = (lookahead_options.candidate_count - 1).div_ceil(2);
+  (0..lookahead_options.candidate_count).map(move |index| {
+    if index == 0 {
+      return preferred_velocity;
+    }
+    let turn = index.div_ceil(2);
+    let angle =
+      lookahead_options.max_candidate_angle * turn as f32 / max_turn as f32;
+    let angle = if index % 2 == 1 { angle } else { -angle };
+    Quat::from_rotation_y(angle) * preferred_velocity
+  })
+}
+
+#[cfg(test)]
+#[path = "lookahead_test.rs"]
+mod test;
@@ -0,0 +1,115 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{Agent, AvoidanceOptions, LookaheadOptions};
+
+#[test]
+fn single_candidate_without_rollout_matches_plain_avoidance() {
+  let agent = Agent::builder().velocity(Vec3::X).build();
+  let neighbours = [Cow::Owned(
+    Agent::builder()
+      .position(Vec3::new(2.0, 0.0, 0.1))
+      .velocity(-Vec3::X)
+      .build(),
+  )];
+  let options = AvoidanceOptions::builder().build();
+
+  let velocity = agent.compute_avoiding_velocity_with_lookahead(
+    &neighbours,
+    &[],
+    Vec3::X,
+    /* max_speed= */ 1.0,
+    /* time_step= */ 0.1,
+    &options,
+    &LookaheadOptions {
+      rollout_steps: 1,
+      candidate_count: 1,
+      ..Default::default()
+    },
+  );
+  assert_eq!(
+    velocity,
+    agent.compute_avoiding_velocity(
+      &neighbours,
+      &[],
+      Vec3::X,
+      /* max_speed= */ 1.0,
+      /* time_step= */ 0.1,
+      &options,
+    )
+  );
+}
+
+#[test]
+fn keeps_preferred_velocity_when_unobstructed() {
+  let agent = Agent::builder().velocity(Vec3::X).build();
+  let velocity = agent.compute_avoiding_velocity_with_lookahead(
+    &[],
+    &[],
+    Vec3::new(2.0, 0.0, 0.0),
+    /* max_speed= */ 1.0,
+    /* time_step= */ 0.1,
+    &AvoidanceOptions::builder().build(),
+    &LookaheadOptions::default(),
+  );
+  assert_eq!(velocity, Vec3::X);
+}
+
+#[test]
+fn passes_behind_crossing_agent() {
+  // Returns the total deviation from the preferred velocity, and the final
+  // position of an agent crossing the path of a neighbour (that does not
+  // avoid it) at an intersection.
+  let run = |lookahead_options: Option<LookaheadOptions>| {
+    let mut agent = Agent::builder().velocity(Vec3::X).build();
+    let mut neighbour = Agent::builder()
+      .position(Vec3::new(3.0, 0.0, -3.0))
+      .velocity(Vec3::Z)
+      .avoidance_responsibility(0.0)
+      .build();
+    let options = AvoidanceOptions::builder().time_horizon(2.0).build();
+
+    let mut total_deviation = 0.0;
+    for _ in 0..100 {
+      let neighbours = [Cow::Borrowed(&neighbour)];
+      let velocity = match &lookahead_options {
+        None => agent.compute_avoiding_velocity(
+          &neighbours,
+          &[],
+          Vec3::X,
+          /* max_speed= */ 1.0,
+          /* time_step= */ 0.1,
+          &options,
+        ),
+        Some(lookahead_options) => agent
+          .compute_avoiding_velocity_with_lookahead(
+            &neighbours,
+            &[],
+            Vec3::X,
+            /* max_speed= */ 1.0,
+            /* time_step= */ 0.1,
+            &options,
+            lookahead_options,
+          ),
+      };
+      total_deviation += velocity.distance(Vec3::X) * 0.1;
+      agent.velocity = velocity;
+      agent.position += velocity * 0.1;
+      neighbour.position += neighbour.velocity * 0.1;
+    }
+    (total_deviation, agent.position)
+  };
+
+  // Plain ORCA dodges towards where the neighbour is heading, so the agent
+  // gets dragged along the neighbour's path.
+  let (plain_deviation, plain_position) = run(None);
+  let (lookahead_deviation, lookahead_position) =
+    run(Some(LookaheadOptions::default()));
+  assert!(
+    lookahead_deviation < plain_deviation * 0.5,
+    "{lookahead_deviation} {plain_deviation}"
+  );
+  assert!(lookahead_position.x > plain_position.x, "{lookahead_position}");
+  assert!(lookahead_position.z.abs() < 1.0, "{lookahead_position}");
+}
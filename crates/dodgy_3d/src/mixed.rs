@@ -0,0 +1,67 @@
+use glam::Vec3;
+
+use crate::Agent;
+
+/// Adjusts `neighbour` for avoidance between ground and flying agents, or
+/// returns None if `agent` should ignore it. `standing_height` and
+/// `neighbour_standing_height` are the heights of the agents if they are
+/// ground agents (or None for flying agents).
+///
+/// - Flying agents avoid the closest point of a ground agent's vertical
+///   extent, so they do not fly through its upper body.
+/// - Ground agents only avoid agents that vertically overlap them (e.g. not
+///   flyers high overhead), and avoid them along the ground.
+pub(crate) fn adjust_neighbour(
+  agent: &Agent,
+  standing_height: Option<f32>,
+  neighbour: &Agent,
+  neighbour_standing_height: Option<f32>,
+) -> Option<Agent> {
+  match standing_height {
+    None => {
+      let Some(neighbour_height) = neighbour_standing_height else {
+        return Some(neighbour.clone());
+      };
+      Some(Agent {
+        position: Vec3::new(
+          neighbour.position.x,
+          agent.position.y.clamp(
+            neighbour.position.y,
+            neighbour.position.y + neighbour_height,
+          ),
+          neighbour.position.z,
+        ),
+        ..neighbour.clone()
+      })
+    }
+    Some(height) => {
+      let (neighbour_bottom, neighbour_top) = match neighbour_standing_height {
+        None => (
+          neighbour.position.y - neighbour.radius,
+          neighbour.position.y + neighbour.radius,
+        ),
+        Some(neighbour_height) => {
+          (neighbour.position.y, neighbour.position.y + neighbour_height)
+        }
+      };
+      if neighbour_top < agent.position.y
+        || neighbour_bottom > agent.position.y + height
+      {
+        return None;
+      }
+      Some(Agent {
+        position: Vec3::new(
+          neighbour.position.x,
+          agent.position.y,
+          neighbour.position.z,
+        ),
+        velocity: Vec3::new(neighbour.velocity.x, 0.0, neighbour.velocity.z),
+        ..neighbour.clone()
+      })
+    }
+  }
+}
+
+#[cfg(test)]
+#[path = "mixed_test.rs"]
+mod test;
@@ -0,0 +1,56 @@
+use glam::Vec3;
+
+use crate::Agent;
+
+use super::*;
+
+fn agent(position: Vec3, velocity: Vec3) -> Agent {
+  Agent { position, velocity, radius: 0.5, avoidance_responsibility: 1.0 }
+}
+
+#[test]
+fn flyers_avoid_closest_point_of_ground_agents() {
+  let ground_agent = agent(Vec3::new(1.0, 0.0, 2.0), Vec3::X);
+  for (flyer_height, expected_height) in [(1.0, 1.0), (5.0, 2.0), (-1.0, 0.0)] {
+    let flyer = agent(Vec3::new(0.0, flyer_height, 0.0), Vec3::ZERO);
+    assert_eq!(
+      adjust_neighbour(&flyer, None, &ground_agent, Some(2.0)),
+      Some(agent(Vec3::new(1.0, expected_height, 2.0), Vec3::X))
+    );
+  }
+
+  // Flyers see other flyers as they are.
+  let other_flyer = agent(Vec3::new(1.0, 3.0, 2.0), Vec3::Y);
+  assert_eq!(
+    adjust_neighbour(&agent(Vec3::ZERO, Vec3::ZERO), None, &other_flyer, None),
+    Some(other_flyer)
+  );
+}
+
+#[test]
+fn ground_agents_only_avoid_overlapping_agents_along_ground() {
+  let ground_agent = agent(Vec3::new(0.0, 1.0, 0.0), Vec3::ZERO);
+  let low_flyer = agent(Vec3::new(2.0, 3.25, 0.0), Vec3::new(1.0, 1.0, 0.0));
+  assert_eq!(
+    adjust_neighbour(&ground_agent, Some(2.0), &low_flyer, None),
+    Some(agent(Vec3::new(2.0, 1.0, 0.0), Vec3::X))
+  );
+
+  let high_flyer = agent(Vec3::new(2.0, 3.75, 0.0), Vec3::ZERO);
+  assert_eq!(
+    adjust_neighbour(&ground_agent, Some(2.0), &high_flyer, None),
+    None
+  );
+
+  // Ground agents on a floor below are ignored.
+  let lower_ground_agent = agent(Vec3::new(2.0, -3.0, 0.0), Vec3::ZERO);
+  assert_eq!(
+    adjust_neighbour(&ground_agent, Some(2.0), &lower_ground_agent, Some(2.0)),
+    None
+  );
+  let nearby_ground_agent = agent(Vec3::new(2.0, 0.5, 0.0), Vec3::ZERO);
+  assert_eq!(
+    adjust_neighbour(&ground_agent, Some(2.0), &nearby_ground_agent, Some(2.0)),
+    Some(agent(Vec3::new(2.0, 1.0, 0.0), Vec3::ZERO))
+  );
+}
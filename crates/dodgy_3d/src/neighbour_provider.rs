@@ -0,0 +1,41 @@
+use glam::Vec3;
+
+use crate::{Agent, Simulator};
+
+/// A read-only source of agents to avoid, managed outside of a
+/// [`Simulator`]. For example, players controlled by another system, or the
+/// agents of a neighbouring region's simulator, so agents managed by different
+/// systems still avoid each other. The agents of the provider are avoided, but
+/// are never moved. Any `Fn(Vec3, f32) -> Vec<Agent>` taking the `position`
+/// and `distance` is a neighbour provider, and so is a [`Simulator`].
+///
+/// To share a [`Simulator`] that is still being stepped, wrap it (e.g. in an
+/// `Arc<RwLock<Simulator>>`) and provide a closure that reads from it.
+pub trait NeighbourProvider {
+  /// The agents within `distance` of `position`. Returning agents further away
+  /// is allowed, but wasteful.
+  fn get_agents_near(&self, position: Vec3, distance: f32) -> Vec<Agent>;
+}
+
+impl<F: Fn(Vec3, f32) -> Vec<Agent>> NeighbourProvider for F {
+  fn get_agents_near(&self, position: Vec3, distance: f32) -> Vec<Agent> {
+    self(position, distance)
+  }
+}
+
+impl NeighbourProvider for Simulator {
+  fn get_agents_near(&self, position: Vec3, distance: f32) -> Vec<Agent> {
+    let distance_squared = distance * distance;
+    (0..self.get_agent_count())
+      .map(|agent_index| self.get_agent(agent_index))
+      .filter(|agent| {
+        agent.position.distance_squared(position) <= distance_squared
+      })
+      .cloned()
+      .collect()
+  }
+}
+
+#[cfg(test)]
+#[path = "neighbour_provider_test.rs"]
+mod test;
@@ -0,0 +1,44 @@
+use glam::{BVec3, Vec3};
+
+use super::*;
+use crate::{AgentParameters, SimulatorMargin};
+
+fn agent_at(position: Vec3) -> Agent {
+  Agent {
+    position,
+    velocity: Vec3::ZERO,
+    radius: 1.0,
+    avoidance_responsibility: 1.0,
+  }
+}
+
+#[test]
+fn simulator_provides_nearby_agents() {
+  let mut simulator = Simulator::new();
+  for position in
+    [Vec3::ZERO, Vec3::new(3.0, 0.0, 0.0), Vec3::new(0.0, 9.0, 0.0)]
+  {
+    simulator.add_agent(
+      agent_at(position),
+      AgentParameters {
+        goal_point: position,
+        max_speed: 1.0,
+        obstacle_margin: SimulatorMargin::AgentRadius,
+        time_horizon: 1.0,
+        obstacle_time_horizon: 1.0,
+        traffic_lane: None,
+        social_force: None,
+        leader_following: None,
+        yielding: None,
+        urgency: 0.0,
+        standing_height: None,
+        locked_axes: BVec3::FALSE,
+      },
+    );
+  }
+
+  assert_eq!(
+    simulator.get_agents_near(Vec3::new(1.0, 0.0, 0.0), 2.5),
+    [agent_at(Vec3::ZERO), agent_at(Vec3::new(3.0, 0.0, 0.0))]
+  );
+}
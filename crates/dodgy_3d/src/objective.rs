@@ -0,0 +1,102 @@
+use glam::Vec3;
+
+use crate::linear_programming::{
+  solve_linear_program_with_outcome, LinearProgramOutcome, Plane, SolverLimits,
+};
+
+/// Weights for secondary terms of the objective used to pick the avoiding
+/// velocity. With all weights at zero (the default), the avoiding velocity is
+/// the valid velocity closest to the preferred velocity. Larger weights make
+/// the corresponding motion more "expensive", so agents prefer other valid
+/// velocities instead. For example, drones can set `vertical_motion` so that
+/// they dodge sideways rather than climbing. Ground agents that must never
+/// move vertically should be restricted to the ground instead (e.g. with
+/// [`crate::Agent::compute_avoiding_velocity_on_terrain`]), since a penalty
+/// still allows some vertical motion when the crowd is dense enough.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ObjectiveWeights {
+  /// The weight of the squared vertical speed, relative to the squared
+  /// distance from the preferred velocity. Must not be negative. Note that
+  /// vertical speed is additionally limited to `max_speed * sqrt((1 +
+  /// velocity_change) / (1 + velocity_change + vertical_motion))`.
+  pub vertical_motion: f32,
+  /// The weight of the squared change from the agent's current velocity,
+  /// relative to the squared distance from the preferred velocity. Must not
+  /// be negative.
+  pub velocity_change: f32,
+}
+
+impl ObjectiveWeights {
+  fn is_zero(&self) -> bool {
+    self.vertical_motion == 0.0 && self.velocity_change == 0.0
+  }
+}
+
+/// Solves the linear program for the velocity minimizing the weighted
+/// objective. The weighted objective is a sum of squared distances along each
+/// axis, so scaling each axis turns it into a plain distance, which the linear
+/// program can minimize. The arguments and result match
+/// [`solve_linear_program_with_outcome`], with the addition of the agent's
+/// `current_velocity`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn solve_weighted_linear_program(
+  constraints: &[Plane],
+  rigid_constraint_count: usize,
+  radius: f32,
+  preferred_velocity: Vec3,
+  current_velocity: Vec3,
+  weights: &ObjectiveWeights,
+  epsilon: f32,
+  limits: &SolverLimits,
+) -> (Vec3, bool, LinearProgramOutcome) {
+  if weights.is_zero() {
+    return solve_linear_program_with_outcome(
+      constraints,
+      rigid_constraint_count,
+      radius,
+      preferred_velocity,
+      epsilon,
+      limits,
+    );
+  }
+
+  // Expanding the objective
+  //   |v - preferred|^2 + velocity_change * |v - current|^2
+  //     + vertical_motion * v.y^2
+  // gives a weight and a target along each axis.
+  let horizontal_weight = 1.0 + weights.velocity_change;
+  let vertical_weight = horizontal_weight + weights.vertical_motion;
+  let axis_weights =
+    Vec3::new(horizontal_weight, vertical_weight, horizontal_weight);
+  let target = (preferred_velocity
+    + weights.velocity_change * current_velocity)
+    / axis_weights;
+
+  let scale = axis_weights.powf(0.5);
+  let scaled_constraints = constraints
+    .iter()
+    .map(|constraint| Plane {
+      point: constraint.point * scale,
+      normal: (constraint.normal / scale).normalize(),
+    })
+    .collect::<Vec<_>>();
+
+  // The max speed sphere becomes an ellipsoid once scaled. Use the largest
+  // sphere inside that ellipsoid so the result never exceeds `radius`.
+  let scaled_radius = radius * scale.min_element();
+
+  let (scaled_velocity, limit_reached, outcome) =
+    solve_linear_program_with_outcome(
+      &scaled_constraints,
+      rigid_constraint_count,
+      scaled_radius,
+      target * scale,
+      epsilon,
+      limits,
+    );
+  (scaled_velocity / scale, limit_reached, outcome)
+}
+
+#[cfg(test)]
+#[path = "objective_test.rs"]
+mod test;
@@ -0,0 +1,88 @@
+use glam::Vec3;
+
+use super::*;
+use crate::linear_programming::{solve_linear_program, RVO_EPSILON};
+
+macro_rules! assert_vec_near {
+  ($left: expr, $right: expr, $eps: expr) => {{
+    let left = $left;
+    let right = $right;
+    let eps = $eps;
+    assert!(
+      left.distance(right) < eps,
+      "left: {}, right: {}, epsilon: {}",
+      left,
+      right,
+      eps
+    );
+  }};
+}
+
+#[test]
+fn zero_weights_match_linear_program() {
+  let constraints =
+    [Plane { point: Vec3::new(0.0, 0.0, 0.5), normal: Vec3::NEG_Z }];
+  let preferred_velocity = Vec3::new(0.0, 0.0, 1.0);
+
+  assert_eq!(
+    solve_weighted_linear_program(
+      &constraints,
+      /* rigid_constraint_count= */ 0,
+      /* radius= */ 2.0,
+      preferred_velocity,
+      /* current_velocity= */ Vec3::X,
+      &ObjectiveWeights::default(),
+      RVO_EPSILON,
+      &SolverLimits::default(),
+    )
+    .0,
+    solve_linear_program(
+      &constraints,
+      /* rigid_constraint_count= */ 0,
+      /* radius= */ 2.0,
+      preferred_velocity,
+      RVO_EPSILON,
+    )
+  );
+}
+
+#[test]
+fn vertical_motion_is_avoided() {
+  // The constraint can be satisfied equally well by moving up or sideways.
+  let constraints = [Plane {
+    point: Vec3::new(0.0, 0.5, 0.5),
+    normal: Vec3::new(0.0, 1.0, 1.0).normalize(),
+  }];
+
+  let velocity = solve_weighted_linear_program(
+    &constraints,
+    /* rigid_constraint_count= */ 0,
+    /* radius= */ 2.0,
+    /* preferred_velocity= */ Vec3::ZERO,
+    /* current_velocity= */ Vec3::ZERO,
+    &ObjectiveWeights { vertical_motion: 3.0, velocity_change: 0.0 },
+    RVO_EPSILON,
+    &SolverLimits::default(),
+  )
+  .0;
+
+  // Minimizing v.z^2 + 4 v.y^2 subject to v.y + v.z >= 1.
+  assert_vec_near!(velocity, Vec3::new(0.0, 0.2, 0.8), 1e-4);
+}
+
+#[test]
+fn velocity_change_pulls_towards_current_velocity() {
+  let velocity = solve_weighted_linear_program(
+    &[],
+    /* rigid_constraint_count= */ 0,
+    /* radius= */ 5.0,
+    /* preferred_velocity= */ Vec3::new(2.0, 0.0, 0.0),
+    /* current_velocity= */ Vec3::new(0.0, 0.0, 2.0),
+    &ObjectiveWeights { vertical_motion: 0.0, velocity_change: 1.0 },
+    RVO_EPSILON,
+    &SolverLimits::default(),
+  )
+  .0;
+
+  assert_vec_near!(velocity, Vec3::new(1.0, 0.0, 1.0), 1e-4);
+}
@@ -0,0 +1,198 @@
+use glam::Vec3;
+
+use crate::Obstacle;
+
+/// An axis-aligned bounding box.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Aabb {
+  pub(crate) min: Vec3,
+  pub(crate) max: Vec3,
+}
+
+impl Aabb {
+  /// The bounds of `obstacle`.
+  pub(crate) fn for_obstacle(obstacle: &Obstacle) -> Self {
+    let (Obstacle::Closed { vertices, bottom, top }
+    | Obstacle::Open { vertices, bottom, top }) = obstacle;
+    let (min, max) = vertices.iter().fold(
+      (Vec3::INFINITY, Vec3::NEG_INFINITY),
+      |(min, max), vertex| {
+        let vertex = Vec3::new(vertex.x, 0.0, vertex.y);
+        (min.min(vertex), max.max(vertex))
+      },
+    );
+    Self {
+      min: Vec3::new(min.x, *bottom, min.z),
+      max: Vec3::new(max.x, *top, max.z),
+    }
+  }
+
+  fn union(&self, other: &Aabb) -> Aabb {
+    Aabb { min: self.min.min(other.min), max: self.max.max(other.max) }
+  }
+
+  fn center(&self) -> Vec3 {
+    (self.min + self.max) * 0.5
+  }
+
+  pub(crate) fn distance_squared_to(&self, position: Vec3) -> f32 {
+    position.clamp(self.min, self.max).distance_squared(position)
+  }
+}
+
+/// A bounding volume hierarchy over obstacles, for quickly finding the
+/// obstacles near an agent. Obstacles that move (e.g. elevators or doors) can
+/// be updated with [`ObstacleBvh::refit`] without rebuilding the whole
+/// hierarchy. Refitting keeps the structure of the hierarchy, so queries get
+/// slower if obstacles move far from where they were when it was built.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ObstacleBvh {
+  nodes: Vec<Node>,
+  /// The index of the leaf node of each obstacle.
+  leaf_nodes: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+  bounds: Aabb,
+  parent: Option<usize>,
+  kind: NodeKind,
+}
+
+#[derive(Clone, Debug)]
+enum NodeKind {
+  /// A leaf containing the obstacle with this index.
+  Leaf(usize),
+  /// A branch with these child nodes.
+  Branch(usize, usize),
+}
+
+impl ObstacleBvh {
+  /// Builds the hierarchy for `obstacles`.
+  pub(crate) fn new(obstacles: &[Obstacle]) -> Self {
+    let mut bvh = Self {
+      nodes: Vec::with_capacity(obstacles.len() * 2),
+      leaf_nodes: vec![0; obstacles.len()],
+    };
+    let mut leaves = obstacles
+      .iter()
+      .enumerate()
+      .map(|(obstacle_index, obstacle)| {
+        (obstacle_index, Aabb::for_obstacle(obstacle))
+      })
+      .collect::<Vec<_>>();
+    if !leaves.is_empty() {
+      bvh.build_node(&mut leaves, None);
+    }
+    bvh
+  }
+
+  /// Builds the node containing `leaves`, returning its index. Leaves are
+  /// split in half along the axis their centers are most spread out on.
+  fn build_node(
+    &mut self,
+    leaves: &mut [(usize, Aabb)],
+    parent: Option<usize>,
+  ) -> usize {
+    let node_index = self.nodes.len();
+    if let [(obstacle_index, bounds)] = *leaves {
+      self.nodes.push(Node {
+        bounds,
+        parent,
+        kind: NodeKind::Leaf(obstacle_index),
+      });
+      self.leaf_nodes[obstacle_index] = node_index;
+      return node_index;
+    }
+
+    let (center_min, center_max) = leaves.iter().fold(
+      (Vec3::INFINITY, Vec3::NEG_INFINITY),
+      |(min, max), (_, bounds)| {
+        (min.min(bounds.center()), max.max(bounds.center()))
+      },
+    );
+    let extent = center_max - center_min;
+    let axis = if extent.x >= extent.y && extent.x >= extent.z {
+      0
+    } else if extent.y >= extent.z {
+      1
+    } else {
+      2
+    };
+    leaves.sort_by(|(_, left), (_, right)| {
+      left.center()[axis].total_cmp(&right.center()[axis])
+    });
+
+    // Push a placeholder so children can refer to their parent.
+    self.nodes.push(Node {
+      bounds: leaves[0].1,
+      parent,
+      kind: NodeKind::Branch(0, 0),
+    });
+    let (left_leaves, right_leaves) = leaves.split_at_mut(leaves.len() / 2);
+    let left = self.build_node(left_leaves, Some(node_index));
+    let right = self.build_node(right_leaves, Some(node_index));
+    self.nodes[node_index].bounds =
+      self.nodes[left].bounds.union(&self.nodes[right].bounds);
+    self.nodes[node_index].kind = NodeKind::Branch(left, right);
+    node_index
+  }
+
+  /// Updates the bounds of the obstacle at `obstacle_index` to fit `obstacle`,
+  /// along with the bounds of every node containing it.
+  pub(crate) fn refit(&mut self, obstacle_index: usize, obstacle: &Obstacle) {
+    let mut node_index = self.leaf_nodes[obstacle_index];
+    self.nodes[node_index].bounds = Aabb::for_obstacle(obstacle);
+    while let Some(parent_index) = self.nodes[node_index].parent {
+      let NodeKind::Branch(left, right) = self.nodes[parent_index].kind else {
+        unreachable!("parents are always branches");
+      };
+      self.nodes[parent_index].bounds =
+        self.nodes[left].bounds.union(&self.nodes[right].bounds);
+      node_index = parent_index;
+    }
+  }
+
+  /// Finds the indices of the obstacles whose bounds are within `distance` of
+  /// `position`.
+  #[cfg(test)]
+  pub(crate) fn query(&self, position: Vec3, distance: f32) -> Vec<usize> {
+    let mut obstacle_indices = Vec::new();
+    self.query_into(position, distance, &mut Vec::new(), &mut obstacle_indices);
+    obstacle_indices
+  }
+
+  /// Same as [`Self::query`], but appends the indices to `obstacle_indices`,
+  /// using `stack` as scratch space (so callers can reuse both buffers).
+  pub(crate) fn query_into(
+    &self,
+    position: Vec3,
+    distance: f32,
+    stack: &mut Vec<usize>,
+    obstacle_indices: &mut Vec<usize>,
+  ) {
+    if self.nodes.is_empty() {
+      return;
+    }
+    let distance_squared = distance * distance;
+    stack.clear();
+    stack.push(0);
+    while let Some(node_index) = stack.pop() {
+      let node = &self.nodes[node_index];
+      if node.bounds.distance_squared_to(position) > distance_squared {
+        continue;
+      }
+      match node.kind {
+        NodeKind::Leaf(obstacle_index) => obstacle_indices.push(obstacle_index),
+        NodeKind::Branch(left, right) => {
+          stack.push(left);
+          stack.push(right);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+#[path = "obstacle_bvh_test.rs"]
+mod test;
@@ -0,0 +1,62 @@
+use glam::{Vec2, Vec3};
+
+use super::*;
+
+fn wall_at(x: f32, z: f32) -> Obstacle {
+  Obstacle::Open {
+    vertices: vec![Vec2::new(x, z), Vec2::new(x + 1.0, z)],
+    bottom: 0.0,
+    top: 2.0,
+  }
+}
+
+fn sorted(mut indices: Vec<usize>) -> Vec<usize> {
+  indices.sort();
+  indices
+}
+
+#[test]
+fn bounds_cover_obstacle() {
+  assert_eq!(
+    Aabb::for_obstacle(&wall_at(1.0, 2.0)),
+    Aabb { min: Vec3::new(1.0, 0.0, 2.0), max: Vec3::new(2.0, 2.0, 2.0) }
+  );
+}
+
+#[test]
+fn finds_nearby_obstacles() {
+  let obstacles = (0..20)
+    .map(|index| wall_at(index as f32 * 10.0, (index % 3) as f32 * 10.0))
+    .collect::<Vec<_>>();
+  let bvh = ObstacleBvh::new(&obstacles);
+
+  for (position, distance) in [
+    (Vec3::new(0.0, 0.0, 0.0), 1.0),
+    (Vec3::new(55.0, 1.0, 10.0), 12.0),
+    (Vec3::new(100.0, 50.0, 0.0), 60.0),
+    (Vec3::new(-100.0, 0.0, 0.0), 10.0),
+  ] {
+    let expected = (0..obstacles.len())
+      .filter(|&index| {
+        Aabb::for_obstacle(&obstacles[index]).distance_squared_to(position)
+          <= distance * distance
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(sorted(bvh.query(position, distance)), expected);
+  }
+
+  assert!(ObstacleBvh::new(&[]).query(Vec3::ZERO, 100.0).is_empty());
+}
+
+#[test]
+fn refit_tracks_moved_obstacles() {
+  let mut obstacles =
+    (0..8).map(|index| wall_at(index as f32 * 10.0, 0.0)).collect::<Vec<_>>();
+  let mut bvh = ObstacleBvh::new(&obstacles);
+  assert_eq!(bvh.query(Vec3::new(30.0, 0.0, 20.0), 5.0), [0; 0]);
+
+  obstacles[3] = wall_at(30.0, 18.0);
+  bvh.refit(3, &obstacles[3]);
+  assert_eq!(bvh.query(Vec3::new(30.0, 0.0, 20.0), 5.0), [3]);
+  assert_eq!(bvh.query(Vec3::new(30.0, 0.0, 0.0), 1.0), [0; 0]);
+}
@@ -0,0 +1,81 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{obstacles::get_closest_point, Agent, Obstacle};
+
+/// Parameters for nudging preferred velocities away from nearby obstacles.
+/// Obstacles only constrain velocities once a collision is imminent, and the
+/// solver then picks the closest allowed velocity, which slides along the
+/// obstacle. So agents heading past a wall at a shallow angle hug it and
+/// scrape along it. Nudging the preferred velocity along the gradient of the
+/// distance to obstacles keeps agents a little away from walls instead. See
+/// [`nudge_away_from_obstacles`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct ObstacleNudge {
+  /// The distance from obstacles (beyond the agent's radius) within which the
+  /// agent is nudged. The nudge fades out towards this distance.
+  pub influence_distance: f32,
+  /// How strongly the agent is pushed away from obstacles (as a fraction of
+  /// its preferred speed), in addition to cancelling its movement into them.
+  pub strength: f32,
+}
+
+impl Default for ObstacleNudge {
+  fn default() -> Self {
+    Self { influence_distance: 1.0, strength: 0.25 }
+  }
+}
+
+/// Nudges the `preferred_velocity` of `agent` away from the `obstacles`
+/// within `nudge.influence_distance`, keeping its speed. Closer obstacles
+/// nudge harder: the part of the preferred velocity heading into them is
+/// cancelled, and the agent is pushed away from them. This is meant to be
+/// used on the preferred velocity before computing the avoiding velocity.
+/// Obstacles are still avoided regardless of the nudge.
+pub fn nudge_away_from_obstacles(
+  agent: &Agent,
+  obstacles: &[Cow<'_, Obstacle>],
+  preferred_velocity: Vec3,
+  nudge: &ObstacleNudge,
+) -> Vec3 {
+  assert!(
+    nudge.influence_distance > 0.0,
+    "influence_distance must be positive, was {}",
+    nudge.influence_distance
+  );
+  let speed = preferred_velocity.length();
+  if speed == 0.0 {
+    return preferred_velocity;
+  }
+
+  let mut correction = Vec3::ZERO;
+  for obstacle in obstacles {
+    let Some((closest_point, fallback_normal)) =
+      get_closest_point(agent.position, obstacle)
+    else {
+      continue;
+    };
+    let offset = agent.position - closest_point;
+    let distance = offset.length();
+    let gap = distance - agent.radius;
+    if gap >= nudge.influence_distance {
+      continue;
+    }
+    // The gradient of the distance to the obstacle points directly away from
+    // the closest point.
+    let away = if distance > 0.0 { offset / distance } else { fallback_normal };
+    let falloff = 1.0 - gap.max(0.0) / nudge.influence_distance;
+    let into_obstacle = (-preferred_velocity.dot(away)).max(0.0);
+    correction += away * (falloff * (into_obstacle + nudge.strength * speed));
+  }
+
+  if correction == Vec3::ZERO {
+    return preferred_velocity;
+  }
+  (preferred_velocity + correction).normalize_or_zero() * speed
+}
+
+#[cfg(test)]
+#[path = "obstacle_nudge_test.rs"]
+mod test;
@@ -0,0 +1,97 @@
+use std::borrow::Cow;
+
+use glam::{Vec2, Vec3};
+
+use crate::{
+  nudge_away_from_obstacles, Agent, AvoidanceOptions, Obstacle, ObstacleNudge,
+};
+
+/// A wall along the X axis from 0 to 10, which is clear on the -Z side.
+fn wall() -> Cow<'static, Obstacle> {
+  Cow::Owned(Obstacle::Open {
+    vertices: vec![Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)],
+    bottom: -10.0,
+    top: 10.0,
+  })
+}
+
+#[test]
+fn distant_obstacles_do_not_nudge() {
+  let agent =
+    Agent::builder().position(Vec3::new(5.0, 0.0, -2.0)).radius(0.5).build();
+
+  assert_eq!(
+    nudge_away_from_obstacles(
+      &agent,
+      &[wall()],
+      Vec3::new(1.0, 0.0, 1.0),
+      &ObstacleNudge::default(),
+    ),
+    Vec3::new(1.0, 0.0, 1.0)
+  );
+}
+
+#[test]
+fn nearby_obstacles_turn_velocity_away() {
+  let agent =
+    Agent::builder().position(Vec3::new(5.0, 0.0, -0.75)).radius(0.5).build();
+
+  let velocity = nudge_away_from_obstacles(
+    &agent,
+    &[wall()],
+    Vec3::new(2.0, 0.0, 1.0),
+    &ObstacleNudge::default(),
+  );
+  // The agent no longer heads into the wall, and keeps its speed.
+  assert!(velocity.z < 0.0, "{velocity}");
+  assert!(velocity.x > 0.0, "{velocity}");
+  assert!((velocity.length() - 5.0f32.sqrt()).abs() < 1e-4, "{velocity}");
+}
+
+#[test]
+fn nudged_agents_do_not_scrape_along_walls() {
+  // The goal is just past the end of the wall, so the preferred velocity
+  // heads slightly into the wall the whole way along it.
+  let goal = Vec3::new(12.0, 0.0, 0.5);
+  let run = |nudge: Option<ObstacleNudge>| {
+    let mut agent =
+      Agent::builder().position(Vec3::new(-1.0, 0.0, -1.0)).radius(0.5).build();
+    let obstacles = [wall()];
+    let options = AvoidanceOptions::builder().obstacle_margin(0.5).build();
+    let mut closest_gap = f32::INFINITY;
+    for _ in 0..400 {
+      let mut preferred_velocity =
+        (goal - agent.position).normalize_or_zero() * 1.5;
+      if let Some(nudge) = &nudge {
+        preferred_velocity = nudge_away_from_obstacles(
+          &agent,
+          &obstacles,
+          preferred_velocity,
+          nudge,
+        );
+      }
+      agent.velocity = agent.compute_avoiding_velocity(
+        &[],
+        &obstacles,
+        preferred_velocity,
+        1.5,
+        0.05,
+        &options,
+      );
+      agent.position += agent.velocity * 0.05;
+      if (2.0..8.0).contains(&agent.position.x) {
+        closest_gap = closest_gap.min(-agent.position.z - agent.radius);
+      }
+    }
+    (closest_gap, agent.position)
+  };
+
+  let (plain_gap, plain_end) = run(None);
+  let (nudged_gap, nudged_end) = run(Some(ObstacleNudge::default()));
+  // Without the nudge, the agent slides along the wall at the margin.
+  assert!(plain_gap < 0.05, "{plain_gap}");
+  assert!(nudged_gap > 0.25, "{nudged_gap}");
+  // Both agents still reach the goal.
+  assert!(plain_end.distance(goal) < 0.1, "{plain_end}");
+  assert!(nudged_end.distance(goal) < 0.1, "{nudged_end}");
+}
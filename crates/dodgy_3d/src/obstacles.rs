@@ -0,0 +1,244 @@
+use glam::{Vec2, Vec3, Vec3Swizzles};
+
+use crate::{linear_programming::Plane, Agent};
+
+/// A single obstacle in the simulation. Obstacles are 2D polygons on the
+/// horizontal XZ plane (where the `x` of each vertex is the X coordinate, and
+/// the `y` of each vertex is the Z coordinate), extruded vertically from
+/// `bottom` to `top`. This describes walls and buildings, and is the format
+/// commonly produced by navigation meshes and level editors.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Obstacle {
+  /// A closed obstacle. The obstacle is closed in that the last vertex will
+  /// have an edge connecting it to the first vertex. The edges cannot cross,
+  /// and the interior of the obstacle is to the "left" of the edges. In
+  /// other words, obstacles with vertices going counter-clockwise will
+  /// prevent objects from getting into the loop, and obstacles with vertices
+  /// going clockwise will prevent objects from leaving the loop. Obstacles
+  /// going counter-clockwise are also capped at `bottom` and `top`, so agents
+  /// can fly over or under them.
+  Closed { vertices: Vec<Vec2>, bottom: f32, top: f32 },
+  /// An open obstacle. The vertices are assumed to be a part of some closed
+  /// obstacle, so the left of the edge is solid, and the right is clear.
+  Open { vertices: Vec<Vec2>, bottom: f32, top: f32 },
+}
+
+/// Computes the planes describing the half-spaces of valid velocities for
+/// `agent` induced by `obstacle`. `time_horizon` determines how much time in
+/// the future should collisions be considered for this obstacle. All planes
+/// are satisfied by a velocity of zero.
+pub fn get_planes_for_agent_to_obstacle(
+  agent: &Agent,
+  obstacle: &Obstacle,
+  obstacle_margin: f32,
+  time_horizon: f32,
+) -> Vec<Plane> {
+  get_closest_points(agent.position, obstacle)
+    .map(|(closest_point, fallback_normal)| {
+      get_plane_for_closest_point(
+        agent,
+        closest_point,
+        fallback_normal,
+        obstacle_margin,
+        time_horizon,
+      )
+    })
+    .collect()
+}
+
+/// Same as [`get_planes_for_agent_to_obstacle`], but for an obstacle that only
+/// blocks movement against `allowed_direction` (e.g. a turnstile). Each plane
+/// only blocks velocities heading against its normal, so planes blocking
+/// velocities along `allowed_direction` are dropped, letting the agent pass
+/// through the obstacle in that direction.
+pub(crate) fn get_planes_for_agent_to_one_way_obstacle(
+  agent: &Agent,
+  obstacle: &Obstacle,
+  allowed_direction: Vec3,
+  obstacle_margin: f32,
+  time_horizon: f32,
+) -> Vec<Plane> {
+  let mut planes = get_planes_for_agent_to_obstacle(
+    agent,
+    obstacle,
+    obstacle_margin,
+    time_horizon,
+  );
+  planes.retain(|plane| plane.normal.dot(allowed_direction) >= 0.0);
+  planes
+}
+
+/// Computes the distance from `position` to the closest part of `obstacle`
+/// that would be avoided by an agent at `position`. Returns infinity if no
+/// part of `obstacle` would be avoided.
+pub(crate) fn get_distance_to_obstacle(
+  position: Vec3,
+  obstacle: &Obstacle,
+) -> f32 {
+  get_closest_points(position, obstacle)
+    .map(|(closest_point, _)| closest_point.distance(position))
+    .fold(f32::INFINITY, f32::min)
+}
+
+/// Finds the closest point to `position` on the part of `obstacle` that would
+/// be avoided by an agent at `position`, along with the outward normal of the
+/// face it is on. Returns None if no part of `obstacle` would be avoided.
+pub(crate) fn get_closest_point(
+  position: Vec3,
+  obstacle: &Obstacle,
+) -> Option<(Vec3, Vec3)> {
+  get_closest_points(position, obstacle).min_by(|(a, _), (b, _)| {
+    a.distance_squared(position).total_cmp(&b.distance_squared(position))
+  })
+}
+
+/// Finds the closest point to `position` on each face of `obstacle` that is
+/// facing `position`, along with the outward normal of the face.
+fn get_closest_points(
+  position: Vec3,
+  obstacle: &Obstacle,
+) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+  let (vertices, bottom, top, closed) = match obstacle {
+    Obstacle::Closed { vertices, bottom, top } => {
+      (vertices, *bottom, *top, true)
+    }
+    Obstacle::Open { vertices, bottom, top } => {
+      (vertices, *bottom, *top, false)
+    }
+  };
+
+  let edge_count =
+    if closed { vertices.len() } else { vertices.len().saturating_sub(1) };
+  let walls = (0..edge_count).filter_map(move |left_index| {
+    let right_index = (left_index + 1) % vertices.len();
+    get_closest_point_on_wall(
+      position,
+      vertices[left_index],
+      vertices[right_index],
+      bottom,
+      top,
+    )
+  });
+
+  let cap = if closed
+    && !vertices.is_empty()
+    && is_counter_clockwise(vertices)
+    && contains(vertices, position.xz())
+  {
+    // The position is directly above or below the obstacle, so the cap of the
+    // obstacle must be avoided.
+    if position.y >= top {
+      Some((Vec3::new(position.x, top, position.z), Vec3::Y))
+    } else if position.y <= bottom {
+      Some((Vec3::new(position.x, bottom, position.z), Vec3::NEG_Y))
+    } else {
+      // The position is inside the obstacle, so there is no reasonable cap to
+      // avoid.
+      None
+    }
+  } else {
+    None
+  };
+
+  walls.chain(cap)
+}
+
+/// Finds the closest point to `position` on the wall formed by extruding the
+/// edge from `left_vertex` to `right_vertex` vertically from `bottom` to
+/// `top`, along with the outward normal of the wall. Returns None if
+/// `position` is behind the wall.
+fn get_closest_point_on_wall(
+  position: Vec3,
+  left_vertex: Vec2,
+  right_vertex: Vec2,
+  bottom: f32,
+  top: f32,
+) -> Option<(Vec3, Vec3)> {
+  let edge = right_vertex - left_vertex;
+  let relative_position = position.xz() - left_vertex;
+  if edge.perp_dot(relative_position) > 0.0 {
+    // The position is on the solid side of the wall, so this wall is a back
+    // face and is ignored.
+    return None;
+  }
+
+  let edge_length_squared = edge.length_squared();
+  let t = if edge_length_squared == 0.0 {
+    0.0
+  } else {
+    (relative_position.dot(edge) / edge_length_squared).clamp(0.0, 1.0)
+  };
+  let closest_horizontal_point = left_vertex + edge * t;
+  // Since the wall is vertical, the closest point can be found separately
+  // along the ground and along the height.
+  let closest_point = Vec3::new(
+    closest_horizontal_point.x,
+    position.y.clamp(bottom, top),
+    closest_horizontal_point.y,
+  );
+
+  // The right side of the edge is clear.
+  let outward_normal = Vec3::new(edge.y, 0.0, -edge.x).normalize_or_zero();
+  Some((closest_point, outward_normal))
+}
+
+/// Creates the plane preventing `agent` from getting (or moving) closer than
+/// `obstacle_margin` to `closest_point` within `time_horizon`.
+/// `fallback_normal` is used if the agent is exactly at `closest_point`.
+fn get_plane_for_closest_point(
+  agent: &Agent,
+  closest_point: Vec3,
+  fallback_normal: Vec3,
+  obstacle_margin: f32,
+  time_horizon: f32,
+) -> Plane {
+  let offset = agent.position - closest_point;
+  let distance = offset.length();
+  let normal = if distance > 0.0 { offset / distance } else { fallback_normal };
+
+  if distance > obstacle_margin {
+    // The agent may move towards the obstacle, but only fast enough to reach
+    // the margin after `time_horizon`.
+    Plane {
+      point: -normal * ((distance - obstacle_margin) / time_horizon),
+      normal,
+    }
+  } else {
+    // The agent is already colliding with the obstacle, so just prevent it
+    // from getting any closer.
+    Plane { point: Vec3::ZERO, normal }
+  }
+}
+
+/// Determines whether the polygon defined by `vertices` goes counter-clockwise
+/// (in the same sense as [`Obstacle::Closed`]).
+fn is_counter_clockwise(vertices: &[Vec2]) -> bool {
+  let twice_area = (0..vertices.len())
+    .map(|index| {
+      vertices[index].perp_dot(vertices[(index + 1) % vertices.len()])
+    })
+    .sum::<f32>();
+  twice_area > 0.0
+}
+
+/// Determines whether `point` is inside the polygon defined by `vertices`.
+fn contains(vertices: &[Vec2], point: Vec2) -> bool {
+  // Count the number of edges crossed by a ray going in the +X direction.
+  let mut inside = false;
+  for index in 0..vertices.len() {
+    let start = vertices[index];
+    let end = vertices[(index + 1) % vertices.len()];
+    if (start.y > point.y) != (end.y > point.y) {
+      let crossing_x =
+        start.x + (point.y - start.y) / (end.y - start.y) * (end.x - start.x);
+      if point.x < crossing_x {
+        inside = !inside;
+      }
+    }
+  }
+  inside
+}
+
+#[cfg(test)]
+#[path = "obstacles_test.rs"]
+mod test;
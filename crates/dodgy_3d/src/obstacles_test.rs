@@ -0,0 +1,178 @@
+use super::*;
+
+macro_rules! assert_planes_eq {
+  ($a: expr, $b: expr) => {{
+    let a = $a;
+    let b = $b;
+
+    assert_eq!(a.len(), b.len(), "\n  left: {:?}\n right: {:?}", a, b);
+    for (a_plane, b_plane) in a.iter().zip(b.iter()) {
+      assert!(
+        a_plane.point.distance_squared(b_plane.point) < 1e-5
+          && a_plane.normal.distance_squared(b_plane.normal) < 1e-5,
+        "\n  left: {:?}\n right: {:?}",
+        a,
+        b
+      );
+    }
+  }};
+}
+
+fn agent_at(position: Vec3) -> Agent {
+  Agent {
+    position,
+    velocity: Vec3::ZERO,
+    radius: 1.0,
+    avoidance_responsibility: 1.0,
+  }
+}
+
+fn wall() -> Obstacle {
+  Obstacle::Open {
+    vertices: vec![Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0)],
+    bottom: -1.0,
+    top: 1.0,
+  }
+}
+
+#[test]
+fn approaching_wall_is_slowed() {
+  let planes = get_planes_for_agent_to_obstacle(
+    &agent_at(Vec3::new(0.0, 0.0, -3.0)),
+    &wall(),
+    /* obstacle_margin= */ 1.0,
+    /* time_horizon= */ 2.0,
+  );
+  assert_planes_eq!(
+    planes,
+    [Plane { point: Vec3::new(0.0, 0.0, 1.0), normal: Vec3::NEG_Z }]
+  );
+}
+
+#[test]
+fn backwards_wall_is_ignored() {
+  let planes = get_planes_for_agent_to_obstacle(
+    &agent_at(Vec3::new(0.0, 0.0, 3.0)),
+    &wall(),
+    /* obstacle_margin= */ 1.0,
+    /* time_horizon= */ 2.0,
+  );
+  assert!(planes.is_empty(), "{:?}", planes);
+}
+
+#[test]
+fn agent_above_wall_avoids_top_edge() {
+  let planes = get_planes_for_agent_to_obstacle(
+    &agent_at(Vec3::new(0.0, 4.0, -3.0)),
+    &wall(),
+    /* obstacle_margin= */ 0.0,
+    /* time_horizon= */ 1.0,
+  );
+  let normal = Vec3::new(0.0, 1.0, -1.0).normalize();
+  assert_planes_eq!(
+    planes,
+    [Plane { point: -normal * 18.0f32.sqrt(), normal }]
+  );
+}
+
+#[test]
+fn colliding_agent_cannot_get_closer() {
+  let planes = get_planes_for_agent_to_obstacle(
+    &agent_at(Vec3::new(0.0, 0.0, -0.5)),
+    &wall(),
+    /* obstacle_margin= */ 1.0,
+    /* time_horizon= */ 2.0,
+  );
+  assert_planes_eq!(planes, [Plane { point: Vec3::ZERO, normal: Vec3::NEG_Z }]);
+}
+
+#[test]
+fn agent_above_building_avoids_roof() {
+  let building = Obstacle::Closed {
+    vertices: vec![
+      Vec2::new(-1.0, -1.0),
+      Vec2::new(1.0, -1.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(-1.0, 1.0),
+    ],
+    bottom: 0.0,
+    top: 2.0,
+  };
+
+  let planes = get_planes_for_agent_to_obstacle(
+    &agent_at(Vec3::new(0.0, 5.0, 0.0)),
+    &building,
+    /* obstacle_margin= */ 1.0,
+    /* time_horizon= */ 1.0,
+  );
+  // All walls are back faces, so only the roof is avoided.
+  assert_planes_eq!(
+    planes,
+    [Plane { point: Vec3::new(0.0, -2.0, 0.0), normal: Vec3::Y }]
+  );
+
+  let planes = get_planes_for_agent_to_obstacle(
+    &agent_at(Vec3::new(0.0, -3.0, 0.0)),
+    &building,
+    /* obstacle_margin= */ 1.0,
+    /* time_horizon= */ 1.0,
+  );
+  assert_planes_eq!(
+    planes,
+    [Plane { point: Vec3::new(0.0, 2.0, 0.0), normal: Vec3::NEG_Y }]
+  );
+}
+
+#[test]
+fn clockwise_room_has_no_roof() {
+  let room = Obstacle::Closed {
+    vertices: vec![
+      Vec2::new(-10.0, -10.0),
+      Vec2::new(-10.0, 10.0),
+      Vec2::new(10.0, 10.0),
+      Vec2::new(10.0, -10.0),
+    ],
+    bottom: 0.0,
+    top: 2.0,
+  };
+
+  let planes = get_planes_for_agent_to_obstacle(
+    &agent_at(Vec3::new(0.0, 1.0, 0.0)),
+    &room,
+    /* obstacle_margin= */ 1.0,
+    /* time_horizon= */ 1.0,
+  );
+  // Each of the four walls keeps the agent inside the room.
+  assert_planes_eq!(
+    planes,
+    [
+      Plane { point: Vec3::new(-9.0, 0.0, 0.0), normal: Vec3::X },
+      Plane { point: Vec3::new(0.0, 0.0, 9.0), normal: Vec3::NEG_Z },
+      Plane { point: Vec3::new(9.0, 0.0, 0.0), normal: Vec3::NEG_X },
+      Plane { point: Vec3::new(0.0, 0.0, -9.0), normal: Vec3::Z },
+    ]
+  );
+}
+
+#[test]
+fn distance_to_obstacle_is_to_closest_point() {
+  let room = Obstacle::Closed {
+    vertices: vec![
+      Vec2::new(-10.0, -10.0),
+      Vec2::new(-10.0, 10.0),
+      Vec2::new(10.0, 10.0),
+      Vec2::new(10.0, -10.0),
+    ],
+    bottom: 0.0,
+    top: 2.0,
+  };
+  assert!(
+    (get_distance_to_obstacle(Vec3::new(6.0, 1.0, 0.0), &room) - 4.0).abs()
+      < 1e-5
+  );
+  // Above the walls, the closest point is on the top edge.
+  assert!(
+    (get_distance_to_obstacle(Vec3::new(6.0, 5.0, 0.0), &room) - 5.0).abs()
+      < 1e-5
+  );
+}
@@ -0,0 +1,84 @@
+use glam::{Mat3, Quat, Vec3};
+
+/// Parameters for [`suggest_orientation`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct OrientationOptions {
+  /// The axis that the agent faces along in its local space (e.g. `Vec3::NEG_Z`
+  /// or `Vec3::Z`, depending on the engine). Must be horizontal and unit
+  /// length. The local up axis is always `Vec3::Y`.
+  pub forward_axis: Vec3,
+  /// The maximum rate the agent can turn at, in radians per second.
+  pub max_turn_rate: f32,
+  /// The speed below which the agent keeps its current orientation. Agents
+  /// that are (nearly) stopped otherwise flip around wildly, since the
+  /// direction of a tiny velocity is mostly noise.
+  pub min_speed: f32,
+  /// How long (in seconds) it takes the agent to turn most of the way
+  /// (about 63%) towards its velocity, ignoring `max_turn_rate`. Zero means no
+  /// smoothing.
+  pub smoothing_time: f32,
+  /// Whether to only turn around the vertical axis (e.g. for ground agents
+  /// that should never pitch up or down).
+  pub keep_upright: bool,
+}
+
+/// Suggests the orientation an agent should have after `time_step`, turning
+/// from its `current_orientation` to face along its `velocity` (usually the
+/// avoiding velocity). The agent stays upright (its local up axis stays in
+/// the vertical plane containing its forward direction), the turn is smoothed,
+/// and the turn is limited by the max turn rate.
+pub fn suggest_orientation(
+  current_orientation: Quat,
+  velocity: Vec3,
+  time_step: f32,
+  options: &OrientationOptions,
+) -> Quat {
+  if velocity.length_squared() < options.min_speed * options.min_speed {
+    return current_orientation;
+  }
+
+  let direction = if options.keep_upright {
+    Vec3::new(velocity.x, 0.0, velocity.z)
+  } else {
+    velocity
+  };
+  let Some(target_orientation) =
+    get_facing_orientation(direction, options.forward_axis)
+  else {
+    // The velocity is (nearly) straight up or down for an upright agent, so
+    // there is no good direction to face.
+    return current_orientation;
+  };
+
+  let angle = current_orientation.angle_between(target_orientation);
+  if angle == 0.0 {
+    return target_orientation;
+  }
+
+  let smoothed_angle = if options.smoothing_time > 0.0 {
+    angle * (1.0 - (-time_step / options.smoothing_time).exp())
+  } else {
+    angle
+  };
+  let turn_angle = smoothed_angle.min(options.max_turn_rate * time_step);
+  current_orientation.slerp(target_orientation, turn_angle / angle)
+}
+
+/// Computes the upright orientation that turns `forward_axis` to face along
+/// `direction`. Returns None if the direction is too close to vertical (or
+/// zero) to determine the orientation.
+fn get_facing_orientation(direction: Vec3, forward_axis: Vec3) -> Option<Quat> {
+  let forward = direction.try_normalize()?;
+  let right = forward.cross(Vec3::Y).try_normalize()?;
+  let up = right.cross(forward);
+
+  let local_right = forward_axis.cross(Vec3::Y);
+  let world_basis = Mat3::from_cols(forward, up, right);
+  let local_basis = Mat3::from_cols(forward_axis, Vec3::Y, local_right);
+  // The local basis is orthonormal, so its inverse is its transpose.
+  Some(Quat::from_mat3(&(world_basis * local_basis.transpose())).normalize())
+}
+
+#[cfg(test)]
+#[path = "orientation_test.rs"]
+mod test;
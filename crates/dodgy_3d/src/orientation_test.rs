@@ -0,0 +1,106 @@
+use glam::{Quat, Vec3};
+
+use super::*;
+
+macro_rules! assert_vec_near {
+  ($left: expr, $right: expr, $eps: expr) => {{
+    let left = $left;
+    let right = $right;
+    let eps = $eps;
+    assert!(
+      left.distance(right) < eps,
+      "left: {}, right: {}, epsilon: {}",
+      left,
+      right,
+      eps
+    );
+  }};
+}
+
+fn options() -> OrientationOptions {
+  OrientationOptions {
+    forward_axis: Vec3::NEG_Z,
+    max_turn_rate: std::f32::consts::PI,
+    min_speed: 0.1,
+    smoothing_time: 0.0,
+    keep_upright: false,
+  }
+}
+
+#[test]
+fn faces_velocity() {
+  let orientation = suggest_orientation(
+    Quat::IDENTITY,
+    Vec3::new(1.0, 0.0, -1.0),
+    /* time_step= */ 1.0,
+    &options(),
+  );
+  assert_vec_near!(
+    orientation * Vec3::NEG_Z,
+    Vec3::new(1.0, 0.0, -1.0).normalize(),
+    1e-5
+  );
+  assert_vec_near!(orientation * Vec3::Y, Vec3::Y, 1e-5);
+}
+
+#[test]
+fn pitches_unless_upright() {
+  let velocity = Vec3::new(0.0, 1.0, -1.0);
+  let orientation =
+    suggest_orientation(Quat::IDENTITY, velocity, 1.0, &options());
+  assert_vec_near!(orientation * Vec3::NEG_Z, velocity.normalize(), 1e-5);
+
+  let orientation = suggest_orientation(
+    Quat::IDENTITY,
+    velocity,
+    1.0,
+    &OrientationOptions { keep_upright: true, ..options() },
+  );
+  assert_vec_near!(orientation * Vec3::NEG_Z, Vec3::NEG_Z, 1e-5);
+}
+
+#[test]
+fn slow_or_vertical_velocity_keeps_orientation() {
+  let current_orientation = Quat::from_rotation_y(1.0);
+  assert_eq!(
+    suggest_orientation(
+      current_orientation,
+      Vec3::new(0.05, 0.0, 0.0),
+      1.0,
+      &options()
+    ),
+    current_orientation
+  );
+  assert_eq!(
+    suggest_orientation(
+      current_orientation,
+      Vec3::new(0.0, 5.0, 0.0),
+      1.0,
+      &options()
+    ),
+    current_orientation
+  );
+}
+
+#[test]
+fn turning_is_limited_and_smoothed() {
+  // Turning to face +X from -Z is a quarter turn.
+  let limited = suggest_orientation(
+    Quat::IDENTITY,
+    Vec3::X,
+    /* time_step= */ 0.25,
+    &OrientationOptions { max_turn_rate: 1.0, ..options() },
+  );
+  assert!((limited.angle_between(Quat::IDENTITY) - 0.25).abs() < 1e-4);
+
+  let smoothed = suggest_orientation(
+    Quat::IDENTITY,
+    Vec3::X,
+    /* time_step= */ 0.5,
+    &OrientationOptions { smoothing_time: 0.5, ..options() },
+  );
+  let expected_angle = std::f32::consts::FRAC_PI_2 * (1.0 - (-1.0f32).exp());
+  assert!(
+    (smoothed.angle_between(Quat::IDENTITY) - expected_angle).abs() < 1e-4
+  );
+}
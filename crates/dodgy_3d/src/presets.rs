@@ -0,0 +1,73 @@
+use glam::Vec3;
+
+use crate::{
+  AgentParameters, AvoidanceOptions, ObjectiveWeights, SimulatorMargin,
+};
+
+/// Presets tuned for common kinds of agents, in meters and seconds. These are
+/// starting points: tune them (e.g. with [`AvoidanceOptions::builder`]) once
+/// the basics work.
+impl AvoidanceOptions {
+  /// Options for people walking in crowds (radius around 0.3 m, walking at
+  /// 1.4 m/s). Pedestrians anticipate each other a few seconds ahead and can
+  /// brush past walls.
+  pub fn pedestrian() -> Self {
+    Self::builder()
+      .obstacle_margin(0.05)
+      .time_horizon(3.0)
+      .obstacle_time_horizon(1.0)
+      .build()
+  }
+
+  /// Options for small flying drones (radius around 0.5 m, flying at up to
+  /// 5 m/s). Drones keep a generous margin from obstacles, and prefer dodging
+  /// sideways to climbing or diving.
+  pub fn drone() -> Self {
+    Self::builder()
+      .obstacle_margin(0.5)
+      .time_horizon(2.0)
+      .obstacle_time_horizon(1.5)
+      .objective_weights(ObjectiveWeights {
+        vertical_motion: 2.0,
+        velocity_change: 0.0,
+      })
+      .build()
+  }
+
+  /// Options for ground vehicles like carts or robots (radius around 1 m,
+  /// driving at up to 10 m/s). Vehicles look further ahead, since they
+  /// cover more ground, and prefer keeping their current velocity, since they
+  /// cannot turn on the spot.
+  pub fn vehicle() -> Self {
+    Self::builder()
+      .obstacle_margin(0.5)
+      .time_horizon(5.0)
+      .obstacle_time_horizon(2.0)
+      .objective_weights(ObjectiveWeights {
+        vertical_motion: 0.0,
+        velocity_change: 1.0,
+      })
+      .build()
+  }
+}
+
+impl AgentParameters {
+  /// Parameters for a person walking at 1.4 m/s, standing 1.8 m tall on the
+  /// ground. The goal point is the origin, so set `goal_point` before adding
+  /// the agent. Pair with [`AvoidanceOptions::pedestrian`] and an agent radius
+  /// of around 0.3 m.
+  pub fn humanoid() -> Self {
+    Self::builder()
+      .goal_point(Vec3::ZERO)
+      .max_speed(1.4)
+      .obstacle_margin(SimulatorMargin::Distance(0.05))
+      .time_horizon(3.0)
+      .obstacle_time_horizon(1.0)
+      .standing_height(1.8)
+      .build()
+  }
+}
+
+#[cfg(test)]
+#[path = "presets_test.rs"]
+mod test;
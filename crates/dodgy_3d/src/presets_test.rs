@@ -0,0 +1,47 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{Agent, AgentParameters, AvoidanceOptions};
+
+#[test]
+fn humanoid_matches_pedestrian_options() {
+  let parameters = AgentParameters::humanoid();
+  let options = AvoidanceOptions::pedestrian();
+  assert_eq!(parameters.time_horizon, options.time_horizon);
+  assert_eq!(parameters.obstacle_time_horizon, options.obstacle_time_horizon);
+  assert_eq!(parameters.standing_height, Some(1.8));
+}
+
+#[test]
+fn drones_dodge_sideways() {
+  // A neighbour right ahead, slightly above and to the side, so the agent
+  // could dodge either way.
+  let agent = Agent::builder().velocity(Vec3::new(5.0, 0.0, 0.0)).build();
+  let neighbours = [Cow::Owned(
+    Agent::builder()
+      .position(Vec3::new(5.0, 0.1, 0.1))
+      .velocity(Vec3::new(-5.0, 0.0, 0.0))
+      .build(),
+  )];
+  let velocity_with = |options: &AvoidanceOptions| {
+    agent.compute_avoiding_velocity(
+      &neighbours,
+      &[],
+      Vec3::new(5.0, 0.0, 0.0),
+      /* max_speed= */ 5.0,
+      /* time_step= */ 0.1,
+      options,
+    )
+  };
+
+  let drone_velocity = velocity_with(&AvoidanceOptions::drone());
+  let unweighted_velocity = velocity_with(&AvoidanceOptions {
+    objective_weights: Default::default(),
+    ..AvoidanceOptions::drone()
+  });
+  assert!(
+    drone_velocity.y.abs() < unweighted_velocity.y.abs() * 0.75,
+    "{drone_velocity} {unweighted_velocity}"
+  );
+}
@@ -0,0 +1,130 @@
+//! Helpers for bridging dodgy_3d and dodgy_2d, e.g. for games mixing flying
+//! units (avoiding in 3D) with ground units (avoiding in 2D). 3D agents are
+//! projected onto a plane to be avoided by 2D agents (or to avoid in 2D), and
+//! the 2D results are lifted back into 3D.
+
+use std::borrow::Cow;
+
+use glam::{Vec2, Vec3};
+
+use crate::{Agent, AvoidanceOptions, Obstacle};
+
+/// A plane that 3D agents are projected onto. Points on the plane are
+/// `origin + x * x_axis + y * y_axis` for the 2D point `(x, y)`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ProjectionPlane {
+  pub origin: Vec3,
+  /// The 3D direction of the 2D X axis. Must be unit length and perpendicular
+  /// to `y_axis`.
+  pub x_axis: Vec3,
+  /// The 3D direction of the 2D Y axis. Must be unit length and perpendicular
+  /// to `x_axis`.
+  pub y_axis: Vec3,
+}
+
+impl ProjectionPlane {
+  /// The horizontal plane at `height`. The 2D axes are the X and Z axes, the
+  /// same as the vertices of [`Obstacle`]s.
+  pub fn horizontal(height: f32) -> Self {
+    Self { origin: Vec3::Y * height, x_axis: Vec3::X, y_axis: Vec3::Z }
+  }
+
+  /// Projects the `point` onto the plane, ignoring its distance from the
+  /// plane.
+  pub fn project_point(&self, point: Vec3) -> Vec2 {
+    self.project_vector(point - self.origin)
+  }
+
+  /// Projects the `vector` (e.g. a velocity) onto the plane.
+  pub fn project_vector(&self, vector: Vec3) -> Vec2 {
+    Vec2::new(vector.dot(self.x_axis), vector.dot(self.y_axis))
+  }
+
+  /// Lifts the 2D `point` back onto the plane in 3D.
+  pub fn lift_point(&self, point: Vec2) -> Vec3 {
+    self.origin + self.lift_vector(point)
+  }
+
+  /// Lifts the 2D `vector` back into 3D, along the plane.
+  pub fn lift_vector(&self, vector: Vec2) -> Vec3 {
+    self.x_axis * vector.x + self.y_axis * vector.y
+  }
+
+  /// Projects the `agent` onto the plane.
+  pub fn project_agent(&self, agent: &Agent) -> dodgy_2d::Agent {
+    dodgy_2d::Agent {
+      position: self.project_point(agent.position),
+      velocity: self.project_vector(agent.velocity),
+      radius: agent.radius,
+      avoidance_responsibility: agent.avoidance_responsibility,
+    }
+  }
+
+  /// Lifts the 2D `agent` back onto the plane in 3D.
+  pub fn lift_agent(&self, agent: &dodgy_2d::Agent) -> Agent {
+    Agent {
+      position: self.lift_point(agent.position),
+      velocity: self.lift_vector(agent.velocity),
+      radius: agent.radius,
+      avoidance_responsibility: agent.avoidance_responsibility,
+    }
+  }
+
+  /// Same as [`Agent::compute_avoiding_velocity`], but avoids in the plane
+  /// using dodgy_2d. The `agent` and `neighbours` are projected onto the
+  /// plane (regardless of their distance from it), and the resulting velocity
+  /// is lifted back into 3D, so it always lies along the plane. The
+  /// `obstacles` must already be 2D (see [`project_obstacle`]). Only the
+  /// margin and time horizons of `avoidance_options` are used.
+  #[allow(clippy::too_many_arguments)]
+  pub fn compute_avoiding_velocity(
+    &self,
+    agent: &Agent,
+    neighbours: &[Cow<'_, Agent>],
+    obstacles: &[Cow<'_, dodgy_2d::Obstacle>],
+    preferred_velocity: Vec3,
+    max_speed: f32,
+    time_step: f32,
+    avoidance_options: &AvoidanceOptions,
+  ) -> Vec3 {
+    let neighbours = neighbours
+      .iter()
+      .map(|neighbour| Cow::Owned(self.project_agent(neighbour)))
+      .collect::<Vec<_>>();
+    let velocity = self.project_agent(agent).compute_avoiding_velocity(
+      &neighbours,
+      obstacles,
+      self.project_vector(preferred_velocity),
+      max_speed,
+      time_step,
+      &dodgy_2d::AvoidanceOptions {
+        obstacle_margin: avoidance_options.obstacle_margin,
+        time_horizon: avoidance_options.time_horizon,
+        obstacle_time_horizon: avoidance_options.obstacle_time_horizon,
+      },
+    );
+    self.lift_vector(velocity)
+  }
+}
+
+/// Slices the `obstacle` at `height` to get the 2D obstacle for agents
+/// avoiding in the horizontal plane at that height (see
+/// [`ProjectionPlane::horizontal`]). Returns None if the obstacle does not
+/// reach that height.
+pub fn project_obstacle(
+  obstacle: &Obstacle,
+  height: f32,
+) -> Option<dodgy_2d::Obstacle> {
+  match obstacle {
+    Obstacle::Closed { vertices, bottom, top } => (*bottom..=*top)
+      .contains(&height)
+      .then(|| dodgy_2d::Obstacle::Closed { vertices: vertices.clone() }),
+    Obstacle::Open { vertices, bottom, top } => (*bottom..=*top)
+      .contains(&height)
+      .then(|| dodgy_2d::Obstacle::Open { vertices: vertices.clone() }),
+  }
+}
+
+#[cfg(test)]
+#[path = "projection_test.rs"]
+mod test;
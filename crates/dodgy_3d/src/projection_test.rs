@@ -0,0 +1,124 @@
+use std::borrow::Cow;
+
+use glam::{Vec2, Vec3};
+
+use crate::{
+  Agent, AvoidanceOptions, ObjectiveWeights, Obstacle, SolverLimits, Tolerances,
+};
+
+use super::*;
+
+macro_rules! assert_vec_near {
+  ($left: expr, $right: expr, $eps: expr) => {{
+    let left = $left;
+    let right = $right;
+    let eps = $eps;
+    assert!(
+      left.distance(right) < eps,
+      "left: {}, right: {}, epsilon: {}",
+      left,
+      right,
+      eps
+    );
+  }};
+}
+
+fn avoidance_options() -> AvoidanceOptions {
+  AvoidanceOptions {
+    obstacle_margin: 0.5,
+    time_horizon: 2.0,
+    obstacle_time_horizon: 2.0,
+    tolerances: Tolerances::default(),
+    strict_rvo2_compatibility: false,
+    objective_weights: ObjectiveWeights::default(),
+    solver_limits: SolverLimits::default(),
+    responsibility_override: None,
+  }
+}
+
+#[test]
+fn projects_and_lifts_agents() {
+  // A tilted plane.
+  let plane = ProjectionPlane {
+    origin: Vec3::new(1.0, 2.0, 3.0),
+    x_axis: Vec3::X,
+    y_axis: Vec3::new(0.0, 1.0, 1.0).normalize(),
+  };
+  let agent = Agent {
+    position: Vec3::new(2.0, 3.0, 4.0),
+    velocity: Vec3::new(1.0, 1.0, 1.0),
+    radius: 0.5,
+    avoidance_responsibility: 0.25,
+  };
+  let projected = plane.project_agent(&agent);
+  assert_vec_near!(projected.position, Vec2::new(1.0, 2.0f32.sqrt()), 1e-5);
+  assert_vec_near!(projected.velocity, Vec2::new(1.0, 2.0f32.sqrt()), 1e-5);
+  assert_eq!(projected.radius, 0.5);
+  assert_eq!(projected.avoidance_responsibility, 0.25);
+
+  // The agent was already on the plane, so lifting it gets it back.
+  let lifted = plane.lift_agent(&projected);
+  assert_vec_near!(lifted.position, agent.position, 1e-5);
+  assert_vec_near!(lifted.velocity, agent.velocity, 1e-5);
+
+  // Offsets off the plane are dropped.
+  assert_vec_near!(
+    plane.lift_point(plane.project_point(Vec3::new(2.0, 4.0, 3.0))),
+    Vec3::new(2.0, 3.0, 4.0),
+    1e-5
+  );
+}
+
+#[test]
+fn slices_obstacles_at_height() {
+  let vertices = vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)];
+  let obstacle =
+    Obstacle::Open { vertices: vertices.clone(), bottom: 0.0, top: 2.0 };
+  assert_eq!(
+    project_obstacle(&obstacle, 1.0),
+    Some(dodgy_2d::Obstacle::Open { vertices })
+  );
+  assert_eq!(project_obstacle(&obstacle, 3.0), None);
+}
+
+#[test]
+fn avoids_in_plane() {
+  let plane = ProjectionPlane::horizontal(0.0);
+  let agent = Agent {
+    position: Vec3::ZERO,
+    velocity: Vec3::X,
+    radius: 0.5,
+    avoidance_responsibility: 1.0,
+  };
+  // A flying unit above the ground, heading straight at the agent.
+  let flyer = Agent {
+    position: Vec3::new(3.0, 4.0, 0.1),
+    velocity: Vec3::NEG_X,
+    radius: 0.5,
+    avoidance_responsibility: 1.0,
+  };
+  let velocity = plane.compute_avoiding_velocity(
+    &agent,
+    &[Cow::Borrowed(&flyer)],
+    &[],
+    Vec3::X,
+    1.0,
+    0.1,
+    &avoidance_options(),
+  );
+  assert_eq!(velocity.y, 0.0);
+  // The flyer is slightly towards +Z, so the agent dodges towards -Z.
+  assert!(velocity.z < -0.01, "{velocity}");
+
+  // Avoiding in 3D with everything on the plane dodges the same way.
+  let grounded_flyer = Agent { position: Vec3::new(3.0, 0.0, 0.1), ..flyer };
+  let velocity_3d = agent.compute_avoiding_velocity(
+    &[Cow::Borrowed(&grounded_flyer)],
+    &[],
+    Vec3::X,
+    1.0,
+    0.1,
+    &avoidance_options(),
+  );
+  assert!(velocity_3d.z < -0.01, "{velocity_3d}");
+}
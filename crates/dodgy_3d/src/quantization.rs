@@ -0,0 +1,102 @@
+use glam::Vec3;
+
+use crate::Agent;
+
+/// Maps values in a range to integers at a fixed precision, for sending
+/// values over the network in fewer bits.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Quantizer {
+  /// The smallest value that can be represented. Smaller values are clamped.
+  pub min: f32,
+  /// The largest value that can be represented. Larger values are clamped.
+  pub max: f32,
+  /// The distance between consecutive representable values. Must be
+  /// positive.
+  pub precision: f32,
+}
+
+impl Quantizer {
+  /// Converts `value` to the nearest representable integer.
+  pub fn quantize(&self, value: f32) -> u32 {
+    let value = value.clamp(self.min, self.max);
+    (((value - self.min) / self.precision).round() as u32)
+      .min(self.get_max_quantized())
+  }
+
+  /// Converts `quantized` back into a value. Quantizing the result returns
+  /// `quantized` again.
+  pub fn dequantize(&self, quantized: u32) -> f32 {
+    self.min + quantized.min(self.get_max_quantized()) as f32 * self.precision
+  }
+
+  /// The number of bits needed to store a quantized value.
+  pub fn get_bit_count(&self) -> u32 {
+    u32::BITS - self.get_max_quantized().leading_zeros()
+  }
+
+  /// Quantizes each component of `value`.
+  pub fn quantize_vec3(&self, value: Vec3) -> [u32; 3] {
+    value.to_array().map(|component| self.quantize(component))
+  }
+
+  /// Dequantizes each component of `quantized`.
+  pub fn dequantize_vec3(&self, quantized: [u32; 3]) -> Vec3 {
+    Vec3::from_array(quantized.map(|component| self.dequantize(component)))
+  }
+
+  /// The largest quantized value.
+  fn get_max_quantized(&self) -> u32 {
+    assert!(
+      self.precision > 0.0,
+      "precision must be positive, was {}",
+      self.precision
+    );
+    ((self.max - self.min) / self.precision).floor() as u32
+  }
+}
+
+/// How to quantize the replicated state of agents.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AgentQuantization {
+  pub position: Quantizer,
+  pub velocity: Quantizer,
+}
+
+/// The quantized replicated state of an agent.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct QuantizedAgentState {
+  pub position: [u32; 3],
+  pub velocity: [u32; 3],
+}
+
+impl AgentQuantization {
+  /// Quantizes the position and velocity of `agent`.
+  pub fn quantize_agent(&self, agent: &Agent) -> QuantizedAgentState {
+    QuantizedAgentState {
+      position: self.position.quantize_vec3(agent.position),
+      velocity: self.velocity.quantize_vec3(agent.velocity),
+    }
+  }
+
+  /// Sets the position and velocity of `agent` from `state`.
+  pub fn dequantize_agent(
+    &self,
+    state: &QuantizedAgentState,
+    agent: &mut Agent,
+  ) {
+    agent.position = self.position.dequantize_vec3(state.position);
+    agent.velocity = self.velocity.dequantize_vec3(state.velocity);
+  }
+
+  /// Snaps the position and velocity of `agent` to the nearest representable
+  /// values. The agent then matches what a remote machine reconstructs from
+  /// the quantized state, so both machines re-simulate from the same state.
+  pub fn snap_agent(&self, agent: &mut Agent) {
+    let state = self.quantize_agent(agent);
+    self.dequantize_agent(&state, agent);
+  }
+}
+
+#[cfg(test)]
+#[path = "quantization_test.rs"]
+mod test;
@@ -0,0 +1,48 @@
+use glam::Vec3;
+
+use super::*;
+
+fn quantizer() -> Quantizer {
+  Quantizer { min: -10.0, max: 10.0, precision: 0.5 }
+}
+
+#[test]
+fn round_trips_to_nearest_value() {
+  let quantizer = quantizer();
+  assert_eq!(quantizer.quantize(-10.0), 0);
+  assert_eq!(quantizer.quantize(0.2), 20);
+  assert_eq!(quantizer.quantize(0.3), 21);
+  assert_eq!(quantizer.dequantize(21), 0.5);
+  // Out-of-range values are clamped.
+  assert_eq!(quantizer.quantize(100.0), 40);
+  assert_eq!(quantizer.quantize(-100.0), 0);
+  assert_eq!(quantizer.dequantize(1000), 10.0);
+
+  assert_eq!(quantizer.get_bit_count(), 6);
+
+  // Dequantized values quantize back to the same value.
+  for quantized in 0..=40 {
+    assert_eq!(quantizer.quantize(quantizer.dequantize(quantized)), quantized);
+  }
+}
+
+#[test]
+fn snapped_agents_match_dequantized_agents() {
+  let quantization =
+    AgentQuantization { position: quantizer(), velocity: quantizer() };
+  let mut agent = Agent {
+    position: Vec3::new(1.1, -2.3, 4.6),
+    velocity: Vec3::new(0.1, 0.0, -0.9),
+    radius: 1.0,
+    avoidance_responsibility: 1.0,
+  };
+
+  let state = quantization.quantize_agent(&agent);
+  let mut remote_agent = agent.clone();
+  quantization.dequantize_agent(&state, &mut remote_agent);
+  quantization.snap_agent(&mut agent);
+  assert_eq!(agent, remote_agent);
+  assert_eq!(agent.position, Vec3::new(1.0, -2.5, 4.5));
+  assert_eq!(agent.velocity, Vec3::new(0.0, 0.0, -1.0));
+  assert_eq!(quantization.quantize_agent(&agent), state);
+}
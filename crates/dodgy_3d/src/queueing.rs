@@ -0,0 +1,65 @@
+use glam::Vec3;
+
+/// A choke point (e.g. a doorway or turnstile) that agents queue up at.
+/// Without queueing, agents crowd around choke points, pushing into each
+/// other from every side. Instead, agents near the entrance that are heading
+/// through the choke point (their goal is past the entrance) are assigned
+/// slots in a line leading up to the entrance, in the order they arrive. The
+/// agent at the front of the line heads for its goal, and every other agent
+/// waits at its slot until the line moves up.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Queue {
+  /// The point agents pass through to get through the choke point.
+  pub entrance: Vec3,
+  /// The direction agents travel through the choke point. Must be unit
+  /// length. The line forms behind the entrance, against this direction.
+  pub direction: Vec3,
+  /// The distance between consecutive slots in the line.
+  pub spacing: f32,
+  /// How close agents must be to the entrance to join the line. Agents
+  /// further away head for their goals as usual.
+  pub capture_radius: f32,
+}
+
+impl Queue {
+  /// Assigns slots to the agents in the line. `agents` are the position and
+  /// goal point of each agent that may queue, and the result is the slot
+  /// (the point to head for instead of the goal) for each of those agents,
+  /// or None if the agent is not waiting in the line.
+  pub(crate) fn assign_slots(
+    &self,
+    agents: impl Iterator<Item = (Vec3, Vec3)>,
+  ) -> Vec<Option<Vec3>> {
+    let mut slots = Vec::new();
+    // The agents in the line and their distance to the entrance.
+    let mut queued_agents = Vec::new();
+    for (agent_index, (position, goal_point)) in agents.enumerate() {
+      slots.push(None);
+      let distance_to_entrance = (self.entrance - position).dot(self.direction);
+      let heading_through = distance_to_entrance > 0.0
+        && (goal_point - self.entrance).dot(self.direction) > 0.0;
+      if heading_through
+        && position.distance_squared(self.entrance)
+          <= self.capture_radius * self.capture_radius
+      {
+        queued_agents.push((agent_index, distance_to_entrance));
+      }
+    }
+
+    queued_agents.sort_by(|(left_index, left), (right_index, right)| {
+      left.total_cmp(right).then(left_index.cmp(right_index))
+    });
+    // The agent at the front of the line is free to go through.
+    for (slot, (agent_index, _)) in
+      queued_agents.into_iter().enumerate().skip(1)
+    {
+      slots[agent_index] =
+        Some(self.entrance - self.direction * self.spacing * slot as f32);
+    }
+    slots
+  }
+}
+
+#[cfg(test)]
+#[path = "queueing_test.rs"]
+mod test;
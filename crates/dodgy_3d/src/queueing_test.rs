@@ -0,0 +1,48 @@
+use glam::Vec3;
+
+use super::*;
+
+fn queue() -> Queue {
+  Queue {
+    entrance: Vec3::ZERO,
+    direction: Vec3::X,
+    spacing: 1.5,
+    capture_radius: 10.0,
+  }
+}
+
+#[test]
+fn agents_line_up_in_arrival_order() {
+  let goal = Vec3::new(20.0, 0.0, 0.0);
+  let slots = queue().assign_slots(
+    [
+      (Vec3::new(-3.0, 0.0, 2.0), goal),
+      (Vec3::new(-1.0, 0.0, -1.0), goal),
+      (Vec3::new(-5.0, 0.0, 0.0), goal),
+    ]
+    .into_iter(),
+  );
+  assert_eq!(
+    slots,
+    [Some(Vec3::new(-1.5, 0.0, 0.0)), None, Some(Vec3::new(-3.0, 0.0, 0.0)),]
+  );
+}
+
+#[test]
+fn only_agents_heading_through_queue() {
+  let slots = queue().assign_slots(
+    [
+      // Closest to the entrance, so it is free to go.
+      (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(20.0, 0.0, 0.0)),
+      // Its goal is on this side of the entrance.
+      (Vec3::new(-2.0, 0.0, 0.0), Vec3::new(-8.0, 0.0, 0.0)),
+      // Already through the entrance.
+      (Vec3::new(2.0, 0.0, 0.0), Vec3::new(20.0, 0.0, 0.0)),
+      // Too far from the entrance.
+      (Vec3::new(-12.0, 0.0, 0.0), Vec3::new(20.0, 0.0, 0.0)),
+      (Vec3::new(-4.0, 0.0, 0.0), Vec3::new(20.0, 0.0, 0.0)),
+    ]
+    .into_iter(),
+  );
+  assert_eq!(slots, [None, None, None, None, Some(Vec3::new(-1.5, 0.0, 0.0))]);
+}
@@ -0,0 +1,171 @@
+use std::io::{self, Write};
+
+use glam::{UVec3, Vec3};
+
+use crate::{density::GridLayout, Simulator};
+
+/// Accumulates per-cell occupancy and average velocity over a run, for
+/// analysing flow rates and bottlenecks. Call [`FlowRecorder::record`] after
+/// each step, then export the grids with [`FlowRecorder::write_csv`] or the
+/// `write_npy_*` functions. Cells are cubes that are `cell_size` wide.
+#[derive(Clone, PartialEq, Debug)]
+pub struct FlowRecorder {
+  layout: GridLayout,
+  /// The number of frames recorded.
+  frame_count: u32,
+  /// The total number of agents seen in each cell across all frames.
+  occupancy_counts: Vec<u32>,
+  /// The sum of the velocities of agents seen in each cell across all frames.
+  velocity_sums: Vec<Vec3>,
+}
+
+impl FlowRecorder {
+  /// Creates a recorder covering the box from `min` to `max`.
+  pub fn new(min: Vec3, max: Vec3, cell_size: f32) -> Self {
+    let layout = GridLayout::new(min, max, cell_size);
+    let cell_count = layout.cell_count();
+    Self {
+      layout,
+      frame_count: 0,
+      occupancy_counts: vec![0; cell_count],
+      velocity_sums: vec![Vec3::ZERO; cell_count],
+    }
+  }
+
+  /// The number of cells along each axis.
+  pub fn get_size(&self) -> UVec3 {
+    self.layout.size
+  }
+
+  /// The number of frames recorded.
+  pub fn get_frame_count(&self) -> u32 {
+    self.frame_count
+  }
+
+  /// Records the current state of every agent in `simulator` as one frame.
+  pub fn record(&mut self, simulator: &Simulator) {
+    self.frame_count += 1;
+    for agent_index in 0..simulator.get_agent_count() {
+      let agent = simulator.get_agent(agent_index);
+      let Some(cell) = self.layout.get_cell(agent.position) else {
+        continue;
+      };
+      let index = self.layout.get_index(cell);
+      self.occupancy_counts[index] += 1;
+      self.velocity_sums[index] += agent.velocity;
+    }
+  }
+
+  /// The average number of agents in `cell` per frame.
+  pub fn get_occupancy(&self, cell: UVec3) -> f32 {
+    if self.frame_count == 0 {
+      return 0.0;
+    }
+    self.occupancy_counts[self.layout.get_index(cell)] as f32
+      / self.frame_count as f32
+  }
+
+  /// The average velocity of agents seen in `cell`, or zero if no agents were
+  /// seen in the cell.
+  pub fn get_average_velocity(&self, cell: UVec3) -> Vec3 {
+    let index = self.layout.get_index(cell);
+    match self.occupancy_counts[index] {
+      0 => Vec3::ZERO,
+      count => self.velocity_sums[index] / count as f32,
+    }
+  }
+
+  /// Writes a CSV with a row for each cell, with the columns `x,y,z` (the
+  /// cell's center), `occupancy`, and `velocity_x,velocity_y,velocity_z` (the
+  /// average velocity).
+  pub fn write_csv(&self, writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "x,y,z,occupancy,velocity_x,velocity_y,velocity_z")?;
+    for cell in self.cells() {
+      let center = self.layout.get_cell_center(cell);
+      let velocity = self.get_average_velocity(cell);
+      writeln!(
+        writer,
+        "{},{},{},{},{},{},{}",
+        center.x,
+        center.y,
+        center.z,
+        self.get_occupancy(cell),
+        velocity.x,
+        velocity.y,
+        velocity.z
+      )?;
+    }
+    Ok(())
+  }
+
+  /// Writes the occupancy grid as an NPY file of 32-bit floats with shape
+  /// `(size.z, size.y, size.x)`.
+  pub fn write_npy_occupancy(&self, writer: &mut impl Write) -> io::Result<()> {
+    let size = self.layout.size;
+    write_npy_header(
+      writer,
+      &[size.z as usize, size.y as usize, size.x as usize],
+    )?;
+    for cell in self.cells() {
+      writer.write_all(&self.get_occupancy(cell).to_le_bytes())?;
+    }
+    Ok(())
+  }
+
+  /// Writes the average velocity grid as an NPY file of 32-bit floats with
+  /// shape `(size.z, size.y, size.x, 3)`.
+  pub fn write_npy_average_velocity(
+    &self,
+    writer: &mut impl Write,
+  ) -> io::Result<()> {
+    let size = self.layout.size;
+    write_npy_header(
+      writer,
+      &[size.z as usize, size.y as usize, size.x as usize, 3],
+    )?;
+    for cell in self.cells() {
+      for component in self.get_average_velocity(cell).to_array() {
+        writer.write_all(&component.to_le_bytes())?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Iterates over every cell, with `x` changing fastest.
+  fn cells(&self) -> impl Iterator<Item = UVec3> + '_ {
+    let size = self.layout.size;
+    (0..size.z).flat_map(move |z| {
+      (0..size.y)
+        .flat_map(move |y| (0..size.x).map(move |x| UVec3::new(x, y, z)))
+    })
+  }
+}
+
+/// Writes the header of a version 1.0 NPY file for a C-ordered array of
+/// little-endian 32-bit floats with `shape`.
+fn write_npy_header(
+  writer: &mut impl Write,
+  shape: &[usize],
+) -> io::Result<()> {
+  let shape = shape
+    .iter()
+    .map(|dimension| format!("{}, ", dimension))
+    .collect::<String>();
+  let mut header = format!(
+    "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}), }}",
+    shape.trim_end()
+  );
+  // The magic string, version, and header length take 10 bytes, and the
+  // header must end in a newline, with the total length a multiple of 64.
+  let padding = 64 - (10 + header.len() + 1) % 64;
+  header.extend(std::iter::repeat_n(' ', padding % 64));
+  header.push('\n');
+
+  writer.write_all(b"\x93NUMPY\x01\x00")?;
+  writer.write_all(&(header.len() as u16).to_le_bytes())?;
+  writer.write_all(header.as_bytes())
+}
+
+#[cfg(test)]
+#[path = "recording_test.rs"]
+mod test;
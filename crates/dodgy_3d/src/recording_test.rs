@@ -0,0 +1,86 @@
+use glam::{BVec3, UVec3, Vec3};
+
+use super::*;
+use crate::{Agent, AgentParameters, SimulatorMargin};
+
+fn simulator() -> Simulator {
+  let mut simulator = Simulator::new();
+  for (position, velocity) in [
+    (Vec3::new(0.5, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0)),
+    (Vec3::new(0.7, 0.5, 0.5), Vec3::new(0.0, 0.0, 1.0)),
+    (Vec3::new(1.5, 0.5, 0.5), Vec3::new(0.0, 0.0, 0.0)),
+  ] {
+    simulator.add_agent(
+      Agent { position, velocity, radius: 0.1, avoidance_responsibility: 1.0 },
+      AgentParameters {
+        goal_point: position,
+        max_speed: 1.0,
+        obstacle_margin: SimulatorMargin::AgentRadius,
+        time_horizon: 1.0,
+        obstacle_time_horizon: 1.0,
+        traffic_lane: None,
+        social_force: None,
+        leader_following: None,
+        yielding: None,
+        urgency: 0.0,
+        standing_height: None,
+        locked_axes: BVec3::FALSE,
+      },
+    );
+  }
+  simulator
+}
+
+#[test]
+fn accumulates_occupancy_and_velocity() {
+  let mut recorder =
+    FlowRecorder::new(Vec3::ZERO, Vec3::new(2.0, 1.0, 1.0), 1.0);
+  assert_eq!(recorder.get_size(), UVec3::new(2, 1, 1));
+  let mut simulator = simulator();
+  recorder.record(&simulator);
+  simulator.remove_agent(2);
+  recorder.record(&simulator);
+
+  assert_eq!(recorder.get_frame_count(), 2);
+  assert_eq!(recorder.get_occupancy(UVec3::new(0, 0, 0)), 2.0);
+  assert_eq!(recorder.get_occupancy(UVec3::new(1, 0, 0)), 0.5);
+  assert_eq!(
+    recorder.get_average_velocity(UVec3::new(0, 0, 0)),
+    Vec3::new(0.5, 0.0, 0.5)
+  );
+
+  let mut csv = Vec::new();
+  recorder.write_csv(&mut csv).unwrap();
+  assert_eq!(
+    String::from_utf8(csv).unwrap(),
+    "x,y,z,occupancy,velocity_x,velocity_y,velocity_z\n\
+     0.5,0.5,0.5,2,0.5,0,0.5\n\
+     1.5,0.5,0.5,0.5,0,0,0\n"
+  );
+}
+
+#[test]
+fn writes_npy_files() {
+  let mut recorder =
+    FlowRecorder::new(Vec3::ZERO, Vec3::new(2.0, 1.0, 1.0), 1.0);
+  recorder.record(&simulator());
+
+  let mut npy = Vec::new();
+  recorder.write_npy_occupancy(&mut npy).unwrap();
+  assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+  let header_length = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+  assert_eq!((10 + header_length) % 64, 0);
+  let header = std::str::from_utf8(&npy[10..10 + header_length]).unwrap();
+  assert!(header.contains("'shape': (1, 1, 2,)"), "{}", header);
+  assert!(header.ends_with('\n'));
+  let data = &npy[10 + header_length..];
+  assert_eq!(data.len(), 2 * 4);
+  assert_eq!(f32::from_le_bytes(data[..4].try_into().unwrap()), 2.0);
+
+  let mut npy = Vec::new();
+  recorder.write_npy_average_velocity(&mut npy).unwrap();
+  let header_length = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+  let header = std::str::from_utf8(&npy[10..10 + header_length]).unwrap();
+  assert!(header.contains("'shape': (1, 1, 2, 3,)"), "{}", header);
+  assert_eq!(npy.len() - 10 - header_length, 2 * 3 * 4);
+}
@@ -0,0 +1,579 @@
+//! Tools for capturing and minimizing reproductions of bad steps.
+//!
+//! When a step goes wrong (e.g. an agent's velocity becomes NaN), capture the
+//! simulator from just before the step with [`Repro::capture`], shrink it to
+//! the fewest agents that still go wrong with [`Repro::minimize`], and write
+//! the result with [`Repro::write_scenario`]. The scenario file can be
+//! attached to a bug report, and read back with [`Repro::read_scenario`] to
+//! replay the step.
+//!
+//! Scenario files contain the agents, their [`AgentParameters`], the
+//! obstacles, and the time step. Simulator-wide settings (e.g. terrain,
+//! boundaries, or crossing flows) and other per-agent state (e.g. archetypes,
+//! tags, or parameter blends) are not written, so [`Repro::write_scenario`]
+//! checks that the scenario alone still reproduces the condition.
+
+use std::{
+  fmt::Write as _,
+  io::{self, Write},
+};
+
+use glam::{BVec3, Vec2, Vec3};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+  are_colliding, Agent, AgentParameters, FollowOptions, LeaderFollowing,
+  MarginCurve, Obstacle, Simulator, SimulatorMargin, SocialForce, TrafficLane,
+  Yielding,
+};
+
+/// A condition that flags a step as bad. Conditions are checked on the
+/// simulator after the step.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ReproCondition {
+  /// Some agent's position or velocity is not finite.
+  NonFiniteState,
+  /// Some pair of agents is overlapping (by more than 1% of their combined
+  /// radii).
+  Collision,
+  /// Some agent further than `goal_distance` from its goal point is moving
+  /// slower than `min_speed`.
+  Deadlock { min_speed: f32, goal_distance: f32 },
+}
+
+impl ReproCondition {
+  /// Whether `simulator` currently meets the condition.
+  pub fn is_met(&self, simulator: &Simulator) -> bool {
+    let mut agents = (0..simulator.get_agent_count())
+      .map(|agent_index| simulator.get_agent(agent_index));
+    match self {
+      Self::NonFiniteState => agents.any(|agent| {
+        !agent.position.is_finite() || !agent.velocity.is_finite()
+      }),
+      Self::Collision => {
+        let agents = agents.collect::<Vec<_>>();
+        agents.iter().enumerate().any(|(index, agent)| {
+          agents[(index + 1)..].iter().any(|other| are_colliding(agent, other))
+        })
+      }
+      Self::Deadlock { min_speed, goal_distance } => {
+        agents.enumerate().any(|(agent_index, agent)| {
+          let goal_point =
+            simulator.get_agent_parameters(agent_index).goal_point;
+          agent.position.distance(goal_point) > *goal_distance
+            && agent.velocity.length() < *min_speed
+        })
+      }
+    }
+  }
+}
+
+/// A captured step that meets a [`ReproCondition`].
+#[derive(Clone)]
+pub struct Repro {
+  /// The simulator from just before the step.
+  simulator: Simulator,
+  time_step: f32,
+  condition: ReproCondition,
+  /// The index of each agent of `simulator` in the simulator it was captured
+  /// from.
+  agent_indices: Vec<usize>,
+}
+
+impl Repro {
+  /// Captures the step of `time_step` from `simulator` (which must not have
+  /// been stepped yet), if the step meets the `condition`. The random number
+  /// generator is reset to its default seed, so the repro replays the same way
+  /// as its scenario file. Returns None if the step does not meet the
+  /// `condition`.
+  pub fn capture(
+    simulator: &Simulator,
+    time_step: f32,
+    condition: ReproCondition,
+  ) -> Option<Self> {
+    let mut simulator = simulator.clone();
+    simulator.set_rng(StdRng::seed_from_u64(0));
+    let repro = Self {
+      agent_indices: (0..simulator.get_agent_count()).collect(),
+      simulator,
+      time_step,
+      condition,
+    };
+    repro.reproduces().then_some(repro)
+  }
+
+  /// The simulator from just before the step.
+  pub fn simulator(&self) -> &Simulator {
+    &self.simulator
+  }
+
+  pub fn time_step(&self) -> f32 {
+    self.time_step
+  }
+
+  pub fn condition(&self) -> &ReproCondition {
+    &self.condition
+  }
+
+  /// The index of each agent of [`Self::simulator`] in the simulator the
+  /// repro was captured from (or in the scenario file it was read from).
+  pub fn agent_indices(&self) -> &[usize] {
+    &self.agent_indices
+  }
+
+  /// Whether the step still meets the condition.
+  pub fn reproduces(&self) -> bool {
+    let mut simulator = self.simulator.clone();
+    simulator.step(self.time_step);
+    self.condition.is_met(&simulator)
+  }
+
+  /// Shrinks the repro to a minimal set of agents that still meets the
+  /// condition, by repeatedly removing groups of agents (halving the group
+  /// size when no group can be removed). Removing any single agent from the
+  /// result no longer meets the condition. This steps the simulator many
+  /// times, so it may be slow for large crowds.
+  pub fn minimize(&self) -> Self {
+    let mut repro = self.clone();
+    let mut group_count = 2;
+    while repro.agent_indices.len() >= 2 {
+      let agent_count = repro.agent_indices.len();
+      let group_size = agent_count.div_ceil(group_count);
+      let smaller_repro = (0..agent_count)
+        .step_by(group_size)
+        .map(|start| repro.without_agents(start..(start + group_size)))
+        .find(Self::reproduces);
+      match smaller_repro {
+        Some(smaller_repro) => {
+          repro = smaller_repro;
+          group_count = (group_count - 1).max(2);
+        }
+        // Every agent was tried on its own, so no agent can be removed.
+        None if group_size == 1 => break,
+        None => group_count = (group_count * 2).min(agent_count),
+      }
+    }
+    repro
+  }
+
+  /// Copies the repro without the agents whose positions in
+  /// [`Self::agent_indices`] are in `positions`.
+  fn without_agents(&self, positions: std::ops::Range<usize>) -> Self {
+    let mut repro = self.clone();
+    let end = positions.end.min(repro.agent_indices.len());
+    // Remove from the back so earlier indices stay valid.
+    for agent_index in (positions.start..end).rev() {
+      repro.simulator.remove_agent(agent_index);
+      repro.agent_indices.remove(agent_index);
+    }
+    repro
+  }
+
+  /// Writes the repro as a scenario file (see the [module docs](self)).
+  /// Returns an error with [`io::ErrorKind::InvalidData`] if the scenario
+  /// alone does not reproduce the condition (e.g. since it depends on the
+  /// terrain), though the scenario is still written.
+  pub fn write_scenario(&self, writer: &mut impl Write) -> io::Result<()> {
+    let scenario = self.format_scenario();
+    writer.write_all(scenario.as_bytes())?;
+    let replayed = Self::read_scenario(&scenario)?;
+    if !replayed.reproduces() {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "the scenario alone does not reproduce the condition",
+      ));
+    }
+    Ok(())
+  }
+
+  fn format_scenario(&self) -> String {
+    // Writing to a string cannot fail.
+    let mut out = String::new();
+    let simulator = &self.simulator;
+    writeln!(
+      out,
+      "# A dodgy_3d step meeting {:?}, with agents {:?} of the captured \
+       simulator.",
+      self.condition, self.agent_indices
+    )
+    .unwrap();
+    writeln!(out, "time_step {:?}", self.time_step).unwrap();
+    match &self.condition {
+      ReproCondition::NonFiniteState => writeln!(out, "condition non_finite"),
+      ReproCondition::Collision => writeln!(out, "condition collision"),
+      ReproCondition::Deadlock { min_speed, goal_distance } => {
+        writeln!(out, "condition deadlock {min_speed:?} {goal_distance:?}")
+      }
+    }
+    .unwrap();
+
+    for agent_index in 0..simulator.get_agent_count() {
+      let agent = simulator.get_agent(agent_index);
+      let parameters = simulator.get_agent_parameters(agent_index);
+      writeln!(out, "agent").unwrap();
+      writeln!(out, "position {}", format_vec3(agent.position)).unwrap();
+      writeln!(out, "velocity {}", format_vec3(agent.velocity)).unwrap();
+      writeln!(out, "radius {:?}", agent.radius).unwrap();
+      writeln!(
+        out,
+        "avoidance_responsibility {:?}",
+        agent.avoidance_responsibility
+      )
+      .unwrap();
+      writeln!(out, "goal_point {}", format_vec3(parameters.goal_point))
+        .unwrap();
+      writeln!(out, "max_speed {:?}", parameters.max_speed).unwrap();
+      match &parameters.obstacle_margin {
+        SimulatorMargin::AgentRadius => {
+          writeln!(out, "obstacle_margin agent_radius")
+        }
+        SimulatorMargin::Distance(distance) => {
+          writeln!(out, "obstacle_margin distance {distance:?}")
+        }
+        SimulatorMargin::SpeedCurve(curve) => {
+          writeln!(out, "obstacle_margin speed_curve{}", format_curve(curve))
+        }
+        SimulatorMargin::ObstacleDistanceCurve(curve) => writeln!(
+          out,
+          "obstacle_margin obstacle_distance_curve{}",
+          format_curve(curve)
+        ),
+      }
+      .unwrap();
+      writeln!(out, "time_horizon {:?}", parameters.time_horizon).unwrap();
+      writeln!(
+        out,
+        "obstacle_time_horizon {:?}",
+        parameters.obstacle_time_horizon
+      )
+      .unwrap();
+      if let Some(lane) = &parameters.traffic_lane {
+        write!(
+          out,
+          "traffic_lane {:?} {:?}",
+          lane.min_altitude, lane.max_altitude
+        )
+        .unwrap();
+        if let Some(direction) = lane.direction {
+          write!(out, " {}", format_vec3(direction)).unwrap();
+        }
+        writeln!(out).unwrap();
+      }
+      if let Some(social_force) = &parameters.social_force {
+        writeln!(
+          out,
+          "social_force {:?} {:?}",
+          social_force.strength, social_force.range
+        )
+        .unwrap();
+      }
+      if let Some(following) = &parameters.leader_following {
+        writeln!(
+          out,
+          "leader_following {} {:?} {:?}",
+          following.leader_index,
+          following.options.distance,
+          following.options.angle
+        )
+        .unwrap();
+      }
+      if let Some(yielding) = &parameters.yielding {
+        writeln!(
+          out,
+          "yielding {:?} {:?} {:?}",
+          yielding.speed, yielding.look_ahead_time, yielding.standing_distance
+        )
+        .unwrap();
+      }
+      writeln!(out, "urgency {:?}", parameters.urgency).unwrap();
+      if let Some(standing_height) = parameters.standing_height {
+        writeln!(out, "standing_height {standing_height:?}").unwrap();
+      }
+      let locked_axes = parameters.locked_axes;
+      writeln!(
+        out,
+        "locked_axes {} {} {}",
+        locked_axes.x, locked_axes.y, locked_axes.z
+      )
+      .unwrap();
+    }
+
+    for obstacle_index in 0..simulator.get_obstacle_count() {
+      let (kind, vertices, bottom, top) =
+        match simulator.get_obstacle(obstacle_index) {
+          Obstacle::Closed { vertices, bottom, top } => {
+            ("closed", vertices, bottom, top)
+          }
+          Obstacle::Open { vertices, bottom, top } => {
+            ("open", vertices, bottom, top)
+          }
+        };
+      writeln!(out, "obstacle {kind} {bottom:?} {top:?}").unwrap();
+      for vertex in vertices {
+        writeln!(out, "vertex {:?} {:?}", vertex.x, vertex.y).unwrap();
+      }
+    }
+    out
+  }
+
+  /// Reads a repro from the `contents` of a scenario file written by
+  /// [`Self::write_scenario`]. The agents are numbered from zero in the order
+  /// they appear in the file. Returns an error with
+  /// [`io::ErrorKind::InvalidData`] if the file is malformed.
+  pub fn read_scenario(contents: &str) -> io::Result<Self> {
+    let mut time_step = None;
+    let mut condition = None;
+    let mut agents = Vec::<(Agent, AgentParameters)>::new();
+    let mut obstacles = Vec::<Obstacle>::new();
+    // Whether the last block was an obstacle (rather than an agent), which
+    // determines where the following lines go.
+    let mut in_obstacle = false;
+
+    for (line_index, line) in contents.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let mut tokens =
+        Tokens { tokens: line.split_whitespace(), line_number: line_index + 1 };
+      let key = tokens.next()?;
+      match key {
+        "time_step" => time_step = Some(tokens.f32()?),
+        "condition" => {
+          condition = Some(match tokens.next()? {
+            "non_finite" => ReproCondition::NonFiniteState,
+            "collision" => ReproCondition::Collision,
+            "deadlock" => ReproCondition::Deadlock {
+              min_speed: tokens.f32()?,
+              goal_distance: tokens.f32()?,
+            },
+            other => {
+              return Err(tokens.error(&format!("unknown condition {other}")))
+            }
+          })
+        }
+        "agent" => {
+          in_obstacle = false;
+          agents.push((
+            Agent::builder().build(),
+            AgentParameters::builder().build(),
+          ));
+        }
+        "obstacle" => {
+          in_obstacle = true;
+          let kind = tokens.next()?;
+          let (bottom, top) = (tokens.f32()?, tokens.f32()?);
+          obstacles.push(match kind {
+            "closed" => Obstacle::Closed { vertices: vec![], bottom, top },
+            "open" => Obstacle::Open { vertices: vec![], bottom, top },
+            other => {
+              return Err(
+                tokens.error(&format!("unknown obstacle kind {other}")),
+              )
+            }
+          });
+        }
+        "vertex" if in_obstacle => {
+          let vertex = Vec2::new(tokens.f32()?, tokens.f32()?);
+          match obstacles.last_mut().unwrap() {
+            Obstacle::Closed { vertices, .. }
+            | Obstacle::Open { vertices, .. } => vertices.push(vertex),
+          }
+        }
+        _ => match agents.last_mut().filter(|_| !in_obstacle) {
+          Some((agent, parameters)) => {
+            read_agent_line(key, &mut tokens, agent, parameters)?
+          }
+          None => return Err(tokens.error(&format!("unexpected {key} line"))),
+        },
+      }
+      tokens.finish()?;
+    }
+
+    let missing = |name: &str| {
+      io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("the scenario is missing its {name}"),
+      )
+    };
+    for (agent_index, (_, parameters)) in agents.iter().enumerate() {
+      let Some(following) = &parameters.leader_following else {
+        continue;
+      };
+      let message = if following.leader_index >= agents.len() {
+        "which does not exist"
+      } else if following.leader_index == agent_index {
+        "which is itself"
+      } else {
+        continue;
+      };
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+          "agent {agent_index} follows agent {}, {message}",
+          following.leader_index
+        ),
+      ));
+    }
+    let mut simulator = Simulator::new();
+    for (agent, parameters) in agents {
+      simulator.add_agent(agent, parameters);
+    }
+    for obstacle in obstacles {
+      simulator.add_obstacle(obstacle);
+    }
+    Ok(Self {
+      agent_indices: (0..simulator.get_agent_count()).collect(),
+      simulator,
+      time_step: time_step.ok_or_else(|| missing("time step"))?,
+      condition: condition.ok_or_else(|| missing("condition"))?,
+    })
+  }
+}
+
+/// Reads the line starting with `key` (after `key`) from `tokens` into
+/// `agent` and `parameters`.
+fn read_agent_line(
+  key: &str,
+  tokens: &mut Tokens<'_>,
+  agent: &mut Agent,
+  parameters: &mut AgentParameters,
+) -> io::Result<()> {
+  match key {
+    "position" => agent.position = tokens.vec3()?,
+    "velocity" => agent.velocity = tokens.vec3()?,
+    "radius" => agent.radius = tokens.f32()?,
+    "avoidance_responsibility" => {
+      agent.avoidance_responsibility = tokens.f32()?
+    }
+    "goal_point" => parameters.goal_point = tokens.vec3()?,
+    "max_speed" => parameters.max_speed = tokens.f32()?,
+    "obstacle_margin" => {
+      parameters.obstacle_margin = match tokens.next()? {
+        "agent_radius" => SimulatorMargin::AgentRadius,
+        "distance" => SimulatorMargin::Distance(tokens.f32()?),
+        "speed_curve" => SimulatorMargin::SpeedCurve(tokens.curve()?),
+        "obstacle_distance_curve" => {
+          SimulatorMargin::ObstacleDistanceCurve(tokens.curve()?)
+        }
+        other => {
+          return Err(tokens.error(&format!("unknown obstacle margin {other}")))
+        }
+      }
+    }
+    "time_horizon" => parameters.time_horizon = tokens.f32()?,
+    "obstacle_time_horizon" => {
+      parameters.obstacle_time_horizon = tokens.f32()?
+    }
+    "traffic_lane" => {
+      parameters.traffic_lane = Some(TrafficLane {
+        min_altitude: tokens.f32()?,
+        max_altitude: tokens.f32()?,
+        direction: if tokens.is_empty() { None } else { Some(tokens.vec3()?) },
+      })
+    }
+    "social_force" => {
+      parameters.social_force =
+        Some(SocialForce { strength: tokens.f32()?, range: tokens.f32()? })
+    }
+    "leader_following" => {
+      parameters.leader_following = Some(LeaderFollowing {
+        leader_index: tokens.parse()?,
+        options: FollowOptions {
+          distance: tokens.f32()?,
+          angle: tokens.f32()?,
+        },
+      })
+    }
+    "yielding" => {
+      parameters.yielding = Some(Yielding {
+        speed: tokens.f32()?,
+        look_ahead_time: tokens.f32()?,
+        standing_distance: tokens.f32()?,
+      })
+    }
+    "urgency" => parameters.urgency = tokens.f32()?,
+    "standing_height" => parameters.standing_height = Some(tokens.f32()?),
+    "locked_axes" => {
+      parameters.locked_axes =
+        BVec3::new(tokens.parse()?, tokens.parse()?, tokens.parse()?)
+    }
+    _ => return Err(tokens.error(&format!("unknown agent line {key}"))),
+  }
+  Ok(())
+}
+
+/// Formats a vector so it can be read back exactly (the debug format of
+/// floats round-trips).
+fn format_vec3(vector: Vec3) -> String {
+  format!("{:?} {:?} {:?}", vector.x, vector.y, vector.z)
+}
+
+/// Formats the points of a curve, each preceded by a space.
+fn format_curve(curve: &MarginCurve) -> String {
+  curve
+    .points
+    .iter()
+    .map(|(input, margin)| format!(" {input:?} {margin:?}"))
+    .collect()
+}
+
+/// The whitespace separated tokens of a line of a scenario file.
+struct Tokens<'a> {
+  tokens: std::str::SplitWhitespace<'a>,
+  line_number: usize,
+}
+
+impl<'a> Tokens<'a> {
+  fn error(&self, message: &str) -> io::Error {
+    io::Error::new(
+      io::ErrorKind::InvalidData,
+      format!("line {}: {message}", self.line_number),
+    )
+  }
+
+  fn is_empty(&self) -> bool {
+    self.tokens.clone().next().is_none()
+  }
+
+  fn next(&mut self) -> io::Result<&'a str> {
+    self.tokens.next().ok_or_else(|| self.error("missing value"))
+  }
+
+  fn parse<T: std::str::FromStr>(&mut self) -> io::Result<T> {
+    let token = self.next()?;
+    token.parse().map_err(|_| self.error(&format!("invalid value {token}")))
+  }
+
+  fn f32(&mut self) -> io::Result<f32> {
+    self.parse()
+  }
+
+  fn vec3(&mut self) -> io::Result<Vec3> {
+    Ok(Vec3::new(self.f32()?, self.f32()?, self.f32()?))
+  }
+
+  /// Reads the rest of the line as the (input, margin) points of a curve.
+  fn curve(&mut self) -> io::Result<MarginCurve> {
+    let mut points = Vec::new();
+    while !self.is_empty() {
+      points.push((self.f32()?, self.f32()?));
+    }
+    if points.is_empty() {
+      return Err(self.error("a curve must have at least one point"));
+    }
+    Ok(MarginCurve { points })
+  }
+
+  /// Fails if there are tokens left on the line.
+  fn finish(&mut self) -> io::Result<()> {
+    match self.tokens.next() {
+      None => Ok(()),
+      Some(token) => Err(self.error(&format!("unexpected value {token}"))),
+    }
+  }
+}
+
+#[cfg(test)]
+#[path = "repro_test.rs"]
+mod test;
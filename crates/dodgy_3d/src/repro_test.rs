@@ -0,0 +1,178 @@
+use glam::{BVec3, Vec2, Vec3};
+
+use super::*;
+use crate::{SocialForce, TrafficLane};
+
+/// Adds a stationary agent at `position` heading for `goal_point`.
+fn add_agent(simulator: &mut Simulator, position: Vec3, goal_point: Vec3) {
+  simulator.add_agent(
+    Agent::builder().position(position).build(),
+    AgentParameters::builder().goal_point(goal_point).build(),
+  );
+}
+
+/// Adds agents walking to their goals far from everything else.
+fn add_bystanders(simulator: &mut Simulator) {
+  for index in 0..6 {
+    let position = Vec3::new(index as f32 * 3.0, 0.0, 50.0);
+    add_agent(simulator, position, position + Vec3::X);
+  }
+}
+
+#[test]
+fn minimizes_collisions_to_colliding_agents() {
+  let mut simulator = Simulator::new();
+  add_bystanders(&mut simulator);
+  // These agents overlap too much to separate in a single step.
+  add_agent(&mut simulator, Vec3::ZERO, Vec3::ZERO);
+  add_bystanders(&mut simulator);
+  add_agent(&mut simulator, Vec3::new(0.1, 0.0, 0.0), Vec3::ZERO);
+
+  let repro = Repro::capture(&simulator, 0.1, ReproCondition::Collision)
+    .unwrap()
+    .minimize();
+  assert_eq!(repro.agent_indices(), [6, 13]);
+  assert!(repro.reproduces());
+}
+
+#[test]
+fn minimizes_deadlocks_to_blocked_agent() {
+  let mut simulator = Simulator::new();
+  add_bystanders(&mut simulator);
+  // This agent's goal is behind a wall right in front of it.
+  add_agent(&mut simulator, Vec3::ZERO, Vec3::new(5.0, 0.0, 0.0));
+  simulator.add_obstacle(Obstacle::Open {
+    vertices: vec![Vec2::new(0.5, 5.0), Vec2::new(0.5, -5.0)],
+    bottom: -10.0,
+    top: 10.0,
+  });
+  add_bystanders(&mut simulator);
+  let condition =
+    ReproCondition::Deadlock { min_speed: 0.1, goal_distance: 1.0 };
+
+  let repro = Repro::capture(&simulator, 0.1, condition).unwrap().minimize();
+  assert_eq!(repro.agent_indices(), [6]);
+  assert_eq!(repro.simulator().get_obstacle_count(), 1);
+}
+
+#[test]
+fn does_not_capture_good_steps() {
+  let mut simulator = Simulator::new();
+  add_bystanders(&mut simulator);
+
+  assert!(Repro::capture(&simulator, 0.1, ReproCondition::Collision).is_none());
+}
+
+#[test]
+fn scenario_round_trips() {
+  let mut simulator = Simulator::new();
+  add_agent(&mut simulator, Vec3::ZERO, Vec3::ZERO);
+  simulator.add_agent(
+    Agent::builder()
+      .position(Vec3::new(0.1, 0.2, 0.3))
+      .velocity(Vec3::new(1.0 / 3.0, 0.0, -1.0))
+      .radius(0.7)
+      .avoidance_responsibility(0.25)
+      .build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(4.0, 5.0, 6.0))
+      .max_speed(2.5)
+      .obstacle_margin(SimulatorMargin::SpeedCurve(MarginCurve {
+        points: vec![(0.0, 0.1), (2.0, 0.5)],
+      }))
+      .time_horizon(4.0)
+      .obstacle_time_horizon(2.0)
+      .traffic_lane(TrafficLane {
+        min_altitude: f32::NEG_INFINITY,
+        max_altitude: 3.0,
+        direction: Some(Vec3::X),
+      })
+      .social_force(SocialForce { strength: 1.0, range: 0.3 })
+      .leader_following(LeaderFollowing {
+        leader_index: 0,
+        options: FollowOptions { distance: 1.5, angle: 0.5 },
+      })
+      .yielding(Yielding {
+        speed: 0.5,
+        look_ahead_time: 2.0,
+        standing_distance: 1.0,
+      })
+      .urgency(0.5)
+      .standing_height(1.8)
+      .locked_axes(BVec3::new(false, true, false))
+      .build(),
+  );
+  simulator.add_obstacle(Obstacle::Closed {
+    vertices: vec![
+      Vec2::new(10.0, 10.0),
+      Vec2::new(11.0, 10.0),
+      Vec2::new(11.0, 11.0),
+    ],
+    bottom: -1.0,
+    top: 1.0,
+  });
+  let repro =
+    Repro::capture(&simulator, 0.1, ReproCondition::Collision).unwrap();
+
+  let mut scenario = Vec::new();
+  repro.write_scenario(&mut scenario).unwrap();
+  let replayed =
+    Repro::read_scenario(std::str::from_utf8(&scenario).unwrap()).unwrap();
+
+  assert_eq!(replayed.time_step(), 0.1);
+  assert_eq!(replayed.condition(), &ReproCondition::Collision);
+  for agent_index in 0..2 {
+    assert_eq!(
+      replayed.simulator().get_agent(agent_index),
+      simulator.get_agent(agent_index)
+    );
+    assert_eq!(
+      replayed.simulator().get_agent_parameters(agent_index),
+      simulator.get_agent_parameters(agent_index)
+    );
+  }
+  assert_eq!(replayed.simulator().get_obstacle(0), simulator.get_obstacle(0));
+}
+
+#[test]
+fn reports_malformed_scenarios() {
+  let error =
+    Repro::read_scenario("time_step 0.1\ncondition collision\nagent\nradius")
+      .err()
+      .unwrap();
+  assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+  assert_eq!(error.to_string(), "line 4: missing value");
+  assert!(Repro::read_scenario("time_step 0.1").is_err());
+}
+
+#[test]
+fn rejects_leaders_that_do_not_exist() {
+  let error = Repro::read_scenario(
+    "time_step 0.1\ncondition collision\nagent\nleader_following 1 1.0 0.0",
+  )
+  .err()
+  .unwrap();
+  assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+  assert_eq!(
+    error.to_string(),
+    "agent 0 follows agent 1, which does not exist"
+  );
+
+  let error = Repro::read_scenario(
+    "time_step 0.1\ncondition collision\nagent\nleader_following 0 1.0 0.0",
+  )
+  .err()
+  .unwrap();
+  assert_eq!(error.to_string(), "agent 0 follows agent 0, which is itself");
+}
+
+#[test]
+fn rejects_empty_margin_curves() {
+  let error = Repro::read_scenario(
+    "time_step 0.1\ncondition collision\nagent\nobstacle_margin speed_curve",
+  )
+  .err()
+  .unwrap();
+  assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+  assert_eq!(error.to_string(), "line 4: a curve must have at least one point");
+}
@@ -0,0 +1,63 @@
+use std::{fmt, sync::Arc};
+
+use crate::Agent;
+
+/// A callback computing the share of responsibility the first agent takes
+/// for avoiding the second (its neighbour), or None to split responsibility
+/// as usual. See [`ResponsibilityOverride`].
+pub type ResponsibilityOverrideFn =
+  dyn Fn(&Agent, &Agent) -> Option<f32> + Send + Sync;
+
+/// Overrides how responsibility for avoidance is split between specific pairs
+/// of agents (e.g. so the player never gives way to NPCs). Usually, the
+/// responsibility is split by the ratio of the agents'
+/// `avoidance_responsibility`. For pairs where the callback returns a share
+/// (between 0 and 1), the agent instead takes that share of the avoidance,
+/// whether or not it is inside the velocity obstacle and even with
+/// `strict_rvo2_compatibility`. For the pair to avoid each other fully, the
+/// shares of both directions (the agent with the neighbour, and the
+/// neighbour with the agent) should add up to 1 (e.g. 0 for the player and 1
+/// for the NPC).
+///
+/// Clones share the same callback, and are only equal to each other.
+#[derive(Clone)]
+pub struct ResponsibilityOverride(Arc<ResponsibilityOverrideFn>);
+
+impl ResponsibilityOverride {
+  pub fn new(
+    responsibility_override: impl Fn(&Agent, &Agent) -> Option<f32>
+      + Send
+      + Sync
+      + 'static,
+  ) -> Self {
+    Self(Arc::new(responsibility_override))
+  }
+
+  /// The share of responsibility `agent` takes for avoiding `neighbour`, if
+  /// overridden. Panics if the share is not between 0 and 1.
+  pub(crate) fn get(&self, agent: &Agent, neighbour: &Agent) -> Option<f32> {
+    let responsibility = (self.0)(agent, neighbour)?;
+    assert!(
+      (0.0..=1.0).contains(&responsibility),
+      "overridden responsibility must be between 0 and 1, was {}",
+      responsibility
+    );
+    Some(responsibility)
+  }
+}
+
+impl PartialEq for ResponsibilityOverride {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.0, &other.0)
+  }
+}
+
+impl fmt::Debug for ResponsibilityOverride {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("ResponsibilityOverride(..)")
+  }
+}
+
+#[cfg(test)]
+#[path = "responsibility_test.rs"]
+mod test;
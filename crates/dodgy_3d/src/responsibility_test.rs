@@ -0,0 +1,109 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{
+  Agent, AgentParameters, AvoidanceOptions, ResponsibilityOverride, Simulator,
+};
+
+/// The player is the only agent with a radius of 0.6.
+fn is_player(agent: &Agent) -> bool {
+  agent.radius == 0.6
+}
+
+/// The player never gives way, so NPCs take all the responsibility.
+fn player_never_gives_way() -> ResponsibilityOverride {
+  ResponsibilityOverride::new(|agent, neighbour| {
+    match (is_player(agent), is_player(neighbour)) {
+      (true, false) => Some(0.0),
+      (false, true) => Some(1.0),
+      _ => None,
+    }
+  })
+}
+
+#[test]
+fn overrides_responsibility_of_pairs() {
+  let player = Agent::builder()
+    .radius(0.6)
+    .velocity(Vec3::X)
+    .avoidance_responsibility(1.0)
+    .build();
+  let npc = Agent::builder()
+    .position(Vec3::new(2.0, 0.0, 0.1))
+    .velocity(-Vec3::X)
+    .avoidance_responsibility(1.0)
+    .build();
+  let compute = |agent: &Agent, neighbour: &Agent, options| {
+    agent.compute_avoiding_velocity(
+      &[Cow::Borrowed(neighbour)],
+      &[],
+      agent.velocity,
+      /* max_speed= */ 1.0,
+      /* time_step= */ 0.1,
+      options,
+    )
+  };
+
+  let options = AvoidanceOptions::builder().build();
+  let overridden_options = AvoidanceOptions::builder()
+    .responsibility_override(player_never_gives_way())
+    .build();
+
+  // Without the override, the player dodges too.
+  assert_ne!(compute(&player, &npc, &options), Vec3::X);
+  assert_eq!(compute(&player, &npc, &overridden_options), Vec3::X);
+
+  // The NPC dodges further to make up for the player.
+  let shared_dodge = compute(&npc, &player, &options).distance(-Vec3::X);
+  let full_dodge =
+    compute(&npc, &player, &overridden_options).distance(-Vec3::X);
+  assert!(full_dodge > shared_dodge * 1.5, "{full_dodge} {shared_dodge}");
+}
+
+#[test]
+fn simulator_overrides_responsibility_of_pairs() {
+  let mut simulator = Simulator::new();
+  simulator.set_responsibility_override(Some(player_never_gives_way()));
+  simulator.add_agent(
+    Agent::builder().radius(0.6).build(),
+    AgentParameters::builder().goal_point(Vec3::new(10.0, 0.0, 0.0)).build(),
+  );
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(5.0, 0.0, 0.1)).build(),
+    AgentParameters::builder().goal_point(Vec3::new(-5.0, 0.0, 0.1)).build(),
+  );
+
+  let (player, npc) = (0, 1);
+  for _ in 0..100 {
+    simulator.step(0.1);
+    // The player walks straight through the NPC's path.
+    let player_position = simulator.get_agent(player).position;
+    assert!(
+      player_position.y.abs() < 1e-4 && player_position.z.abs() < 1e-4,
+      "{player_position}"
+    );
+    let distance = simulator
+      .get_agent(player)
+      .position
+      .distance(simulator.get_agent(npc).position);
+    assert!(distance > 1.0, "{distance}");
+  }
+}
+
+#[test]
+#[should_panic = "overridden responsibility must be between 0 and 1"]
+fn panics_on_invalid_responsibility() {
+  let agent = Agent::builder().build();
+  let neighbour = Agent::builder().position(Vec3::new(2.0, 0.0, 0.0)).build();
+  agent.compute_avoiding_velocity(
+    &[Cow::Owned(neighbour)],
+    &[],
+    Vec3::X,
+    /* max_speed= */ 1.0,
+    /* time_step= */ 0.1,
+    &AvoidanceOptions::builder()
+      .responsibility_override(ResponsibilityOverride::new(|_, _| Some(2.0)))
+      .build(),
+  );
+}
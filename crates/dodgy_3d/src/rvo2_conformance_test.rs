@@ -0,0 +1,243 @@
+// Checks that `AvoidanceOptions::strict_rvo2_compatibility` matches RVO2-3D.
+// The reference planes below are a direct transcription of
+// `Agent::computeNewVelocity` from Agent.cc in RVO2-3D, so that any change in
+// dodgy's plane construction that deviates from the original library is
+// caught.
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{
+  linear_programming::{solve_linear_program, Plane},
+  Agent, AvoidanceOptions, ObjectiveWeights, SolverLimits, Tolerances,
+};
+
+/// Computes the ORCA plane for `agent` induced by `other` exactly as RVO2-3D
+/// does.
+fn rvo2_plane(
+  agent: &Agent,
+  other: &Agent,
+  time_horizon: f32,
+  time_step: f32,
+) -> Plane {
+  let inv_time_horizon = 1.0 / time_horizon;
+
+  let relative_position = other.position - agent.position;
+  let relative_velocity = agent.velocity - other.velocity;
+  let dist_sq = relative_position.length_squared();
+  let combined_radius = agent.radius + other.radius;
+  let combined_radius_sq = combined_radius * combined_radius;
+
+  let normal;
+  let u;
+  if dist_sq > combined_radius_sq {
+    let w = relative_velocity - inv_time_horizon * relative_position;
+    let w_length_sq = w.length_squared();
+    let dot_product = w.dot(relative_position);
+
+    if dot_product < 0.0
+      && dot_product * dot_product > combined_radius_sq * w_length_sq
+    {
+      let w_length = w_length_sq.sqrt();
+      let unit_w = w / w_length;
+      normal = unit_w;
+      u = (combined_radius * inv_time_horizon - w_length) * unit_w;
+    } else {
+      let a = dist_sq;
+      let b = relative_position.dot(relative_velocity);
+      let c = relative_velocity.length_squared()
+        - relative_position.cross(relative_velocity).length_squared()
+          / (dist_sq - combined_radius_sq);
+      let t = (b + (b * b - a * c).sqrt()) / a;
+      let w = relative_velocity - t * relative_position;
+      let w_length = w.length();
+      let unit_w = w / w_length;
+      normal = unit_w;
+      u = (combined_radius * t - w_length) * unit_w;
+    }
+  } else {
+    let inv_time_step = 1.0 / time_step;
+    let w = relative_velocity - inv_time_step * relative_position;
+    let w_length = w.length();
+    let unit_w = w / w_length;
+    normal = unit_w;
+    u = (combined_radius * inv_time_step - w_length) * unit_w;
+  }
+
+  Plane { point: agent.velocity + 0.5 * u, normal }
+}
+
+/// Finds the (at most) `max_neighbours` nearest agents within
+/// `neighbour_distance` of `agents[index]`, as RVO2-3D's k-d tree does.
+fn rvo2_neighbours(
+  agents: &[Agent],
+  index: usize,
+  neighbour_distance: f32,
+  max_neighbours: usize,
+) -> Vec<usize> {
+  let mut neighbours = (0..agents.len())
+    .filter(|&other| other != index)
+    .map(|other| {
+      (other, agents[index].position.distance_squared(agents[other].position))
+    })
+    .filter(|&(_, distance_squared)| {
+      distance_squared < neighbour_distance * neighbour_distance
+    })
+    .collect::<Vec<_>>();
+  neighbours.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+  neighbours.truncate(max_neighbours);
+  neighbours.into_iter().map(|(other, _)| other).collect()
+}
+
+/// Runs a scenario in both dodgy (in strict mode) and the RVO2-3D reference,
+/// asserting that every agent's new velocity matches within tolerance.
+fn assert_scenario_conforms(
+  mut agents: Vec<Agent>,
+  goals: &[Vec3],
+  steps: usize,
+) {
+  const TIME_STEP: f32 = 0.125;
+  const NEIGHBOUR_DISTANCE: f32 = 15.0;
+  const MAX_NEIGHBOURS: usize = 10;
+  const TIME_HORIZON: f32 = 10.0;
+  const MAX_SPEED: f32 = 2.0;
+
+  let avoidance_options = AvoidanceOptions {
+    obstacle_margin: 0.0,
+    time_horizon: TIME_HORIZON,
+    obstacle_time_horizon: TIME_HORIZON,
+    tolerances: Tolerances::default(),
+    strict_rvo2_compatibility: true,
+    objective_weights: ObjectiveWeights::default(),
+    solver_limits: SolverLimits::default(),
+    responsibility_override: None,
+  };
+
+  for step in 0..steps {
+    let mut new_velocities = Vec::with_capacity(agents.len());
+    for (index, agent) in agents.iter().enumerate() {
+      let mut preferred_velocity = goals[index] - agent.position;
+      if preferred_velocity.length_squared() > 1.0 {
+        preferred_velocity = preferred_velocity.normalize();
+      }
+
+      let neighbour_indices =
+        rvo2_neighbours(&agents, index, NEIGHBOUR_DISTANCE, MAX_NEIGHBOURS);
+
+      let reference_planes = neighbour_indices
+        .iter()
+        .map(|&other| {
+          rvo2_plane(agent, &agents[other], TIME_HORIZON, TIME_STEP)
+        })
+        .collect::<Vec<_>>();
+      let expected_velocity = solve_linear_program(
+        &reference_planes,
+        /* rigid_constraint_count= */ 0,
+        MAX_SPEED,
+        preferred_velocity,
+        avoidance_options.tolerances.solver_epsilon,
+      );
+
+      let neighbours = neighbour_indices
+        .iter()
+        .map(|&other| Cow::Borrowed(&agents[other]))
+        .collect::<Vec<_>>();
+      let actual_velocity = agent.compute_avoiding_velocity(
+        &neighbours,
+        &[],
+        preferred_velocity,
+        MAX_SPEED,
+        TIME_STEP,
+        &avoidance_options,
+      );
+
+      assert!(
+        actual_velocity.distance(expected_velocity) < 1e-3,
+        "step {}, agent {}: dodgy={} rvo2={}",
+        step,
+        index,
+        actual_velocity,
+        expected_velocity
+      );
+      new_velocities.push(expected_velocity);
+    }
+
+    for (agent, new_velocity) in agents.iter_mut().zip(new_velocities) {
+      agent.velocity = new_velocity;
+      agent.position += new_velocity * TIME_STEP;
+    }
+  }
+}
+
+fn rvo2_agent(position: Vec3) -> Agent {
+  Agent {
+    position,
+    velocity: Vec3::ZERO,
+    radius: 1.5,
+    avoidance_responsibility: 1.0,
+  }
+}
+
+#[test]
+fn sphere_scenario_conforms() {
+  // The "Sphere" example from RVO2-3D: agents spread over a sphere with goals
+  // at their antipodal points.
+  let mut agents = Vec::new();
+  let mut goals = Vec::new();
+  let mut a = 0.0f32;
+  while a < std::f32::consts::PI {
+    let z = 100.0 * a.cos();
+    let r = 100.0 * a.sin();
+    let ring_count = r / 2.5;
+    let mut i = 0;
+    while (i as f32) < ring_count {
+      let angle = i as f32 * 2.0 * std::f32::consts::PI / ring_count;
+      let position = Vec3::new(r * angle.cos(), r * angle.sin(), z);
+      agents.push(rvo2_agent(position));
+      goals.push(-position);
+      i += 1;
+    }
+    a += 0.1;
+  }
+
+  assert_scenario_conforms(agents, &goals, 5);
+}
+
+#[test]
+fn head_on_scenario_conforms() {
+  let agents = vec![
+    rvo2_agent(Vec3::new(-10.0, 0.0, 0.0)),
+    rvo2_agent(Vec3::new(10.0, 0.0, 0.1)),
+  ];
+  let goals = [Vec3::new(10.0, 0.0, 0.0), Vec3::new(-10.0, 0.0, 0.0)];
+
+  assert_scenario_conforms(agents, &goals, 100);
+}
+
+#[test]
+fn ignores_avoidance_responsibility() {
+  let agent = Agent {
+    position: Vec3::ZERO,
+    velocity: Vec3::new(1.5, 0.0, 0.0),
+    radius: 1.0,
+    avoidance_responsibility: 1.0,
+  };
+  let neighbour = Agent {
+    position: Vec3::new(4.0, 0.0, 0.0),
+    velocity: Vec3::ZERO,
+    radius: 1.0,
+    avoidance_responsibility: 3.0,
+  };
+
+  let actual_plane = agent.get_plane_for_neighbour(
+    &neighbour,
+    /* time_horizon= */ 2.0,
+    /* time_step= */ 0.5,
+    &Tolerances::rvo2(),
+    /* strict_rvo2_compatibility= */ true,
+    /* responsibility_override= */ None,
+  );
+  let expected_plane = rvo2_plane(&agent, &neighbour, 2.0, 0.5);
+  assert!(actual_plane.point.distance(expected_plane.point) < 1e-5);
+  assert!(actual_plane.normal.distance(expected_plane.normal) < 1e-5);
+}
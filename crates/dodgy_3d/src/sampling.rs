@@ -0,0 +1,93 @@
+use glam::Vec3;
+
+use crate::linear_programming::{
+  solve_linear_program_with_limits, Plane, SolverLimits,
+};
+
+/// Selects the velocity with the lowest `cost` that satisfies all
+/// `constraints` and is at most `radius` long. The candidates are the solution
+/// of the linear program for `preferred_velocity`, and `sample_count` samples
+/// spread throughout the sphere of `radius`. Each sample is a candidate if it
+/// is valid, and the solution of the linear program for the sample (on the
+/// surface of the sphere) is always a candidate, so the boundary of the valid
+/// region is also explored. If no candidate is valid (since the linear program
+/// had to relax the constraints), the solution for `preferred_velocity` is
+/// returned. Each solve of the linear program is limited by `limits`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn select_lowest_cost_velocity(
+  constraints: &[Plane],
+  rigid_constraint_count: usize,
+  radius: f32,
+  preferred_velocity: Vec3,
+  cost: &dyn Fn(Vec3) -> f32,
+  sample_count: usize,
+  epsilon: f32,
+  limits: &SolverLimits,
+) -> Vec3 {
+  let solve = |target: Vec3| {
+    solve_linear_program_with_limits(
+      constraints,
+      rigid_constraint_count,
+      radius,
+      target,
+      epsilon,
+      limits,
+    )
+    .0
+  };
+  // The constraints can be slightly violated due to floating point error, so
+  // allow some slack when checking candidates.
+  let slack = epsilon.max(1e-4);
+  let is_valid = |velocity: Vec3| {
+    velocity.length() <= radius + slack
+      && constraints.iter().all(|constraint| {
+        constraint.signed_distance_to_plane(velocity) >= -slack
+      })
+  };
+
+  let preferred_solution = solve(preferred_velocity);
+  if !is_valid(preferred_solution) {
+    return preferred_solution;
+  }
+
+  let mut best_velocity = preferred_solution;
+  let mut best_cost = cost(preferred_solution);
+  let mut consider = |velocity: Vec3| {
+    let velocity_cost = cost(velocity);
+    if velocity_cost < best_cost {
+      best_cost = velocity_cost;
+      best_velocity = velocity;
+    }
+  };
+
+  for (index, direction) in sample_directions(sample_count).enumerate() {
+    // Cycle through a few speeds so the interior of the sphere is sampled too.
+    let sample = direction * (radius * ((index % 3) as f32 + 1.0) / 3.0);
+    if is_valid(sample) {
+      consider(sample);
+    }
+
+    let boundary_velocity = solve(direction * radius);
+    if is_valid(boundary_velocity) {
+      consider(boundary_velocity);
+    }
+  }
+
+  best_velocity
+}
+
+/// Creates `count` unit vectors evenly distributed over the sphere using a
+/// Fibonacci lattice.
+fn sample_directions(count: usize) -> impl Iterator<Item = Vec3> {
+  let golden_angle = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+  (0..count).map(move |index| {
+    let y = 1.0 - 2.0 * (index as f32 + 0.5) / count as f32;
+    let ring_radius = (1.0 - y * y).sqrt();
+    let angle = golden_angle * index as f32;
+    Vec3::new(angle.cos() * ring_radius, y, angle.sin() * ring_radius)
+  })
+}
+
+#[cfg(test)]
+#[path = "sampling_test.rs"]
+mod test;
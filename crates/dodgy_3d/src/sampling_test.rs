@@ -0,0 +1,71 @@
+use glam::Vec3;
+
+use super::*;
+use crate::linear_programming::{solve_linear_program, RVO_EPSILON};
+
+#[test]
+fn sample_directions_are_unit_length() {
+  let directions = sample_directions(20).collect::<Vec<_>>();
+  assert_eq!(directions.len(), 20);
+  for direction in directions {
+    assert!((direction.length() - 1.0).abs() < 1e-5, "{}", direction);
+  }
+}
+
+#[test]
+fn preferred_velocity_wins_with_distance_cost() {
+  let preferred_velocity = Vec3::new(1.0, 0.0, 0.0);
+  let velocity = select_lowest_cost_velocity(
+    &[],
+    /* rigid_constraint_count= */ 0,
+    /* radius= */ 2.0,
+    preferred_velocity,
+    &|velocity| velocity.distance(preferred_velocity),
+    /* sample_count= */ 32,
+    RVO_EPSILON,
+    &SolverLimits::default(),
+  );
+  assert_eq!(velocity, preferred_velocity);
+}
+
+#[test]
+fn finds_boundary_of_valid_region() {
+  // Prefers going as high as possible, but can't go above 0.5.
+  let constraints =
+    [Plane { point: Vec3::new(0.0, 0.5, 0.0), normal: Vec3::NEG_Y }];
+  let velocity = select_lowest_cost_velocity(
+    &constraints,
+    /* rigid_constraint_count= */ 0,
+    /* radius= */ 2.0,
+    /* preferred_velocity= */ Vec3::ZERO,
+    &|velocity| -velocity.y,
+    /* sample_count= */ 32,
+    RVO_EPSILON,
+    &SolverLimits::default(),
+  );
+  assert!((velocity.y - 0.5).abs() < 1e-4, "{}", velocity);
+  assert!(velocity.length() <= 2.0 + 1e-4, "{}", velocity);
+}
+
+#[test]
+fn falls_back_to_linear_program_if_infeasible() {
+  // These constraints cannot both be satisfied.
+  let constraints = [
+    Plane { point: Vec3::new(1.0, 0.0, 0.0), normal: Vec3::X },
+    Plane { point: Vec3::new(-1.0, 0.0, 0.0), normal: Vec3::NEG_X },
+  ];
+  let velocity = select_lowest_cost_velocity(
+    &constraints,
+    /* rigid_constraint_count= */ 0,
+    /* radius= */ 2.0,
+    /* preferred_velocity= */ Vec3::ZERO,
+    &|velocity| velocity.z,
+    /* sample_count= */ 32,
+    RVO_EPSILON,
+    &SolverLimits::default(),
+  );
+  assert_eq!(
+    velocity,
+    solve_linear_program(&constraints, 0, 2.0, Vec3::ZERO, RVO_EPSILON)
+  );
+}
@@ -0,0 +1,183 @@
+//! Standard scenarios for benchmarking and tuning avoidance.
+//!
+//! Each function generates a [`Scenario`] with the requested number of agents.
+//! Scenarios can be run directly through the raw API (using
+//! [`Scenario::agents`] and [`Scenario::goal_points`]), or converted into a
+//! [`Simulator`] with [`Scenario::into_simulator`].
+
+use glam::{BVec3, Vec3};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{Agent, AgentParameters, Simulator, SimulatorMargin};
+
+/// The radius of every agent in the generated scenarios.
+pub const SCENARIO_AGENT_RADIUS: f32 = 0.5;
+
+/// The distance between the centers of adjacent agents when agents are packed
+/// together.
+const AGENT_SPACING: f32 = SCENARIO_AGENT_RADIUS * 3.0;
+
+/// A set of agents and the goals they are trying to reach.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Scenario {
+  /// The agents in the scenario.
+  pub agents: Vec<Agent>,
+  /// The goal point for each agent in `agents`.
+  pub goal_points: Vec<Vec3>,
+  /// The max speed that agents should use for this scenario.
+  pub max_speed: f32,
+  /// The time horizon that agents should use for this scenario.
+  pub time_horizon: f32,
+}
+
+impl Scenario {
+  /// Creates a [`Simulator`] containing all the agents of this scenario.
+  pub fn into_simulator(self) -> Simulator {
+    let mut simulator = Simulator::new();
+    for (agent, goal_point) in self.agents.into_iter().zip(self.goal_points) {
+      simulator.add_agent(
+        agent,
+        AgentParameters {
+          goal_point,
+          max_speed: self.max_speed,
+          obstacle_margin: SimulatorMargin::AgentRadius,
+          time_horizon: self.time_horizon,
+          obstacle_time_horizon: self.time_horizon,
+          traffic_lane: None,
+          social_force: None,
+          leader_following: None,
+          yielding: None,
+          urgency: 0.0,
+          standing_height: None,
+          locked_axes: BVec3::FALSE,
+        },
+      );
+    }
+    simulator
+  }
+}
+
+fn scenario_agent(position: Vec3) -> Agent {
+  Agent {
+    position,
+    velocity: Vec3::ZERO,
+    radius: SCENARIO_AGENT_RADIUS,
+    avoidance_responsibility: 1.0,
+  }
+}
+
+/// Agents are evenly spread out over the surface of a sphere, and each agent
+/// tries to reach the opposite side of the sphere. All agents meet in the
+/// center, making this a worst case for dense, symmetric conflicts.
+pub fn sphere_antipodal_swap(agent_count: usize) -> Scenario {
+  // Size the sphere so each agent has roughly `AGENT_SPACING` squared of
+  // surface area.
+  let sphere_radius = AGENT_SPACING
+    * (agent_count as f32 / (4.0 * std::f32::consts::PI)).sqrt()
+    + AGENT_SPACING;
+
+  // Use a Fibonacci lattice to evenly distribute the agents.
+  let golden_angle = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+  let mut agents = Vec::with_capacity(agent_count);
+  let mut goal_points = Vec::with_capacity(agent_count);
+  for index in 0..agent_count {
+    let y = 1.0 - 2.0 * (index as f32 + 0.5) / agent_count as f32;
+    let ring_radius = (1.0 - y * y).sqrt();
+    let angle = golden_angle * index as f32;
+    let position =
+      Vec3::new(angle.cos() * ring_radius, y, angle.sin() * ring_radius)
+        * sphere_radius;
+
+    agents.push(scenario_agent(position));
+    goal_points.push(-position);
+  }
+
+  Scenario { agents, goal_points, max_speed: 2.0, time_horizon: 5.0 }
+}
+
+/// Two groups of agents start at either end of a square corridor along the x
+/// axis, and each group tries to reach the opposite end. The groups must pass
+/// through each other in the corridor.
+pub fn corridor_crossing(agent_count: usize) -> Scenario {
+  let group_size = agent_count.div_ceil(2);
+  // The number of agents along each side of the corridor's cross-section.
+  let side = (group_size as f32).sqrt().ceil().max(1.0) as usize;
+  let group_depth = group_size.div_ceil(side * side);
+  let corridor_half_length =
+    group_depth as f32 * AGENT_SPACING + 10.0 * AGENT_SPACING;
+  let cross_section_offset = (side - 1) as f32 * AGENT_SPACING * 0.5;
+
+  let mut agents = Vec::with_capacity(agent_count);
+  let mut goal_points = Vec::with_capacity(agent_count);
+  for index in 0..agent_count {
+    let group_index = index / 2;
+    // Alternate groups so each group has (almost) the same number of agents.
+    let direction = if index % 2 == 0 { 1.0 } else { -1.0 };
+
+    let depth = (group_index / (side * side)) as f32;
+    let y = (group_index % side) as f32 * AGENT_SPACING - cross_section_offset;
+    let z = ((group_index / side) % side) as f32 * AGENT_SPACING
+      - cross_section_offset;
+    let x = corridor_half_length - depth * AGENT_SPACING;
+
+    agents.push(scenario_agent(Vec3::new(-x * direction, y, z)));
+    goal_points.push(Vec3::new(x * direction, y, z));
+  }
+
+  Scenario { agents, goal_points, max_speed: 2.0, time_horizon: 3.0 }
+}
+
+/// Agents are scattered randomly throughout a cube, each heading to a random
+/// point in the cube. The cube is sized so the density of agents stays the
+/// same regardless of `agent_count`. The same `seed` always generates the same
+/// scenario.
+pub fn random_soup(agent_count: usize, seed: u64) -> Scenario {
+  let mut rng = StdRng::seed_from_u64(seed);
+  // Each agent gets roughly 8 times the space of a packed agent.
+  let half_extent = AGENT_SPACING * (agent_count as f32).cbrt() + AGENT_SPACING;
+
+  let random_point = |rng: &mut StdRng| {
+    Vec3::new(
+      rng.random_range(-half_extent..half_extent),
+      rng.random_range(-half_extent..half_extent),
+      rng.random_range(-half_extent..half_extent),
+    )
+  };
+
+  let mut agents = Vec::with_capacity(agent_count);
+  let mut goal_points = Vec::with_capacity(agent_count);
+  for _ in 0..agent_count {
+    agents.push(scenario_agent(random_point(&mut rng)));
+    goal_points.push(random_point(&mut rng));
+  }
+
+  Scenario { agents, goal_points, max_speed: 2.0, time_horizon: 3.0 }
+}
+
+/// Agents are tightly packed in a cubic grid, and each agent tries to reach
+/// the point mirrored through the center of the grid. This requires the whole
+/// grid to turn itself inside out.
+pub fn dense_grid(agent_count: usize) -> Scenario {
+  let side = (agent_count as f32).cbrt().ceil().max(1.0) as usize;
+  let offset = (side - 1) as f32 * AGENT_SPACING * 0.5;
+
+  let mut agents = Vec::with_capacity(agent_count);
+  let mut goal_points = Vec::with_capacity(agent_count);
+  for index in 0..agent_count {
+    let position = Vec3::new(
+      (index % side) as f32,
+      ((index / side) % side) as f32,
+      (index / (side * side)) as f32,
+    ) * AGENT_SPACING
+      - Vec3::splat(offset);
+
+    agents.push(scenario_agent(position));
+    goal_points.push(-position);
+  }
+
+  Scenario { agents, goal_points, max_speed: 2.0, time_horizon: 2.0 }
+}
+
+#[cfg(test)]
+#[path = "scenarios_test.rs"]
+mod test;
@@ -0,0 +1,61 @@
+use super::*;
+
+fn assert_no_overlaps(scenario: &Scenario) {
+  for (index, agent) in scenario.agents.iter().enumerate() {
+    for other in &scenario.agents[(index + 1)..] {
+      let distance = agent.position.distance(other.position);
+      assert!(
+        distance > agent.radius + other.radius,
+        "agents at {} and {} overlap",
+        agent.position,
+        other.position
+      );
+    }
+  }
+}
+
+#[test]
+fn generates_requested_agent_count() {
+  for agent_count in [1, 2, 7, 64, 100] {
+    for scenario in [
+      sphere_antipodal_swap(agent_count),
+      corridor_crossing(agent_count),
+      random_soup(agent_count, /* seed= */ 1),
+      dense_grid(agent_count),
+    ] {
+      assert_eq!(scenario.agents.len(), agent_count);
+      assert_eq!(scenario.goal_points.len(), agent_count);
+    }
+  }
+}
+
+#[test]
+fn packed_scenarios_have_no_overlaps() {
+  assert_no_overlaps(&sphere_antipodal_swap(100));
+  assert_no_overlaps(&corridor_crossing(100));
+  assert_no_overlaps(&dense_grid(100));
+}
+
+#[test]
+fn sphere_goals_are_antipodal() {
+  let scenario = sphere_antipodal_swap(50);
+  for (agent, goal_point) in scenario.agents.iter().zip(&scenario.goal_points) {
+    assert_eq!(*goal_point, -agent.position);
+  }
+}
+
+#[test]
+fn random_soup_is_deterministic() {
+  assert_eq!(random_soup(20, /* seed= */ 5), random_soup(20, /* seed= */ 5));
+  assert_ne!(random_soup(20, /* seed= */ 5), random_soup(20, /* seed= */ 6));
+}
+
+#[test]
+fn into_simulator_adds_all_agents() {
+  let scenario = corridor_crossing(10);
+  let first_goal = scenario.goal_points[0];
+
+  let simulator = scenario.into_simulator();
+  assert_eq!(simulator.get_agent_count(), 10);
+  assert_eq!(simulator.get_agent_parameters(0).goal_point, first_goal);
+}
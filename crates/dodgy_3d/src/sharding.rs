@@ -0,0 +1,439 @@
+use std::collections::BTreeMap;
+
+use glam::{Vec2, Vec3, Vec3Swizzles};
+
+use crate::{
+  Agent, AgentParameters, FollowOptions, LeaderFollowing, Obstacle, Simulator,
+};
+
+/// A handle to an agent in a [`ShardedSimulator`]. Agents move between
+/// regions as they walk around, so unlike in [`Simulator`], agents are
+/// identified by a handle rather than an index.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct ShardedAgentId(u64);
+
+/// A simulation partitioned into square regions (along the horizontal plane),
+/// each with its own [`Simulator`]. Each step, the regions are stepped across
+/// a thread per core, so large crowds (e.g. on a dedicated server) can be
+/// spread across cores. Agents near the border of a region also avoid "ghosts" of the
+/// agents in neighbouring regions, so agents avoid each other across borders.
+/// After each step, agents that left their region move to the region they are
+/// now in.
+///
+/// Moving between regions resets the agent's parameter blend (the agent
+/// immediately uses the target parameters). Terrain and boundaries are not
+/// supported. Agents in different regions cannot refer to each other by index,
+/// so leaders are set with [`Self::set_agent_leader`] instead of
+/// [`AgentParameters::leader_following`] (which the sharded simulator
+/// manages).
+pub struct ShardedSimulator {
+  /// The width of each (square) region.
+  region_size: f32,
+  /// The regions, ordered (rather than hashed) so that agents migrate in the
+  /// same order every run, which keeps the simulation deterministic.
+  regions: BTreeMap<RegionKey, Region>,
+  /// The region each agent is currently in.
+  agent_regions: BTreeMap<ShardedAgentId, RegionKey>,
+  /// The leader of each following agent. Followers are kept in the same
+  /// region as their leader (even once they are across its border), and their
+  /// [`AgentParameters::leader_following`] is pointed at their leader's index
+  /// in that region whenever agents are added, removed, or migrated.
+  leaders: BTreeMap<ShardedAgentId, (ShardedAgentId, FollowOptions)>,
+  obstacles: Vec<Obstacle>,
+  next_agent_id: u64,
+}
+
+/// Steps each region in `regions` with its ghosts.
+fn step_regions(regions: &mut [(&mut Region, &Vec<Agent>)], time_step: f32) {
+  for (region, ghosts) in regions.iter_mut() {
+    region.simulator.step_with_ghosts(time_step, ghosts);
+  }
+}
+
+/// The coordinates of a region in the grid of regions.
+type RegionKey = (i32, i32);
+
+/// A region of a [`ShardedSimulator`].
+struct Region {
+  simulator: Simulator,
+  /// The handle of each agent in `simulator`, in the same order.
+  agent_ids: Vec<ShardedAgentId>,
+}
+
+impl ShardedSimulator {
+  /// Creates an empty simulation split into regions that are `region_size`
+  /// wide. Regions should be much larger than the distance agents avoid each
+  /// other at, so that most agents are not near a border.
+  pub fn new(region_size: f32) -> Self {
+    assert!(
+      region_size > 0.0,
+      "region_size must be positive, was {}",
+      region_size
+    );
+    Self {
+      region_size,
+      regions: BTreeMap::new(),
+      agent_regions: BTreeMap::new(),
+      leaders: BTreeMap::new(),
+      obstacles: Vec::new(),
+      next_agent_id: 0,
+    }
+  }
+
+  /// Adds an agent. The agent does not follow a leader (even if
+  /// `agent_parameters` has [`AgentParameters::leader_following`] set) until
+  /// one is set with [`Self::set_agent_leader`].
+  pub fn add_agent(
+    &mut self,
+    agent: Agent,
+    mut agent_parameters: AgentParameters,
+  ) -> ShardedAgentId {
+    let agent_id = ShardedAgentId(self.next_agent_id);
+    self.next_agent_id += 1;
+    agent_parameters.leader_following = None;
+    let region_key = self.get_region_key(agent.position);
+    self.insert_agent(agent_id, region_key, agent, agent_parameters);
+    agent_id
+  }
+
+  /// Removes the agent `agent_id`, returning it and its parameters. Agents
+  /// following the removed agent stop following. Panics if there is no such
+  /// agent.
+  pub fn remove_agent(
+    &mut self,
+    agent_id: ShardedAgentId,
+  ) -> (Agent, AgentParameters) {
+    let (agent, mut agent_parameters) = self.take_agent(agent_id);
+    agent_parameters.leader_following = None;
+    self.leaders.remove(&agent_id);
+    let follower_ids = self
+      .leaders
+      .iter()
+      .filter(|(_, (leader_id, _))| *leader_id == agent_id)
+      .map(|(&follower_id, _)| follower_id)
+      .collect::<Vec<_>>();
+    for follower_id in follower_ids {
+      self.set_agent_leader(follower_id, None);
+    }
+    self.update_leader_following();
+    (agent, agent_parameters)
+  }
+
+  /// Makes the agent `follower_id` follow the agent `leader` (with the
+  /// options to follow it with), or stops it following if `leader` is
+  /// `None`. Followers stay in their leader's region, so they keep following
+  /// their leader across region borders. Panics if either agent does not
+  /// exist, or if the agent would follow itself.
+  pub fn set_agent_leader(
+    &mut self,
+    follower_id: ShardedAgentId,
+    leader: Option<(ShardedAgentId, FollowOptions)>,
+  ) {
+    assert!(
+      self.agent_regions.contains_key(&follower_id),
+      "no agent with id {:?}",
+      follower_id
+    );
+    match leader {
+      None => {
+        self.leaders.remove(&follower_id);
+        self.get_agent_parameters_mut(follower_id).leader_following = None;
+      }
+      Some((leader_id, options)) => {
+        assert!(
+          self.agent_regions.contains_key(&leader_id),
+          "no agent with id {:?}",
+          leader_id
+        );
+        assert_ne!(follower_id, leader_id, "an agent cannot follow itself");
+        self.leaders.insert(follower_id, (leader_id, options));
+      }
+    }
+    self.migrate_agents();
+  }
+
+  /// Gets the agent that the agent `follower_id` follows, if any.
+  pub fn get_agent_leader(
+    &self,
+    follower_id: ShardedAgentId,
+  ) -> Option<ShardedAgentId> {
+    self.leaders.get(&follower_id).map(|&(leader_id, _)| leader_id)
+  }
+
+  /// Removes the agent `agent_id` from its region, without updating any
+  /// leaders.
+  fn take_agent(
+    &mut self,
+    agent_id: ShardedAgentId,
+  ) -> (Agent, AgentParameters) {
+    let region_key = self
+      .agent_regions
+      .remove(&agent_id)
+      .unwrap_or_else(|| panic!("no agent with id {:?}", agent_id));
+    let region = self.regions.get_mut(&region_key).unwrap();
+    let agent_index = region.get_agent_index(agent_id);
+    let agent = region.simulator.get_agent(agent_index).clone();
+    let agent_parameters =
+      region.simulator.get_agent_parameters(agent_index).clone();
+    region.simulator.remove_agent(agent_index);
+    region.agent_ids.remove(agent_index);
+    if region.agent_ids.is_empty() {
+      self.regions.remove(&region_key);
+    }
+    (agent, agent_parameters)
+  }
+
+  /// Gets the agent `agent_id`. Panics if there is no such agent.
+  pub fn get_agent(&self, agent_id: ShardedAgentId) -> &Agent {
+    let (simulator, agent_index) = self.find_agent(agent_id);
+    simulator.get_agent(agent_index)
+  }
+
+  /// Gets the agent `agent_id` mutably. Panics if there is no such agent. If
+  /// the agent is moved to another region, it only changes region at the end
+  /// of the next step.
+  pub fn get_agent_mut(&mut self, agent_id: ShardedAgentId) -> &mut Agent {
+    let (simulator, agent_index) = self.find_agent_mut(agent_id);
+    simulator.get_agent_mut(agent_index)
+  }
+
+  pub fn get_agent_parameters(
+    &self,
+    agent_id: ShardedAgentId,
+  ) -> &AgentParameters {
+    let (simulator, agent_index) = self.find_agent(agent_id);
+    simulator.get_agent_parameters(agent_index)
+  }
+
+  pub fn get_agent_parameters_mut(
+    &mut self,
+    agent_id: ShardedAgentId,
+  ) -> &mut AgentParameters {
+    let (simulator, agent_index) = self.find_agent_mut(agent_id);
+    simulator.get_agent_parameters_mut(agent_index)
+  }
+
+  pub fn get_agent_count(&self) -> usize {
+    self.agent_regions.len()
+  }
+
+  /// The handles of all agents, in the order they were added.
+  pub fn get_agent_ids(&self) -> impl Iterator<Item = ShardedAgentId> + '_ {
+    self.agent_regions.keys().copied()
+  }
+
+  /// The number of regions that currently contain agents.
+  pub fn get_region_count(&self) -> usize {
+    self.regions.len()
+  }
+
+  /// Adds an obstacle to every region.
+  pub fn add_obstacle(&mut self, obstacle: Obstacle) {
+    for region in self.regions.values_mut() {
+      region.simulator.add_obstacle(obstacle.clone());
+    }
+    self.obstacles.push(obstacle);
+  }
+
+  pub fn get_obstacle_count(&self) -> usize {
+    self.obstacles.len()
+  }
+
+  /// Steps every region, then moves agents that left their region into their
+  /// new region. The regions are split into (at most) one batch per available
+  /// core (see [`std::thread::available_parallelism`]), and each batch is
+  /// stepped on its own thread.
+  pub fn step(&mut self, time_step: f32) {
+    let region_ghosts = self
+      .regions
+      .keys()
+      .map(|&region_key| self.get_ghosts_for_region(region_key))
+      .collect::<Vec<_>>();
+    let mut regions =
+      self.regions.values_mut().zip(region_ghosts.iter()).collect::<Vec<_>>();
+
+    let thread_count = std::thread::available_parallelism()
+      .map_or(1, |thread_count| thread_count.get())
+      .min(regions.len())
+      .max(1);
+    let batch_size = regions.len().div_ceil(thread_count).max(1);
+    std::thread::scope(|scope| {
+      let mut batches = regions.chunks_mut(batch_size);
+      // Step the first batch on this thread, rather than waiting idly.
+      let first_batch = batches.next();
+      for batch in batches {
+        scope.spawn(move || step_regions(batch, time_step));
+      }
+      if let Some(batch) = first_batch {
+        step_regions(batch, time_step);
+      }
+    });
+
+    self.migrate_agents();
+  }
+
+  /// Finds the agents in regions around `region_key` that agents in
+  /// `region_key` may need to avoid.
+  fn get_ghosts_for_region(&self, region_key: RegionKey) -> Vec<Agent> {
+    let region_min =
+      Vec2::new(region_key.0 as f32, region_key.1 as f32) * self.region_size;
+    let region_max = region_min + Vec2::splat(self.region_size);
+    // The furthest any agent in the region looks for neighbours. Agents
+    // further than this from the region cannot be anyone's neighbour.
+    let ghost_distance =
+      self.regions[&region_key].simulator.get_max_neighbour_query_distance();
+
+    let mut ghosts = Vec::new();
+    for x in -1..=1 {
+      for z in -1..=1 {
+        if x == 0 && z == 0 {
+          continue;
+        }
+        let Some(other_region) =
+          self.regions.get(&(region_key.0 + x, region_key.1 + z))
+        else {
+          continue;
+        };
+        let simulator = &other_region.simulator;
+        ghosts.extend(
+          (0..simulator.get_agent_count())
+            .map(|agent_index| simulator.get_agent(agent_index))
+            .filter(|agent| {
+              let position = agent.position.xz();
+              position.clamp(region_min, region_max).distance(position)
+                <= ghost_distance
+            })
+            .cloned(),
+        );
+      }
+    }
+    ghosts
+  }
+
+  /// Moves every agent that is outside its region into the region it is in
+  /// (or the region its leader is moving to, for followers), then points
+  /// followers at their leaders' new indices.
+  fn migrate_agents(&mut self) {
+    let mut migrating_agents = Vec::new();
+    for (&region_key, region) in self.regions.iter() {
+      for &agent_id in region.agent_ids.iter() {
+        let new_region_key = self.get_new_region_key(agent_id);
+        if new_region_key != region_key {
+          migrating_agents.push((agent_id, new_region_key));
+        }
+      }
+    }
+
+    for (agent_id, region_key) in migrating_agents {
+      let (agent, agent_parameters) = self.take_agent(agent_id);
+      self.insert_agent(agent_id, region_key, agent, agent_parameters);
+    }
+    self.update_leader_following();
+  }
+
+  /// The region the agent `agent_id` belongs in: the region containing the
+  /// agent leading its group (the agent itself, if it does not follow a
+  /// leader).
+  fn get_new_region_key(&self, agent_id: ShardedAgentId) -> RegionKey {
+    let mut leading_id = agent_id;
+    // Stop after visiting every follower, in case the leaders form a cycle.
+    for _ in 0..self.leaders.len() {
+      match self.leaders.get(&leading_id) {
+        None => break,
+        Some(&(leader_id, _)) => leading_id = leader_id,
+      }
+    }
+    self.get_region_key(self.get_agent(leading_id).position)
+  }
+
+  /// Points the [`AgentParameters::leader_following`] of every follower at
+  /// its leader's index in their region.
+  fn update_leader_following(&mut self) {
+    for (follower_id, (leader_id, options)) in self.leaders.iter() {
+      let region_key = self.agent_regions[follower_id];
+      let region = self.regions.get_mut(&region_key).unwrap();
+      let follower_index = region.get_agent_index(*follower_id);
+      // Only leaders in a cycle can end up in another region.
+      let leader_following = (self.agent_regions[leader_id] == region_key)
+        .then(|| LeaderFollowing {
+          leader_index: region.get_agent_index(*leader_id),
+          options: options.clone(),
+        });
+      region
+        .simulator
+        .get_agent_parameters_mut(follower_index)
+        .leader_following = leader_following;
+    }
+  }
+
+  /// Adds the agent to the region at `region_key`, creating the region if
+  /// needed.
+  fn insert_agent(
+    &mut self,
+    agent_id: ShardedAgentId,
+    region_key: RegionKey,
+    agent: Agent,
+    mut agent_parameters: AgentParameters,
+  ) {
+    // The leader index is only meaningful in the agent's old region, and is
+    // pointed at the leader in the new region afterwards.
+    agent_parameters.leader_following = None;
+    let obstacles = &self.obstacles;
+    let region = self.regions.entry(region_key).or_insert_with(|| {
+      let mut simulator = Simulator::new();
+      for obstacle in obstacles.iter() {
+        simulator.add_obstacle(obstacle.clone());
+      }
+      Region { simulator, agent_ids: Vec::new() }
+    });
+    region.simulator.add_agent(agent, agent_parameters);
+    region.agent_ids.push(agent_id);
+    self.agent_regions.insert(agent_id, region_key);
+  }
+
+  /// The key of the region containing `position`.
+  fn get_region_key(&self, position: Vec3) -> RegionKey {
+    let cell = (position.xz() / self.region_size).floor();
+    (cell.x as i32, cell.y as i32)
+  }
+
+  fn find_agent(&self, agent_id: ShardedAgentId) -> (&Simulator, usize) {
+    let region = self.get_agent_region(agent_id);
+    (&region.simulator, region.get_agent_index(agent_id))
+  }
+
+  fn find_agent_mut(
+    &mut self,
+    agent_id: ShardedAgentId,
+  ) -> (&mut Simulator, usize) {
+    let region_key = *self
+      .agent_regions
+      .get(&agent_id)
+      .unwrap_or_else(|| panic!("no agent with id {:?}", agent_id));
+    let region = self.regions.get_mut(&region_key).unwrap();
+    let agent_index = region.get_agent_index(agent_id);
+    (&mut region.simulator, agent_index)
+  }
+
+  fn get_agent_region(&self, agent_id: ShardedAgentId) -> &Region {
+    let region_key = self
+      .agent_regions
+      .get(&agent_id)
+      .unwrap_or_else(|| panic!("no agent with id {:?}", agent_id));
+    &self.regions[region_key]
+  }
+}
+
+impl Region {
+  fn get_agent_index(&self, agent_id: ShardedAgentId) -> usize {
+    self
+      .agent_ids
+      .iter()
+      .position(|&other_id| other_id == agent_id)
+      .expect("agent is in its region")
+  }
+}
+
+#[cfg(test)]
+#[path = "sharding_test.rs"]
+mod test;
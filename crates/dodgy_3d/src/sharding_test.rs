@@ -0,0 +1,172 @@
+use glam::{BVec3, Vec3};
+
+use super::*;
+use crate::SimulatorMargin;
+
+fn parameters(goal_point: Vec3) -> AgentParameters {
+  AgentParameters {
+    goal_point,
+    max_speed: 1.0,
+    obstacle_margin: SimulatorMargin::AgentRadius,
+    time_horizon: 2.0,
+    obstacle_time_horizon: 1.0,
+    traffic_lane: None,
+    social_force: None,
+    leader_following: None,
+    yielding: None,
+    urgency: 0.0,
+    standing_height: None,
+    locked_axes: BVec3::FALSE,
+  }
+}
+
+fn agent_at(position: Vec3) -> Agent {
+  Agent {
+    position,
+    velocity: Vec3::ZERO,
+    radius: 0.5,
+    avoidance_responsibility: 1.0,
+  }
+}
+
+#[test]
+fn agents_avoid_each_other_across_regions() {
+  let mut simulator = ShardedSimulator::new(/* region_size= */ 10.0);
+  // The agents start in different regions, and swap places.
+  let left = simulator.add_agent(
+    agent_at(Vec3::new(5.0, 0.0, 5.0)),
+    parameters(Vec3::new(15.0, 0.0, 5.0)),
+  );
+  let right = simulator.add_agent(
+    agent_at(Vec3::new(15.0, 0.0, 5.1)),
+    parameters(Vec3::new(5.0, 0.0, 5.1)),
+  );
+  assert_eq!(simulator.get_agent_count(), 2);
+  assert_eq!(simulator.get_region_count(), 2);
+
+  for _ in 0..300 {
+    simulator.step(0.1);
+    let distance = simulator
+      .get_agent(left)
+      .position
+      .distance(simulator.get_agent(right).position);
+    assert!(distance > 0.95, "{}", distance);
+  }
+
+  assert!(
+    simulator.get_agent(left).position.distance(Vec3::new(15.0, 0.0, 5.0))
+      < 0.5
+  );
+  assert!(
+    simulator.get_agent(right).position.distance(Vec3::new(5.0, 0.0, 5.1))
+      < 0.5
+  );
+  // Both agents changed regions.
+  assert_eq!(simulator.get_region_count(), 2);
+  assert_eq!(simulator.get_agent_parameters(left).goal_point.x, 15.0);
+
+  let (agent, _) = simulator.remove_agent(left);
+  assert!(agent.position.x > 10.0);
+  assert_eq!(simulator.get_agent_count(), 1);
+  assert_eq!(simulator.get_region_count(), 1);
+}
+
+#[test]
+fn obstacles_are_in_every_region() {
+  let mut simulator = ShardedSimulator::new(/* region_size= */ 10.0);
+  simulator
+    .add_agent(agent_at(Vec3::new(5.0, 0.0, 5.0)), parameters(Vec3::ZERO));
+  simulator.add_obstacle(Obstacle::Open {
+    vertices: vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)],
+    bottom: 0.0,
+    top: 1.0,
+  });
+  simulator
+    .add_agent(agent_at(Vec3::new(25.0, 0.0, 5.0)), parameters(Vec3::ZERO));
+
+  assert_eq!(simulator.get_obstacle_count(), 1);
+  for region in simulator.regions.values() {
+    assert_eq!(region.simulator.get_obstacle_count(), 1);
+  }
+}
+
+#[test]
+fn runs_are_deterministic() {
+  let run = || {
+    let mut simulator = ShardedSimulator::new(/* region_size= */ 5.0);
+    // A ring of agents crossing through the middle, so many agents migrate
+    // between regions at once.
+    let agent_ids = (0..24)
+      .map(|index| {
+        let angle = index as f32 / 24.0 * std::f32::consts::TAU;
+        let offset = Vec3::new(angle.cos(), 0.0, angle.sin()) * 8.0;
+        simulator.add_agent(agent_at(offset), parameters(-offset))
+      })
+      .collect::<Vec<_>>();
+    for _ in 0..100 {
+      simulator.step(0.1);
+    }
+    agent_ids
+      .into_iter()
+      .map(|agent_id| simulator.get_agent(agent_id).clone())
+      .collect::<Vec<_>>()
+  };
+
+  let expected = run();
+  for _ in 0..5 {
+    assert_eq!(run(), expected);
+  }
+}
+
+#[test]
+fn followers_follow_their_leader_across_regions() {
+  let mut simulator = ShardedSimulator::new(/* region_size= */ 10.0);
+  // A follower ahead of its leader, which crosses into the next region on its
+  // own first, and an unrelated agent added in between to shift the indices.
+  let follower = simulator.add_agent(
+    agent_at(Vec3::new(9.5, 0.0, 5.0)),
+    parameters(Vec3::new(9.5, 0.0, 5.0)),
+  );
+  simulator.add_agent(
+    agent_at(Vec3::new(2.0, 0.0, 2.0)),
+    parameters(Vec3::new(2.0, 0.0, 2.0)),
+  );
+  let leader = simulator.add_agent(
+    agent_at(Vec3::new(8.0, 0.0, 5.0)),
+    parameters(Vec3::new(25.0, 0.0, 5.0)),
+  );
+  simulator.set_agent_leader(
+    follower,
+    Some((leader, FollowOptions { distance: 1.5, angle: 0.0 })),
+  );
+  assert_eq!(simulator.get_agent_leader(follower), Some(leader));
+
+  for _ in 0..200 {
+    simulator.step(0.1);
+    let leader_following = simulator
+      .get_agent_parameters(follower)
+      .leader_following
+      .as_ref()
+      .expect("follower still follows its leader");
+    let (region_simulator, leader_index) = simulator.find_agent(leader);
+    assert_eq!(leader_following.leader_index, leader_index);
+    assert_eq!(
+      region_simulator.get_agent(leader_following.leader_index),
+      simulator.get_agent(leader)
+    );
+  }
+
+  // The follower kept up with its leader in the leader's new region.
+  let leader_position = simulator.get_agent(leader).position;
+  let follower_position = simulator.get_agent(follower).position;
+  assert!(leader_position.distance(Vec3::new(25.0, 0.0, 5.0)) < 0.5);
+  assert!(
+    follower_position.distance(leader_position) < 2.5,
+    "{follower_position} {leader_position}"
+  );
+
+  // Removing the leader stops the follower following.
+  simulator.remove_agent(leader);
+  assert_eq!(simulator.get_agent_leader(follower), None);
+  assert_eq!(simulator.get_agent_parameters(follower).leader_following, None);
+}
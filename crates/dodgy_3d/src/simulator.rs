@@ -1,39 +1,1200 @@
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+  borrow::Cow,
+  cmp::Ordering,
+  collections::HashMap,
+  ops::Range,
+  sync::Arc,
+  time::{Duration, Instant},
+};
 
-use glam::Vec3;
+use glam::{BVec3, Vec3};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-use crate::{Agent, AvoidanceOptions};
+#[cfg(feature = "metrics")]
+use crate::telemetry;
+use crate::{
+  axis_locks,
+  chunked_vec::ChunkedVec,
+  crossing::bias_for_crossing_flow,
+  following::suggest_follow_point,
+  linear_programming::Plane,
+  mixed::adjust_neighbour,
+  nudge_away_from_obstacles,
+  obstacle_bvh::{Aabb, ObstacleBvh},
+  obstacles::{
+    get_distance_to_obstacle, get_planes_for_agent_to_one_way_obstacle,
+  },
+  social_force::get_social_force,
+  suggest_time_horizon, terrain, traffic,
+  yielding::get_sidestep_velocity,
+  Agent, AgentQuantization, AvoidanceOptions, Boundary, CrossingFlow,
+  DensityGrid, LeaderFollowing, NeighbourProvider, ObjectiveWeights, Obstacle,
+  ObstacleNudge, Queue, ResponsibilityOverride, SocialForce, SolverLimits,
+  Terrain, ThreatFilter, TimeHorizonOptions, Tolerances, TrafficLane,
+  UnitScale, Yielding,
+};
 
+/// A collection of agents and obstacles that avoid each other.
+///
+/// The simulator is `Send + Sync`. Besides [`Self::step`], stepping can be
+/// split into three phases for use from job systems (e.g. bevy tasks, rayon,
+/// or a custom thread pool) without wrapping the simulator in a `Mutex`:
+/// 1. [`Self::begin_step`] (`&mut self`) updates per-step data.
+/// 2. [`Self::compute_agent_velocity`] (`&self`) computes each agent's new
+///    velocity. This only reads the simulator, so it can be called for many
+///    agents in parallel from any thread.
+/// 3. [`Self::end_step`] (`&mut self`) applies the new velocities.
+///
+/// Stepping is deterministic: the same agents, obstacles, and seed (see
+/// [`Self::set_rng`]) always produce the same results, bit for bit. This holds
+/// regardless of the order agents were added (or removed) in, and of the order
+/// (or threads) agent velocities are computed in, since each agent's
+/// neighbours are sorted before being avoided, and each agent's random choices
+/// are seeded from its state. The only exception is agents with exactly the
+/// same position, velocity, radius, goal point, and max speed, which are told
+/// apart by the order they were added in.
+///
+/// Cloning the simulator is cheap: agents are stored in shared copy-on-write
+/// chunks, so a clone (e.g. for prediction rollback in netcode) only copies
+/// the agents that change afterwards. The terrain, boundary, and neighbour
+/// providers are shared between clones.
+#[derive(Clone)]
 pub struct Simulator {
-  agents: Vec<Agent>,
-  agent_parameters: Vec<AgentParameters>,
+  // Per-agent state is stored in copy-on-write chunks, so cloning the
+  // simulator (e.g. for rollback) only copies the agents that change
+  // afterwards.
+  agents: ChunkedVec<Agent>,
+  agent_parameters: ChunkedVec<AgentParameters>,
+  agent_archetypes: ChunkedVec<Option<String>>,
+  agent_tags: ChunkedVec<Vec<String>>,
+  parameter_blends: ChunkedVec<Option<ParameterBlend>>,
+  radius_animations: ChunkedVec<Option<RadiusAnimation>>,
+  agent_importances: ChunkedVec<Importance>,
+  archetypes: HashMap<String, AgentParameters>,
+  obstacles: Arc<Vec<Obstacle>>,
+  /// The hierarchy for finding the `obstacles` near each agent.
+  obstacle_bvh: Arc<ObstacleBvh>,
+  /// The properties of each obstacle.
+  obstacle_properties: Arc<Vec<ObstacleProperties>>,
+  terrain: Option<Arc<dyn Terrain + Send + Sync>>,
+  boundary: Option<Arc<dyn Boundary + Send + Sync>>,
+  neighbour_providers: Vec<Arc<dyn NeighbourProvider + Send + Sync>>,
+  /// How agents coordinate at crossing flows, if at all. See
+  /// [`Simulator::set_crossing_flow`].
+  crossing_flow: Option<CrossingFlow>,
+  /// Nudges preferred velocities away from nearby obstacles, if set. See
+  /// [`Simulator::set_obstacle_nudge`].
+  obstacle_nudge: Option<ObstacleNudge>,
+  /// Overrides the split of responsibility between pairs of agents. See
+  /// [`Simulator::set_responsibility_override`].
+  responsibility_override: Option<ResponsibilityOverride>,
+  /// Skips neighbours that pose little threat, if set. See
+  /// [`Simulator::set_threat_filter`].
+  threat_filter: Option<ThreatFilter>,
+  /// Classifies the importance of each agent at the start of each step. See
+  /// [`Simulator::set_importance_classifier`].
+  importance_classifier: Option<Arc<ImportanceClassifier>>,
+  queues: Vec<Queue>,
+  /// The queue slot each agent heads for in the current step, if any. Updated
+  /// at the start of each step.
+  queue_slots: Vec<Option<Vec3>>,
+  /// The step in progress from [`Simulator::step_with_budget`], if any.
+  budgeted_step: Option<BudgetedStep>,
+  /// The state of the agents at the end of the last step.
+  snapshot: Arc<SimulatorSnapshot>,
+  /// The source of all randomness in the simulation. See [`Simulator::set_rng`].
+  rng: StdRng,
+  /// The seed for the randomness of the current step, drawn from `rng` at the
+  /// start of each step. Each agent derives its own generator from this, so
+  /// agents can be computed in any order (or in parallel).
+  step_seed: u64,
+  /// The buffers reused by [`Simulator::step`] and
+  /// [`Simulator::step_with_budget`].
+  scratch: StepScratch,
+  /// Whether steps reuse the velocities of unchanged agents. See
+  /// [`Simulator::set_skip_unchanged_agents`].
+  skip_unchanged_agents: bool,
+  /// The inputs and results of the last step, if they can be reused.
+  velocity_cache: Option<VelocityCache>,
+  /// The number of agents whose velocity was reused in the last step.
+  skipped_agent_count: usize,
+  /// The limits on solving for each agent's velocity.
+  solver_limits: SolverLimits,
+  /// The tolerances used when computing each agent's velocity.
+  tolerances: Tolerances,
+  /// The number of agents whose solver reached its limits in the last step.
+  solver_limited_agent_count: usize,
+  /// When the step in progress was started by [`Simulator::begin_step`], to
+  /// record the step time.
+  #[cfg(feature = "metrics")]
+  step_start: Option<Instant>,
 }
 
+/// Reusable buffers for computing agent velocities (the neighbour and obstacle
+/// index lists, the obstacle query stack, and the plane buffers), so stepping
+/// does not allocate them again for every agent. The lists of neighbouring
+/// agents and obstacles built from those indices are still allocated per
+/// agent, since they borrow from the simulator. [`Simulator::step`] keeps its
+/// own. When stepping from a job system, keep one per thread and pass it to
+/// [`Simulator::compute_agent_velocity_with_scratch`]. The buffers grow to the
+/// largest size needed and are never shrunk.
+#[derive(Default, Debug)]
+pub struct StepScratch {
+  neighbour_indices: Vec<usize>,
+  obstacle_indices: Vec<usize>,
+  /// The stack for traversing the obstacle hierarchy.
+  obstacle_query_stack: Vec<usize>,
+  /// The rigid planes, and later all the planes, of the agent.
+  planes: Vec<Plane>,
+  neighbour_planes: Vec<Plane>,
+}
+
+impl Clone for StepScratch {
+  /// The contents are only meaningful while computing an agent's velocity, so
+  /// clones (e.g. of the simulator for rollback) start out empty.
+  fn clone(&self) -> Self {
+    Self::default()
+  }
+}
+
+/// A read-only copy of the state of every agent at the end of a step, from
+/// [`Simulator::get_snapshot`]. Snapshots never change, so they can be read
+/// (e.g. by rendering or gameplay on another thread) while the next step is in
+/// flight, without locks or torn reads.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct SimulatorSnapshot {
+  agents: ChunkedVec<Agent>,
+  step_count: u64,
+}
+
+impl SimulatorSnapshot {
+  pub fn get_agent(&self, agent_index: usize) -> &Agent {
+    &self.agents[agent_index]
+  }
+
+  pub fn get_agent_count(&self) -> usize {
+    self.agents.len()
+  }
+
+  /// The number of steps the simulator had completed when the snapshot was
+  /// taken.
+  pub fn get_step_count(&self) -> u64 {
+    self.step_count
+  }
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub struct AgentParameters {
   pub goal_point: Vec3,
   pub max_speed: f32,
   pub obstacle_margin: SimulatorMargin,
   pub time_horizon: f32,
   pub obstacle_time_horizon: f32,
+  /// The traffic lane the agent must follow, if any.
+  pub traffic_lane: Option<TrafficLane>,
+  /// The social forces that perturb the agent's preferred velocity before
+  /// avoiding collisions, if any.
+  pub social_force: Option<SocialForce>,
+  /// The agent to follow, if any. While following, `goal_point` is ignored.
+  /// Agent indices are only meaningful within a single simulator, so in a
+  /// [`crate::ShardedSimulator`], set leaders with
+  /// [`crate::ShardedSimulator::set_agent_leader`] instead.
+  pub leader_following: Option<LeaderFollowing>,
+  /// How the agent steps out of the way of moving agents while standing, if
+  /// at all.
+  pub yielding: Option<Yielding>,
+  /// How urgently the agent is moving, from 0 (calm walking) to 1 (panic,
+  /// e.g. in an evacuation or combat). Urgent agents push through crowds:
+  /// they keep less distance from other agents (down to
+  /// [`MAX_URGENCY_RADIUS_SHRINK`] less than their radius), react later (with
+  /// a time horizon up to [`MAX_URGENCY_TIME_HORIZON_SHRINK`] shorter), and
+  /// accept moving into other agents' space (by up to
+  /// [`MAX_URGENCY_VIOLATION`] of their max speed). Obstacles are avoided as
+  /// usual.
+  pub urgency: f32,
+  /// If set, the agent is a ground agent standing this tall, which only moves
+  /// along the ground (along the terrain if the simulator has terrain, or
+  /// horizontally otherwise). Otherwise, the agent flies freely in 3D (unless
+  /// the simulator has terrain). Flying and ground agents avoid each other
+  /// sensibly: flying agents avoid the whole vertical extent of ground agents,
+  /// and ground agents ignore flying agents high overhead.
+  pub standing_height: Option<f32>,
+  /// The components of the agent's velocity that are kept at zero (e.g. to
+  /// keep an agent at a fixed altitude).
+  pub locked_axes: BVec3,
 }
 
+/// The fraction of an agent's radius that is ignored when avoiding other
+/// agents at full urgency. See [`AgentParameters::urgency`].
+pub const MAX_URGENCY_RADIUS_SHRINK: f32 = 0.3;
+/// The fraction of an agent's time horizon that is cut at full urgency. See
+/// [`AgentParameters::urgency`].
+pub const MAX_URGENCY_TIME_HORIZON_SHRINK: f32 = 0.5;
+/// The fraction of an agent's max speed that it may move into other agents'
+/// space at full urgency. See [`AgentParameters::urgency`].
+pub const MAX_URGENCY_VIOLATION: f32 = 0.25;
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum SimulatorMargin {
   AgentRadius,
   Distance(f32),
+  /// The margin is computed from the agent's speed (which is also its speed
+  /// relative to obstacles). For example, fast agents can keep a larger margin
+  /// to smooth out their avoidance.
+  SpeedCurve(MarginCurve),
+  /// The margin is computed from the distance to the nearest obstacle. For
+  /// example, agents can keep a large margin when far from obstacles for
+  /// smoother avoidance, and a small margin when close to obstacles so they
+  /// can pack tightly.
+  ObstacleDistanceCurve(MarginCurve),
+}
+
+/// A piecewise linear curve mapping some input to a margin.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MarginCurve {
+  /// The (input, margin) points of the curve, sorted by input. Inputs before
+  /// the first point use the margin of the first point, and inputs after the
+  /// last point use the margin of the last point. Must not be empty.
+  pub points: Vec<(f32, f32)>,
+}
+
+impl MarginCurve {
+  /// Computes the margin for `input`.
+  pub fn sample(&self, input: f32) -> f32 {
+    let after_index = self.points.partition_point(|&(x, _)| x <= input);
+    if after_index == 0 {
+      return self.points[0].1;
+    }
+    if after_index == self.points.len() {
+      return self.points[after_index - 1].1;
+    }
+    let (before_x, before_margin) = self.points[after_index - 1];
+    let (after_x, after_margin) = self.points[after_index];
+    let t = (input - before_x) / (after_x - before_x);
+    before_margin + (after_margin - before_margin) * t
+  }
+
+  /// The largest margin of the curve.
+  fn max_margin(&self) -> f32 {
+    self.points.iter().map(|&(_, margin)| margin).fold(f32::MIN, f32::max)
+  }
+}
+
+impl SimulatorMargin {
+  /// Computes the margin if it does not depend on the state of the agent.
+  fn get_constant(&self, agent_radius: f32) -> Option<f32> {
+    match self {
+      SimulatorMargin::AgentRadius => Some(agent_radius),
+      SimulatorMargin::Distance(distance) => Some(*distance),
+      SimulatorMargin::SpeedCurve(_)
+      | SimulatorMargin::ObstacleDistanceCurve(_) => None,
+    }
+  }
+
+  /// Computes the largest margin the agent could have.
+  fn get_max(&self, agent_radius: f32) -> f32 {
+    match self {
+      SimulatorMargin::AgentRadius => agent_radius,
+      SimulatorMargin::Distance(distance) => *distance,
+      SimulatorMargin::SpeedCurve(curve)
+      | SimulatorMargin::ObstacleDistanceCurve(curve) => curve.max_margin(),
+    }
+  }
+
+  /// Computes the margin for `agent`. `get_obstacle_distance` computes the
+  /// distance to the nearest obstacle, and is only called if needed.
+  fn resolve(
+    &self,
+    agent: &Agent,
+    get_obstacle_distance: impl FnOnce() -> f32,
+  ) -> f32 {
+    match self {
+      SimulatorMargin::AgentRadius => agent.radius,
+      SimulatorMargin::Distance(distance) => *distance,
+      SimulatorMargin::SpeedCurve(curve) => {
+        curve.sample(agent.velocity.length())
+      }
+      SimulatorMargin::ObstacleDistanceCurve(curve) => {
+        curve.sample(get_obstacle_distance())
+      }
+    }
+  }
+}
+
+/// The simulator-specific properties of an obstacle.
+#[derive(Clone, Default)]
+struct ObstacleProperties {
+  /// None if the obstacle is not a door, otherwise whether the door is open.
+  door_open: Option<bool>,
+  /// If set, the obstacle only blocks movement against this direction.
+  allowed_direction: Option<Vec3>,
+}
+
+/// An in-progress blend between two sets of agent parameters.
+#[derive(Clone)]
+struct ParameterBlend {
+  from: AgentParameters,
+  to: AgentParameters,
+  elapsed_seconds: f32,
+  blend_seconds: f32,
+}
+
+/// An in-progress change of an agent's radius.
+#[derive(Clone, PartialEq)]
+struct RadiusAnimation {
+  target_radius: f32,
+  /// How fast the radius changes, in units per second.
+  rate: f32,
+}
+
+/// How important an agent is to update (e.g. based on its distance to the
+/// camera), which determines how often its velocity is recomputed. Between
+/// updates, the agent keeps moving with its last velocity, so distant or
+/// off-screen crowds can be simulated for a fraction of the cost. Other agents
+/// still avoid it as usual. See [`Simulator::set_agent_importance`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Importance {
+  /// The agent is updated every step.
+  #[default]
+  High,
+  /// The agent is updated every second step.
+  Medium,
+  /// The agent is updated every fourth step.
+  Low,
+}
+
+impl Importance {
+  /// The number of steps between updates of agents with this importance.
+  pub fn get_update_interval(self) -> u64 {
+    match self {
+      Self::High => 1,
+      Self::Medium => 2,
+      Self::Low => 4,
+    }
+  }
+}
+
+/// Where an agent went after [`Simulator::split`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SplitAgentIndex {
+  /// The agent stayed in the original simulator, at this index.
+  Kept(usize),
+  /// The agent moved to the new simulator, at this index.
+  Moved(usize),
+}
+
+/// A function from an agent's index and position to its [`Importance`]. See
+/// [`Simulator::set_importance_classifier`].
+pub type ImportanceClassifier = dyn Fn(usize, Vec3) -> Importance + Send + Sync;
+
+/// A step that is spread across several calls to
+/// [`Simulator::step_with_budget`].
+#[derive(Clone)]
+struct BudgetedStep {
+  time_step: f32,
+  /// The new velocities of the agents processed so far (in order), or None
+  /// for agents that are not updated this step (see [`Importance`]).
+  new_velocities: Vec<Option<Vec3>>,
+  /// Whether the solver reached its limits for each of `new_velocities`.
+  solver_limit_reached: Vec<bool>,
+  /// The indices of processed agents whose new velocities must be recomputed
+  /// (e.g. since an obstacle near them changed).
+  stale_agent_indices: Vec<usize>,
+  /// The velocity of each agent that can be reused from the last step, from
+  /// [`Simulator::get_reusable_velocities`]. Agents past the end are always
+  /// recomputed.
+  reusable_velocities: Vec<Option<Vec3>>,
+  /// The inputs of this step (without velocities), to cache once the step
+  /// completes.
+  inputs: Option<VelocityCache>,
+  /// The time spent on this step so far, across calls.
+  #[cfg(feature = "metrics")]
+  elapsed: Duration,
+}
+
+/// The inputs of a step (everything an agent's new velocity depends on that
+/// can change between steps without invalidating the whole cache), along with
+/// the resulting velocities.
+#[derive(Clone)]
+struct VelocityCache {
+  time_step: f32,
+  agents: ChunkedVec<Agent>,
+  agent_parameters: ChunkedVec<AgentParameters>,
+  queue_slots: Vec<Option<Vec3>>,
+  /// The velocity computed for each agent, or None if the agent was not
+  /// updated.
+  velocities: Vec<Option<Vec3>>,
 }
 
 impl Simulator {
   pub fn new() -> Simulator {
-    Self { agents: Vec::new(), agent_parameters: Vec::new() }
+    Self {
+      agents: ChunkedVec::new(),
+      agent_parameters: ChunkedVec::new(),
+      agent_archetypes: ChunkedVec::new(),
+      agent_tags: ChunkedVec::new(),
+      parameter_blends: ChunkedVec::new(),
+      radius_animations: ChunkedVec::new(),
+      agent_importances: ChunkedVec::new(),
+      archetypes: HashMap::new(),
+      obstacles: Arc::new(Vec::new()),
+      obstacle_bvh: Arc::new(ObstacleBvh::default()),
+      obstacle_properties: Arc::new(Vec::new()),
+      terrain: None,
+      boundary: None,
+      neighbour_providers: Vec::new(),
+      crossing_flow: None,
+      obstacle_nudge: None,
+      responsibility_override: None,
+      threat_filter: None,
+      importance_classifier: None,
+      queues: Vec::new(),
+      queue_slots: Vec::new(),
+      budgeted_step: None,
+      snapshot: Arc::new(SimulatorSnapshot::default()),
+      rng: StdRng::seed_from_u64(0),
+      step_seed: 0,
+      scratch: StepScratch::default(),
+      skip_unchanged_agents: false,
+      velocity_cache: None,
+      skipped_agent_count: 0,
+      solver_limits: SolverLimits::default(),
+      tolerances: Tolerances::default(),
+      solver_limited_agent_count: 0,
+      #[cfg(feature = "metrics")]
+      step_start: None,
+    }
+  }
+
+  /// Sets the random number generator used for all randomness in the
+  /// simulation. By default, the generator is seeded with zero, so
+  /// simulations are reproducible as long as they are given the same inputs.
+  ///
+  /// The generator is only used at the start of each step (in [`Self::step`],
+  /// [`Self::begin_step`], or the first [`Self::step_with_budget`] call of a
+  /// step) to draw a seed for that step. Each agent's velocity computation
+  /// derives its own generator from the step's seed and the agent's state (its
+  /// position, velocity, radius, goal point, and max speed), not its index, so
+  /// results do not depend on the order agents were added in. Only agents
+  /// with exactly the same state are told apart by their order. The generator
+  /// is only used to break ties: picking the direction to push apart in when
+  /// the agent exactly overlaps a neighbour with a matching velocity.
+  pub fn set_rng(&mut self, rng: StdRng) {
+    self.rng = rng;
+  }
+
+  /// Same as [`Self::set_rng`], with a generator seeded from `seed`.
+  pub fn set_seed(&mut self, seed: u64) {
+    self.set_rng(StdRng::seed_from_u64(seed));
+  }
+
+  /// Sets whether [`Self::step`] and [`Self::step_with_budget`] skip agents
+  /// that are unaffected since the last step, reusing their last velocity. An
+  /// agent is unaffected if neither it nor its parameters changed (so it did
+  /// not move), its leader (if any) did not change, and no agent that changed
+  /// is (or was) within its neighbour query distance. This makes mostly idle
+  /// crowds (e.g. agents waiting at their goals) nearly free to step. Disabled
+  /// by default.
+  ///
+  /// Reused velocities are exactly the recomputed velocities, except for
+  /// agents that had to break a tie randomly (see [`Self::set_rng`]). Changing
+  /// obstacles, the terrain, or the boundary, or adding or removing agents,
+  /// recomputes every agent in the next step. Agents are never skipped while
+  /// there are neighbour providers, since their agents may change at any
+  /// time, or when stepping from a job system.
+  pub fn set_skip_unchanged_agents(&mut self, skip_unchanged_agents: bool) {
+    self.skip_unchanged_agents = skip_unchanged_agents;
+    self.velocity_cache = None;
+  }
+
+  /// The number of agents that were skipped (see
+  /// [`Self::set_skip_unchanged_agents`]) in the last completed step.
+  pub fn get_skipped_agent_count(&self) -> usize {
+    self.skipped_agent_count
+  }
+
+  /// Sets the limits on the solver when computing each agent's velocity (see
+  /// [`SolverLimits`]), so pathological crowds cannot blow the frame budget.
+  /// By default, there are no limits.
+  pub fn set_solver_limits(&mut self, solver_limits: SolverLimits) {
+    self.solver_limits = solver_limits;
+    self.velocity_cache = None;
+  }
+
+  /// Sets the tolerances used when computing each agent's velocity (see
+  /// [`Tolerances`]). By default, the tolerances are tuned for meters.
+  pub fn set_tolerances(&mut self, tolerances: Tolerances) {
+    self.tolerances = tolerances;
+    self.invalidate_velocity_cache();
   }
 
+  pub fn get_tolerances(&self) -> &Tolerances {
+    &self.tolerances
+  }
+
+  /// Sets the tolerances to the defaults converted to `scale`, for worlds that
+  /// are not measured in meters (see [`UnitScale`]). Agents and their
+  /// parameters still need to be given in `scale` (e.g. with
+  /// [`AgentParameters::scaled_to_units`]).
+  pub fn set_unit_scale(&mut self, scale: UnitScale) {
+    self.set_tolerances(Tolerances::default().scaled_to_units(scale));
+  }
+
+  /// The number of agents whose solver reached its limits (see
+  /// [`Self::set_solver_limits`]) in the last step completed by
+  /// [`Self::step`] or [`Self::step_with_budget`]. These agents use the best
+  /// velocity found before the solver stopped.
+  pub fn get_solver_limited_agent_count(&self) -> usize {
+    self.solver_limited_agent_count
+  }
+
+  /// Sets the terrain that all agents walk on. Agents are kept on the ground
+  /// and only avoid each other along the ground. If `None`, agents move freely
+  /// in 3D.
+  pub fn set_terrain(
+    &mut self,
+    terrain: Option<Box<dyn Terrain + Send + Sync>>,
+  ) {
+    self.terrain = terrain.map(Arc::from);
+    self.invalidate_velocity_cache();
+  }
+
+  /// Sets the boundary (e.g. the border of a navigation mesh) that agents must
+  /// stay within. Each step, the obstacles of the boundary near each agent are
+  /// avoided along with all other obstacles. If `None`, there is no boundary.
+  pub fn set_boundary(
+    &mut self,
+    boundary: Option<Box<dyn Boundary + Send + Sync>>,
+  ) {
+    self.boundary = boundary.map(Arc::from);
+    self.invalidate_velocity_cache();
+  }
+
+  /// Sets how agents coordinate where dense flows of agents cross (see
+  /// [`CrossingFlow`]). Each step, agents with enough neighbours crossing
+  /// ahead of them turn their preferred velocity towards the crossing flow.
+  /// If `None` (the default), agents do not coordinate.
+  pub fn set_crossing_flow(&mut self, crossing_flow: Option<CrossingFlow>) {
+    self.crossing_flow = crossing_flow;
+    self.invalidate_velocity_cache();
+  }
+
+  /// Sets how agents are nudged away from nearby obstacles (see
+  /// [`ObstacleNudge`]), so they do not hug and scrape along walls. If `None`
+  /// (the default), agents head straight for their goals and only avoid
+  /// obstacles once a collision is imminent.
+  pub fn set_obstacle_nudge(&mut self, obstacle_nudge: Option<ObstacleNudge>) {
+    self.obstacle_nudge = obstacle_nudge;
+    self.invalidate_velocity_cache();
+  }
+
+  /// Sets the override for how responsibility is split between pairs of
+  /// agents (see [`ResponsibilityOverride`]), e.g. so the player never gives
+  /// way to NPCs. The override is called with each agent and each of its
+  /// neighbours (including agents of neighbour providers). If `None` (the
+  /// default), responsibility is split by `avoidance_responsibility`.
+  pub fn set_responsibility_override(
+    &mut self,
+    responsibility_override: Option<ResponsibilityOverride>,
+  ) {
+    self.responsibility_override = responsibility_override;
+    self.invalidate_velocity_cache();
+  }
+
+  /// Sets the filter for skipping neighbours that pose little threat (see
+  /// [`ThreatFilter`]), e.g. neighbours moving away. Skipped neighbours are
+  /// still used for social forces and yielding, but do not constrain the
+  /// agent's velocity. If `None` (the default), every neighbour is avoided.
+  pub fn set_threat_filter(&mut self, threat_filter: Option<ThreatFilter>) {
+    self.threat_filter = threat_filter;
+    self.invalidate_velocity_cache();
+  }
+
+  /// Adds a read-only source of agents (e.g. another simulator's agents) that
+  /// this simulator's agents must avoid. Agents of the provider are never
+  /// moved by this simulator.
+  pub fn add_neighbour_provider(
+    &mut self,
+    neighbour_provider: Box<dyn NeighbourProvider + Send + Sync>,
+  ) {
+    self.neighbour_providers.push(Arc::from(neighbour_provider));
+  }
+
+  pub fn remove_neighbour_provider(&mut self, neighbour_provider_index: usize) {
+    self.neighbour_providers.remove(neighbour_provider_index);
+  }
+
+  pub fn get_neighbour_provider_count(&self) -> usize {
+    self.neighbour_providers.len()
+  }
+
+  /// Adds an agent after the existing agents. Panics if the agent follows a
+  /// leader that does not exist (see [`AgentParameters::leader_following`]).
   pub fn add_agent(&mut self, agent: Agent, agent_parameters: AgentParameters) {
+    self.assert_valid_leader(
+      self.agents.len(),
+      &agent_parameters,
+      self.agents.len() + 1,
+    );
+    self.budgeted_step = None;
+    self.velocity_cache = None;
+    self.agents.push(agent);
+    self.agent_parameters.push(agent_parameters);
+    self.agent_archetypes.push(None);
+    self.agent_tags.push(Vec::new());
+    self.parameter_blends.push(None);
+    self.radius_animations.push(None);
+    self.agent_importances.push(Importance::default());
+  }
+
+  /// Adds an agent that is a member of the archetype named `archetype`, using
+  /// the archetype's parameters (except for `goal_point`, and
+  /// `leader_following`, since leaders are specific to each agent). Panics if
+  /// there is no archetype named `archetype`.
+  pub fn add_agent_with_archetype(
+    &mut self,
+    agent: Agent,
+    archetype: &str,
+    goal_point: Vec3,
+  ) {
+    let agent_parameters = AgentParameters {
+      goal_point,
+      leader_following: None,
+      ..self.get_archetype_or_panic(archetype)
+    };
+    self.budgeted_step = None;
+    self.velocity_cache = None;
     self.agents.push(agent);
     self.agent_parameters.push(agent_parameters);
+    self.agent_archetypes.push(Some(archetype.to_string()));
+    self.agent_tags.push(Vec::new());
+    self.parameter_blends.push(None);
+    self.radius_animations.push(None);
+    self.agent_importances.push(Importance::default());
+  }
+
+  /// Sets the parameters of the archetype named `archetype` (e.g. "soldier" or
+  /// "drone"), creating the archetype if it does not exist. The parameters
+  /// (except for `goal_point` and `leader_following`, which each member keeps)
+  /// are also applied to every member of the archetype, so agents can be tuned
+  /// in bulk.
+  pub fn set_archetype(
+    &mut self,
+    archetype: &str,
+    agent_parameters: AgentParameters,
+  ) {
+    for agent_index in 0..self.agents.len() {
+      if self.agent_archetypes[agent_index].as_deref() != Some(archetype) {
+        continue;
+      }
+      let parameters = &mut self.agent_parameters[agent_index];
+      *parameters = AgentParameters {
+        goal_point: parameters.goal_point,
+        leader_following: parameters.leader_following.take(),
+        ..agent_parameters.clone()
+      };
+      self.parameter_blends[agent_index] = None;
+    }
+    self.archetypes.insert(archetype.to_string(), agent_parameters);
+  }
+
+  /// Gets the parameters of the archetype named `archetype`, if it exists.
+  pub fn get_archetype(&self, archetype: &str) -> Option<&AgentParameters> {
+    self.archetypes.get(archetype)
+  }
+
+  /// Removes the archetype named `archetype`. Its members keep their current
+  /// parameters, but are no longer members of any archetype.
+  pub fn remove_archetype(&mut self, archetype: &str) {
+    self.archetypes.remove(archetype);
+    for agent_index in 0..self.agents.len() {
+      if self.agent_archetypes[agent_index].as_deref() == Some(archetype) {
+        self.agent_archetypes[agent_index] = None;
+      }
+    }
+  }
+
+  /// Makes the agent at `agent_index` a member of the archetype named
+  /// `archetype`, applying the archetype's parameters (except for
+  /// `goal_point` and `leader_following`). If `None`, the agent keeps its current parameters but is
+  /// no longer a member of any archetype. Panics if there is no archetype named
+  /// `archetype`.
+  pub fn set_agent_archetype(
+    &mut self,
+    agent_index: usize,
+    archetype: Option<&str>,
+  ) {
+    if let Some(archetype) = archetype {
+      let archetype_parameters = self.get_archetype_or_panic(archetype);
+      let parameters = &mut self.agent_parameters[agent_index];
+      *parameters = AgentParameters {
+        goal_point: parameters.goal_point,
+        leader_following: parameters.leader_following.take(),
+        ..archetype_parameters
+      };
+      self.parameter_blends[agent_index] = None;
+    }
+    self.agent_archetypes[agent_index] = archetype.map(str::to_string);
+  }
+
+  /// Gets the name of the archetype that the agent at `agent_index` is a
+  /// member of, if any.
+  pub fn get_agent_archetype(&self, agent_index: usize) -> Option<&str> {
+    self.agent_archetypes[agent_index].as_deref()
+  }
+
+  /// Adds all `agents` (with their parameters) at once, e.g. when streaming in
+  /// a chunk of the world. Returns the indices of the new agents, which are
+  /// added in order after the existing agents. Panics if an agent follows a
+  /// leader that does not exist.
+  pub fn add_agents_bulk(
+    &mut self,
+    agents: &[(Agent, AgentParameters)],
+  ) -> Range<usize> {
+    let start = self.agents.len();
+    for (offset, (_, agent_parameters)) in agents.iter().enumerate() {
+      self.assert_valid_leader(
+        start + offset,
+        agent_parameters,
+        start + agents.len(),
+      );
+    }
+    self.budgeted_step = None;
+    self.velocity_cache = None;
+    for (agent, agent_parameters) in agents {
+      self.agents.push(agent.clone());
+      self.agent_parameters.push(agent_parameters.clone());
+      self.agent_archetypes.push(None);
+      self.agent_tags.push(Vec::new());
+      self.parameter_blends.push(None);
+      self.radius_animations.push(None);
+      self.agent_importances.push(Importance::default());
+    }
+    start..self.agents.len()
+  }
+
+  /// Removes every agent whose position is within the box from `min` to `max`
+  /// (inclusive), e.g. when streaming out a chunk of the world. Returns the
+  /// removed agents (with their parameters), so they can be added back later
+  /// with [`Self::add_agents_bulk`] (the leader indices in the returned
+  /// parameters are left as they were). The remaining agents keep their order,
+  /// but their indices shift down (as with [`Self::remove_agent`]). This is
+  /// much faster than removing the agents one at a time.
+  pub fn remove_agents_in_aabb(
+    &mut self,
+    min: Vec3,
+    max: Vec3,
+  ) -> Vec<(Agent, AgentParameters)> {
+    let removed = self.find_agents_in_aabb(min, max);
+    self.remove_agents(&removed)
+  }
+
+  /// Moves every agent whose position is within the box from `min` to `max`
+  /// (inclusive) into a new simulator, e.g. to hand a crowd over to another
+  /// thread or server as it migrates across the map. Agents keep all their
+  /// state (including archetypes, tags, and parameter blends). The new
+  /// simulator starts with a copy of this simulator's world (e.g. obstacles,
+  /// terrain, and archetypes) and settings. Agents that follow a leader that
+  /// ends up in the other simulator stop following it.
+  ///
+  /// Returns the new simulator and where each agent went, indexed by the
+  /// agent's index before the split.
+  pub fn split(
+    &mut self,
+    min: Vec3,
+    max: Vec3,
+  ) -> (Simulator, Vec<SplitAgentIndex>) {
+    let moved = self.find_agents_in_aabb(min, max);
+    let kept = moved.iter().map(|moved| !moved).collect::<Vec<_>>();
+    // Cloning is cheap, and carries over the world and all agent state.
+    let mut split = self.clone();
+    let moved_indices = split.retain_agents(&moved);
+    let kept_indices = self.retain_agents(&kept);
+    let agent_indices = kept_indices
+      .into_iter()
+      .zip(moved_indices)
+      .map(|(kept_index, moved_index)| match (kept_index, moved_index) {
+        (Some(kept_index), _) => SplitAgentIndex::Kept(kept_index),
+        (None, Some(moved_index)) => SplitAgentIndex::Moved(moved_index),
+        (None, None) => unreachable!("every agent is kept or moved"),
+      })
+      .collect();
+    (split, agent_indices)
+  }
+
+  /// Moves all agents of `other` (e.g. from [`Self::split`]) into this
+  /// simulator, keeping all their state (including archetypes, tags, and
+  /// parameter blends). The world (e.g. obstacles and terrain) and settings of
+  /// `other` are discarded, except for archetypes this simulator does not have.
+  /// Returns the indices of the merged agents, which are added in order after
+  /// the existing agents.
+  pub fn merge(&mut self, other: Simulator) -> Range<usize> {
+    self.budgeted_step = None;
+    self.velocity_cache = None;
+    let start = self.agents.len();
+    for agent_index in 0..other.agents.len() {
+      self.agents.push(other.agents[agent_index].clone());
+      self.agent_parameters.push(other.agent_parameters[agent_index].clone());
+      self.agent_archetypes.push(other.agent_archetypes[agent_index].clone());
+      self.agent_tags.push(other.agent_tags[agent_index].clone());
+      self.parameter_blends.push(other.parameter_blends[agent_index].clone());
+      self.radius_animations.push(other.radius_animations[agent_index].clone());
+      self.agent_importances.push(other.agent_importances[agent_index]);
+    }
+    for (archetype, agent_parameters) in other.archetypes {
+      self.archetypes.entry(archetype).or_insert(agent_parameters);
+    }
+    self.remap_leader_indices(start..self.agents.len(), |leader_index| {
+      Some(leader_index + start)
+    });
+    start..self.agents.len()
+  }
+
+  /// Whether each agent's position is within the box from `min` to `max`
+  /// (inclusive).
+  fn find_agents_in_aabb(&self, min: Vec3, max: Vec3) -> Vec<bool> {
+    self
+      .agents
+      .iter()
+      .map(|agent| {
+        agent.position.cmpge(min).all() && agent.position.cmple(max).all()
+      })
+      .collect()
+  }
+
+  /// Removes the agents whose entry in `removed` is true, returning them (with
+  /// their parameters) in order.
+  fn remove_agents(
+    &mut self,
+    removed: &[bool],
+  ) -> Vec<(Agent, AgentParameters)> {
+    if !removed.contains(&true) {
+      return Vec::new();
+    }
+    let removed_agents = (0..self.agents.len())
+      .filter(|&agent_index| removed[agent_index])
+      .map(|agent_index| {
+        (
+          self.agents[agent_index].clone(),
+          self.agent_parameters[agent_index].clone(),
+        )
+      })
+      .collect();
+    let keep = removed.iter().map(|removed| !removed).collect::<Vec<_>>();
+    self.retain_agents(&keep);
+    removed_agents
+  }
+
+  /// Keeps only the agents whose entry in `keep` is true, preserving their
+  /// order. Returns the new index of each agent, if it was kept.
+  fn retain_agents(&mut self, keep: &[bool]) -> Vec<Option<usize>> {
+    self.budgeted_step = None;
+    self.velocity_cache = None;
+    let mut new_indices = Vec::with_capacity(keep.len());
+    let mut kept_count = 0;
+    for &keep in keep {
+      new_indices.push(keep.then_some(kept_count));
+      kept_count += usize::from(keep);
+    }
+
+    self.agents.retain(keep);
+    self.agent_parameters.retain(keep);
+    self.agent_archetypes.retain(keep);
+    self.agent_tags.retain(keep);
+    self.parameter_blends.retain(keep);
+    self.radius_animations.retain(keep);
+    self.agent_importances.retain(keep);
+
+    // Keep followers pointing at the same leaders.
+    self.remap_leader_indices(0..kept_count, |leader_index| {
+      new_indices.get(leader_index).copied().unwrap_or(Some(leader_index))
+    });
+    new_indices
+  }
+
+  /// Tags the agent at `agent_index` with `tag` (e.g. "squad_1" or
+  /// "level_3"), so whole cohorts of agents can be found (see
+  /// [`Self::iter_tagged_agents`]) and manipulated at once. Agents can have any
+  /// number of tags. Tagging an agent with a tag it already has does nothing.
+  pub fn add_agent_tag(&mut self, agent_index: usize, tag: &str) {
+    if !self.has_agent_tag(agent_index, tag) {
+      self.agent_tags[agent_index].push(tag.to_string());
+    }
+  }
+
+  /// Removes `tag` from the agent at `agent_index`, if it has it.
+  pub fn remove_agent_tag(&mut self, agent_index: usize, tag: &str) {
+    if self.has_agent_tag(agent_index, tag) {
+      self.agent_tags[agent_index].retain(|agent_tag| agent_tag != tag);
+    }
+  }
+
+  /// Gets the tags of the agent at `agent_index`, in the order they were added.
+  pub fn get_agent_tags(&self, agent_index: usize) -> &[String] {
+    &self.agent_tags[agent_index]
+  }
+
+  pub fn has_agent_tag(&self, agent_index: usize, tag: &str) -> bool {
+    self.agent_tags[agent_index].iter().any(|agent_tag| agent_tag == tag)
+  }
+
+  /// Iterates over the indices of the agents tagged with `tag`, in increasing
+  /// order.
+  pub fn iter_tagged_agents<'a>(
+    &'a self,
+    tag: &'a str,
+  ) -> impl Iterator<Item = usize> + 'a {
+    (0..self.agents.len())
+      .filter(move |&agent_index| self.has_agent_tag(agent_index, tag))
+  }
+
+  /// Removes every agent tagged with `tag` (e.g. when streaming out a level).
+  /// The remaining agents keep their order, but their indices shift down (as
+  /// with [`Self::remove_agent`]).
+  pub fn remove_tagged_agents(&mut self, tag: &str) {
+    let removed = (0..self.agents.len())
+      .map(|agent_index| self.has_agent_tag(agent_index, tag))
+      .collect::<Vec<_>>();
+    self.remove_agents(&removed);
+  }
+
+  /// Sets the parameters (except for `goal_point` and `leader_following`) of
+  /// every agent tagged with `tag`, cancelling any parameter blends of those
+  /// agents.
+  pub fn set_tagged_agent_parameters(
+    &mut self,
+    tag: &str,
+    agent_parameters: AgentParameters,
+  ) {
+    for agent_index in 0..self.agents.len() {
+      if !self.has_agent_tag(agent_index, tag) {
+        continue;
+      }
+      let parameters = &mut self.agent_parameters[agent_index];
+      *parameters = AgentParameters {
+        goal_point: parameters.goal_point,
+        leader_following: parameters.leader_following.take(),
+        ..agent_parameters.clone()
+      };
+      self.parameter_blends[agent_index] = None;
+    }
+  }
+
+  /// Panics if the agent at `agent_index` would follow itself or a leader
+  /// that is not one of the `agent_count` agents.
+  fn assert_valid_leader(
+    &self,
+    agent_index: usize,
+    agent_parameters: &AgentParameters,
+    agent_count: usize,
+  ) {
+    let Some(following) = &agent_parameters.leader_following else {
+      return;
+    };
+    assert!(
+      following.leader_index < agent_count,
+      "leader_index must be less than the agent count ({}), was {}",
+      agent_count,
+      following.leader_index
+    );
+    assert_ne!(
+      following.leader_index, agent_index,
+      "agent {} cannot follow itself",
+      agent_index
+    );
+  }
+
+  fn get_archetype_or_panic(&self, archetype: &str) -> AgentParameters {
+    match self.archetypes.get(archetype) {
+      Some(parameters) => parameters.clone(),
+      None => panic!("no archetype named \"{}\"", archetype),
+    }
+  }
+
+  pub fn add_obstacle(&mut self, obstacle: Obstacle) {
+    Arc::make_mut(&mut self.obstacles).push(obstacle);
+    Arc::make_mut(&mut self.obstacle_properties)
+      .push(ObstacleProperties::default());
+    self.obstacle_bvh = Arc::new(ObstacleBvh::new(&self.obstacles));
+    self.invalidate_agents_near_obstacle(self.obstacles.len() - 1);
+    self.invalidate_velocity_cache();
+  }
+
+  pub fn get_obstacle(&self, obstacle_index: usize) -> &Obstacle {
+    &self.obstacles[obstacle_index]
+  }
+
+  /// Replaces the obstacle at `obstacle_index`. This is intended for obstacles
+  /// that move slowly (e.g. elevators or sliding doors), so it only refits the
+  /// bounding volume hierarchy of obstacles instead of rebuilding it. If
+  /// obstacles move far from where they were added, queries slow down, so
+  /// prefer removing and re-adding them instead.
+  pub fn set_obstacle(&mut self, obstacle_index: usize, obstacle: Obstacle) {
+    self.invalidate_agents_near_obstacle(obstacle_index);
+    Arc::make_mut(&mut self.obstacle_bvh).refit(obstacle_index, &obstacle);
+    Arc::make_mut(&mut self.obstacles)[obstacle_index] = obstacle;
+    self.invalidate_agents_near_obstacle(obstacle_index);
+    self.invalidate_velocity_cache();
+  }
+
+  /// Adds an openable obstacle (e.g. a door). Closed doors are avoided like
+  /// any other obstacle, while open doors are ignored. Use
+  /// [`Self::set_door_open`] to open or close the door.
+  pub fn add_door(&mut self, obstacle: Obstacle, open: bool) {
+    self.add_obstacle(obstacle);
+    Arc::make_mut(&mut self.obstacle_properties)
+      .last_mut()
+      .unwrap()
+      .door_open = Some(open);
+  }
+
+  /// Adds an obstacle that only blocks movement against `allowed_direction`
+  /// (e.g. a turnstile or a one-way drop-off). Agents moving along
+  /// `allowed_direction` pass through the obstacle, while agents moving the
+  /// other way avoid it.
+  pub fn add_one_way_obstacle(
+    &mut self,
+    obstacle: Obstacle,
+    allowed_direction: Vec3,
+  ) {
+    self.add_obstacle(obstacle);
+    Arc::make_mut(&mut self.obstacle_properties)
+      .last_mut()
+      .unwrap()
+      .allowed_direction = Some(allowed_direction);
+  }
+
+  /// Opens or closes the door at `obstacle_index`. Panics if the obstacle is
+  /// not a door (see [`Self::add_door`]).
+  pub fn set_door_open(&mut self, obstacle_index: usize, open: bool) {
+    let door_state = &mut Arc::make_mut(&mut self.obstacle_properties)
+      [obstacle_index]
+      .door_open;
+    assert!(door_state.is_some(), "obstacle {} is not a door", obstacle_index);
+    if *door_state == Some(open) {
+      return;
+    }
+    *door_state = Some(open);
+    self.invalidate_agents_near_obstacle(obstacle_index);
+    self.invalidate_velocity_cache();
+  }
+
+  /// Whether the door at `obstacle_index` is open, or None if the obstacle is
+  /// not a door.
+  pub fn is_door_open(&self, obstacle_index: usize) -> Option<bool> {
+    self.obstacle_properties[obstacle_index].door_open
+  }
+
+  /// Marks the new velocities of agents near the obstacle at `obstacle_index`
+  /// in the in-progress budgeted step as stale, since they were computed with
+  /// the obstacle's old state.
+  fn invalidate_agents_near_obstacle(&mut self, obstacle_index: usize) {
+    let Some(budgeted_step) = &self.budgeted_step else {
+      return;
+    };
+    let bounds = Aabb::for_obstacle(&self.obstacles[obstacle_index]);
+    let stale_agent_indices = (0..budgeted_step.new_velocities.len())
+      .filter(|&agent_index| {
+        let query_distance = self.get_obstacle_query_distance(agent_index);
+        bounds.distance_squared_to(self.agents[agent_index].position)
+          <= query_distance * query_distance
+          && !budgeted_step.stale_agent_indices.contains(&agent_index)
+      })
+      .collect::<Vec<_>>();
+    self
+      .budgeted_step
+      .as_mut()
+      .unwrap()
+      .stale_agent_indices
+      .extend(stale_agent_indices);
+  }
+
+  /// Forgets the last step's velocities, so every agent is recomputed. Agents
+  /// in the in-progress budgeted step that were not processed yet are also
+  /// recomputed.
+  fn invalidate_velocity_cache(&mut self) {
+    self.velocity_cache = None;
+    if let Some(budgeted_step) = &mut self.budgeted_step {
+      budgeted_step
+        .reusable_velocities
+        .truncate(budgeted_step.new_velocities.len());
+      budgeted_step.inputs = None;
+    }
   }
 
   pub fn remove_agent(&mut self, agent_index: usize) {
+    self.budgeted_step = None;
+    self.velocity_cache = None;
     self.agents.remove(agent_index);
+    self.agent_parameters.remove(agent_index);
+    self.agent_archetypes.remove(agent_index);
+    self.agent_tags.remove(agent_index);
+    self.parameter_blends.remove(agent_index);
+    self.radius_animations.remove(agent_index);
+    self.agent_importances.remove(agent_index);
+
+    // Keep followers pointing at the same leaders.
+    self.remap_leader_indices(0..self.agents.len(), |leader_index| {
+      match leader_index.cmp(&agent_index) {
+        Ordering::Less => Some(leader_index),
+        Ordering::Equal => None,
+        Ordering::Greater => Some(leader_index - 1),
+      }
+    });
+  }
+
+  /// Points the followers in `agent_indices` at the new indices of their
+  /// leaders, given by `new_leader_index` (or `None` if the leader was
+  /// removed, which stops the follower following). This includes the
+  /// parameters of pending blends, so a blend does not restore a stale leader
+  /// when it completes. Only agents whose leader changes are written to, to
+  /// avoid copying shared chunks.
+  fn remap_leader_indices(
+    &mut self,
+    agent_indices: Range<usize>,
+    new_leader_index: impl Fn(usize) -> Option<usize>,
+  ) {
+    // The new following, if it changed.
+    let remap = |leader_following: &Option<LeaderFollowing>| {
+      let following = leader_following.as_ref()?;
+      let leader_index = new_leader_index(following.leader_index);
+      (leader_index != Some(following.leader_index)).then(|| {
+        leader_index.map(|leader_index| LeaderFollowing {
+          leader_index,
+          ..following.clone()
+        })
+      })
+    };
+    for agent_index in agent_indices {
+      if let Some(leader_following) =
+        remap(&self.agent_parameters[agent_index].leader_following)
+      {
+        self.agent_parameters[agent_index].leader_following = leader_following;
+      }
+      let Some(blend) = &self.parameter_blends[agent_index] else {
+        continue;
+      };
+      let from = remap(&blend.from.leader_following);
+      let to = remap(&blend.to.leader_following);
+      if from.is_none() && to.is_none() {
+        continue;
+      }
+      let blend = self.parameter_blends[agent_index].as_mut().unwrap();
+      if let Some(leader_following) = from {
+        blend.from.leader_following = leader_following;
+      }
+      if let Some(leader_following) = to {
+        blend.to.leader_following = leader_following;
+      }
+    }
+  }
+
+  pub fn remove_obstacle(&mut self, obstacle_index: usize) {
+    self.invalidate_agents_near_obstacle(obstacle_index);
+    Arc::make_mut(&mut self.obstacles).remove(obstacle_index);
+    Arc::make_mut(&mut self.obstacle_properties).remove(obstacle_index);
+    self.obstacle_bvh = Arc::new(ObstacleBvh::new(&self.obstacles));
+    self.invalidate_velocity_cache();
   }
 
   pub fn get_agent(&self, agent_index: usize) -> &Agent {
@@ -48,10 +1209,186 @@ impl Simulator {
     self.agents.len()
   }
 
+  /// The density of agents around `point`: the number of agents whose center
+  /// is within `radius` of `point`, divided by the volume of that sphere.
+  /// This can be used to find how packed an area is (e.g. to pick uncrowded
+  /// spawn points, or to slow agents down in congestion). Panics if `radius`
+  /// is not positive.
+  pub fn density_at(&self, point: Vec3, radius: f32) -> f32 {
+    assert!(radius > 0.0, "radius must be positive, was {}", radius);
+    let radius_squared = radius * radius;
+    let count = self
+      .agents
+      .iter()
+      .filter(|agent| agent.position.distance_squared(point) <= radius_squared)
+      .count();
+    count as f32 / (4.0 / 3.0 * std::f32::consts::PI * radius.powi(3))
+  }
+
+  /// Sets the time horizon of every agent to the horizon suggested by
+  /// [`suggest_time_horizon`], from its radius, max speed, and the density of
+  /// agents within `density_radius` of it (see [`Self::density_at`]). Call this
+  /// every so often (this takes time quadratic in the number of agents) to
+  /// keep horizons up to date as crowds form and disperse. Agents blending
+  /// parameters (see [`Self::set_agent_parameters`]) keep the blended horizon.
+  pub fn tune_time_horizons(
+    &mut self,
+    density_radius: f32,
+    options: &TimeHorizonOptions,
+  ) {
+    for agent_index in 0..self.agents.len() {
+      if self.parameter_blends[agent_index].is_some() {
+        continue;
+      }
+      let agent = &self.agents[agent_index];
+      let density = self.density_at(agent.position, density_radius);
+      let time_horizon = suggest_time_horizon(
+        agent.radius,
+        self.agent_parameters[agent_index].max_speed,
+        density,
+        options,
+      );
+      // Avoid copying shared chunks for agents whose horizon is unchanged.
+      if self.agent_parameters[agent_index].time_horizon != time_horizon {
+        self.agent_parameters[agent_index].time_horizon = time_horizon;
+      }
+    }
+  }
+
+  /// Computes the density of agents in a grid of cubic cells that are
+  /// `cell_size` wide, covering the box from `min` to `max`.
+  pub fn get_density_grid(
+    &self,
+    min: Vec3,
+    max: Vec3,
+    cell_size: f32,
+  ) -> DensityGrid {
+    let mut grid = DensityGrid::new(min, max, cell_size);
+    for agent in self.agents.iter() {
+      grid.add_agent(agent.position);
+    }
+    grid
+  }
+
+  /// Snaps the position and velocity of every agent to the nearest values
+  /// representable by `quantization`. Call this after each step on every
+  /// machine (including the authority) so that the local state matches the
+  /// replicated state, and re-simulation stays in sync.
+  pub fn quantize_agents(&mut self, quantization: &AgentQuantization) {
+    for agent_index in 0..self.agents.len() {
+      let agent = &self.agents[agent_index];
+      let mut snapped_agent = agent.clone();
+      quantization.snap_agent(&mut snapped_agent);
+      // Avoid copying shared chunks for agents that are already snapped.
+      if snapped_agent != *agent {
+        self.agents[agent_index] = snapped_agent;
+      }
+    }
+  }
+
+  pub fn get_obstacle_count(&self) -> usize {
+    self.obstacles.len()
+  }
+
+  /// Adds a queue at a choke point. Agents heading through the choke point
+  /// line up at the queue instead of crowding around it. See [`Queue`].
+  pub fn add_queue(&mut self, queue: Queue) {
+    self.queues.push(queue);
+  }
+
+  pub fn remove_queue(&mut self, queue_index: usize) {
+    self.queues.remove(queue_index);
+  }
+
+  pub fn get_queue(&self, queue_index: usize) -> &Queue {
+    &self.queues[queue_index]
+  }
+
+  pub fn get_queue_count(&self) -> usize {
+    self.queues.len()
+  }
+
   pub fn get_agent_parameters(&self, agent_index: usize) -> &AgentParameters {
     &self.agent_parameters[agent_index]
   }
 
+  /// Sets the parameters of the agent at `agent_index`, smoothly blending
+  /// from its current parameters over `blend_seconds` of simulated time. This
+  /// avoids agents visibly "popping" when switching between sets of parameters
+  /// (e.g. from walking to combat). `max_speed`, `time_horizon`,
+  /// `obstacle_time_horizon`, `obstacle_margin`, and `urgency` are
+  /// interpolated. All other parameters are applied immediately. Modifying the
+  /// agent's parameters during the blend (e.g. through
+  /// [`Self::get_agent_parameters_mut`]) is overridden by the blend. Panics if
+  /// the agent would follow a leader that does not exist.
+  pub fn set_agent_parameters(
+    &mut self,
+    agent_index: usize,
+    agent_parameters: AgentParameters,
+    blend_seconds: f32,
+  ) {
+    self.assert_valid_leader(agent_index, &agent_parameters, self.agents.len());
+    if blend_seconds <= 0.0 {
+      self.agent_parameters[agent_index] = agent_parameters;
+      self.parameter_blends[agent_index] = None;
+      return;
+    }
+
+    let from = std::mem::replace(
+      &mut self.agent_parameters[agent_index],
+      agent_parameters.clone(),
+    );
+    let radius = self.agents[agent_index].radius;
+    // Start from the parameters "as is", so the blend is continuous.
+    self.agent_parameters[agent_index] = AgentParameters {
+      max_speed: from.max_speed,
+      time_horizon: from.time_horizon,
+      obstacle_time_horizon: from.obstacle_time_horizon,
+      urgency: from.urgency,
+      obstacle_margin: match (
+        from.obstacle_margin.get_constant(radius),
+        agent_parameters.obstacle_margin.get_constant(radius),
+      ) {
+        (Some(from_margin), Some(_)) => SimulatorMargin::Distance(from_margin),
+        _ => agent_parameters.obstacle_margin.clone(),
+      },
+      ..agent_parameters.clone()
+    };
+    self.parameter_blends[agent_index] = Some(ParameterBlend {
+      from,
+      to: agent_parameters,
+      elapsed_seconds: 0.0,
+      blend_seconds,
+    });
+  }
+
+  /// Smoothly changes the radius of the agent at `agent_index` to
+  /// `target_radius`, changing by `rate` units per second. This avoids
+  /// instantaneous overlaps (and the violent corrections they cause) when
+  /// agents grow, crouch, or mount a vehicle. Neighbours avoid the agent's
+  /// current (interpolated) radius. Setting the radius directly (e.g. through
+  /// [`Self::get_agent_mut`]) does not stop the change. Panics if `rate` is not
+  /// positive.
+  pub fn set_agent_target_radius(
+    &mut self,
+    agent_index: usize,
+    target_radius: f32,
+    rate: f32,
+  ) {
+    assert!(rate > 0.0, "rate must be positive, was {}", rate);
+    self.radius_animations[agent_index] =
+      Some(RadiusAnimation { target_radius, rate });
+  }
+
+  /// Gets the radius the agent at `agent_index` is changing to, or its
+  /// current radius if it is not changing size.
+  pub fn get_agent_target_radius(&self, agent_index: usize) -> f32 {
+    match &self.radius_animations[agent_index] {
+      None => self.agents[agent_index].radius,
+      Some(animation) => animation.target_radius,
+    }
+  }
+
   pub fn get_agent_parameters_mut(
     &mut self,
     agent_index: usize,
@@ -59,52 +1396,876 @@ impl Simulator {
     &mut self.agent_parameters[agent_index]
   }
 
-  pub fn step(&mut self, time_step: f32) {
-    let mut agent_pair_to_distance_squared = HashMap::new();
+  /// Sets how often the agent at `agent_index` is updated by [`Self::step`]
+  /// and [`Self::step_with_budget`] (see [`Importance`]). Agents are updated
+  /// in staggered steps (based on their index), so the cost of less important
+  /// agents is spread evenly between steps. As a result, the velocities of
+  /// less important agents depend on their index. Agents are
+  /// [`Importance::High`] by default.
+  pub fn set_agent_importance(
+    &mut self,
+    agent_index: usize,
+    importance: Importance,
+  ) {
+    self.agent_importances[agent_index] = importance;
+  }
+
+  pub fn get_agent_importance(&self, agent_index: usize) -> Importance {
+    self.agent_importances[agent_index]
+  }
+
+  /// Sets the function that classifies the importance of every agent (e.g.
+  /// based on whether it is within the camera's view frustum) at the start of
+  /// each step, given the agent's index and position. While set, this
+  /// overrides the importances from [`Self::set_agent_importance`]. If `None`,
+  /// agents keep the importance they were last given.
+  pub fn set_importance_classifier(
+    &mut self,
+    importance_classifier: Option<Box<ImportanceClassifier>>,
+  ) {
+    self.importance_classifier = importance_classifier.map(Arc::from);
+  }
+
+  /// Classifies the importance of each agent using the importance classifier,
+  /// if any.
+  fn update_agent_importances(&mut self) {
+    let Some(importance_classifier) = self.importance_classifier.clone() else {
+      return;
+    };
+    for agent_index in 0..self.agents.len() {
+      let importance =
+        importance_classifier(agent_index, self.agents[agent_index].position);
+      // Only write changed importances, so chunks shared with clones are not
+      // copied needlessly.
+      if self.agent_importances[agent_index] != importance {
+        self.agent_importances[agent_index] = importance;
+      }
+    }
+  }
+
+  /// Whether the agent at `agent_index` is updated in the current step, based
+  /// on its importance.
+  fn is_agent_updated_this_step(&self, agent_index: usize) -> bool {
+    let update_interval =
+      self.agent_importances[agent_index].get_update_interval();
+    (self.snapshot.step_count + agent_index as u64)
+      .is_multiple_of(update_interval)
+  }
+
+  #[cfg(feature = "debug")]
+  /// Returns the indices of the agents that the agent at `agent_index` would
+  /// avoid if the simulation were stepped now.
+  pub fn get_agent_neighbours(&self, agent_index: usize) -> Vec<usize> {
+    self.find_neighbours(agent_index)
+  }
+
+  /// Finds the indices of the agents that the agent at `agent_index` should
+  /// avoid.
+  #[cfg(feature = "debug")]
+  fn find_neighbours(&self, agent_index: usize) -> Vec<usize> {
+    let mut neighbour_indices = Vec::new();
+    self.find_neighbours_into(agent_index, &mut neighbour_indices);
+    neighbour_indices
+  }
+
+  /// Same as [`Self::find_neighbours`], but replaces the contents of
+  /// `neighbour_indices` (so callers can reuse the buffer).
+  fn find_neighbours_into(
+    &self,
+    agent_index: usize,
+    neighbour_indices: &mut Vec<usize>,
+  ) {
+    let query_distance = self.get_neighbour_query_distance(agent_index);
+    let agent = &self.agents[agent_index];
+    let query_distance_squared = query_distance * query_distance;
+
+    neighbour_indices.clear();
     // TODO: Make this fast.
-    for i in 0..self.agents.len() {
-      for j in (i + 1)..self.agents.len() {
-        let distance_squared =
-          self.agents[i].position.distance_squared(self.agents[j].position);
-        agent_pair_to_distance_squared.insert((i, j), distance_squared);
-        agent_pair_to_distance_squared.insert((j, i), distance_squared);
+    neighbour_indices.extend(
+      self
+        .agents
+        .iter()
+        .enumerate()
+        .filter(|&(other_index, other_agent)| {
+          other_index != agent_index
+            && agent.position.distance_squared(other_agent.position)
+              <= query_distance_squared
+        })
+        .map(|(other_index, _)| other_index),
+    );
+  }
+
+  /// The distance within which the agent at `agent_index` should avoid other
+  /// agents.
+  fn get_neighbour_query_distance(&self, agent_index: usize) -> f32 {
+    let agent = &self.agents[agent_index];
+    let parameters = &self.agent_parameters[agent_index];
+    parameters.max_speed * parameters.time_horizon + agent.radius * 2.0
+  }
+
+  /// The distance within which the agent at `agent_index` should avoid
+  /// obstacles. Agents can only reach obstacles within this distance before
+  /// the obstacle time horizon.
+  fn get_obstacle_query_distance(&self, agent_index: usize) -> f32 {
+    let parameters = &self.agent_parameters[agent_index];
+    parameters.max_speed * parameters.obstacle_time_horizon
+      + parameters.obstacle_margin.get_max(self.agents[agent_index].radius)
+  }
+
+  /// The largest distance any agent looks for neighbours at.
+  pub(crate) fn get_max_neighbour_query_distance(&self) -> f32 {
+    (0..self.agents.len())
+      .map(|agent_index| self.get_neighbour_query_distance(agent_index))
+      .fold(0.0, f32::max)
+  }
+
+  /// Advances all radius changes by `time_step`.
+  fn update_radius_animations(&mut self, time_step: f32) {
+    for agent_index in 0..self.agents.len() {
+      // Only touch agents that are changing size, to avoid copying shared
+      // chunks.
+      let Some(animation) = self.radius_animations[agent_index].clone() else {
+        continue;
+      };
+      let agent = &mut self.agents[agent_index];
+      let max_change = animation.rate * time_step;
+      let change = animation.target_radius - agent.radius;
+      if change.abs() <= max_change {
+        agent.radius = animation.target_radius;
+        self.radius_animations[agent_index] = None;
+      } else {
+        agent.radius += max_change.copysign(change);
       }
     }
+  }
+
+  /// Advances all parameter blends by `time_step`.
+  fn update_parameter_blends(&mut self, time_step: f32) {
+    for agent_index in 0..self.agents.len() {
+      // Only touch agents that are blending, to avoid copying shared chunks.
+      if self.parameter_blends[agent_index].is_none() {
+        continue;
+      }
+      let agent = &self.agents[agent_index];
+      let parameters = &mut self.agent_parameters[agent_index];
+      let blend_slot = &mut self.parameter_blends[agent_index];
+      let blend = blend_slot.as_mut().unwrap();
+      blend.elapsed_seconds += time_step;
+      if blend.elapsed_seconds >= blend.blend_seconds {
+        *parameters = blend.to.clone();
+        *blend_slot = None;
+        continue;
+      }
+
+      let t = blend.elapsed_seconds / blend.blend_seconds;
+      let lerp = |from: f32, to: f32| from + (to - from) * t;
+      parameters.max_speed = lerp(blend.from.max_speed, blend.to.max_speed);
+      parameters.urgency = lerp(blend.from.urgency, blend.to.urgency);
+      parameters.time_horizon =
+        lerp(blend.from.time_horizon, blend.to.time_horizon);
+      parameters.obstacle_time_horizon =
+        lerp(blend.from.obstacle_time_horizon, blend.to.obstacle_time_horizon);
+      if let (Some(from_margin), Some(to_margin)) = (
+        blend.from.obstacle_margin.get_constant(agent.radius),
+        blend.to.obstacle_margin.get_constant(agent.radius),
+      ) {
+        parameters.obstacle_margin =
+          SimulatorMargin::Distance(lerp(from_margin, to_margin));
+      }
+    }
+  }
 
-    let mut new_velocities = Vec::with_capacity(self.agents.len());
-    for (index, (agent, parameters)) in
-      self.agents.iter().zip(self.agent_parameters.iter()).enumerate()
+  /// Assigns queue slots to the agents waiting in each queue.
+  fn update_queue_slots(&mut self) {
+    self.queue_slots.clear();
+    if self.queues.is_empty() {
+      return;
+    }
+    self.queue_slots.resize(self.agents.len(), None);
+    // Agents following a leader never queue.
+    let queueing_agent_indices = (0..self.agents.len())
+      .filter(|&agent_index| {
+        self.agent_parameters[agent_index].leader_following.is_none()
+      })
+      .collect::<Vec<_>>();
+    for queue in self.queues.iter() {
+      let slots =
+        queue.assign_slots(queueing_agent_indices.iter().map(|&agent_index| {
+          (
+            self.agents[agent_index].position,
+            self.agent_parameters[agent_index].goal_point,
+          )
+        }));
+      for (&agent_index, slot) in queueing_agent_indices.iter().zip(slots) {
+        // Agents only wait in the first queue that wants them.
+        if self.queue_slots[agent_index].is_none() {
+          self.queue_slots[agent_index] = slot;
+        }
+      }
+    }
+  }
+
+  pub fn step(&mut self, time_step: f32) {
+    self.step_with_ghosts(time_step, &[]);
+  }
+
+  /// Same as [`Self::step`], but additionally avoids the `ghosts`, which are
+  /// agents managed by another simulator (e.g. across a region border).
+  pub(crate) fn step_with_ghosts(&mut self, time_step: f32, ghosts: &[Agent]) {
+    self.begin_step(time_step);
+    // Ghosts are not tracked between steps, so nothing can be reused.
+    let inputs =
+      if ghosts.is_empty() { self.get_step_inputs(time_step) } else { None };
+    let reusable_velocities = match &inputs {
+      None => Vec::new(),
+      Some(_) => self.get_reusable_velocities(time_step),
+    };
+    let mut scratch = std::mem::take(&mut self.scratch);
+    let mut solver_limited_agent_count = 0;
+    let new_velocities = (0..self.agents.len())
+      .map(|index| {
+        if let Some(velocity) =
+          reusable_velocities.get(index).copied().flatten()
+        {
+          return Some(velocity);
+        }
+        if !self.is_agent_updated_this_step(index) {
+          return None;
+        }
+        let (velocity, solver_limit_reached) =
+          self.compute_new_velocity(index, time_step, ghosts, &mut scratch);
+        solver_limited_agent_count += usize::from(solver_limit_reached);
+        Some(velocity)
+      })
+      .collect::<Vec<_>>();
+    self.scratch = scratch;
+    self.solver_limited_agent_count = solver_limited_agent_count;
+    self.cache_velocities(inputs, &reusable_velocities, &new_velocities);
+    let new_velocities = self.keep_velocities_of_skipped_agents(new_velocities);
+    self.end_step(new_velocities, time_step);
+  }
+
+  /// Starts a step of `time_step`, for stepping from a job system. See
+  /// [`Simulator`]. Discards any step in progress from
+  /// [`Self::step_with_budget`].
+  pub fn begin_step(&mut self, time_step: f32) {
+    self.budgeted_step = None;
+    self.update_parameter_blends(time_step);
+    self.update_radius_animations(time_step);
+    self.update_queue_slots();
+    self.update_agent_importances();
+    self.step_seed = self.rng.random();
+    self.skipped_agent_count = 0;
+    #[cfg(feature = "metrics")]
     {
-      let mut neighbours = Vec::new();
-      for other_index in 0..self.agents.len() {
-        if index == other_index {
+      self.step_start = Some(Instant::now());
+    }
+  }
+
+  /// Computes the new velocity of the agent at `agent_index` for the step
+  /// started by [`Self::begin_step`], without moving any agents. This can be
+  /// called in parallel for different agents. See [`Simulator`].
+  pub fn compute_agent_velocity(
+    &self,
+    agent_index: usize,
+    time_step: f32,
+  ) -> Vec3 {
+    self.compute_agent_velocity_with_scratch(
+      agent_index,
+      time_step,
+      &mut StepScratch::default(),
+    )
+  }
+
+  /// Same as [`Self::compute_agent_velocity`], but reuses the buffers in
+  /// `scratch` instead of allocating new ones. Keep one [`StepScratch`] per
+  /// thread to avoid allocating for every agent.
+  pub fn compute_agent_velocity_with_scratch(
+    &self,
+    agent_index: usize,
+    time_step: f32,
+    scratch: &mut StepScratch,
+  ) -> Vec3 {
+    self.compute_new_velocity(agent_index, time_step, &[], scratch).0
+  }
+
+  /// Finishes the step started by [`Self::begin_step`], setting each agent's
+  /// velocity to the corresponding entry in `new_velocities` (computed by
+  /// [`Self::compute_agent_velocity`]) and moving agents by `time_step`. See
+  /// [`Simulator`].
+  pub fn end_step(&mut self, new_velocities: Vec<Vec3>, time_step: f32) {
+    assert_eq!(
+      new_velocities.len(),
+      self.agents.len(),
+      "every agent must have a new velocity"
+    );
+    self.apply_new_velocities(new_velocities, time_step);
+    #[cfg(feature = "metrics")]
+    telemetry::record_step(
+      self.step_start.take().map(|step_start| step_start.elapsed()),
+      self.agents.len(),
+    );
+  }
+
+  /// Same as [`Self::step`], but only computes the new velocities of as many
+  /// agents as fit in the `budget`, resuming from where it left off on the
+  /// next call. This spreads the cost of stepping large crowds over several
+  /// frames without a dedicated thread. Agents only move once every agent's
+  /// new velocity has been computed, at which point this returns true. At
+  /// least one agent is processed per call, so the step always progresses.
+  /// The `time_step` is only used when starting a new step. Adding or removing
+  /// agents (or calling [`Self::step`]) discards the in-progress step.
+  pub fn step_with_budget(&mut self, time_step: f32, budget: Duration) -> bool {
+    let start = Instant::now();
+    if self.budgeted_step.is_none() {
+      self.update_parameter_blends(time_step);
+      self.update_radius_animations(time_step);
+      self.update_queue_slots();
+      self.update_agent_importances();
+      self.step_seed = self.rng.random();
+    }
+    let mut budgeted_step = self.budgeted_step.take().unwrap_or_else(|| {
+      let inputs = self.get_step_inputs(time_step);
+      BudgetedStep {
+        time_step,
+        new_velocities: Vec::with_capacity(self.agents.len()),
+        solver_limit_reached: Vec::with_capacity(self.agents.len()),
+        stale_agent_indices: Vec::new(),
+        reusable_velocities: match &inputs {
+          None => Vec::new(),
+          Some(_) => self.get_reusable_velocities(time_step),
+        },
+        inputs,
+        #[cfg(feature = "metrics")]
+        elapsed: Duration::ZERO,
+      }
+    });
+
+    let mut scratch = std::mem::take(&mut self.scratch);
+    loop {
+      if let Some(agent_index) = budgeted_step.stale_agent_indices.pop() {
+        // Agents that are not updated this step keep their velocity anyway.
+        if budgeted_step.new_velocities[agent_index].is_none() {
           continue;
         }
+        let (new_velocity, solver_limit_reached) = self.compute_new_velocity(
+          agent_index,
+          budgeted_step.time_step,
+          &[],
+          &mut scratch,
+        );
+        budgeted_step.new_velocities[agent_index] = Some(new_velocity);
+        budgeted_step.solver_limit_reached[agent_index] = solver_limit_reached;
+        if let Some(reusable_velocity) =
+          budgeted_step.reusable_velocities.get_mut(agent_index)
+        {
+          *reusable_velocity = None;
+        }
+      } else if budgeted_step.new_velocities.len() < self.agents.len() {
+        let agent_index = budgeted_step.new_velocities.len();
+        let (new_velocity, solver_limit_reached) = match budgeted_step
+          .reusable_velocities
+          .get(agent_index)
+          .copied()
+          .flatten()
+        {
+          Some(velocity) => (Some(velocity), false),
+          None if !self.is_agent_updated_this_step(agent_index) => {
+            (None, false)
+          }
+          None => {
+            let (velocity, solver_limit_reached) = self.compute_new_velocity(
+              agent_index,
+              budgeted_step.time_step,
+              &[],
+              &mut scratch,
+            );
+            (Some(velocity), solver_limit_reached)
+          }
+        };
+        budgeted_step.new_velocities.push(new_velocity);
+        budgeted_step.solver_limit_reached.push(solver_limit_reached);
+      } else {
+        break;
+      }
+      if start.elapsed() >= budget {
+        break;
+      }
+    }
+    self.scratch = scratch;
+    #[cfg(feature = "metrics")]
+    {
+      budgeted_step.elapsed += start.elapsed();
+    }
 
-        let query_distance =
-          parameters.max_speed * parameters.time_horizon + agent.radius * 2.0;
-        if agent_pair_to_distance_squared[&(index, other_index)]
-          <= query_distance * query_distance
+    if !budgeted_step.stale_agent_indices.is_empty()
+      || budgeted_step.new_velocities.len() < self.agents.len()
+    {
+      self.budgeted_step = Some(budgeted_step);
+      return false;
+    }
+    self.solver_limited_agent_count = budgeted_step
+      .solver_limit_reached
+      .iter()
+      .filter(|&&solver_limit_reached| solver_limit_reached)
+      .count();
+    self.cache_velocities(
+      budgeted_step.inputs,
+      &budgeted_step.reusable_velocities,
+      &budgeted_step.new_velocities,
+    );
+    let new_velocities =
+      self.keep_velocities_of_skipped_agents(budgeted_step.new_velocities);
+    self.apply_new_velocities(new_velocities, budgeted_step.time_step);
+    #[cfg(feature = "metrics")]
+    telemetry::record_step(Some(budgeted_step.elapsed), self.agents.len());
+    true
+  }
+
+  /// Captures the inputs of a step of `time_step` (without velocities), if
+  /// the step's velocities may be reused in the next step.
+  fn get_step_inputs(&self, time_step: f32) -> Option<VelocityCache> {
+    if !self.skip_unchanged_agents || !self.neighbour_providers.is_empty() {
+      return None;
+    }
+    Some(VelocityCache {
+      time_step,
+      // These share chunks with the simulator, so they are cheap to copy.
+      agents: self.agents.clone(),
+      agent_parameters: self.agent_parameters.clone(),
+      queue_slots: self.queue_slots.clone(),
+      velocities: Vec::new(),
+    })
+  }
+
+  /// The velocity of each agent from the last step if the agent is
+  /// unaffected since then (see [`Self::set_skip_unchanged_agents`]), or None
+  /// if it must be recomputed.
+  fn get_reusable_velocities(&self, time_step: f32) -> Vec<Option<Vec3>> {
+    let agent_count = self.agents.len();
+    let Some(cache) = self.velocity_cache.as_ref().filter(|cache| {
+      cache.time_step == time_step && cache.agents.len() == agent_count
+    }) else {
+      return vec![None; agent_count];
+    };
+    let changed = (0..agent_count)
+      .map(|agent_index| {
+        self.agents[agent_index] != cache.agents[agent_index]
+          || self.agent_parameters[agent_index]
+            != cache.agent_parameters[agent_index]
+          || self.queue_slots.get(agent_index)
+            != cache.queue_slots.get(agent_index)
+      })
+      .collect::<Vec<_>>();
+    let changed_agent_indices = (0..agent_count)
+      .filter(|&agent_index| changed[agent_index])
+      .collect::<Vec<_>>();
+
+    (0..agent_count)
+      .map(|agent_index| {
+        if changed[agent_index] {
+          return None;
+        }
+        if let Some(following) =
+          &self.agent_parameters[agent_index].leader_following
         {
-          continue;
+          if changed[following.leader_index] {
+            return None;
+          }
+        }
+        let position = self.agents[agent_index].position;
+        let query_distance = self.get_neighbour_query_distance(agent_index);
+        let query_distance_squared = query_distance * query_distance;
+        // Changed agents may have moved in or out of range, so check both
+        // where they were and where they are now.
+        let near_changed_agent =
+          changed_agent_indices.iter().any(|&other_index| {
+            self.agents[other_index].position.distance_squared(position)
+              <= query_distance_squared
+              || cache.agents[other_index].position.distance_squared(position)
+                <= query_distance_squared
+          });
+        if near_changed_agent {
+          return None;
         }
+        cache.velocities[agent_index]
+      })
+      .collect()
+  }
+
+  /// Stores the `new_velocities` computed from `inputs` (from
+  /// [`Self::get_step_inputs`]) for the next step, and counts the agents that
+  /// reused their velocity.
+  fn cache_velocities(
+    &mut self,
+    inputs: Option<VelocityCache>,
+    reusable_velocities: &[Option<Vec3>],
+    new_velocities: &[Option<Vec3>],
+  ) {
+    self.skipped_agent_count = reusable_velocities.iter().flatten().count();
+    self.velocity_cache = inputs.map(|inputs| VelocityCache {
+      velocities: new_velocities.to_vec(),
+      ..inputs
+    });
+  }
 
-        neighbours.push(Cow::Borrowed(&self.agents[other_index]));
+  /// Replaces the missing `new_velocities` (for agents that are not updated
+  /// this step) with the agent's current velocity, so the agent keeps moving
+  /// the same way.
+  fn keep_velocities_of_skipped_agents(
+    &self,
+    new_velocities: Vec<Option<Vec3>>,
+  ) -> Vec<Vec3> {
+    new_velocities
+      .into_iter()
+      .enumerate()
+      .map(|(agent_index, new_velocity)| {
+        new_velocity.unwrap_or(self.agents[agent_index].velocity)
+      })
+      .collect()
+  }
+
+  /// Computes the new velocity of the agent at `index`, without moving any
+  /// agents. Also returns whether the solver reached its limits.
+  fn compute_new_velocity(
+    &self,
+    index: usize,
+    time_step: f32,
+    ghosts: &[Agent],
+    scratch: &mut StepScratch,
+  ) -> (Vec3, bool) {
+    let agent = &self.agents[index];
+    let parameters = &self.agent_parameters[index];
+    self.find_neighbours_into(index, &mut scratch.neighbour_indices);
+    let seed = self.get_agent_seed(index, &scratch.neighbour_indices);
+    // Each neighbour along with its standing height (if it is a ground agent).
+    let mut neighbours = scratch
+      .neighbour_indices
+      .iter()
+      .map(|&other_index| {
+        (
+          Cow::Borrowed(&self.agents[other_index]),
+          self.agent_parameters[other_index].standing_height,
+        )
+      })
+      .collect::<Vec<_>>();
+    if !self.neighbour_providers.is_empty() || !ghosts.is_empty() {
+      let query_distance = self.get_neighbour_query_distance(index);
+      let query_distance_squared = query_distance * query_distance;
+      neighbours.extend(
+        ghosts
+          .iter()
+          .filter(|ghost| {
+            ghost.position.distance_squared(agent.position)
+              <= query_distance_squared
+          })
+          .map(|ghost| (Cow::Borrowed(ghost), None)),
+      );
+      for neighbour_provider in self.neighbour_providers.iter() {
+        neighbours.extend(
+          neighbour_provider
+            .get_agents_near(agent.position, query_distance)
+            .into_iter()
+            .map(|neighbour| (Cow::Owned(neighbour), None)),
+        );
       }
+    }
+    let mut neighbours = neighbours
+      .into_iter()
+      .filter_map(|(neighbour, neighbour_standing_height)| {
+        if parameters.standing_height.is_none()
+          && neighbour_standing_height.is_none()
+        {
+          return Some(neighbour);
+        }
+        adjust_neighbour(
+          agent,
+          parameters.standing_height,
+          &neighbour,
+          neighbour_standing_height,
+        )
+        .map(Cow::Owned)
+      })
+      .collect::<Vec<_>>();
+    // The order of constraints (and of summing forces) slightly changes the
+    // result, so sort the neighbours to not depend on agent indices.
+    sort_neighbours(agent.position, &mut neighbours);
+    let obstacle_query_distance = self.get_obstacle_query_distance(index);
+    let obstacle_indices = &mut scratch.obstacle_indices;
+    obstacle_indices.clear();
+    self.obstacle_bvh.query_into(
+      agent.position,
+      obstacle_query_distance,
+      &mut scratch.obstacle_query_stack,
+      obstacle_indices,
+    );
+    // Open doors do not block agents.
+    obstacle_indices.retain(|&obstacle_index| {
+      self.obstacle_properties[obstacle_index].door_open != Some(true)
+    });
+    // Keep obstacles in a consistent order regardless of the hierarchy.
+    obstacle_indices.sort_unstable();
+    let is_one_way = |obstacle_index: &&usize| {
+      self.obstacle_properties[**obstacle_index].allowed_direction.is_some()
+    };
+    let one_way_obstacle_indices = obstacle_indices.iter().filter(is_one_way);
+    let mut obstacles = obstacle_indices
+      .iter()
+      .filter(|obstacle_index| !is_one_way(obstacle_index))
+      .map(|&obstacle_index| Cow::Borrowed(&self.obstacles[obstacle_index]))
+      .collect::<Vec<_>>();
+    if let Some(boundary) = &self.boundary {
+      obstacles.extend(
+        boundary
+          .get_obstacles_near(agent.position, obstacle_query_distance)
+          .into_iter()
+          .map(Cow::Owned),
+      );
+    }
 
-      new_velocities.push(agent.compute_avoiding_velocity(
+    let obstacle_margin = parameters.obstacle_margin.resolve(agent, || {
+      obstacles
+        .iter()
+        .map(|obstacle| obstacle.as_ref())
+        .chain(
+          one_way_obstacle_indices
+            .clone()
+            .map(|&obstacle_index| &self.obstacles[obstacle_index]),
+        )
+        .map(|obstacle| get_distance_to_obstacle(agent.position, obstacle))
+        .fold(f32::INFINITY, f32::min)
+    });
+
+    let urgency = parameters.urgency.clamp(0.0, 1.0);
+    let avoidance_options = AvoidanceOptions {
+      obstacle_margin,
+      time_horizon: parameters.time_horizon
+        * (1.0 - MAX_URGENCY_TIME_HORIZON_SHRINK * urgency),
+      obstacle_time_horizon: parameters.obstacle_time_horizon,
+      tolerances: self.tolerances.clone(),
+      strict_rvo2_compatibility: false,
+      objective_weights: ObjectiveWeights::default(),
+      solver_limits: self.solver_limits.clone(),
+      responsibility_override: self.responsibility_override.clone(),
+    };
+
+    let mut preferred_velocity = match &parameters.leader_following {
+      None => {
+        self
+          .queue_slots
+          .get(index)
+          .copied()
+          .flatten()
+          .unwrap_or(parameters.goal_point)
+          - agent.position
+      }
+      Some(following) => {
+        let leader = &self.agents[following.leader_index];
+        // Match the leader's velocity so the follower does not lag behind
+        // the follow point.
+        suggest_follow_point(leader, agent, &following.options) - agent.position
+          + leader.velocity
+      }
+    };
+    if let Some(yielding) = &parameters.yielding {
+      // Followers keep up with their leader, so they are never standing.
+      if parameters.leader_following.is_none()
+        && preferred_velocity.length() <= yielding.standing_distance
+      {
+        if let Some(sidestep) =
+          get_sidestep_velocity(agent, &neighbours, yielding)
+        {
+          preferred_velocity = sidestep;
+        }
+      }
+    }
+    if let Some(social_force) = &parameters.social_force {
+      // Limit the preferred velocity first, so the social forces are not
+      // drowned out by a distant goal.
+      preferred_velocity = preferred_velocity
+        .clamp_length_max(parameters.max_speed)
+        + get_social_force(agent, &neighbours, social_force);
+    }
+    // Followers keep up with their leader, regardless of crossing flows.
+    if let (Some(crossing_flow), None) =
+      (&self.crossing_flow, &parameters.leader_following)
+    {
+      preferred_velocity = bias_for_crossing_flow(
+        agent,
         &neighbours,
-        parameters.goal_point - agent.position,
-        parameters.max_speed,
-        time_step,
-        &AvoidanceOptions { time_horizon: parameters.time_horizon },
+        preferred_velocity.clamp_length_max(parameters.max_speed),
+        crossing_flow,
+      );
+    }
+    if let Some(obstacle_nudge) = &self.obstacle_nudge {
+      preferred_velocity = nudge_away_from_obstacles(
+        agent,
+        &obstacles,
+        preferred_velocity.clamp_length_max(parameters.max_speed),
+        obstacle_nudge,
+      );
+    }
+    let mut rigid_planes = std::mem::take(&mut scratch.planes);
+    rigid_planes.clear();
+    if let Some(lane) = &parameters.traffic_lane {
+      rigid_planes.extend(traffic::get_planes_for_lane(
+        agent,
+        lane,
+        parameters.obstacle_time_horizon,
+      ));
+    }
+    let mut locked_axes = parameters.locked_axes;
+    match (&self.terrain, parameters.standing_height) {
+      (Some(terrain), _) => {
+        let ground_normal =
+          terrain.normal_at(agent.position.x, agent.position.z);
+        rigid_planes.extend(terrain::get_planes_for_tangent(ground_normal));
+        preferred_velocity =
+          terrain::project_onto_tangent(preferred_velocity, ground_normal);
+      }
+      // Without terrain, ground agents walk on flat ground. Locking the Y
+      // axis also zeroes the solved vertical velocity, so agents do not drift
+      // off the ground within the slack of the constraints.
+      (None, Some(_)) => locked_axes.y = true,
+      (None, None) => {}
+    }
+    rigid_planes.extend(axis_locks::get_planes_for_locked_axes(locked_axes));
+    preferred_velocity =
+      axis_locks::zero_locked_axes(preferred_velocity, locked_axes);
+    // One-way obstacles are still satisfied by a velocity of zero, so they can
+    // be rigid.
+    for &obstacle_index in one_way_obstacle_indices {
+      rigid_planes.extend(get_planes_for_agent_to_one_way_obstacle(
+        agent,
+        &self.obstacles[obstacle_index],
+        self.obstacle_properties[obstacle_index].allowed_direction.unwrap(),
+        obstacle_margin,
+        parameters.obstacle_time_horizon,
       ));
     }
 
-    for (agent, new_velocity) in self.agents.iter_mut().zip(new_velocities) {
+    if let Some(threat_filter) = &self.threat_filter {
+      threat_filter.filter_neighbours(
+        agent,
+        &mut neighbours,
+        avoidance_options.time_horizon,
+      );
+    }
+    let avoiding_agent = Agent {
+      radius: agent.radius * (1.0 - MAX_URGENCY_RADIUS_SHRINK * urgency),
+      ..agent.clone()
+    };
+    let neighbour_planes = &mut scratch.neighbour_planes;
+    neighbour_planes.clear();
+    avoiding_agent.extend_planes_for_neighbours(
+      &neighbours,
+      time_step,
+      &avoidance_options,
+      &mut StdRng::seed_from_u64(seed),
+      neighbour_planes,
+    );
+    // Shifting the planes against their normals lets urgent agents move
+    // partway into the space of other agents.
+    let violation = MAX_URGENCY_VIOLATION * urgency * parameters.max_speed;
+    for plane in neighbour_planes.iter_mut() {
+      plane.point -= plane.normal * violation;
+    }
+    let constraints = avoiding_agent.build_constraints_for_planes(
+      &obstacles,
+      rigid_planes,
+      neighbour_planes,
+      &avoidance_options,
+    );
+    #[cfg(not(feature = "metrics"))]
+    let (velocity, solver_limit_reached) =
+      constraints.solve_within_limits(preferred_velocity, parameters.max_speed);
+    #[cfg(feature = "metrics")]
+    let (velocity, solver_limit_reached) = {
+      let (velocity, solver_limit_reached, outcome) = constraints
+        .solve_with_outcome(preferred_velocity, parameters.max_speed);
+      telemetry::record_agent_velocity(
+        agent,
+        &neighbours,
+        &outcome,
+        solver_limit_reached,
+      );
+      (velocity, solver_limit_reached)
+    };
+    // Keep the combined planes' buffer for the next agent.
+    scratch.planes = constraints.planes;
+    (axis_locks::zero_locked_axes(velocity, locked_axes), solver_limit_reached)
+  }
+
+  /// Gets the seed for the random choices of the agent at `index` this step.
+  /// The seed comes from the agent's state rather than its index, so it does
+  /// not depend on the order agents were added in. Agents with exactly the
+  /// same state (which would otherwise make the same choices, and never push
+  /// apart) are told apart by their order among each other, which
+  /// `neighbour_indices` (the agent's neighbours) includes.
+  fn get_agent_seed(&self, index: usize, neighbour_indices: &[usize]) -> u64 {
+    let seed_bits = |index: usize| {
+      let agent = &self.agents[index];
+      let parameters = &self.agent_parameters[index];
+      let [px, py, pz] = agent.position.to_array();
+      let [vx, vy, vz] = agent.velocity.to_array();
+      let [gx, gy, gz] = parameters.goal_point.to_array();
+      [px, py, pz, vx, vy, vz, gx, gy, gz, agent.radius, parameters.max_speed]
+        .map(f32::to_bits)
+    };
+    let bits = seed_bits(index);
+    let duplicate_count = neighbour_indices
+      .iter()
+      .filter(|&&other_index| {
+        other_index < index && seed_bits(other_index) == bits
+      })
+      .count();
+    bits
+      .into_iter()
+      .chain([duplicate_count as u32])
+      .fold(self.step_seed, mix_seed)
+  }
+
+  /// Gets the snapshot of the agents at the end of the last completed step.
+  /// Agents added or removed since then are only reflected after the next
+  /// step. Agent indices in the snapshot match the simulator's indices at the
+  /// time of the snapshot.
+  pub fn get_snapshot(&self) -> Arc<SimulatorSnapshot> {
+    self.snapshot.clone()
+  }
+
+  /// Updates the snapshot to the current state of the agents. The snapshot
+  /// shares chunks with the agents, so this is cheap.
+  fn update_snapshot(&mut self) {
+    self.snapshot = Arc::new(SimulatorSnapshot {
+      agents: self.agents.clone(),
+      step_count: self.snapshot.step_count + 1,
+    });
+  }
+
+  /// Sets the velocity of every agent and moves them by `time_step`.
+  fn apply_new_velocities(
+    &mut self,
+    new_velocities: Vec<Vec3>,
+    time_step: f32,
+  ) {
+    for (agent_index, new_velocity) in new_velocities.into_iter().enumerate() {
+      // Stationary agents are unchanged, so leave their chunks shared.
+      if new_velocity == Vec3::ZERO
+        && self.agents[agent_index].velocity == Vec3::ZERO
+      {
+        continue;
+      }
+      let agent = &mut self.agents[agent_index];
       agent.velocity = new_velocity;
       agent.position += new_velocity * time_step;
+      if let Some(terrain) = &self.terrain {
+        // Moving along the tangent plane drifts off curved terrain, so snap
+        // agents back to the ground.
+        agent.position.y =
+          terrain.height_at(agent.position.x, agent.position.z);
+      }
     }
+    self.update_snapshot();
   }
 }
 
@@ -114,6 +2275,38 @@ impl Default for Simulator {
   }
 }
 
+/// Sorts `neighbours` by their distance to `position` (closest first), as in
+/// RVO2-3D, breaking ties by their position and velocity. The order only
+/// depends on the neighbours themselves, so it is the same regardless of agent
+/// indices (e.g. agents being added in a different order) or where the
+/// neighbours came from.
+fn sort_neighbours(position: Vec3, neighbours: &mut [Cow<'_, Agent>]) {
+  neighbours.sort_by(|a, b| {
+    a.position
+      .distance_squared(position)
+      .total_cmp(&b.position.distance_squared(position))
+      .then_with(|| {
+        a.position
+          .to_array()
+          .into_iter()
+          .chain(a.velocity.to_array())
+          .zip(b.position.to_array().into_iter().chain(b.velocity.to_array()))
+          .map(|(a, b)| a.total_cmp(&b))
+          .find(|ordering| ordering.is_ne())
+          .unwrap_or(Ordering::Equal)
+      })
+  });
+}
+
+/// Mixes `bits` into `seed`, using the finalizer of SplitMix64 so that every
+/// bit affects the whole seed.
+fn mix_seed(seed: u64, bits: u32) -> u64 {
+  let mut seed = (seed ^ u64::from(bits)).wrapping_add(0x9e37_79b9_7f4a_7c15);
+  seed = (seed ^ (seed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+  seed = (seed ^ (seed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+  seed ^ (seed >> 31)
+}
+
 #[cfg(test)]
 #[path = "simulator_test.rs"]
 mod test;
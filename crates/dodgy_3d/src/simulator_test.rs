@@ -1,8 +1,15 @@
-use glam::Vec3;
+use std::time::Duration;
+
+use glam::{BVec3, Vec2, Vec3};
 
 use crate::{
-  simulator::{AgentParameters, Simulator, SimulatorMargin},
-  Agent,
+  simulator::{
+    AgentParameters, Importance, MarginCurve, Simulator, SimulatorMargin,
+    SplitAgentIndex, StepScratch,
+  },
+  suggest_time_horizon, Agent, AgentQuantization, CrossingFlow, FollowOptions,
+  LeaderFollowing, Obstacle, ObstacleNudge, Quantizer, Queue, SocialForce,
+  SolverLimits, TimeHorizonOptions, TrafficLane, Yielding,
 };
 
 macro_rules! assert_vec_near {
@@ -25,36 +32,25 @@ fn two_agent_simulation() {
   let mut simulator = Simulator::new();
 
   simulator.add_agent(
-    Agent {
+    Agent::builder()
       // Perturb one agent slightly so they can find a reasonable solution.
-      position: Vec3::new(10.0, 0.0, 0.01),
-      velocity: Vec3::ZERO,
-      radius: 1.0,
-      avoidance_responsibility: 1.0,
-    },
-    AgentParameters {
-      goal_point: Vec3::new(-10.0, 0.0, 0.0),
-      max_speed: 2.0,
-      obstacle_margin: SimulatorMargin::AgentRadius,
-      time_horizon: 2.0,
-      obstacle_time_horizon: 1.0,
-    },
+      .position(Vec3::new(10.0, 0.0, 0.01))
+      .radius(1.0)
+      .build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(-10.0, 0.0, 0.0))
+      .max_speed(2.0)
+      .time_horizon(2.0)
+      .build(),
   );
 
   simulator.add_agent(
-    Agent {
-      position: Vec3::new(-10.0, 0.0, 0.0),
-      velocity: Vec3::ZERO,
-      radius: 1.0,
-      avoidance_responsibility: 1.0,
-    },
-    AgentParameters {
-      goal_point: Vec3::new(10.0, 0.0, 0.0),
-      max_speed: 2.0,
-      obstacle_margin: SimulatorMargin::AgentRadius,
-      time_horizon: 2.0,
-      obstacle_time_horizon: 1.0,
-    },
+    Agent::builder().position(Vec3::new(-10.0, 0.0, 0.0)).radius(1.0).build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(10.0, 0.0, 0.0))
+      .max_speed(2.0)
+      .time_horizon(2.0)
+      .build(),
   );
 
   // Test accessors.
@@ -96,4 +92,1953 @@ fn two_agent_simulation() {
     Vec3::new(10.0, 0.0, 0.0),
     1e-4
   );
+  assert_eq!(
+    simulator.get_agent_parameters(0).goal_point,
+    Vec3::new(10.0, 0.0, 0.0)
+  );
+}
+
+#[cfg(feature = "debug")]
+#[test]
+fn only_nearby_agents_are_neighbours() {
+  let mut simulator = Simulator::new();
+
+  for position in
+    [Vec3::ZERO, Vec3::new(3.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 100.0)]
+  {
+    simulator.add_agent(
+      Agent::builder().position(position).radius(1.0).build(),
+      AgentParameters::builder()
+        .goal_point(position)
+        .max_speed(2.0)
+        .time_horizon(2.0)
+        .build(),
+    );
+  }
+
+  assert_eq!(simulator.get_agent_neighbours(0), [1]);
+  assert_eq!(simulator.get_agent_neighbours(1), [0]);
+  assert_eq!(simulator.get_agent_neighbours(2), [] as [usize; 0]);
+}
+
+#[test]
+fn agents_stay_on_terrain() {
+  let mut simulator = Simulator::new();
+  simulator
+    .set_terrain(Some(Box::new(|x: f32, z: f32| (x * 0.1).sin() + z * 0.2)));
+
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(0.0, 0.0, 0.0)).radius(1.0).build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(10.0, 1.0f32.sin() + 2.0, 10.0))
+      .max_speed(2.0)
+      .time_horizon(2.0)
+      .build(),
+  );
+
+  for _ in 0..10 {
+    simulator.step(0.1);
+
+    let position = simulator.get_agent(0).position;
+    assert!(
+      (position.y - ((position.x * 0.1).sin() + position.z * 0.2)).abs() < 1e-5,
+      "agent at {} is not on the ground",
+      position
+    );
+  }
+  // The agent must have made progress along the ground.
+  assert!(simulator.get_agent(0).position.x > 0.5);
+}
+
+#[test]
+fn agents_do_not_pass_through_obstacles() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(-10.0, 0.0, 0.0)).radius(1.0).build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(10.0, 0.0, 0.0))
+      .max_speed(2.0)
+      .time_horizon(2.0)
+      .build(),
+  );
+  // A wide and tall wall between the agent and its goal.
+  simulator.add_obstacle(Obstacle::Closed {
+    vertices: vec![
+      Vec2::new(-1.0, -20.0),
+      Vec2::new(1.0, -20.0),
+      Vec2::new(1.0, 20.0),
+      Vec2::new(-1.0, 20.0),
+    ],
+    bottom: -20.0,
+    top: 20.0,
+  });
+  assert_eq!(simulator.get_obstacle_count(), 1);
+
+  for _ in 0..100 {
+    simulator.step(0.1);
+    let position = simulator.get_agent(0).position;
+    assert!(position.x <= -1.99, "agent entered the wall at {}", position);
+  }
+
+  simulator.remove_obstacle(0);
+  assert_eq!(simulator.get_obstacle_count(), 0);
+}
+
+#[test]
+fn agents_stay_within_boundary() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(0.0, 0.0, 0.0)).radius(1.0).build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(10.0, 0.0, 0.0))
+      .max_speed(2.0)
+      .time_horizon(2.0)
+      .build(),
+  );
+  // The edge of the navigation mesh is at x = 5, with the walkable area at
+  // x < 5.
+  simulator.set_boundary(Some(Box::new(|_position: Vec3, distance: f32| {
+    assert!(distance > 0.0);
+    vec![Obstacle::from_boundary_edges(
+      &[Vec3::new(5.0, 0.0, 10.0), Vec3::new(5.0, 0.0, -10.0)],
+      /* height= */ 1.0,
+    )]
+  })));
+
+  for _ in 0..100 {
+    simulator.step(0.1);
+    let position = simulator.get_agent(0).position;
+    assert!(position.x <= 4.01, "agent left the boundary at {}", position);
+  }
+  // The agent should still get as close as it can to its goal.
+  assert!(simulator.get_agent(0).position.x > 3.5);
+}
+
+#[test]
+fn agents_stay_in_traffic_lane() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(0.0, 35.0, 0.0)).radius(1.0).build(),
+    AgentParameters::builder()
+      // The goal is well above the lane.
+      .goal_point(Vec3::new(10.0, 100.0, 0.0))
+      .max_speed(2.0)
+      .time_horizon(2.0)
+      .traffic_lane(TrafficLane {
+        min_altitude: 30.0,
+        max_altitude: 40.0,
+        direction: Some(Vec3::X),
+      })
+      .build(),
+  );
+
+  for _ in 0..100 {
+    simulator.step(0.1);
+    let position = simulator.get_agent(0).position;
+    assert!(position.y <= 40.0 + 1e-3, "agent left the lane at {}", position);
+  }
+  assert!(simulator.get_agent(0).position.x > 0.5);
+}
+
+#[test]
+fn archetypes_update_all_members() {
+  let soldier =
+    AgentParameters::builder().max_speed(2.0).time_horizon(2.0).build();
+  let agent = Agent::builder().radius(1.0).build();
+
+  let mut simulator = Simulator::new();
+  simulator.set_archetype("soldier", soldier.clone());
+  simulator.add_agent_with_archetype(
+    agent.clone(),
+    "soldier",
+    Vec3::new(1.0, 0.0, 0.0),
+  );
+  simulator.add_agent(agent.clone(), soldier.clone());
+  simulator.add_agent(agent, soldier.clone());
+  simulator.set_agent_archetype(2, Some("soldier"));
+
+  assert_eq!(simulator.get_agent_archetype(0), Some("soldier"));
+  assert_eq!(simulator.get_agent_archetype(1), None);
+  assert_eq!(simulator.get_agent_parameters(0).max_speed, 2.0);
+
+  simulator.set_archetype(
+    "soldier",
+    AgentParameters { max_speed: 5.0, ..soldier.clone() },
+  );
+  assert_eq!(simulator.get_archetype("soldier").unwrap().max_speed, 5.0);
+  // Members are updated, but keep their goals.
+  assert_eq!(simulator.get_agent_parameters(0).max_speed, 5.0);
+  assert_eq!(
+    simulator.get_agent_parameters(0).goal_point,
+    Vec3::new(1.0, 0.0, 0.0)
+  );
+  assert_eq!(simulator.get_agent_parameters(2).max_speed, 5.0);
+  // Non-members are untouched.
+  assert_eq!(simulator.get_agent_parameters(1).max_speed, 2.0);
+
+  simulator.remove_agent(0);
+  assert_eq!(simulator.get_agent_archetype(1), Some("soldier"));
+
+  simulator.remove_archetype("soldier");
+  assert_eq!(simulator.get_archetype("soldier"), None);
+  assert_eq!(simulator.get_agent_archetype(1), None);
+  assert_eq!(simulator.get_agent_parameters(1).max_speed, 5.0);
+}
+
+#[test]
+fn tags_select_cohorts_of_agents() {
+  let mut simulator = create_idle_crowd();
+  for agent_index in 0..5 {
+    simulator.add_agent_tag(agent_index, "left");
+  }
+  simulator.add_agent_tag(3, "squad");
+  simulator.add_agent_tag(3, "squad");
+  simulator.add_agent_tag(7, "squad");
+  assert_eq!(simulator.get_agent_tags(3), ["left", "squad"]);
+  assert!(simulator.has_agent_tag(7, "squad"));
+  assert!(!simulator.has_agent_tag(7, "left"));
+  assert_eq!(simulator.iter_tagged_agents("squad").collect::<Vec<_>>(), [3, 7]);
+
+  let goal_point = simulator.get_agent_parameters(7).goal_point;
+  simulator.set_tagged_agent_parameters(
+    "squad",
+    AgentParameters {
+      max_speed: 3.0,
+      ..simulator.get_agent_parameters(0).clone()
+    },
+  );
+  assert_eq!(simulator.get_agent_parameters(3).max_speed, 3.0);
+  assert_eq!(simulator.get_agent_parameters(7).max_speed, 3.0);
+  // Agents keep their goals.
+  assert_eq!(simulator.get_agent_parameters(7).goal_point, goal_point);
+  // Untagged agents are untouched.
+  assert_eq!(simulator.get_agent_parameters(4).max_speed, 1.0);
+
+  simulator.remove_tagged_agents("left");
+  assert_eq!(simulator.get_agent_count(), 6);
+  assert_eq!(simulator.iter_tagged_agents("left").count(), 0);
+  assert_eq!(simulator.iter_tagged_agents("squad").collect::<Vec<_>>(), [2]);
+  assert_eq!(simulator.get_agent(0).position, Vec3::new(15.0, 0.0, 0.0));
+
+  simulator.remove_agent_tag(2, "squad");
+  assert!(simulator.get_agent_tags(2).is_empty());
+}
+
+#[test]
+fn streams_agents_in_and_out_in_bulk() {
+  let mut simulator = create_idle_crowd();
+  let agent = |position| Agent::builder().position(position).build();
+  let base_parameters = simulator.get_agent_parameters(0).clone();
+  let parameters = |leader_following| AgentParameters {
+    leader_following,
+    ..base_parameters.clone()
+  };
+  let follow = |leader_index| {
+    Some(LeaderFollowing {
+      leader_index,
+      options: FollowOptions { distance: 2.0, angle: 0.0 },
+    })
+  };
+  let chunk = vec![
+    (agent(Vec3::new(100.0, 0.0, 0.0)), parameters(None)),
+    (agent(Vec3::new(103.0, 0.0, 0.0)), parameters(follow(11))),
+    // Follows an agent outside the chunk.
+    (agent(Vec3::new(106.0, 0.0, 0.0)), parameters(follow(9))),
+  ];
+  assert_eq!(simulator.add_agents_bulk(&chunk), 11..14);
+  assert_eq!(simulator.get_agent_count(), 14);
+  assert_eq!(simulator.get_agent(12).position, Vec3::new(103.0, 0.0, 0.0));
+  simulator.add_agent_tag(13, "streamed");
+  // Followers of removed agents stop following.
+  simulator.get_agent_parameters_mut(2).leader_following = follow(1);
+
+  let removed = simulator.remove_agents_in_aabb(
+    Vec3::new(-1.0, -1.0, -1.0),
+    Vec3::new(3.0, 1.0, 1.0),
+  );
+  assert_eq!(
+    removed.iter().map(|(agent, _)| agent.position).collect::<Vec<_>>(),
+    [Vec3::ZERO, Vec3::new(3.0, 0.0, 0.0)]
+  );
+  assert_eq!(simulator.get_agent_count(), 12);
+  assert_eq!(simulator.get_agent(0).position, Vec3::new(6.0, 0.0, 0.0));
+  assert_eq!(simulator.get_agent_parameters(0).leader_following, None);
+  assert_eq!(simulator.get_agent_parameters(10), &parameters(follow(9)));
+  assert_eq!(simulator.get_agent_parameters(11), &parameters(follow(7)));
+  assert_eq!(
+    simulator.iter_tagged_agents("streamed").collect::<Vec<_>>(),
+    [11]
+  );
+
+  // Unloading the chunk and loading it back restores the agents.
+  let chunk = simulator.remove_agents_in_aabb(
+    Vec3::new(99.0, -1.0, -1.0),
+    Vec3::new(107.0, 1.0, 1.0),
+  );
+  assert_eq!(chunk.len(), 3);
+  assert_eq!(simulator.get_agent_count(), 9);
+  assert_eq!(simulator.add_agents_bulk(&chunk), 9..12);
+  assert_eq!(simulator.get_agent_parameters(11), &parameters(follow(7)));
+  simulator.step(0.1);
+}
+
+#[test]
+fn split_and_merge_move_agents_between_simulators() {
+  let mut simulator = create_idle_crowd();
+  simulator.add_agent_tag(5, "squad");
+  simulator.set_agent_importance(6, Importance::Low);
+  let follow = |leader_index| {
+    Some(LeaderFollowing {
+      leader_index,
+      options: FollowOptions { distance: 2.0, angle: 0.0 },
+    })
+  };
+  // One follower has its leader split off with it, the other does not.
+  simulator.get_agent_parameters_mut(6).leader_following = follow(5);
+  simulator.get_agent_parameters_mut(2).leader_following = follow(5);
+
+  let (mut split, agent_indices) =
+    simulator.split(Vec3::new(14.0, -1.0, -1.0), Vec3::new(19.0, 1.0, 1.0));
+  assert_eq!(agent_indices[4], SplitAgentIndex::Kept(4));
+  assert_eq!(agent_indices[5], SplitAgentIndex::Moved(0));
+  assert_eq!(agent_indices[6], SplitAgentIndex::Moved(1));
+  assert_eq!(agent_indices[7], SplitAgentIndex::Kept(5));
+  assert_eq!(simulator.get_agent_count(), 9);
+  assert_eq!(split.get_agent_count(), 2);
+  assert_eq!(split.get_agent(0).position, Vec3::new(15.0, 0.0, 0.0));
+  assert_eq!(split.get_agent_tags(0), ["squad"]);
+  assert_eq!(split.get_agent_importance(1), Importance::Low);
+  assert_eq!(split.get_agent_parameters(1).leader_following, follow(0));
+  assert_eq!(simulator.get_agent_parameters(2).leader_following, None);
+
+  split.step(0.1);
+  simulator.step(0.1);
+
+  assert_eq!(simulator.merge(split), 9..11);
+  assert_eq!(simulator.get_agent_count(), 11);
+  assert_eq!(simulator.iter_tagged_agents("squad").collect::<Vec<_>>(), [9]);
+  assert_eq!(simulator.get_agent_importance(10), Importance::Low);
+  assert_eq!(simulator.get_agent_parameters(10).leader_following, follow(9));
+  simulator.step(0.1);
+}
+
+#[test]
+fn bulk_parameter_updates_keep_each_agents_leader() {
+  let mut simulator = Simulator::new();
+  simulator.set_archetype("walker", AgentParameters::builder().build());
+  simulator.add_agent_with_archetype(
+    Agent::builder().build(),
+    "walker",
+    Vec3::X,
+  );
+  let follower = AgentParameters::builder()
+    .leader_following(LeaderFollowing {
+      leader_index: 0,
+      options: FollowOptions { distance: 1.0, angle: 0.0 },
+    })
+    .build();
+  simulator.add_agent(Agent::builder().build(), follower.clone());
+  simulator.set_agent_archetype(1, Some("walker"));
+  simulator.add_agent_tag(1, "squad");
+  assert_eq!(simulator.get_agent_parameters(1), &follower);
+
+  // An archetype's leader is never applied to its members.
+  let runner = AgentParameters {
+    leader_following: Some(LeaderFollowing {
+      leader_index: 5,
+      options: FollowOptions { distance: 1.0, angle: 0.0 },
+    }),
+    ..AgentParameters::builder().max_speed(2.0).build()
+  };
+  simulator.set_archetype("walker", runner.clone());
+  simulator.set_tagged_agent_parameters("squad", runner.clone());
+  simulator.add_agent_with_archetype(
+    Agent::builder().build(),
+    "walker",
+    Vec3::Z,
+  );
+  assert_eq!(simulator.get_agent_parameters(0).leader_following, None);
+  assert_eq!(
+    simulator.get_agent_parameters(1).leader_following,
+    follower.leader_following
+  );
+  assert_eq!(simulator.get_agent_parameters(1).max_speed, 2.0);
+  assert_eq!(simulator.get_agent_parameters(2).leader_following, None);
+  simulator.step(0.1);
+}
+
+#[test]
+#[should_panic = "leader_index must be less than the agent count (1), was 1"]
+fn following_a_missing_leader_panics() {
+  let mut simulator = Simulator::new();
+  simulator
+    .add_agent(Agent::builder().build(), AgentParameters::builder().build());
+  simulator.set_agent_parameters(
+    0,
+    AgentParameters::builder()
+      .leader_following(LeaderFollowing {
+        leader_index: 1,
+        options: FollowOptions { distance: 1.0, angle: 0.0 },
+      })
+      .build(),
+    /* blend_seconds= */ 1.0,
+  );
+}
+
+#[test]
+#[should_panic = "agent 0 cannot follow itself"]
+fn following_itself_panics() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent(
+    Agent::builder().build(),
+    AgentParameters::builder()
+      .leader_following(LeaderFollowing {
+        leader_index: 0,
+        options: FollowOptions { distance: 1.0, angle: 0.0 },
+      })
+      .build(),
+  );
+}
+
+#[test]
+#[should_panic]
+fn unknown_archetype_panics() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent_with_archetype(
+    Agent::builder().radius(1.0).build(),
+    "tank",
+    Vec3::ZERO,
+  );
+}
+
+#[test]
+fn parameters_blend_over_time() {
+  let walk =
+    AgentParameters::builder().max_speed(2.0).time_horizon(2.0).build();
+  let combat = AgentParameters::builder()
+    .goal_point(Vec3::new(5.0, 0.0, 0.0))
+    .max_speed(4.0)
+    .obstacle_margin(SimulatorMargin::Distance(0.0))
+    .time_horizon(1.0)
+    .obstacle_time_horizon(2.0)
+    .build();
+
+  let mut simulator = Simulator::new();
+  simulator.add_agent(Agent::builder().radius(1.0).build(), walk.clone());
+
+  simulator.set_agent_parameters(
+    0,
+    combat.clone(),
+    /* blend_seconds= */ 1.0,
+  );
+  // The goal point changes immediately, but nothing else.
+  let parameters = simulator.get_agent_parameters(0);
+  assert_eq!(parameters.goal_point, combat.goal_point);
+  assert_eq!(parameters.max_speed, 2.0);
+
+  simulator.step(0.25);
+  let parameters = simulator.get_agent_parameters(0);
+  assert!((parameters.max_speed - 2.5).abs() < 1e-5);
+  assert!((parameters.time_horizon - 1.75).abs() < 1e-5);
+  assert!((parameters.obstacle_time_horizon - 1.25).abs() < 1e-5);
+  assert_eq!(parameters.obstacle_margin, SimulatorMargin::Distance(0.75));
+
+  for _ in 0..3 {
+    simulator.step(0.25);
+  }
+  assert_eq!(simulator.get_agent_parameters(0), &combat);
+
+  // Without blending, parameters are applied immediately.
+  simulator.set_agent_parameters(
+    0,
+    walk.clone(),
+    /* blend_seconds= */ 0.0,
+  );
+  assert_eq!(simulator.get_agent_parameters(0), &walk);
+}
+
+#[test]
+fn urgency_blends_from_the_current_value() {
+  let calm = AgentParameters::builder().time_horizon(1.0).urgency(0.2).build();
+  let hurried = AgentParameters { urgency: 1.0, ..calm.clone() };
+
+  let mut simulator = Simulator::new();
+  simulator.add_agent(Agent::builder().radius(1.0).build(), calm);
+
+  simulator.set_agent_parameters(
+    0,
+    hurried.clone(),
+    /* blend_seconds= */ 1.0,
+  );
+  // The urgency starts from the agent's current urgency, not the new one.
+  assert_eq!(simulator.get_agent_parameters(0).urgency, 0.2);
+
+  simulator.step(0.5);
+  assert!((simulator.get_agent_parameters(0).urgency - 0.6).abs() < 1e-5);
+
+  simulator.step(0.5);
+  assert_eq!(simulator.get_agent_parameters(0), &hurried);
+}
+
+#[test]
+fn margin_curves_are_sampled() {
+  let curve = MarginCurve { points: vec![(1.0, 0.5), (3.0, 1.5)] };
+  assert_eq!(curve.sample(0.0), 0.5);
+  assert_eq!(curve.sample(2.0), 1.0);
+  assert_eq!(curve.sample(5.0), 1.5);
+
+  let agent =
+    Agent::builder().velocity(Vec3::new(2.0, 0.0, 0.0)).radius(1.0).build();
+  assert_eq!(
+    SimulatorMargin::SpeedCurve(curve.clone()).resolve(&agent, || 0.0),
+    1.0
+  );
+  assert_eq!(
+    SimulatorMargin::ObstacleDistanceCurve(curve).resolve(&agent, || 3.0),
+    1.5
+  );
+}
+
+#[test]
+fn obstacle_distance_margin_shrinks_near_walls() {
+  let mut simulator = Simulator::new();
+  simulator.add_obstacle(Obstacle::Open {
+    vertices: vec![Vec2::new(-10.0, 0.0), Vec2::new(10.0, 0.0)],
+    bottom: -10.0,
+    top: 10.0,
+  });
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(0.0, 0.0, -10.0)).radius(1.0).build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(0.0, 0.0, 10.0))
+      // Keep a large margin far from the wall, but allow getting close once
+      // near the wall.
+      .obstacle_margin(SimulatorMargin::ObstacleDistanceCurve(MarginCurve {
+        points: vec![(0.5, 0.5), (5.0, 3.0)],
+      }))
+      .time_horizon(1.0)
+      .build(),
+  );
+
+  for _ in 0..200 {
+    simulator.step(0.1);
+  }
+  let z = simulator.get_agent(0).position.z;
+  // The agent gets closer than the far-field margin, but never touches the
+  // wall.
+  assert!(z > -3.0, "{}", z);
+  assert!(z < 0.0, "{}", z);
+}
+
+#[test]
+fn agents_avoid_provided_neighbours() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(-5.0, 0.0, 0.0)).radius(1.0).build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(5.0, 0.0, 0.0))
+      .time_horizon(2.0)
+      .build(),
+  );
+  // A stationary agent (e.g. a player) managed elsewhere blocks the way.
+  let player =
+    Agent::builder().position(Vec3::new(0.0, 0.1, 0.0)).radius(1.0).build();
+  simulator.add_neighbour_provider(Box::new(
+    move |position: Vec3, distance| {
+      if player.position.distance(position) <= distance {
+        vec![player.clone()]
+      } else {
+        vec![]
+      }
+    },
+  ));
+  assert_eq!(simulator.get_neighbour_provider_count(), 1);
+
+  for _ in 0..200 {
+    simulator.step(0.1);
+    let position = simulator.get_agent(0).position;
+    assert!(position.distance(Vec3::new(0.0, 0.1, 0.0)) > 1.9, "{}", position);
+  }
+  assert!(simulator.get_agent(0).position.x > 0.0);
+
+  simulator.remove_neighbour_provider(0);
+  assert_eq!(simulator.get_neighbour_provider_count(), 0);
+}
+
+#[test]
+fn budgeted_step_matches_step() {
+  let create_simulator = || {
+    let mut simulator = Simulator::new();
+    for (position, goal_point) in [
+      (Vec3::new(-5.0, 0.0, 0.0), Vec3::new(5.0, 0.0, 0.0)),
+      (Vec3::new(5.0, 0.0, 0.1), Vec3::new(-5.0, 0.0, 0.1)),
+      (Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 5.0)),
+    ] {
+      simulator.add_agent(
+        Agent::builder().position(position).radius(1.0).build(),
+        AgentParameters::builder()
+          .goal_point(goal_point)
+          .time_horizon(2.0)
+          .build(),
+      );
+    }
+    simulator
+  };
+
+  let mut stepped = create_simulator();
+  let mut budgeted = create_simulator();
+  for _ in 0..10 {
+    let start_position = stepped.get_agent(0).position;
+    stepped.step(0.1);
+    // A zero budget processes a single agent per call, and agents only move
+    // once the step is complete.
+    assert!(!budgeted.step_with_budget(0.1, Duration::ZERO));
+    assert_eq!(budgeted.get_agent(0).position, start_position);
+    assert!(!budgeted.step_with_budget(0.1, Duration::ZERO));
+    assert!(budgeted.step_with_budget(0.1, Duration::ZERO));
+    for index in 0..3 {
+      assert_eq!(budgeted.get_agent(index), stepped.get_agent(index));
+    }
+  }
+
+  // A large budget completes the step in one call.
+  stepped.step(0.1);
+  assert!(budgeted.step_with_budget(0.1, Duration::from_secs(60)));
+  for index in 0..3 {
+    assert_eq!(budgeted.get_agent(index), stepped.get_agent(index));
+  }
+}
+
+#[test]
+fn snapshot_is_state_after_last_step() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent(
+    Agent::builder().radius(1.0).build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(10.0, 0.0, 0.0))
+      .time_horizon(1.0)
+      .build(),
+  );
+  assert_eq!(simulator.get_snapshot().get_agent_count(), 0);
+
+  simulator.step(1.0);
+  let first_snapshot = simulator.get_snapshot();
+  assert_eq!(first_snapshot.get_step_count(), 1);
+  assert_eq!(first_snapshot.get_agent(0), simulator.get_agent(0));
+
+  // The held snapshot is unaffected by later steps.
+  simulator.step(1.0);
+  assert_eq!(first_snapshot.get_agent(0).position, Vec3::new(1.0, 0.0, 0.0));
+  let second_snapshot = simulator.get_snapshot();
+  assert_eq!(second_snapshot.get_step_count(), 2);
+  assert_eq!(second_snapshot.get_agent(0).position, Vec3::new(2.0, 0.0, 0.0));
+
+  // Partially completed steps are not visible.
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(0.0, 0.0, 5.0)).radius(1.0).build(),
+    simulator.get_agent_parameters(0).clone(),
+  );
+  assert!(!simulator.step_with_budget(1.0, Duration::ZERO));
+  assert_eq!(simulator.get_snapshot(), second_snapshot);
+}
+
+#[test]
+fn simulator_can_be_stepped_from_threads() {
+  fn assert_send_sync<T: Send + Sync>() {}
+  assert_send_sync::<Simulator>();
+
+  let create_simulator = || {
+    let mut simulator = Simulator::new();
+    for index in 0..8 {
+      let angle = index as f32 / 8.0 * std::f32::consts::TAU;
+      let position = Vec3::new(angle.cos(), 0.0, angle.sin()) * 5.0;
+      simulator.add_agent(
+        Agent::builder().position(position).build(),
+        AgentParameters::builder()
+          .goal_point(-position)
+          .time_horizon(2.0)
+          .build(),
+      );
+    }
+    simulator
+  };
+
+  let mut stepped = create_simulator();
+  let mut threaded = create_simulator();
+  for _ in 0..20 {
+    stepped.step(0.1);
+
+    threaded.begin_step(0.1);
+    let new_velocities = std::thread::scope(|scope| {
+      let threaded = &threaded;
+      let handles = (0..threaded.get_agent_count())
+        .map(|index| {
+          scope.spawn(move || threaded.compute_agent_velocity(index, 0.1))
+        })
+        .collect::<Vec<_>>();
+      handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>()
+    });
+    threaded.end_step(new_velocities, 0.1);
+  }
+
+  for index in 0..8 {
+    assert_eq!(threaded.get_agent(index), stepped.get_agent(index));
+  }
+}
+
+#[test]
+fn reused_scratch_gives_same_velocities() {
+  let create_simulator = || {
+    let mut simulator = Simulator::new();
+    for index in 0..8 {
+      let angle = index as f32 / 8.0 * std::f32::consts::TAU;
+      let position = Vec3::new(angle.cos(), 0.0, angle.sin()) * 5.0;
+      simulator.add_agent(
+        Agent::builder().position(position).build(),
+        AgentParameters::builder()
+          .goal_point(-position)
+          .time_horizon(2.0)
+          .build(),
+      );
+    }
+    // An obstacle in the middle, so the obstacle buffers get used as well.
+    simulator.add_obstacle(Obstacle::Closed {
+      vertices: vec![
+        Vec2::new(-1.0, -1.0),
+        Vec2::new(1.0, -1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(-1.0, 1.0),
+      ],
+      bottom: -1.0,
+      top: 1.0,
+    });
+    simulator
+  };
+
+  let mut stepped = create_simulator();
+  let mut manual = create_simulator();
+  let mut scratch = StepScratch::default();
+  for _ in 0..20 {
+    stepped.step(0.1);
+
+    manual.begin_step(0.1);
+    let new_velocities = (0..manual.get_agent_count())
+      .map(|index| {
+        manual.compute_agent_velocity_with_scratch(index, 0.1, &mut scratch)
+      })
+      .collect::<Vec<_>>();
+    manual.end_step(new_velocities, 0.1);
+  }
+
+  for index in 0..8 {
+    assert_eq!(manual.get_agent(index), stepped.get_agent(index));
+  }
+}
+
+#[test]
+fn cloned_simulator_can_be_rolled_back() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent(
+    Agent::builder().radius(1.0).build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(10.0, 0.0, 0.0))
+      .time_horizon(1.0)
+      .build(),
+  );
+
+  let saved = simulator.clone();
+  simulator.step(1.0);
+  simulator.get_agent_parameters_mut(0).max_speed = 2.0;
+  simulator.step(1.0);
+  assert_eq!(simulator.get_agent(0).position, Vec3::new(3.0, 0.0, 0.0));
+
+  // The clone is unaffected, and can be re-simulated.
+  assert_eq!(saved.get_agent(0).position, Vec3::ZERO);
+  assert_eq!(saved.get_agent_parameters(0).max_speed, 1.0);
+  let mut resimulated = saved.clone();
+  resimulated.step(1.0);
+  assert_eq!(resimulated.get_agent(0).position, Vec3::new(1.0, 0.0, 0.0));
+}
+
+#[test]
+fn quantized_agents_are_snapped() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent(
+    Agent::builder()
+      .position(Vec3::new(0.3, 0.0, 0.0))
+      .velocity(Vec3::new(0.6, 0.0, 0.0))
+      .radius(1.0)
+      .build(),
+    AgentParameters::builder().time_horizon(1.0).build(),
+  );
+
+  let quantizer = Quantizer { min: -8.0, max: 8.0, precision: 0.25 };
+  simulator.quantize_agents(&AgentQuantization {
+    position: quantizer,
+    velocity: quantizer,
+  });
+  assert_eq!(simulator.get_agent(0).position, Vec3::new(0.25, 0.0, 0.0));
+  assert_eq!(simulator.get_agent(0).velocity, Vec3::new(0.5, 0.0, 0.0));
+}
+
+#[test]
+fn moved_obstacles_are_avoided() {
+  let closed_door = Obstacle::Open {
+    vertices: vec![Vec2::new(-10.0, 0.0), Vec2::new(10.0, 0.0)],
+    bottom: -10.0,
+    top: 10.0,
+  };
+  // The same wall, slid far to the side.
+  let open_door = Obstacle::Open {
+    vertices: vec![Vec2::new(20.0, 0.0), Vec2::new(40.0, 0.0)],
+    bottom: -10.0,
+    top: 10.0,
+  };
+
+  let mut simulator = Simulator::new();
+  simulator.add_obstacle(open_door.clone());
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(0.0, 0.0, -5.0)).radius(1.0).build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(0.0, 0.0, 5.0))
+      .time_horizon(1.0)
+      .build(),
+  );
+
+  simulator.set_obstacle(0, closed_door.clone());
+  assert_eq!(simulator.get_obstacle(0), &closed_door);
+  for _ in 0..50 {
+    simulator.step(0.1);
+  }
+  let z = simulator.get_agent(0).position.z;
+  assert!(z < -0.9, "{}", z);
+
+  simulator.set_obstacle(0, open_door);
+  for _ in 0..100 {
+    simulator.step(0.1);
+  }
+  let z = simulator.get_agent(0).position.z;
+  assert!(z > 4.0, "{}", z);
+}
+
+#[test]
+fn closed_doors_block_agents() {
+  let mut simulator = Simulator::new();
+  simulator.add_door(
+    Obstacle::Open {
+      vertices: vec![Vec2::new(-10.0, 0.0), Vec2::new(10.0, 0.0)],
+      bottom: -10.0,
+      top: 10.0,
+    },
+    /* open= */ false,
+  );
+  simulator.add_obstacle(Obstacle::Open {
+    vertices: vec![Vec2::new(-10.0, 50.0), Vec2::new(10.0, 50.0)],
+    bottom: -10.0,
+    top: 10.0,
+  });
+  assert_eq!(simulator.is_door_open(0), Some(false));
+  assert_eq!(simulator.is_door_open(1), None);
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(0.0, 0.0, -5.0)).radius(1.0).build(),
+    AgentParameters::builder()
+      .goal_point(Vec3::new(0.0, 0.0, 5.0))
+      .time_horizon(1.0)
+      .build(),
+  );
+
+  for _ in 0..50 {
+    simulator.step(0.1);
+  }
+  let z = simulator.get_agent(0).position.z;
+  assert!(z < -0.9, "{}", z);
+
+  // Opening the door during a budgeted step recomputes the velocity of the
+  // agent near the door.
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(100.0, 0.0, 0.0)).radius(1.0).build(),
+    simulator.get_agent_parameters(0).clone(),
+  );
+  assert!(!simulator.step_with_budget(0.1, Duration::ZERO));
+  simulator.set_door_open(0, true);
+  assert_eq!(simulator.is_door_open(0), Some(true));
+  while !simulator.step_with_budget(0.1, Duration::ZERO) {}
+  assert!(simulator.get_agent(0).velocity.z > 0.5);
+
+  for _ in 0..100 {
+    simulator.step(0.1);
+  }
+  let z = simulator.get_agent(0).position.z;
+  assert!(z > 4.0, "{}", z);
+}
+
+#[test]
+#[should_panic]
+fn only_doors_can_open() {
+  let mut simulator = Simulator::new();
+  simulator.add_obstacle(Obstacle::Open {
+    vertices: vec![Vec2::new(-10.0, 0.0), Vec2::new(10.0, 0.0)],
+    bottom: -10.0,
+    top: 10.0,
+  });
+  simulator.set_door_open(0, true);
+}
+
+#[test]
+fn one_way_obstacles_block_one_direction() {
+  let create_simulator = |start: Vec3, goal_point: Vec3| {
+    let mut simulator = Simulator::new();
+    // A turnstile that can only be walked through towards +Z.
+    simulator.add_one_way_obstacle(
+      Obstacle::Closed {
+        vertices: vec![
+          Vec2::new(-10.0, -0.5),
+          Vec2::new(10.0, -0.5),
+          Vec2::new(10.0, 0.5),
+          Vec2::new(-10.0, 0.5),
+        ],
+        bottom: -10.0,
+        top: 10.0,
+      },
+      Vec3::Z,
+    );
+    simulator.add_agent(
+      Agent::builder().position(start).radius(1.0).build(),
+      AgentParameters::builder()
+        .goal_point(goal_point)
+        .time_horizon(1.0)
+        .build(),
+    );
+    simulator
+  };
+
+  let mut forwards =
+    create_simulator(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 5.0));
+  let mut backwards =
+    create_simulator(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -5.0));
+  for _ in 0..200 {
+    forwards.step(0.1);
+    backwards.step(0.1);
+  }
+
+  let forwards_z = forwards.get_agent(0).position.z;
+  assert!(forwards_z > 4.0, "{}", forwards_z);
+  let backwards_z = backwards.get_agent(0).position.z;
+  assert!(backwards_z > 1.4, "{}", backwards_z);
+}
+
+#[test]
+fn density_counts_nearby_agents() {
+  let mut simulator = Simulator::new();
+  for position in [
+    Vec3::new(0.5, 0.0, 0.0),
+    Vec3::new(-0.5, 0.0, 0.5),
+    Vec3::new(10.0, 0.0, 0.0),
+  ] {
+    simulator.add_agent(
+      Agent::builder().position(position).radius(0.25).build(),
+      AgentParameters::builder().goal_point(position).time_horizon(1.0).build(),
+    );
+  }
+
+  let sphere_volume = 4.0 / 3.0 * std::f32::consts::PI;
+  assert_eq!(simulator.density_at(Vec3::ZERO, 1.0), 2.0 / sphere_volume);
+  assert_eq!(simulator.density_at(Vec3::new(0.0, 5.0, 0.0), 1.0), 0.0);
+
+  let grid = simulator.get_density_grid(
+    Vec3::new(-2.0, -1.0, -2.0),
+    Vec3::new(12.0, 1.0, 2.0),
+    2.0,
+  );
+  let total = grid.densities.iter().sum::<f32>() * 8.0;
+  assert_eq!(total, 3.0);
+  assert_eq!(
+    grid.get_density(grid.get_cell(Vec3::new(10.0, 0.0, 0.0)).unwrap()),
+    1.0 / 8.0
+  );
+}
+
+#[test]
+#[should_panic = "radius must be positive, was 0"]
+fn density_at_panics_for_non_positive_radius() {
+  let simulator = Simulator::new();
+  simulator.density_at(Vec3::ZERO, 0.0);
+}
+
+#[test]
+fn tunes_time_horizons_from_density() {
+  let mut simulator = Simulator::new();
+  // A tight cluster of agents, and a lone agent far away.
+  for position in [
+    Vec3::new(0.0, 0.0, 0.0),
+    Vec3::new(1.0, 0.0, 0.0),
+    Vec3::new(0.0, 0.0, 1.0),
+    Vec3::new(100.0, 0.0, 0.0),
+  ] {
+    simulator.add_agent(
+      Agent::builder().position(position).radius(0.25).build(),
+      AgentParameters::builder().goal_point(position).build(),
+    );
+  }
+
+  let options = TimeHorizonOptions::default();
+  simulator.tune_time_horizons(/* density_radius= */ 2.0, &options);
+
+  let clustered_time_horizon = simulator.get_agent_parameters(0).time_horizon;
+  assert!(
+    clustered_time_horizon < options.max_time_horizon,
+    "{clustered_time_horizon}"
+  );
+  assert_eq!(
+    simulator.get_agent_parameters(1).time_horizon,
+    clustered_time_horizon
+  );
+  assert_eq!(
+    simulator.get_agent_parameters(3).time_horizon,
+    // The lone agent only counts itself, so the density is low.
+    suggest_time_horizon(
+      0.25,
+      1.0,
+      simulator.density_at(Vec3::new(100.0, 0.0, 0.0), 2.0),
+      &options
+    )
+  );
+  assert!(
+    simulator.get_agent_parameters(3).time_horizon > clustered_time_horizon
+  );
+}
+
+#[test]
+fn tuning_time_horizons_skips_blending_agents() {
+  let mut simulator = Simulator::new();
+  for position in [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)] {
+    simulator.add_agent(
+      Agent::builder().position(position).radius(0.25).build(),
+      AgentParameters::builder().goal_point(position).build(),
+    );
+  }
+  simulator.set_agent_parameters(
+    1,
+    AgentParameters::builder()
+      .goal_point(Vec3::new(1.0, 0.0, 0.0))
+      .time_horizon(10.0)
+      .build(),
+    /* blend_seconds= */ 1.0,
+  );
+  let blended_time_horizon = simulator.get_agent_parameters(1).time_horizon;
+
+  let options = TimeHorizonOptions::default();
+  simulator.tune_time_horizons(/* density_radius= */ 2.0, &options);
+
+  assert_ne!(
+    simulator.get_agent_parameters(0).time_horizon,
+    blended_time_horizon
+  );
+  assert_eq!(
+    simulator.get_agent_parameters(1).time_horizon,
+    blended_time_horizon
+  );
+}
+
+#[test]
+fn social_forces_spread_agents_apart() {
+  let run = |social_force: Option<SocialForce>| {
+    let mut simulator = Simulator::new();
+    for z in [-0.6, 0.6] {
+      simulator.add_agent(
+        Agent::builder().position(Vec3::new(0.0, 0.0, z)).build(),
+        AgentParameters {
+          social_force: social_force.clone(),
+          ..AgentParameters::builder()
+            .goal_point(Vec3::new(100.0, 0.0, z))
+            .time_horizon(1.0)
+            .build()
+        },
+      );
+    }
+    for _ in 0..50 {
+      simulator.step(0.1);
+      let distance = simulator
+        .get_agent(0)
+        .position
+        .distance(simulator.get_agent(1).position);
+      assert!(distance >= 1.0 - 1e-3, "agents collided: {distance}");
+    }
+    simulator.get_agent(0).position.distance(simulator.get_agent(1).position)
+  };
+
+  let without_forces = run(None);
+  let with_forces = run(Some(SocialForce { strength: 0.5, range: 0.5 }));
+  assert!((without_forces - 1.2).abs() < 1e-3, "{without_forces}");
+  assert!(with_forces > without_forces + 0.5, "{with_forces}");
+}
+
+#[test]
+fn followers_trail_leader() {
+  let mut simulator = Simulator::new();
+  let agent = |position| Agent::builder().position(position).build();
+  let parameters = |goal_point, leader_following| AgentParameters {
+    goal_point,
+    leader_following,
+    ..AgentParameters::builder().max_speed(2.0).time_horizon(1.0).build()
+  };
+  // An agent that is removed later, to check indices are kept up to date.
+  simulator.add_agent(
+    agent(Vec3::new(0.0, 0.0, 50.0)),
+    parameters(Vec3::new(0.0, 0.0, 50.0), None),
+  );
+  simulator
+    .add_agent(agent(Vec3::ZERO), parameters(Vec3::new(100.0, 0.0, 0.0), None));
+  simulator.add_agent(
+    agent(Vec3::new(5.0, 0.0, 0.0)),
+    parameters(
+      Vec3::ZERO,
+      Some(LeaderFollowing {
+        leader_index: 1,
+        options: FollowOptions { distance: 2.0, angle: 0.0 },
+      }),
+    ),
+  );
+  simulator.remove_agent(0);
+  assert_eq!(
+    simulator
+      .get_agent_parameters(1)
+      .leader_following
+      .as_ref()
+      .unwrap()
+      .leader_index,
+    0
+  );
+
+  // Slow the leader down so the follower can catch up.
+  simulator.get_agent_parameters_mut(0).max_speed = 1.0;
+  for _ in 0..200 {
+    simulator.step(0.1);
+  }
+  let leader = simulator.get_agent(0).position;
+  let follower = simulator.get_agent(1).position;
+  assert!(follower.x < leader.x - 1.0, "{follower} should trail {leader}");
+  assert!((follower.distance(leader) - 2.0).abs() < 0.5, "{follower} {leader}");
+
+  simulator.remove_agent(0);
+  assert_eq!(simulator.get_agent_parameters(0).leader_following, None);
+}
+
+#[test]
+fn removing_agents_during_a_blend_keeps_leaders_up_to_date() {
+  let mut simulator = Simulator::new();
+  for x in [0.0, 5.0, 10.0, 15.0] {
+    simulator.add_agent(
+      Agent::builder().position(Vec3::new(x, 0.0, 0.0)).build(),
+      AgentParameters::builder().build(),
+    );
+  }
+  let follow = |leader_index| {
+    AgentParameters::builder()
+      .leader_following(LeaderFollowing {
+        leader_index,
+        options: FollowOptions { distance: 2.0, angle: 0.0 },
+      })
+      .build()
+  };
+  simulator.set_agent_parameters(0, follow(3), /* blend_seconds= */ 1.0);
+  simulator.set_agent_parameters(1, follow(2), /* blend_seconds= */ 1.0);
+
+  // The leader of agent 1 is removed, and the leader of agent 0 moves down.
+  simulator.remove_agent(2);
+  for _ in 0..15 {
+    simulator.step(0.1);
+  }
+  let leader_following = |agent_index| {
+    simulator
+      .get_agent_parameters(agent_index)
+      .leader_following
+      .as_ref()
+      .map(|following| following.leader_index)
+  };
+  assert_eq!(leader_following(0), Some(2));
+  assert_eq!(leader_following(1), None);
+}
+
+#[test]
+fn agents_queue_at_choke_point() {
+  let mut simulator = Simulator::new();
+  simulator.add_queue(Queue {
+    entrance: Vec3::ZERO,
+    direction: Vec3::X,
+    spacing: 1.5,
+    capture_radius: 10.0,
+  });
+  assert_eq!(simulator.get_queue_count(), 1);
+  for position in [
+    Vec3::new(-2.0, 0.0, 3.0),
+    Vec3::new(-2.0, 0.0, -3.0),
+    Vec3::new(-1.0, 0.0, 0.0),
+  ] {
+    simulator.add_agent(
+      Agent::builder().position(position).build(),
+      AgentParameters::builder()
+        .goal_point(Vec3::new(20.0, 0.0, 0.0))
+        .time_horizon(1.0)
+        .build(),
+    );
+  }
+
+  // While the front agent walks through, the others line up behind the
+  // entrance instead of crowding it.
+  for _ in 0..5 {
+    simulator.step(0.1);
+  }
+  for agent_index in [0, 1] {
+    let position = simulator.get_agent(agent_index).position;
+    assert!(position.x < -1.0 && position.z.abs() < 3.0, "{position}");
+  }
+
+  // Agents go through the entrance one at a time, in the order they lined up.
+  let mut crossing_order = Vec::new();
+  for _ in 0..400 {
+    simulator.step(0.1);
+    for agent_index in 0..3 {
+      if simulator.get_agent(agent_index).position.x > 0.0
+        && !crossing_order.contains(&agent_index)
+      {
+        crossing_order.push(agent_index);
+        if agent_index == 0 {
+          // The next agent in line is still waiting its turn.
+          let position = simulator.get_agent(1).position;
+          assert!(position.x < -1.0, "{position}");
+        }
+      }
+    }
+  }
+  assert_eq!(crossing_order, [2, 0, 1]);
+  for agent_index in 0..3 {
+    let position = simulator.get_agent(agent_index).position;
+    assert!(position.x > 15.0, "{position}");
+  }
+
+  simulator.remove_queue(0);
+  assert_eq!(simulator.get_queue_count(), 0);
+}
+
+#[test]
+fn standing_agents_yield_to_moving_agents() {
+  let run = |yielding: Option<Yielding>| {
+    let mut simulator = Simulator::new();
+    let agent = |position| Agent::builder().position(position).build();
+    let parameters = |goal_point, yielding| AgentParameters {
+      goal_point,
+      yielding,
+      ..AgentParameters::builder().time_horizon(1.0).build()
+    };
+    simulator.add_agent(
+      agent(Vec3::new(-5.0, 0.0, 0.0)),
+      parameters(Vec3::new(5.0, 0.0, 0.0), None),
+    );
+    // A standing agent right in the moving agent's path.
+    simulator.add_agent(agent(Vec3::ZERO), parameters(Vec3::ZERO, yielding));
+
+    let mut max_displacement = 0.0f32;
+    for _ in 0..150 {
+      simulator.step(0.1);
+      max_displacement =
+        max_displacement.max(simulator.get_agent(1).position.length());
+    }
+    (
+      simulator.get_agent(0).position,
+      max_displacement,
+      simulator.get_agent(1).position,
+    )
+  };
+
+  // Without yielding, the moving agent shoves the standing agent out of its
+  // spot.
+  let (_, _, final_position) = run(None);
+  assert!(final_position.length() > 1.0, "{final_position}");
+
+  // With yielding, the standing agent steps aside just far enough and returns
+  // to its spot afterwards.
+  let (moving_position, max_displacement, final_position) =
+    run(Some(Yielding {
+      speed: 1.0,
+      look_ahead_time: 3.0,
+      standing_distance: 2.0,
+    }));
+  assert!(moving_position.x > 4.5, "{moving_position}");
+  assert!(max_displacement < 2.0, "{max_displacement}");
+  assert!(final_position.length() < 0.1, "{final_position}");
+}
+
+#[test]
+fn urgent_agents_keep_less_distance() {
+  let run = |urgency: f32| {
+    let mut simulator = Simulator::new();
+    for (position, goal_point) in [
+      (Vec3::new(-5.0, 0.0, 0.1), Vec3::new(5.0, 0.0, 0.1)),
+      (Vec3::new(5.0, 0.0, -0.1), Vec3::new(-5.0, 0.0, -0.1)),
+    ] {
+      simulator.add_agent(
+        Agent::builder().position(position).build(),
+        AgentParameters::builder()
+          .goal_point(goal_point)
+          .time_horizon(4.0)
+          .urgency(urgency)
+          .build(),
+      );
+    }
+    let mut min_distance = f32::INFINITY;
+    let mut min_time_to_pass = None;
+    for step in 0..200 {
+      simulator.step(0.1);
+      let left = simulator.get_agent(0).position;
+      let right = simulator.get_agent(1).position;
+      min_distance = min_distance.min(left.distance(right));
+      if min_time_to_pass.is_none() && left.x > right.x {
+        min_time_to_pass = Some(step);
+      }
+    }
+    (min_distance, min_time_to_pass.unwrap())
+  };
+
+  let (calm_distance, calm_time_to_pass) = run(0.0);
+  let (urgent_distance, urgent_time_to_pass) = run(1.0);
+  assert!(calm_distance > 0.99, "{calm_distance}");
+  assert!(urgent_distance < calm_distance, "{urgent_distance} {calm_distance}");
+  assert!(
+    urgent_time_to_pass <= calm_time_to_pass,
+    "{urgent_time_to_pass} {calm_time_to_pass}"
+  );
+}
+
+#[test]
+fn radius_changes_smoothly() {
+  let mut simulator = Simulator::new();
+  for position in [Vec3::ZERO, Vec3::new(1.5, 0.0, 0.0)] {
+    simulator.add_agent(
+      Agent::builder().position(position).build(),
+      AgentParameters::builder()
+        .goal_point(position)
+        .max_speed(2.0)
+        .time_horizon(1.0)
+        .build(),
+    );
+  }
+
+  simulator.set_agent_target_radius(0, 1.5, 0.5);
+  assert_eq!(simulator.get_agent_target_radius(0), 1.5);
+  assert_eq!(simulator.get_agent_target_radius(1), 0.5);
+
+  let mut previous_radius = 0.5;
+  for _ in 0..40 {
+    simulator.step(0.1);
+    let radius = simulator.get_agent(0).radius;
+    assert!((radius - previous_radius - 0.05).abs() < 1e-4 || radius == 1.5);
+    previous_radius = radius;
+  }
+  assert_eq!(simulator.get_agent(0).radius, 1.5);
+  assert_eq!(simulator.get_agent_target_radius(0), 1.5);
+  // The neighbour moved out of the way as the agent grew.
+  let distance =
+    simulator.get_agent(0).position.distance(simulator.get_agent(1).position);
+  assert!(distance > 1.9, "{distance}");
+
+  // Shrinking works too.
+  simulator.set_agent_target_radius(0, 0.25, 10.0);
+  simulator.step(0.1);
+  assert_eq!(simulator.get_agent(0).radius, 0.5);
+  simulator.step(0.1);
+  assert_eq!(simulator.get_agent(0).radius, 0.25);
+}
+
+#[test]
+#[should_panic = "rate must be positive, was 0"]
+fn target_radius_panics_for_non_positive_rate() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent(
+    Agent::builder().build(),
+    AgentParameters::builder().time_horizon(1.0).build(),
+  );
+  simulator.set_agent_target_radius(0, 1.0, 0.0);
+}
+
+#[test]
+fn ground_and_flying_agents_mix() {
+  let mut simulator = Simulator::new();
+  let agent = |position| Agent::builder().position(position).build();
+  let parameters = |goal_point, standing_height| AgentParameters {
+    goal_point,
+    standing_height,
+    ..AgentParameters::builder().time_horizon(2.0).build()
+  };
+  // A ground agent walking under a high flyer going the other way.
+  simulator.add_agent(
+    agent(Vec3::ZERO),
+    parameters(Vec3::new(10.0, 5.0, 0.0), Some(2.0)),
+  );
+  simulator.add_agent(
+    agent(Vec3::new(10.0, 5.0, 0.0)),
+    parameters(Vec3::new(0.0, 5.0, 0.0), None),
+  );
+  // A standing ground agent, and a flyer heading for its head.
+  simulator.add_agent(
+    agent(Vec3::new(0.0, 0.0, 10.0)),
+    parameters(Vec3::new(0.0, 0.0, 10.0), Some(2.0)),
+  );
+  simulator.add_agent(
+    agent(Vec3::new(-5.0, 1.5, 10.0)),
+    parameters(Vec3::new(5.0, 1.5, 10.0), None),
+  );
+
+  for _ in 0..150 {
+    simulator.step(0.1);
+    // The walking ground agent stays on the ground and ignores the flyer.
+    let walker = simulator.get_agent(0).position;
+    assert_eq!(walker.y, 0.0);
+    assert!(walker.z.abs() < 1e-4, "{walker}");
+
+    let standing = simulator.get_agent(2).position;
+    let flyer = simulator.get_agent(3).position;
+    let horizontal_distance =
+      Vec2::new(flyer.x - standing.x, flyer.z - standing.z).length();
+    assert!(
+      horizontal_distance > 0.99 || flyer.y > standing.y + 2.0 + 0.99,
+      "{flyer} flew through {standing}"
+    );
+  }
+  assert!(simulator.get_agent(0).position.x > 9.9);
+  assert!(simulator.get_agent(3).position.x > 4.0);
+}
+
+#[test]
+fn locked_axes_keep_agents_at_altitude() {
+  let mut simulator = Simulator::new();
+  let agent = |position| Agent::builder().position(position).build();
+  let parameters = |goal_point| {
+    AgentParameters::builder()
+      .goal_point(goal_point)
+      .time_horizon(2.0)
+      .locked_axes(BVec3::new(false, true, false))
+      .build()
+  };
+  // Nearly head-on flyers at slightly different altitudes, which would
+  // normally dodge vertically.
+  simulator.add_agent(
+    agent(Vec3::new(0.0, 5.0, 0.0)),
+    parameters(Vec3::new(10.0, 6.0, 0.0)),
+  );
+  simulator.add_agent(
+    agent(Vec3::new(10.0, 5.1, 0.1)),
+    parameters(Vec3::new(0.0, 4.0, 0.1)),
+  );
+
+  for _ in 0..150 {
+    simulator.step(0.1);
+    let first = simulator.get_agent(0).position;
+    let second = simulator.get_agent(1).position;
+    assert_eq!(first.y, 5.0);
+    assert_eq!(second.y, 5.1);
+    assert!(first.distance(second) > 0.98, "{first} {second}");
+  }
+  assert!(simulator.get_agent(0).position.x > 9.5);
+  assert!(simulator.get_agent(1).position.x < 0.5);
+}
+
+#[test]
+fn ground_agents_never_dodge_vertically() {
+  let mut simulator = Simulator::new();
+  let agent = |position| Agent::builder().position(position).build();
+  let parameters = |goal_point, standing_height| AgentParameters {
+    goal_point,
+    standing_height,
+    ..AgentParameters::builder().time_horizon(2.0).build()
+  };
+  // A pair of flyers approaching head-on, slightly offset vertically so that
+  // the easiest dodge is flying over each other.
+  simulator.add_agent(
+    agent(Vec3::new(0.0, 0.0, 0.0)),
+    parameters(Vec3::new(10.0, 0.0, 0.0), None),
+  );
+  simulator.add_agent(
+    agent(Vec3::new(10.0, 0.1, 0.0)),
+    parameters(Vec3::new(0.0, 0.1, 0.0), None),
+  );
+  // Two uneven groups of ground agents at different heights crossing
+  // head-on, so the avoidance is not symmetric.
+  for (start, goal) in [
+    (Vec3::new(0.0, 0.0, 10.0), Vec3::new(10.0, 0.0, 10.3)),
+    (Vec3::new(0.5, 0.0, 11.1), Vec3::new(10.0, 0.0, 11.0)),
+    (Vec3::new(-0.7, 0.0, 12.3), Vec3::new(9.0, 0.0, 12.0)),
+    (Vec3::new(10.0, 0.3, 10.4), Vec3::new(0.0, 0.3, 10.0)),
+    (Vec3::new(9.2, 0.3, 11.6), Vec3::new(0.0, 0.3, 11.9)),
+    (Vec3::new(11.0, 0.3, 12.5), Vec3::new(1.0, 0.3, 12.5)),
+  ] {
+    simulator.add_agent(agent(start), parameters(goal, Some(1.8)));
+  }
+
+  let mut max_flyer_height = 0.0f32;
+  for _ in 0..150 {
+    simulator.step(0.1);
+    max_flyer_height = max_flyer_height.max(simulator.get_agent(1).position.y);
+    for agent_index in 2..8 {
+      let agent = simulator.get_agent(agent_index);
+      assert_eq!(agent.velocity.y, 0.0);
+      assert_eq!(
+        agent.position.y,
+        simulator.get_agent_parameters(agent_index).goal_point.y
+      );
+    }
+  }
+  // The flyers dodge vertically, while the ground agents get past each other
+  // on the ground.
+  assert!(max_flyer_height > 0.3, "{max_flyer_height}");
+  for agent_index in 2..8 {
+    let position = simulator.get_agent(agent_index).position;
+    let goal_point = simulator.get_agent_parameters(agent_index).goal_point;
+    assert!(position.distance(goal_point) < 0.5, "{position} {goal_point}");
+  }
+}
+
+#[test]
+fn seeded_simulations_are_reproducible() {
+  let run = |seed| {
+    let mut simulator = Simulator::new();
+    simulator.set_seed(seed);
+    // Exactly overlapping agents, which have to pick a random direction to
+    // push apart in.
+    for _ in 0..2 {
+      simulator.add_agent(
+        Agent::builder().build(),
+        AgentParameters::builder().time_horizon(1.0).build(),
+      );
+    }
+    for _ in 0..10 {
+      simulator.step(0.1);
+    }
+    (simulator.get_agent(0).position, simulator.get_agent(1).position)
+  };
+
+  let (first, second) = run(1);
+  assert!(first.distance(second) > 0.1, "{first} {second}");
+  assert_eq!(run(1), (first, second));
+  assert_ne!(run(2), (first, second));
+}
+
+#[test]
+fn results_do_not_depend_on_agent_order() {
+  let scenario = crate::scenarios::random_soup(/* agent_count= */ 40, 3);
+  let create_simulator = |order: &[usize]| {
+    let mut simulator = Simulator::new();
+    for &index in order {
+      simulator.add_agent(
+        scenario.agents[index].clone(),
+        AgentParameters::builder()
+          .goal_point(scenario.goal_points[index])
+          .max_speed(scenario.max_speed)
+          .time_horizon(scenario.time_horizon)
+          .obstacle_time_horizon(scenario.time_horizon)
+          .build(),
+      );
+    }
+    simulator
+  };
+
+  let order = (0..scenario.agents.len()).collect::<Vec<_>>();
+  let mut forward = create_simulator(&order);
+  // Add the agents in reverse, after an extra agent that is removed again, to
+  // shuffle the history.
+  let reversed_order = order.iter().rev().copied().collect::<Vec<_>>();
+  let mut backward = create_simulator(
+    &std::iter::once(0)
+      .chain(reversed_order.iter().copied())
+      .collect::<Vec<_>>(),
+  );
+  backward.remove_agent(0);
+
+  for _ in 0..20 {
+    forward.step(0.1);
+    // Compute velocities in a scrambled order, as a job system might.
+    backward.begin_step(0.1);
+    let mut new_velocities = vec![Vec3::ZERO; backward.get_agent_count()];
+    for agent_index in (0..new_velocities.len())
+      .rev()
+      .step_by(2)
+      .chain((0..new_velocities.len()).rev().skip(1).step_by(2))
+    {
+      new_velocities[agent_index] =
+        backward.compute_agent_velocity(agent_index, 0.1);
+    }
+    backward.end_step(new_velocities, 0.1);
+  }
+
+  for (index, &reversed_index) in reversed_order.iter().enumerate() {
+    assert_eq!(
+      forward.get_agent(reversed_index),
+      backward.get_agent(index),
+      "agent {reversed_index}"
+    );
+  }
+}
+
+#[test]
+fn coincident_agents_do_not_depend_on_agent_order() {
+  let goal_points = [Vec3::new(5.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 5.0)];
+  let run = |order: [usize; 2]| {
+    let mut simulator = Simulator::new();
+    simulator.set_seed(1);
+    // Exactly overlapping agents, which have to pick a random direction to
+    // push apart in.
+    for index in order {
+      simulator.add_agent(
+        Agent::builder().build(),
+        AgentParameters::builder()
+          .goal_point(goal_points[index])
+          .time_horizon(1.0)
+          .build(),
+      );
+    }
+    for _ in 0..10 {
+      simulator.step(0.1);
+    }
+    let mut agents = [0, 1].map(|index| simulator.get_agent(index).clone());
+    if order[0] == 1 {
+      agents.swap(0, 1);
+    }
+    agents
+  };
+
+  let [first, second] = run([0, 1]);
+  assert!(first.position.distance(second.position) > 0.1);
+  assert_eq!(run([1, 0]), [first, second]);
+}
+
+/// A crowd of agents standing at their goals, and one agent walking past the
+/// end of the crowd, which disturbs the agents near it.
+fn create_idle_crowd() -> Simulator {
+  let mut simulator = Simulator::new();
+  let parameters = |goal_point| {
+    AgentParameters::builder().goal_point(goal_point).time_horizon(1.0).build()
+  };
+  for index in 0..10 {
+    let position = Vec3::new(index as f32 * 3.0, 0.0, 0.0);
+    simulator.add_agent(
+      Agent::builder().position(position).build(),
+      parameters(position),
+    );
+  }
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(27.0, 0.0, -5.0)).build(),
+    parameters(Vec3::new(27.0, 0.0, 5.0)),
+  );
+  simulator
+}
+
+#[test]
+fn skipping_unchanged_agents_gives_same_results() {
+  let mut simulator = create_idle_crowd();
+  let mut skipping = create_idle_crowd();
+  skipping.set_skip_unchanged_agents(true);
+  let mut budgeted = create_idle_crowd();
+  budgeted.set_skip_unchanged_agents(true);
+
+  let mut total_skipped_agent_count = 0;
+  for _ in 0..100 {
+    simulator.step(0.1);
+    skipping.step(0.1);
+    while !budgeted.step_with_budget(0.1, Duration::ZERO) {}
+
+    assert_eq!(simulator.get_skipped_agent_count(), 0);
+    assert_eq!(
+      skipping.get_skipped_agent_count(),
+      budgeted.get_skipped_agent_count()
+    );
+    total_skipped_agent_count += skipping.get_skipped_agent_count();
+    for index in 0..simulator.get_agent_count() {
+      assert_eq!(skipping.get_agent(index), simulator.get_agent(index));
+      assert_eq!(budgeted.get_agent(index), simulator.get_agent(index));
+    }
+  }
+
+  // Most of the crowd is far from the walking agent, so it is mostly skipped.
+  assert!(total_skipped_agent_count > 100 * 7, "{total_skipped_agent_count}");
+  // The walking agent passed through the crowd.
+  assert!(simulator.get_agent(10).position.z > 0.0);
+}
+
+#[test]
+fn changes_recompute_skipped_agents() {
+  let mut simulator = create_idle_crowd();
+  simulator.remove_agent(10);
+  simulator.set_skip_unchanged_agents(true);
+  simulator.step(0.1);
+  assert_eq!(simulator.get_skipped_agent_count(), 0);
+  simulator.step(0.1);
+  assert_eq!(simulator.get_skipped_agent_count(), 10);
+
+  // Moving an agent recomputes the agents near it.
+  simulator.get_agent_mut(0).position.x = 1.0;
+  simulator.step(0.1);
+  assert_eq!(simulator.get_skipped_agent_count(), 8);
+  assert_ne!(simulator.get_agent(0).velocity, Vec3::ZERO);
+
+  // Changing the time step recomputes everything.
+  simulator.step(0.2);
+  assert_eq!(simulator.get_skipped_agent_count(), 0);
+
+  // Obstacles may be near any agent, so adding them recomputes everything.
+  let agent = simulator.get_agent_mut(0);
+  agent.position = Vec3::ZERO;
+  agent.velocity = Vec3::ZERO;
+  simulator.step(0.2);
+  simulator.step(0.2);
+  assert_eq!(simulator.get_skipped_agent_count(), 10);
+  simulator.add_obstacle(Obstacle::Closed {
+    vertices: vec![
+      Vec2::new(-1.0, -1.0),
+      Vec2::new(1.0, -1.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(-1.0, 1.0),
+    ],
+    bottom: -1.0,
+    top: 1.0,
+  });
+  simulator.step(0.2);
+  assert_eq!(simulator.get_skipped_agent_count(), 0);
+}
+
+#[test]
+fn solver_limits_bound_work_in_dense_crowds() {
+  let mut unlimited =
+    crate::scenarios::dense_grid(/* agent_count= */ 27).into_simulator();
+  let mut limited = unlimited.clone();
+  limited.set_solver_limits(SolverLimits {
+    max_iterations: Some(1),
+    time_budget: None,
+  });
+
+  for _ in 0..10 {
+    unlimited.step(0.1);
+    limited.step(0.1);
+    assert_eq!(unlimited.get_solver_limited_agent_count(), 0);
+  }
+  // Agents in the middle of the grid have many conflicting neighbours.
+  assert!(limited.get_solver_limited_agent_count() > 0);
+  while !limited.step_with_budget(0.1, Duration::ZERO) {}
+  assert!(limited.get_solver_limited_agent_count() > 0);
+  for index in 0..limited.get_agent_count() {
+    let agent = limited.get_agent(index);
+    assert!(agent.position.is_finite(), "{agent:?}");
+    assert!(agent.velocity.length() <= 2.0 + 1e-4, "{agent:?}");
+  }
+}
+
+#[test]
+fn less_important_agents_keep_velocity_between_updates() {
+  let mut simulator = create_idle_crowd();
+  simulator.set_agent_importance(10, Importance::Low);
+  assert_eq!(simulator.get_agent_importance(10), Importance::Low);
+  assert_eq!(simulator.get_agent_importance(0), Importance::High);
+
+  // The walker is staggered by its index, so it is first updated in the third
+  // step.
+  simulator.step(0.1);
+  simulator.step(0.1);
+  assert_eq!(simulator.get_agent(10).velocity, Vec3::ZERO);
+  simulator.step(0.1);
+  let velocity = simulator.get_agent(10).velocity;
+  assert_ne!(velocity, Vec3::ZERO);
+
+  // Between updates, the walker keeps moving with its last velocity.
+  simulator.get_agent_parameters_mut(10).goal_point =
+    simulator.get_agent(10).position;
+  for _ in 0..3 {
+    let position = simulator.get_agent(10).position;
+    simulator.step(0.1);
+    assert_eq!(simulator.get_agent(10).velocity, velocity);
+    assert_vec_near!(
+      simulator.get_agent(10).position,
+      position + velocity * 0.1,
+      1e-5
+    );
+  }
+  simulator.step(0.1);
+  assert_ne!(simulator.get_agent(10).velocity, velocity);
+}
+
+#[test]
+fn budgeted_steps_respect_importance() {
+  let mut simulator = create_idle_crowd();
+  for index in 0..simulator.get_agent_count() {
+    let importance = match index % 3 {
+      0 => Importance::High,
+      1 => Importance::Medium,
+      _ => Importance::Low,
+    };
+    simulator.set_agent_importance(index, importance);
+  }
+  let mut budgeted = simulator.clone();
+  budgeted.set_skip_unchanged_agents(true);
+
+  for _ in 0..10 {
+    simulator.step(0.1);
+    while !budgeted.step_with_budget(0.1, Duration::ZERO) {}
+    for index in 0..simulator.get_agent_count() {
+      assert_eq!(simulator.get_agent(index), budgeted.get_agent(index));
+    }
+  }
+}
+
+#[test]
+fn importance_classifier_sets_importance_each_step() {
+  let mut simulator = create_idle_crowd();
+  simulator.set_importance_classifier(Some(Box::new(
+    |agent_index, position| {
+      if agent_index == 0 {
+        Importance::Medium
+      } else if position.x > 10.0 {
+        Importance::Low
+      } else {
+        Importance::High
+      }
+    },
+  )));
+  // Importances are only classified when stepping.
+  assert_eq!(simulator.get_agent_importance(0), Importance::High);
+  simulator.step(0.1);
+  assert_eq!(simulator.get_agent_importance(0), Importance::Medium);
+  assert_eq!(simulator.get_agent_importance(3), Importance::High);
+  assert_eq!(simulator.get_agent_importance(4), Importance::Low);
+  assert_eq!(simulator.get_agent_importance(10), Importance::Low);
+
+  // The classifier overrides manually set importances.
+  simulator.set_agent_importance(4, Importance::High);
+  simulator.step(0.1);
+  assert_eq!(simulator.get_agent_importance(4), Importance::Low);
+
+  // Without a classifier, agents keep their last importance.
+  simulator.set_importance_classifier(None);
+  simulator.set_agent_importance(4, Importance::High);
+  simulator.step(0.1);
+  assert_eq!(simulator.get_agent_importance(4), Importance::High);
+  assert_eq!(simulator.get_agent_importance(10), Importance::Low);
+}
+
+#[test]
+fn crossing_flows_jam_less_when_coordinated() {
+  // Returns the number of agent-steps spent nearly stopped at the crossing
+  // of two perpendicular flows of ground agents.
+  let run = |crossing_flow: Option<CrossingFlow>| {
+    let mut simulator = Simulator::new();
+    simulator.set_crossing_flow(crossing_flow);
+    for row in 0..8 {
+      for column in 0..6 {
+        let across = (column as f32 - 2.5) * 1.1;
+        let along = -6.0 - row as f32 * 1.1;
+        for (position, goal_point) in [
+          (Vec3::new(along, 0.0, across), Vec3::new(40.0, 0.0, across)),
+          (Vec3::new(across, 0.0, along), Vec3::new(across, 0.0, 40.0)),
+        ] {
+          simulator.add_agent(
+            Agent::builder().position(position).build(),
+            AgentParameters::builder()
+              .goal_point(goal_point)
+              .max_speed(1.5)
+              .time_horizon(5.0)
+              .standing_height(1.0)
+              .build(),
+          );
+        }
+      }
+    }
+
+    let mut jammed_agent_steps = 0;
+    for _ in 0..300 {
+      simulator.step(0.1);
+      jammed_agent_steps += (0..simulator.get_agent_count())
+        .map(|agent_index| simulator.get_agent(agent_index))
+        .filter(|agent| {
+          agent.position.length() < 8.0 && agent.velocity.length() < 0.5
+        })
+        .count();
+    }
+    jammed_agent_steps
+  };
+
+  let uncoordinated = run(None);
+  let coordinated = run(Some(CrossingFlow::default()));
+  assert!(
+    coordinated * 2 < uncoordinated,
+    "coordinated: {coordinated}, uncoordinated: {uncoordinated}"
+  );
+}
+
+#[test]
+fn obstacle_nudge_keeps_agents_off_walls() {
+  // Returns the smallest gap between the agent and a wall it passes at a
+  // shallow angle.
+  let run = |obstacle_nudge: Option<ObstacleNudge>| {
+    let mut simulator = Simulator::new();
+    simulator.set_obstacle_nudge(obstacle_nudge);
+    simulator.add_obstacle(Obstacle::Open {
+      vertices: vec![Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)],
+      bottom: -10.0,
+      top: 10.0,
+    });
+    simulator.add_agent(
+      Agent::builder().position(Vec3::new(-1.0, 0.0, -1.0)).build(),
+      AgentParameters::builder()
+        .goal_point(Vec3::new(12.0, 0.0, 0.5))
+        .max_speed(1.5)
+        .build(),
+    );
+
+    let mut closest_gap = f32::INFINITY;
+    for _ in 0..200 {
+      simulator.step(0.1);
+      let agent = simulator.get_agent(0);
+      if (2.0..8.0).contains(&agent.position.x) {
+        closest_gap = closest_gap.min(-agent.position.z - agent.radius);
+      }
+    }
+    closest_gap
+  };
+
+  let plain = run(None);
+  let nudged = run(Some(ObstacleNudge::default()));
+  assert!(plain < 0.05, "{plain}");
+  assert!(nudged > 0.25, "{nudged}");
+}
+
+#[test]
+fn adding_and_removing_obstacles_recomputes_budgeted_agents() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent(
+    Agent::builder().build(),
+    AgentParameters::builder().goal_point(Vec3::new(10.0, 0.0, 0.0)).build(),
+  );
+  simulator.add_agent(
+    Agent::builder().position(Vec3::new(100.0, 0.0, 0.0)).build(),
+    AgentParameters::builder().build(),
+  );
+  let wall = Obstacle::Open {
+    vertices: vec![Vec2::new(0.8, 5.0), Vec2::new(0.8, -5.0)],
+    bottom: -10.0,
+    top: 10.0,
+  };
+
+  // The first agent is processed before the wall appears in front of it.
+  assert!(!simulator.step_with_budget(0.1, Duration::ZERO));
+  simulator.add_obstacle(wall);
+  while !simulator.step_with_budget(0.1, Duration::ZERO) {}
+  let velocity = simulator.get_agent(0).velocity;
+  assert!(velocity.x < 0.5, "{velocity}");
+
+  // The first agent is processed before the wall in front of it disappears.
+  assert!(!simulator.step_with_budget(0.1, Duration::ZERO));
+  simulator.remove_obstacle(0);
+  while !simulator.step_with_budget(0.1, Duration::ZERO) {}
+  let velocity = simulator.get_agent(0).velocity;
+  assert!(velocity.x > 0.9, "{velocity}");
 }
@@ -0,0 +1,249 @@
+//! A soak test harness for checking that avoidance stays well behaved over
+//! long runs.
+//!
+//! [`run_soak`] runs many randomized [`crate::scenarios`] back to back (for
+//! millions of steps in total by default), checking invariants after every
+//! step: velocities are finite, agents respect their max speed, and agents do
+//! not stay interpenetrating. This can be used as a gate before shipping
+//! (e.g. after tuning parameters or upgrading dodgy). [`run_soak_with`] checks
+//! the same invariants on scenarios from your own factory. On failure, the
+//! scenario's seed and the step are reported, so the failure can be replayed
+//! with [`create_soak_scenario`] (or your factory) and then captured with
+//! [`crate::repro::Repro`].
+
+use std::{collections::HashMap, fmt};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{scenarios, Simulator};
+
+/// The parameters of a soak test.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SoakOptions {
+  /// The number of scenarios to run.
+  pub scenario_count: u64,
+  /// The number of steps to run each scenario for.
+  pub steps_per_scenario: u64,
+  /// The largest number of agents in a scenario. Each scenario has a random
+  /// number of agents from 2 up to this.
+  pub max_agent_count: usize,
+  pub time_step: f32,
+  /// The seed of the first scenario. Each following scenario uses the next
+  /// seed.
+  pub seed: u64,
+  /// How far (as a fraction of the max speed) agents may exceed their max
+  /// speed, to allow for floating point error.
+  pub max_speed_tolerance: f32,
+  /// How deep (as a fraction of their combined radii) agents may overlap
+  /// without interpenetrating. Agents may overlap briefly in dense crowds.
+  pub penetration_tolerance: f32,
+  /// The number of steps in a row that a pair of agents may interpenetrate
+  /// for before the soak test fails.
+  pub max_penetration_steps: u32,
+}
+
+impl Default for SoakOptions {
+  fn default() -> Self {
+    Self {
+      scenario_count: 1000,
+      steps_per_scenario: 1000,
+      max_agent_count: 32,
+      time_step: 0.1,
+      seed: 0,
+      max_speed_tolerance: 1e-3,
+      penetration_tolerance: 0.1,
+      max_penetration_steps: 20,
+    }
+  }
+}
+
+/// The result of a soak test that passed.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SoakReport {
+  /// The number of scenarios run.
+  pub scenario_count: u64,
+  /// The total number of steps run across all scenarios.
+  pub step_count: u64,
+}
+
+/// An invariant that was broken during a soak test.
+#[derive(Clone, PartialEq, Debug)]
+pub enum SoakViolation {
+  /// The agent's position or velocity is not finite.
+  NonFiniteState { agent_index: usize },
+  /// The agent moved faster than its max speed.
+  ExceededMaxSpeed { agent_index: usize, speed: f32, max_speed: f32 },
+  /// The agents interpenetrated for more than the allowed number of steps in
+  /// a row.
+  PersistentInterpenetration {
+    agent_index: usize,
+    other_agent_index: usize,
+    /// How deep the agents overlap, as a fraction of their combined radii.
+    depth: f32,
+  },
+}
+
+/// A failed soak test.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SoakFailure {
+  /// The seed of the failing scenario (see [`create_soak_scenario`]).
+  pub seed: u64,
+  /// The step of the scenario (starting from zero) after which the invariant
+  /// was broken.
+  pub step: u64,
+  pub violation: SoakViolation,
+}
+
+impl fmt::Display for SoakFailure {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "soak test failed at step {} of seed {}: ",
+      self.step, self.seed
+    )?;
+    match &self.violation {
+      SoakViolation::NonFiniteState { agent_index } => {
+        write!(f, "agent {agent_index} is not finite")
+      }
+      SoakViolation::ExceededMaxSpeed { agent_index, speed, max_speed } => {
+        write!(
+          f,
+          "agent {agent_index} moved at {speed}, over its max speed of \
+           {max_speed}"
+        )
+      }
+      SoakViolation::PersistentInterpenetration {
+        agent_index,
+        other_agent_index,
+        depth,
+      } => write!(
+        f,
+        "agents {agent_index} and {other_agent_index} are still \
+         interpenetrating, overlapping by {depth} of their combined radii"
+      ),
+    }
+  }
+}
+
+impl std::error::Error for SoakFailure {}
+
+/// Runs the soak test described by `options` on the scenarios of
+/// [`create_soak_scenario`]. Returns the first broken invariant if any.
+pub fn run_soak(options: &SoakOptions) -> Result<SoakReport, SoakFailure> {
+  run_soak_with(options, |seed| create_soak_scenario(seed, options))
+}
+
+/// Runs the soak test described by `options` on the scenarios created by
+/// `create_scenario` from each scenario's seed. This can be used to soak a
+/// game's own scenarios (e.g. its levels, with its own agent parameters).
+/// `options.max_agent_count` is ignored. Returns the first broken invariant if
+/// any.
+pub fn run_soak_with(
+  options: &SoakOptions,
+  mut create_scenario: impl FnMut(u64) -> Simulator,
+) -> Result<SoakReport, SoakFailure> {
+  for scenario_index in 0..options.scenario_count {
+    let seed = options.seed.wrapping_add(scenario_index);
+    let mut simulator = create_scenario(seed);
+    let mut checker = InvariantChecker::default();
+    for step in 0..options.steps_per_scenario {
+      simulator.step(options.time_step);
+      checker.check(&simulator, options).map_err(|violation| SoakFailure {
+        seed,
+        step,
+        violation,
+      })?;
+    }
+  }
+  Ok(SoakReport {
+    scenario_count: options.scenario_count,
+    step_count: options.scenario_count * options.steps_per_scenario,
+  })
+}
+
+/// Creates the randomized scenario that [`run_soak`] runs for `seed`. The same
+/// `seed` and `options` always create the same simulator, which steps the same
+/// way.
+pub fn create_soak_scenario(seed: u64, options: &SoakOptions) -> Simulator {
+  assert!(
+    options.max_agent_count >= 2,
+    "max_agent_count must be at least 2, was {}",
+    options.max_agent_count
+  );
+  let mut rng = StdRng::seed_from_u64(seed);
+  let agent_count = rng.random_range(2..=options.max_agent_count);
+  let scenario = match rng.random_range(0..4) {
+    0 => scenarios::sphere_antipodal_swap(agent_count),
+    1 => scenarios::corridor_crossing(agent_count),
+    2 => scenarios::random_soup(agent_count, rng.random()),
+    _ => scenarios::dense_grid(agent_count),
+  };
+  let mut simulator = scenario.into_simulator();
+  simulator.set_rng(StdRng::seed_from_u64(rng.random()));
+  simulator
+}
+
+/// Checks the invariants after each step of a scenario.
+#[derive(Default)]
+struct InvariantChecker {
+  /// The number of steps in a row that each pair of agents (by their indices,
+  /// smallest first) has been interpenetrating for.
+  penetration_steps: HashMap<(usize, usize), u32>,
+}
+
+impl InvariantChecker {
+  /// Checks the invariants on `simulator` after a step.
+  fn check(
+    &mut self,
+    simulator: &Simulator,
+    options: &SoakOptions,
+  ) -> Result<(), SoakViolation> {
+    let agent_count = simulator.get_agent_count();
+    for agent_index in 0..agent_count {
+      let agent = simulator.get_agent(agent_index);
+      if !agent.position.is_finite() || !agent.velocity.is_finite() {
+        return Err(SoakViolation::NonFiniteState { agent_index });
+      }
+      let speed = agent.velocity.length();
+      let max_speed = simulator.get_agent_parameters(agent_index).max_speed;
+      if speed > max_speed * (1.0 + options.max_speed_tolerance) {
+        return Err(SoakViolation::ExceededMaxSpeed {
+          agent_index,
+          speed,
+          max_speed,
+        });
+      }
+    }
+
+    // Pairs that stopped interpenetrating start counting from zero again.
+    let mut penetration_steps = HashMap::new();
+    for agent_index in 0..agent_count {
+      let agent = simulator.get_agent(agent_index);
+      for other_agent_index in (agent_index + 1)..agent_count {
+        let other_agent = simulator.get_agent(other_agent_index);
+        let radius = agent.radius + other_agent.radius;
+        let depth =
+          1.0 - agent.position.distance(other_agent.position) / radius;
+        if depth <= options.penetration_tolerance {
+          continue;
+        }
+        let pair = (agent_index, other_agent_index);
+        let steps = self.penetration_steps.get(&pair).copied().unwrap_or(0) + 1;
+        if steps > options.max_penetration_steps {
+          return Err(SoakViolation::PersistentInterpenetration {
+            agent_index,
+            other_agent_index,
+            depth,
+          });
+        }
+        penetration_steps.insert(pair, steps);
+      }
+    }
+    self.penetration_steps = penetration_steps;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+#[path = "soak_test.rs"]
+mod test;
@@ -0,0 +1,129 @@
+use glam::Vec3;
+
+use super::*;
+use crate::{Agent, AgentParameters};
+
+fn short_options() -> SoakOptions {
+  SoakOptions {
+    scenario_count: 8,
+    steps_per_scenario: 100,
+    max_agent_count: 16,
+    ..Default::default()
+  }
+}
+
+#[test]
+fn short_soak_passes() {
+  assert_eq!(
+    run_soak(&short_options()),
+    Ok(SoakReport { scenario_count: 8, step_count: 800 })
+  );
+}
+
+#[test]
+fn same_seed_creates_same_scenario() {
+  let options = short_options();
+  let mut first = create_soak_scenario(3, &options);
+  let mut second = create_soak_scenario(3, &options);
+  for _ in 0..10 {
+    first.step(options.time_step);
+    second.step(options.time_step);
+  }
+
+  assert_eq!(first.get_agent_count(), second.get_agent_count());
+  for agent_index in 0..first.get_agent_count() {
+    assert_eq!(first.get_agent(agent_index), second.get_agent(agent_index));
+  }
+}
+
+#[test]
+fn soaks_custom_scenarios() {
+  let options = SoakOptions {
+    scenario_count: 3,
+    steps_per_scenario: 10,
+    seed: 5,
+    max_penetration_steps: 3,
+    ..Default::default()
+  };
+  let mut seeds = vec![];
+  let result = run_soak_with(&options, |seed| {
+    seeds.push(seed);
+    let mut simulator = Simulator::new();
+    // The agents cannot move, and only overlap in the last scenario.
+    let spacing = if seed == 7 { 0.5 } else { 2.0 };
+    for x in [0.0, spacing] {
+      simulator.add_agent(
+        Agent::builder().position(Vec3::new(x, 0.0, 0.0)).build(),
+        AgentParameters::builder().max_speed(0.0).build(),
+      );
+    }
+    simulator
+  });
+
+  assert_eq!(seeds, [5, 6, 7]);
+  let failure = result.unwrap_err();
+  assert_eq!((failure.seed, failure.step), (7, 3));
+  assert!(matches!(
+    failure.violation,
+    SoakViolation::PersistentInterpenetration { .. }
+  ));
+}
+
+#[test]
+fn detects_persistent_interpenetration() {
+  let options = SoakOptions { max_penetration_steps: 3, ..Default::default() };
+  let mut simulator = Simulator::new();
+  // The agents cannot move, so they stay interpenetrating.
+  for x in [0.0, 0.5] {
+    simulator.add_agent(
+      Agent::builder().position(Vec3::new(x, 0.0, 0.0)).build(),
+      AgentParameters::builder().max_speed(0.0).build(),
+    );
+  }
+
+  let mut checker = InvariantChecker::default();
+  for _ in 0..3 {
+    simulator.step(options.time_step);
+    assert_eq!(checker.check(&simulator, &options), Ok(()));
+  }
+  simulator.step(options.time_step);
+  assert_eq!(
+    checker.check(&simulator, &options),
+    Err(SoakViolation::PersistentInterpenetration {
+      agent_index: 0,
+      other_agent_index: 1,
+      depth: 0.5,
+    })
+  );
+}
+
+#[test]
+fn detects_exceeded_max_speed() {
+  let mut simulator = Simulator::new();
+  simulator.add_agent(
+    Agent::builder().velocity(Vec3::new(2.0, 0.0, 0.0)).build(),
+    AgentParameters::builder().max_speed(1.0).build(),
+  );
+
+  assert_eq!(
+    InvariantChecker::default().check(&simulator, &SoakOptions::default()),
+    Err(SoakViolation::ExceededMaxSpeed {
+      agent_index: 0,
+      speed: 2.0,
+      max_speed: 1.0,
+    })
+  );
+}
+
+#[test]
+fn failures_report_seed_and_step() {
+  let failure = SoakFailure {
+    seed: 12,
+    step: 345,
+    violation: SoakViolation::NonFiniteState { agent_index: 6 },
+  };
+  assert_eq!(
+    failure.to_string(),
+    "soak test failed at step 345 of seed 12: agent 6 is not finite"
+  );
+}
@@ -0,0 +1,47 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::Agent;
+
+/// Parameters for perturbing an agent's preferred velocity with repulsive
+/// social forces (as in Helbing's social force model) before avoiding
+/// collisions. The social forces push agents apart before they get close,
+/// giving more natural spacing between pedestrians. Collisions are still
+/// avoided as usual, so the social forces cannot cause collisions.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SocialForce {
+  /// How strongly neighbours push the agent away when touching, in units of
+  /// velocity.
+  pub strength: f32,
+  /// The distance over which the push falls off (by about 63%). The push from
+  /// a neighbour is `strength * exp(-gap / range)`, where `gap` is the
+  /// distance between the edges of the agents.
+  pub range: f32,
+}
+
+/// Computes the sum of the social forces that `neighbours` apply to `agent`.
+/// The result is a change in the agent's preferred velocity.
+pub(crate) fn get_social_force(
+  agent: &Agent,
+  neighbours: &[Cow<'_, Agent>],
+  social_force: &SocialForce,
+) -> Vec3 {
+  neighbours
+    .iter()
+    .filter_map(|neighbour| {
+      let offset = agent.position - neighbour.position;
+      let distance = offset.length();
+      // Agents at the same position have no direction to push each other in.
+      let direction = offset.try_normalize()?;
+      let gap = distance - agent.radius - neighbour.radius;
+      Some(
+        direction * social_force.strength * (-gap / social_force.range).exp(),
+      )
+    })
+    .sum()
+}
+
+#[cfg(test)]
+#[path = "social_force_test.rs"]
+mod test;
@@ -0,0 +1,49 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::Agent;
+
+use super::*;
+
+fn agent(position: Vec3) -> Agent {
+  Agent {
+    position,
+    velocity: Vec3::ZERO,
+    radius: 0.5,
+    avoidance_responsibility: 1.0,
+  }
+}
+
+#[test]
+fn pushes_away_from_neighbours() {
+  let social_force = SocialForce { strength: 2.0, range: 0.5 };
+  let force = get_social_force(
+    &agent(Vec3::ZERO),
+    &[Cow::Owned(agent(Vec3::new(1.5, 0.0, 0.0)))],
+    &social_force,
+  );
+  // The gap between the agents is 0.5.
+  let expected = Vec3::NEG_X * 2.0 * (-1.0f32).exp();
+  assert!(force.distance(expected) < 1e-5, "{force}");
+}
+
+#[test]
+fn forces_from_neighbours_add_up() {
+  let social_force = SocialForce { strength: 1.0, range: 0.5 };
+  let force = get_social_force(
+    &agent(Vec3::ZERO),
+    &[
+      Cow::Owned(agent(Vec3::new(1.0, 0.0, 0.0))),
+      Cow::Owned(agent(Vec3::new(-1.0, 0.0, 0.0))),
+      Cow::Owned(agent(Vec3::new(0.0, 0.0, 2.0))),
+      // Neighbours at the same position are ignored.
+      Cow::Owned(agent(Vec3::ZERO)),
+    ],
+    &social_force,
+  );
+  // The pushes along X cancel out.
+  assert!(force.x.abs() < 1e-5, "{force}");
+  assert!(force.y.abs() < 1e-5, "{force}");
+  assert!((force.z + (-2.0f32).exp()).abs() < 1e-5, "{force}");
+}
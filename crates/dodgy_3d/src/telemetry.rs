@@ -0,0 +1,102 @@
+use std::{borrow::Cow, time::Duration};
+
+use metrics::{
+  counter, describe_counter, describe_gauge, describe_histogram, gauge,
+  histogram, Unit,
+};
+
+use crate::{are_colliding, Agent, LinearProgramOutcome};
+
+/// The histogram of the time taken by each step, in seconds. For steps driven
+/// by a job system, this is the time from [`crate::Simulator::begin_step`] to
+/// [`crate::Simulator::end_step`]. For [`crate::Simulator::step_with_budget`],
+/// this is only the time spent within the calls that made up the step.
+pub const STEP_SECONDS_METRIC: &str = "dodgy_3d_step_seconds";
+/// The counter of completed steps.
+pub const STEPS_METRIC: &str = "dodgy_3d_steps_total";
+/// The gauge of the number of agents in the simulator, as of the last step.
+pub const AGENTS_METRIC: &str = "dodgy_3d_agents";
+/// The counter of agent velocities for which the linear program was
+/// infeasible, so the solver fell back to violating constraints the least.
+/// Spikes mean agents are being squeezed (e.g. a crowd crush).
+pub const SOLVER_FALLBACKS_METRIC: &str = "dodgy_3d_solver_fallbacks_total";
+/// The counter of agent velocities for which the solver stopped early, having
+/// reached its limits (see [`crate::SolverLimits`]).
+pub const SOLVER_LIMITED_METRIC: &str = "dodgy_3d_solver_limited_total";
+/// The counter of agent velocities computed while the agent was overlapping
+/// at least one of its neighbours (by more than 1% of their combined radii).
+pub const COLLISIONS_METRIC: &str = "dodgy_3d_collisions_total";
+
+/// Describes each of the metrics recorded by the simulator to the installed
+/// recorder, so exporters (e.g. for Prometheus) can include their units and
+/// help text. Call this once after installing the recorder.
+pub fn describe_metrics() {
+  describe_histogram!(
+    STEP_SECONDS_METRIC,
+    Unit::Seconds,
+    "The time taken by each simulator step."
+  );
+  describe_counter!(
+    STEPS_METRIC,
+    Unit::Count,
+    "The number of steps completed."
+  );
+  describe_gauge!(
+    AGENTS_METRIC,
+    Unit::Count,
+    "The number of agents in the simulator."
+  );
+  describe_counter!(
+    SOLVER_FALLBACKS_METRIC,
+    Unit::Count,
+    "The number of velocities where the solver fell back to relaxing \
+     constraints."
+  );
+  describe_counter!(
+    SOLVER_LIMITED_METRIC,
+    Unit::Count,
+    "The number of velocities where the solver reached its limits."
+  );
+  describe_counter!(
+    COLLISIONS_METRIC,
+    Unit::Count,
+    "The number of velocities computed while overlapping a neighbour."
+  );
+}
+
+/// Records the metrics of computing the velocity of `agent`, which avoided
+/// `neighbours`, and whose linear program was solved with `outcome`.
+pub(crate) fn record_agent_velocity(
+  agent: &Agent,
+  neighbours: &[Cow<'_, Agent>],
+  outcome: &LinearProgramOutcome,
+  solver_limit_reached: bool,
+) {
+  if matches!(
+    outcome,
+    LinearProgramOutcome::Relaxed { .. }
+      | LinearProgramOutcome::RigidInfeasible { .. }
+  ) {
+    counter!(SOLVER_FALLBACKS_METRIC).increment(1);
+  }
+  if solver_limit_reached {
+    counter!(SOLVER_LIMITED_METRIC).increment(1);
+  }
+  if neighbours.iter().any(|neighbour| are_colliding(agent, neighbour)) {
+    counter!(COLLISIONS_METRIC).increment(1);
+  }
+}
+
+/// Records the metrics of a completed step, which took `duration` (if known),
+/// leaving `agent_count` agents in the simulator.
+pub(crate) fn record_step(duration: Option<Duration>, agent_count: usize) {
+  counter!(STEPS_METRIC).increment(1);
+  gauge!(AGENTS_METRIC).set(agent_count as f64);
+  if let Some(duration) = duration {
+    histogram!(STEP_SECONDS_METRIC).record(duration.as_secs_f64());
+  }
+}
+
+#[cfg(test)]
+#[path = "telemetry_test.rs"]
+mod test;
@@ -0,0 +1,163 @@
+use std::{
+  borrow::Cow,
+  collections::HashMap,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+  },
+};
+
+use glam::Vec3;
+use metrics::{
+  with_local_recorder, Counter, CounterFn, Gauge, GaugeFn, Histogram,
+  HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+};
+
+use crate::{
+  telemetry::record_agent_velocity, Agent, AgentParameters,
+  LinearProgramOutcome, Simulator, AGENTS_METRIC, COLLISIONS_METRIC,
+  SOLVER_FALLBACKS_METRIC, SOLVER_LIMITED_METRIC, STEPS_METRIC,
+  STEP_SECONDS_METRIC,
+};
+
+/// A metric that remembers every value it was given.
+#[derive(Default)]
+struct Values {
+  /// The sum of all increments (less any decrements), or the last value set.
+  total: AtomicU64,
+  /// The number of values recorded.
+  count: AtomicU64,
+}
+
+impl CounterFn for Values {
+  fn increment(&self, value: u64) {
+    self.total.fetch_add(value, Ordering::Relaxed);
+    self.count.fetch_add(1, Ordering::Relaxed);
+  }
+
+  fn absolute(&self, value: u64) {
+    self.total.store(value, Ordering::Relaxed);
+    self.count.fetch_add(1, Ordering::Relaxed);
+  }
+}
+
+impl GaugeFn for Values {
+  fn increment(&self, value: f64) {
+    self.total.fetch_add(value as u64, Ordering::Relaxed);
+    self.count.fetch_add(1, Ordering::Relaxed);
+  }
+
+  fn decrement(&self, value: f64) {
+    self.total.fetch_sub(value as u64, Ordering::Relaxed);
+    self.count.fetch_add(1, Ordering::Relaxed);
+  }
+
+  fn set(&self, value: f64) {
+    self.total.store(value as u64, Ordering::Relaxed);
+    self.count.fetch_add(1, Ordering::Relaxed);
+  }
+}
+
+impl HistogramFn for Values {
+  fn record(&self, _value: f64) {
+    self.count.fetch_add(1, Ordering::Relaxed);
+  }
+}
+
+/// A recorder that keeps every metric in memory.
+#[derive(Default)]
+struct MemoryRecorder {
+  metrics: Mutex<HashMap<String, Arc<Values>>>,
+}
+
+impl MemoryRecorder {
+  fn get(&self, name: &str) -> Arc<Values> {
+    self.metrics.lock().unwrap().entry(name.to_string()).or_default().clone()
+  }
+
+  fn total(&self, name: &str) -> u64 {
+    self.get(name).total.load(Ordering::Relaxed)
+  }
+
+  fn count(&self, name: &str) -> u64 {
+    self.get(name).count.load(Ordering::Relaxed)
+  }
+}
+
+impl Recorder for MemoryRecorder {
+  fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+  fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+  fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+  fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+    Counter::from_arc(self.get(key.name()))
+  }
+
+  fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+    Gauge::from_arc(self.get(key.name()))
+  }
+
+  fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+    Histogram::from_arc(self.get(key.name()))
+  }
+}
+
+#[test]
+fn records_steps_and_collisions() {
+  let recorder = MemoryRecorder::default();
+  with_local_recorder(&recorder, || {
+    let mut simulator = Simulator::new();
+    // The agents start overlapping, but want to stay where they are.
+    for x in [-0.25, 0.25] {
+      simulator.add_agent(
+        Agent::builder().position(Vec3::new(x, 0.0, 0.0)).build(),
+        AgentParameters::builder().goal_point(Vec3::new(x, 0.0, 0.0)).build(),
+      );
+    }
+    for _ in 0..10 {
+      simulator.step(0.1);
+    }
+  });
+
+  assert_eq!(recorder.total(STEPS_METRIC), 10);
+  assert_eq!(recorder.count(STEP_SECONDS_METRIC), 10);
+  assert_eq!(recorder.total(AGENTS_METRIC), 2);
+  // Both agents collide for the three steps it takes them to separate at their
+  // max speed. After that, they are touching (within floating point error),
+  // which is not a collision.
+  assert_eq!(recorder.total(COLLISIONS_METRIC), 6);
+}
+
+#[test]
+fn records_solver_fallbacks() {
+  let recorder = MemoryRecorder::default();
+  let agent = Agent::builder().build();
+  let neighbours =
+    [Cow::Owned(Agent::builder().position(Vec3::new(5.0, 0.0, 0.0)).build())];
+  with_local_recorder(&recorder, || {
+    record_agent_velocity(
+      &agent,
+      &neighbours,
+      &LinearProgramOutcome::Feasible,
+      false,
+    );
+    record_agent_velocity(
+      &agent,
+      &neighbours,
+      &LinearProgramOutcome::Relaxed { index_of_failed_constraint: 0 },
+      false,
+    );
+    record_agent_velocity(
+      &agent,
+      &neighbours,
+      &LinearProgramOutcome::Interrupted { index_of_failed_constraint: 0 },
+      true,
+    );
+  });
+
+  assert_eq!(recorder.total(SOLVER_FALLBACKS_METRIC), 1);
+  assert_eq!(recorder.total(SOLVER_LIMITED_METRIC), 1);
+  assert_eq!(recorder.total(COLLISIONS_METRIC), 0);
+}
@@ -0,0 +1,53 @@
+use glam::Vec3;
+
+use crate::linear_programming::Plane;
+
+/// The ground that agents walk on. The terrain is a heightfield: every point on
+/// the horizontal XZ plane has a single ground height along the Y axis (which
+/// is up). Any `Fn(f32, f32) -> f32` taking `x` and `z` is a terrain.
+pub trait Terrain {
+  /// The height of the ground at (`x`, `z`).
+  fn height_at(&self, x: f32, z: f32) -> f32;
+
+  /// The normal of the ground at (`x`, `z`). This must be unit length and
+  /// point upwards. By default this is estimated from [`Self::height_at`]
+  /// using central differences.
+  fn normal_at(&self, x: f32, z: f32) -> Vec3 {
+    const DELTA: f32 = 0.01;
+
+    let slope_x = (self.height_at(x + DELTA, z) - self.height_at(x - DELTA, z))
+      / (2.0 * DELTA);
+    let slope_z = (self.height_at(x, z + DELTA) - self.height_at(x, z - DELTA))
+      / (2.0 * DELTA);
+    Vec3::new(-slope_x, 1.0, -slope_z).normalize()
+  }
+}
+
+impl<F: Fn(f32, f32) -> f32> Terrain for F {
+  fn height_at(&self, x: f32, z: f32) -> f32 {
+    self(x, z)
+  }
+}
+
+/// Half the thickness of the slab of velocities allowed around the tangent
+/// plane. A single plane cannot be satisfied exactly with floating point
+/// error, so the valid velocities are a very thin slab instead.
+const TANGENT_SLAB_HALF_THICKNESS: f32 = 1e-4;
+
+/// Creates the (rigid) planes that restrict velocities to the tangent plane
+/// with `normal`.
+pub(crate) fn get_planes_for_tangent(normal: Vec3) -> [Plane; 2] {
+  [
+    Plane { point: -normal * TANGENT_SLAB_HALF_THICKNESS, normal },
+    Plane { point: normal * TANGENT_SLAB_HALF_THICKNESS, normal: -normal },
+  ]
+}
+
+/// Projects `velocity` onto the tangent plane with `normal`.
+pub(crate) fn project_onto_tangent(velocity: Vec3, normal: Vec3) -> Vec3 {
+  velocity - velocity.dot(normal) * normal
+}
+
+#[cfg(test)]
+#[path = "terrain_test.rs"]
+mod test;
@@ -0,0 +1,63 @@
+use glam::Vec3;
+
+use super::*;
+
+macro_rules! assert_vec_near {
+  ($left: expr, $right: expr, $eps: expr) => {{
+    let left = $left;
+    let right = $right;
+    let eps = $eps;
+    assert!(
+      left.distance(right) < eps,
+      "left: {}, right: {}, epsilon: {}",
+      left,
+      right,
+      eps
+    );
+  }};
+}
+
+#[test]
+fn flat_terrain_normal_is_up() {
+  let terrain = |_x: f32, _z: f32| 3.0;
+  assert_vec_near!(terrain.normal_at(1.0, -2.0), Vec3::Y, 1e-5);
+}
+
+#[test]
+fn sloped_terrain_normal_faces_downhill() {
+  // Rises one unit in y for every unit in x.
+  let terrain = |x: f32, _z: f32| x;
+  assert_vec_near!(
+    terrain.normal_at(5.0, 5.0),
+    Vec3::new(-1.0, 1.0, 0.0).normalize(),
+    1e-3
+  );
+}
+
+#[test]
+fn tangent_planes_contain_tangent_velocities() {
+  let normal = Vec3::new(-1.0, 1.0, 0.0).normalize();
+  let planes = get_planes_for_tangent(normal);
+
+  let tangent_velocity = Vec3::new(1.0, 1.0, 3.0);
+  assert!(planes
+    .iter()
+    .all(|plane| plane.signed_distance_to_plane(tangent_velocity) > 0.0));
+  assert!(planes
+    .iter()
+    .all(|plane| plane.signed_distance_to_plane(Vec3::ZERO) > 0.0));
+
+  let vertical_velocity = Vec3::new(0.0, 1.0, 0.0);
+  assert!(planes
+    .iter()
+    .any(|plane| plane.signed_distance_to_plane(vertical_velocity) < 0.0));
+}
+
+#[test]
+fn projects_onto_tangent() {
+  assert_vec_near!(
+    project_onto_tangent(Vec3::new(1.0, 2.0, 3.0), Vec3::Y),
+    Vec3::new(1.0, 0.0, 3.0),
+    1e-5
+  );
+}
@@ -0,0 +1,110 @@
+use std::borrow::Cow;
+
+use crate::Agent;
+
+/// Skips neighbours that pose little threat to an agent (see
+/// [`get_threat_score`]), e.g. neighbours moving away from the agent. Every
+/// neighbour usually adds a constraint, even if it can never collide with the
+/// agent, which costs time and can make the agent dodge needlessly. See
+/// [`crate::Simulator::set_threat_filter`].
+///
+/// Threats are predicted from the current velocities, so an agent may turn
+/// into the path of a skipped neighbour. The threat of that neighbour rises as
+/// soon as it does, but keep `always_avoid_gap` large enough to react in time.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ThreatFilter {
+  /// The threat score below which neighbours are skipped. Must be between 0
+  /// and 1.
+  pub threshold: f32,
+  /// Neighbours whose gap to the agent (the distance between their edges) is
+  /// at most this are always avoided, regardless of their threat.
+  pub always_avoid_gap: f32,
+}
+
+impl Default for ThreatFilter {
+  fn default() -> Self {
+    Self { threshold: 0.1, always_avoid_gap: 0.5 }
+  }
+}
+
+impl ThreatFilter {
+  /// Whether `agent` should avoid `neighbour`, with collisions considered
+  /// `time_horizon` into the future.
+  pub fn should_avoid(
+    &self,
+    agent: &Agent,
+    neighbour: &Agent,
+    time_horizon: f32,
+  ) -> bool {
+    let gap = agent.position.distance(neighbour.position)
+      - agent.radius
+      - neighbour.radius;
+    gap <= self.always_avoid_gap
+      || get_threat_score(agent, neighbour, time_horizon) >= self.threshold
+  }
+
+  /// Removes the `neighbours` that `agent` does not need to avoid (see
+  /// [`Self::should_avoid`]).
+  pub fn filter_neighbours(
+    &self,
+    agent: &Agent,
+    neighbours: &mut Vec<Cow<'_, Agent>>,
+    time_horizon: f32,
+  ) {
+    assert!(
+      (0.0..=1.0).contains(&self.threshold),
+      "threshold must be between 0 and 1, was {}",
+      self.threshold
+    );
+    neighbours
+      .retain(|neighbour| self.should_avoid(agent, neighbour, time_horizon));
+  }
+}
+
+/// Scores how threatening `neighbour` is to `agent` (from 0 to 1), assuming
+/// both keep their current velocities. Agents that overlap or are on a
+/// collision course (they pass closer than the sum of their radii) within
+/// `time_horizon` score 1. The score falls off with the distance they pass at
+/// (relative to the sum of their radii), and with the time until they are
+/// closest (relative to `time_horizon`). Neighbours moving away from the
+/// agent score 0.
+pub fn get_threat_score(
+  agent: &Agent,
+  neighbour: &Agent,
+  time_horizon: f32,
+) -> f32 {
+  assert!(
+    time_horizon > 0.0,
+    "time_horizon must be positive, was {}",
+    time_horizon
+  );
+  let relative_position = neighbour.position - agent.position;
+  let relative_velocity = neighbour.velocity - agent.velocity;
+  let sum_radius = agent.radius + neighbour.radius;
+  let distance_squared = relative_position.length_squared();
+  if distance_squared <= sum_radius * sum_radius {
+    return 1.0;
+  }
+
+  let approach = -relative_position.dot(relative_velocity);
+  if approach <= 0.0 {
+    // The agents are moving apart (or keeping their distance), so they are
+    // closest now.
+    return 0.0;
+  }
+  let time_to_closest_approach = approach / relative_velocity.length_squared();
+  let closest_distance =
+    (relative_position + relative_velocity * time_to_closest_approach).length();
+
+  let proximity = (sum_radius / closest_distance).min(1.0);
+  let imminence = if time_to_closest_approach <= time_horizon {
+    1.0
+  } else {
+    time_horizon / time_to_closest_approach
+  };
+  proximity * imminence
+}
+
+#[cfg(test)]
+#[path = "threat_test.rs"]
+mod test;
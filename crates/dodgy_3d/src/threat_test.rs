@@ -0,0 +1,98 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{
+  get_threat_score, Agent, AgentParameters, Simulator, ThreatFilter,
+};
+
+fn agent_at(position: Vec3, velocity: Vec3) -> Agent {
+  Agent::builder().position(position).velocity(velocity).build()
+}
+
+#[test]
+fn scores_collision_courses_highest() {
+  let agent = agent_at(Vec3::ZERO, Vec3::X);
+
+  // Head on.
+  let oncoming = agent_at(Vec3::new(4.0, 0.0, 0.0), -Vec3::X);
+  assert_eq!(get_threat_score(&agent, &oncoming, 3.0), 1.0);
+  // Overlapping, even though moving apart.
+  let overlapping = agent_at(Vec3::new(0.5, 0.0, 0.0), Vec3::X * 2.0);
+  assert_eq!(get_threat_score(&agent, &overlapping, 3.0), 1.0);
+  // Moving apart.
+  let diverging = agent_at(Vec3::new(-4.0, 0.0, 0.0), -Vec3::X);
+  assert_eq!(get_threat_score(&agent, &diverging, 3.0), 0.0);
+  // Keeping the same distance.
+  let alongside = agent_at(Vec3::new(0.0, 0.0, 2.0), Vec3::X);
+  assert_eq!(get_threat_score(&agent, &alongside, 3.0), 0.0);
+}
+
+#[test]
+fn threat_falls_off_with_miss_distance_and_time() {
+  let agent = agent_at(Vec3::ZERO, Vec3::X);
+
+  // Passes 4 units away, with a sum of radii of 1.
+  let near_miss = agent_at(Vec3::new(4.0, 0.0, 4.0), -Vec3::X);
+  let score = get_threat_score(&agent, &near_miss, 3.0);
+  assert!((score - 0.25).abs() < 1e-5, "{score}");
+
+  // On a collision course, but closest in 6 seconds.
+  let distant = agent_at(Vec3::new(12.0, 0.0, 0.0), -Vec3::X);
+  let score = get_threat_score(&agent, &distant, 3.0);
+  assert!((score - 0.5).abs() < 1e-5, "{score}");
+}
+
+#[test]
+fn filter_keeps_threats_and_close_neighbours() {
+  let agent = agent_at(Vec3::ZERO, Vec3::X);
+  let mut neighbours = vec![
+    // A threat.
+    Cow::Owned(agent_at(Vec3::new(4.0, 0.0, 0.0), -Vec3::X)),
+    // Diverging, but close.
+    Cow::Owned(agent_at(Vec3::new(-1.2, 0.0, 0.0), -Vec3::X)),
+    // Diverging and far away.
+    Cow::Owned(agent_at(Vec3::new(-3.0, 0.0, 0.0), -Vec3::X)),
+  ];
+
+  ThreatFilter { threshold: 0.1, always_avoid_gap: 0.5 }.filter_neighbours(
+    &agent,
+    &mut neighbours,
+    3.0,
+  );
+  assert_eq!(
+    neighbours.iter().map(|neighbour| neighbour.position).collect::<Vec<_>>(),
+    [Vec3::new(4.0, 0.0, 0.0), Vec3::new(-1.2, 0.0, 0.0)]
+  );
+}
+
+#[test]
+fn simulator_skips_diverging_neighbours() {
+  // Returns the velocity of an agent heading away from a crowd that is
+  // dispersing behind it.
+  let run = |threat_filter: Option<ThreatFilter>| {
+    let mut simulator = Simulator::new();
+    simulator.set_threat_filter(threat_filter);
+    simulator.add_agent(
+      agent_at(Vec3::ZERO, Vec3::X),
+      AgentParameters::builder().goal_point(Vec3::new(10.0, 0.0, 0.0)).build(),
+    );
+    for z in [-1.0, 1.0] {
+      let position = Vec3::new(-2.5, 0.0, z);
+      simulator.add_agent(
+        agent_at(position, Vec3::new(-1.0, 0.0, z)),
+        AgentParameters::builder().goal_point(position * 10.0).build(),
+      );
+    }
+    simulator.step(0.1);
+    simulator.get_agent(0).velocity
+  };
+
+  // The crowd never constrains the agent, so skipping it changes nothing
+  // (but saves building its planes).
+  assert_eq!(run(None), Vec3::X);
+  assert_eq!(
+    run(Some(ThreatFilter { threshold: 0.1, always_avoid_gap: 0.0 })),
+    Vec3::X
+  );
+}
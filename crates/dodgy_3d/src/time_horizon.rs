@@ -0,0 +1,62 @@
+/// Parameters for [`suggest_time_horizon`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct TimeHorizonOptions {
+  /// The shortest time horizon to suggest. Shorter horizons react to
+  /// neighbours too late to avoid them smoothly.
+  pub min_time_horizon: f32,
+  /// The longest time horizon to suggest, used in open space. Longer horizons
+  /// make agents react to distant agents that are unlikely to matter.
+  pub max_time_horizon: f32,
+  /// How many gaps between neighbours (at the local density) the agent looks
+  /// ahead. Around 2 lets agents react to the neighbour ahead and the one
+  /// behind it.
+  pub look_ahead_gaps: f32,
+}
+
+impl Default for TimeHorizonOptions {
+  fn default() -> Self {
+    Self { min_time_horizon: 0.5, max_time_horizon: 5.0, look_ahead_gaps: 2.0 }
+  }
+}
+
+/// Suggests a time horizon for an agent with `radius` moving at up to
+/// `max_speed` through a crowd of `density` (agents per unit volume, e.g.
+/// from [`crate::Simulator::density_at`]). A single horizon for every agent is
+/// either too timid in open space (agents swerve around distant agents) or
+/// too late in crowds (agents freeze, since every velocity collides with some
+/// neighbour within the horizon). The suggested horizon is the time to cover
+/// `look_ahead_gaps` gaps between neighbours, clamped between the min and max
+/// time horizons.
+pub fn suggest_time_horizon(
+  radius: f32,
+  max_speed: f32,
+  density: f32,
+  options: &TimeHorizonOptions,
+) -> f32 {
+  assert!(
+    options.min_time_horizon > 0.0,
+    "min_time_horizon must be positive, was {}",
+    options.min_time_horizon
+  );
+  assert!(
+    options.min_time_horizon <= options.max_time_horizon,
+    "min_time_horizon ({}) must not be larger than max_time_horizon ({})",
+    options.min_time_horizon,
+    options.max_time_horizon
+  );
+  if density <= 0.0 || max_speed <= 0.0 {
+    return options.max_time_horizon;
+  }
+
+  // Each agent has about 1 / density of volume to itself, so neighbours are
+  // spaced about the cube root of that apart (center to center). Agents in a
+  // jammed crowd still need some gap to react to, so take at least a radius.
+  let spacing = density.powf(-1.0 / 3.0);
+  let gap = (spacing - 2.0 * radius).max(radius);
+  (options.look_ahead_gaps * gap / max_speed)
+    .clamp(options.min_time_horizon, options.max_time_horizon)
+}
+
+#[cfg(test)]
+#[path = "time_horizon_test.rs"]
+mod test;
@@ -0,0 +1,68 @@
+use crate::{suggest_time_horizon, TimeHorizonOptions};
+
+#[test]
+fn uses_max_time_horizon_in_open_space() {
+  let options = TimeHorizonOptions::default();
+  assert_eq!(
+    suggest_time_horizon(
+      /* radius= */ 0.5, /* max_speed= */ 1.0,
+      /* density= */ 0.0, &options
+    ),
+    options.max_time_horizon
+  );
+  assert_eq!(
+    suggest_time_horizon(
+      /* radius= */ 0.5, /* max_speed= */ 0.0,
+      /* density= */ 1.0, &options
+    ),
+    options.max_time_horizon
+  );
+}
+
+#[test]
+fn shortens_time_horizon_in_crowds_and_at_speed() {
+  let options = TimeHorizonOptions {
+    min_time_horizon: 0.1,
+    max_time_horizon: 100.0,
+    look_ahead_gaps: 2.0,
+  };
+
+  // Agents spaced 4 apart, with gaps of 3.
+  let sparse = suggest_time_horizon(0.5, 1.0, 1.0 / 64.0, &options);
+  assert!((sparse - 6.0).abs() < 1e-4, "{sparse}");
+  // Agents spaced 2 apart, with gaps of 1.
+  let dense = suggest_time_horizon(0.5, 1.0, 1.0 / 8.0, &options);
+  assert!((dense - 2.0).abs() < 1e-4, "{dense}");
+  // Faster agents cover the gaps sooner.
+  let fast = suggest_time_horizon(0.5, 2.0, 1.0 / 8.0, &options);
+  assert!((fast - 1.0).abs() < 1e-4, "{fast}");
+  // Jammed agents still look ahead a radius.
+  let jammed = suggest_time_horizon(0.5, 1.0, 1.0, &options);
+  assert!((jammed - 1.0).abs() < 1e-4, "{jammed}");
+}
+
+#[test]
+fn clamps_to_bounds() {
+  let options = TimeHorizonOptions {
+    min_time_horizon: 1.5,
+    max_time_horizon: 4.0,
+    look_ahead_gaps: 2.0,
+  };
+  assert_eq!(suggest_time_horizon(0.5, 1.0, 1.0 / 64.0, &options), 4.0);
+  assert_eq!(suggest_time_horizon(0.5, 2.0, 1.0 / 8.0, &options), 1.5);
+}
+
+#[test]
+#[should_panic = "must not be larger than max_time_horizon"]
+fn panics_on_inverted_bounds() {
+  suggest_time_horizon(
+    0.5,
+    1.0,
+    1.0,
+    &TimeHorizonOptions {
+      min_time_horizon: 2.0,
+      max_time_horizon: 1.0,
+      look_ahead_gaps: 2.0,
+    },
+  );
+}
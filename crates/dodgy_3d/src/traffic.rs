@@ -0,0 +1,61 @@
+use glam::Vec3;
+
+use crate::{linear_programming::Plane, Agent};
+
+/// A traffic rule assigning an agent to an altitude band, and optionally a
+/// direction of travel. For example, eastbound drones may be assigned to fly
+/// between 30 and 40 units of altitude with a direction of `Vec3::X`. Agents
+/// follow the lane while avoiding each other. Agents outside the altitude band
+/// are not allowed to move further away from it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TrafficLane {
+  /// The lowest altitude (Y coordinate) the agent may fly at. Use
+  /// `f32::NEG_INFINITY` for no lower bound.
+  pub min_altitude: f32,
+  /// The highest altitude (Y coordinate) the agent may fly at. Use
+  /// `f32::INFINITY` for no upper bound.
+  pub max_altitude: f32,
+  /// The direction of travel in the lane. If present, the agent may not move
+  /// against this direction, though it may still stop or move sideways to
+  /// avoid other agents.
+  pub direction: Option<Vec3>,
+}
+
+/// Creates the (rigid) planes that keep `agent` in `lane`. The agent may only
+/// approach the edges of the altitude band fast enough to reach them after
+/// `time_horizon`. All planes are satisfied by a velocity of zero.
+pub(crate) fn get_planes_for_lane(
+  agent: &Agent,
+  lane: &TrafficLane,
+  time_horizon: f32,
+) -> Vec<Plane> {
+  let mut planes = Vec::with_capacity(3);
+
+  if lane.max_altitude.is_finite() {
+    let distance_to_ceiling = lane.max_altitude - agent.position.y;
+    planes.push(Plane {
+      point: Vec3::Y * (distance_to_ceiling.max(0.0) / time_horizon),
+      normal: Vec3::NEG_Y,
+    });
+  }
+  if lane.min_altitude.is_finite() {
+    let distance_to_floor = agent.position.y - lane.min_altitude;
+    planes.push(Plane {
+      point: Vec3::NEG_Y * (distance_to_floor.max(0.0) / time_horizon),
+      normal: Vec3::Y,
+    });
+  }
+
+  if let Some(direction) = lane.direction {
+    let direction = direction.normalize_or_zero();
+    if direction != Vec3::ZERO {
+      planes.push(Plane { point: Vec3::ZERO, normal: direction });
+    }
+  }
+
+  planes
+}
+
+#[cfg(test)]
+#[path = "traffic_test.rs"]
+mod test;
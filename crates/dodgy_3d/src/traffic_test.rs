@@ -0,0 +1,82 @@
+use glam::Vec3;
+
+use super::*;
+
+macro_rules! assert_planes_eq {
+  ($a: expr, $b: expr) => {{
+    let a = $a;
+    let b = $b;
+
+    assert_eq!(a.len(), b.len(), "\n  left: {:?}\n right: {:?}", a, b);
+    for (a_plane, b_plane) in a.iter().zip(b.iter()) {
+      assert!(
+        a_plane.point.distance_squared(b_plane.point) < 1e-5
+          && a_plane.normal.distance_squared(b_plane.normal) < 1e-5,
+        "\n  left: {:?}\n right: {:?}",
+        a,
+        b
+      );
+    }
+  }};
+}
+
+fn agent_at_altitude(altitude: f32) -> Agent {
+  Agent {
+    position: Vec3::new(0.0, altitude, 0.0),
+    velocity: Vec3::ZERO,
+    radius: 1.0,
+    avoidance_responsibility: 1.0,
+  }
+}
+
+#[test]
+fn agent_in_band_may_approach_edges() {
+  let planes = get_planes_for_lane(
+    &agent_at_altitude(32.0),
+    &TrafficLane { min_altitude: 30.0, max_altitude: 40.0, direction: None },
+    /* time_horizon= */ 2.0,
+  );
+  assert_planes_eq!(
+    planes,
+    [
+      Plane { point: Vec3::new(0.0, 4.0, 0.0), normal: Vec3::NEG_Y },
+      Plane { point: Vec3::new(0.0, -1.0, 0.0), normal: Vec3::Y },
+    ]
+  );
+}
+
+#[test]
+fn agent_outside_band_cannot_move_further_away() {
+  let planes = get_planes_for_lane(
+    &agent_at_altitude(45.0),
+    &TrafficLane { min_altitude: 30.0, max_altitude: 40.0, direction: None },
+    /* time_horizon= */ 2.0,
+  );
+  assert_planes_eq!(
+    planes,
+    [
+      Plane { point: Vec3::ZERO, normal: Vec3::NEG_Y },
+      Plane { point: Vec3::new(0.0, -7.5, 0.0), normal: Vec3::Y },
+    ]
+  );
+}
+
+#[test]
+fn unbounded_band_and_direction() {
+  let planes = get_planes_for_lane(
+    &agent_at_altitude(5.0),
+    &TrafficLane {
+      min_altitude: 0.0,
+      max_altitude: f32::INFINITY,
+      direction: Some(Vec3::new(2.0, 0.0, 0.0)),
+    },
+    /* time_horizon= */ 1.0,
+  );
+  assert_planes_eq!(
+    planes,
+    [
+      Plane { point: Vec3::new(0.0, -5.0, 0.0), normal: Vec3::Y },
+      Plane { point: Vec3::ZERO, normal: Vec3::X },
+    ]
+  );
+}
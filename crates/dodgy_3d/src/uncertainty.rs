@@ -0,0 +1,116 @@
+use glam::Mat3;
+
+use crate::linear_programming::Plane;
+
+/// The uncertainty in the state of a neighbour, for example from a noisy
+/// state estimator. The covariances are relative to the agent computing its
+/// avoiding velocity, so the agent's own uncertainty can be added to them.
+#[derive(Clone, PartialEq, Debug)]
+pub struct NeighbourUncertainty {
+  /// The covariance of the neighbour's position.
+  pub position_covariance: Mat3,
+  /// The covariance of the neighbour's velocity.
+  pub velocity_covariance: Mat3,
+}
+
+impl Default for NeighbourUncertainty {
+  fn default() -> Self {
+    Self { position_covariance: Mat3::ZERO, velocity_covariance: Mat3::ZERO }
+  }
+}
+
+/// Computes the number of standard deviations a plane must be offset by so
+/// that a Gaussian error crosses the plane with at most
+/// `max_collision_probability`.
+pub(crate) fn get_confidence_factor(max_collision_probability: f32) -> f32 {
+  assert!(
+    max_collision_probability > 0.0,
+    "max_collision_probability must be positive, was {}",
+    max_collision_probability
+  );
+  if max_collision_probability >= 0.5 {
+    // Offsetting the plane "backwards" would accept more collisions than the
+    // deterministic plane, which is never what the caller wants.
+    return 0.0;
+  }
+  -inverse_normal_cdf(max_collision_probability as f64) as f32
+}
+
+/// Offsets `plane` (induced by a neighbour with `uncertainty`) so that the
+/// agent is still safe with high probability. `confidence_factor` is the
+/// number of standard deviations to offset by (see
+/// [`get_confidence_factor`]). Position uncertainty is converted into velocity
+/// uncertainty over `time_horizon`, matching the scale of the velocity
+/// obstacle's cut-off sphere.
+pub(crate) fn offset_plane_for_uncertainty(
+  plane: Plane,
+  uncertainty: &NeighbourUncertainty,
+  time_horizon: f32,
+  confidence_factor: f32,
+) -> Plane {
+  let covariance = uncertainty.velocity_covariance
+    + uncertainty.position_covariance / (time_horizon * time_horizon);
+  // The variance of the error projected onto the plane's normal. Clamp to zero
+  // in case the covariance is not quite positive semi-definite.
+  let variance = plane.normal.dot(covariance * plane.normal).max(0.0);
+  Plane {
+    point: plane.point + plane.normal * (confidence_factor * variance.sqrt()),
+    normal: plane.normal,
+  }
+}
+
+/// Computes the inverse of the standard normal cumulative distribution
+/// function. This uses Peter Acklam's rational approximation, which has a
+/// relative error below 1.15e-9.
+fn inverse_normal_cdf(p: f64) -> f64 {
+  const A: [f64; 6] = [
+    -3.969683028665376e1,
+    2.209460984245205e2,
+    -2.759285104469687e2,
+    1.38357751867269e2,
+    -3.066479806614716e1,
+    2.506628277459239e0,
+  ];
+  const B: [f64; 5] = [
+    -5.447609879822406e1,
+    1.615858368580409e2,
+    -1.556989798598866e2,
+    6.680131188771972e1,
+    -1.328068155288572e1,
+  ];
+  const C: [f64; 6] = [
+    -7.784894002430293e-3,
+    -3.223964580411365e-1,
+    -2.400758277161838e0,
+    -2.549732539343734e0,
+    4.374664141464968e0,
+    2.938163982698783e0,
+  ];
+  const D: [f64; 4] = [
+    7.784695709041462e-3,
+    3.224671290700398e-1,
+    2.445134137142996e0,
+    3.754408661907416e0,
+  ];
+  const P_LOW: f64 = 0.02425;
+
+  let tail = |q: f64| {
+    (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+      / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+  };
+
+  if p < P_LOW {
+    tail((-2.0 * p.ln()).sqrt())
+  } else if p <= 1.0 - P_LOW {
+    let q = p - 0.5;
+    let r = q * q;
+    (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+      / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+  } else {
+    -tail((-2.0 * (1.0 - p).ln()).sqrt())
+  }
+}
+
+#[cfg(test)]
+#[path = "uncertainty_test.rs"]
+mod test;
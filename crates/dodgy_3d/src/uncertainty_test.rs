@@ -0,0 +1,51 @@
+use glam::{Mat3, Vec3};
+
+use super::*;
+
+#[test]
+fn inverse_normal_cdf_matches_known_values() {
+  assert!(inverse_normal_cdf(0.5).abs() < 1e-9);
+  assert!((inverse_normal_cdf(0.975) - 1.959963985).abs() < 1e-6);
+  assert!((inverse_normal_cdf(0.01) + 2.326347874).abs() < 1e-6);
+  assert!((inverse_normal_cdf(1e-6) + 4.753424309).abs() < 1e-5);
+}
+
+#[test]
+fn confidence_factor_is_clamped_at_zero() {
+  assert_eq!(get_confidence_factor(0.5), 0.0);
+  assert_eq!(get_confidence_factor(0.9), 0.0);
+  assert!((get_confidence_factor(0.025) - 1.959964).abs() < 1e-4);
+}
+
+#[test]
+fn certain_neighbour_does_not_offset_plane() {
+  let plane = Plane { point: Vec3::new(1.0, 2.0, 3.0), normal: Vec3::X };
+  let offset_plane = offset_plane_for_uncertainty(
+    plane.clone(),
+    &NeighbourUncertainty::default(),
+    /* time_horizon= */ 2.0,
+    /* confidence_factor= */ 3.0,
+  );
+  assert_eq!(offset_plane.point, plane.point);
+  assert_eq!(offset_plane.normal, plane.normal);
+}
+
+#[test]
+fn offset_uses_variance_along_normal() {
+  let plane = Plane { point: Vec3::ZERO, normal: Vec3::X };
+  let uncertainty = NeighbourUncertainty {
+    // Variance of 16 along x, which is 4 in velocity over a time horizon of 2.
+    position_covariance: Mat3::from_diagonal(Vec3::new(16.0, 100.0, 100.0)),
+    // Variance of 5 along x.
+    velocity_covariance: Mat3::from_diagonal(Vec3::new(5.0, 100.0, 100.0)),
+  };
+  let offset_plane = offset_plane_for_uncertainty(
+    plane,
+    &uncertainty,
+    /* time_horizon= */ 2.0,
+    /* confidence_factor= */ 2.0,
+  );
+  // The standard deviation along the normal is sqrt(4 + 5) = 3.
+  assert!(offset_plane.point.distance(Vec3::new(6.0, 0.0, 0.0)) < 1e-5);
+  assert_eq!(offset_plane.normal, Vec3::X);
+}
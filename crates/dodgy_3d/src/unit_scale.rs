@@ -0,0 +1,127 @@
+use crate::{
+  Agent, AgentParameters, AvoidanceOptions, SimulatorMargin, Tolerances,
+};
+
+/// The units distances are measured in. The default tolerances (and the
+/// presets like [`AvoidanceOptions::pedestrian`]) are tuned for meters, so
+/// worlds in other units should scale them with `scaled_to_units` (e.g.
+/// [`Tolerances::scaled_to_units`]). Otherwise, absolute tolerances are far
+/// too small for worlds in centimeters (so floating point error looks like an
+/// infeasible linear program), and far too large for worlds in kilometers.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum UnitScale {
+  #[default]
+  Meters,
+  Centimeters,
+  Millimeters,
+  /// Arbitrary units, with `units_per_meter` units in one meter.
+  Custom {
+    units_per_meter: f32,
+  },
+}
+
+impl UnitScale {
+  /// The number of units in one meter.
+  pub fn units_per_meter(&self) -> f32 {
+    match self {
+      Self::Meters => 1.0,
+      Self::Centimeters => 100.0,
+      Self::Millimeters => 1000.0,
+      Self::Custom { units_per_meter } => {
+        assert!(
+          *units_per_meter > 0.0,
+          "units_per_meter must be positive, was {}",
+          units_per_meter
+        );
+        *units_per_meter
+      }
+    }
+  }
+}
+
+impl Tolerances {
+  /// Converts tolerances tuned for meters to `scale`. Relative tolerances are
+  /// unchanged, while absolute tolerances are scaled with the units they are
+  /// measured in.
+  pub fn scaled_to_units(self, scale: UnitScale) -> Self {
+    let units_per_meter = scale.units_per_meter();
+    Self {
+      normalization_epsilon: self.normalization_epsilon
+        * units_per_meter
+        * units_per_meter,
+      solver_epsilon: self.solver_epsilon * units_per_meter,
+      ..self
+    }
+  }
+}
+
+impl AvoidanceOptions {
+  /// Converts options given in meters to `scale`, scaling the obstacle margin
+  /// and the tolerances. Time horizons and objective weights are unchanged.
+  pub fn scaled_to_units(self, scale: UnitScale) -> Self {
+    Self {
+      obstacle_margin: self.obstacle_margin * scale.units_per_meter(),
+      tolerances: self.tolerances.scaled_to_units(scale),
+      ..self
+    }
+  }
+}
+
+impl Agent {
+  /// Converts an agent given in meters to `scale`.
+  pub fn scaled_to_units(self, scale: UnitScale) -> Self {
+    let units_per_meter = scale.units_per_meter();
+    Self {
+      position: self.position * units_per_meter,
+      velocity: self.velocity * units_per_meter,
+      radius: self.radius * units_per_meter,
+      ..self
+    }
+  }
+}
+
+impl AgentParameters {
+  /// Converts parameters given in meters to `scale`, scaling every distance
+  /// and speed (including those of margin curves, traffic lanes, social
+  /// forces, following, and yielding). Times and the urgency are unchanged.
+  pub fn scaled_to_units(mut self, scale: UnitScale) -> Self {
+    let units_per_meter = scale.units_per_meter();
+    self.goal_point *= units_per_meter;
+    self.max_speed *= units_per_meter;
+    match &mut self.obstacle_margin {
+      SimulatorMargin::AgentRadius => {}
+      SimulatorMargin::Distance(margin) => *margin *= units_per_meter,
+      // The inputs of both curves (speeds and distances) are also scaled.
+      SimulatorMargin::SpeedCurve(curve)
+      | SimulatorMargin::ObstacleDistanceCurve(curve) => {
+        for (input, margin) in curve.points.iter_mut() {
+          *input *= units_per_meter;
+          *margin *= units_per_meter;
+        }
+      }
+    }
+    if let Some(traffic_lane) = &mut self.traffic_lane {
+      traffic_lane.min_altitude *= units_per_meter;
+      traffic_lane.max_altitude *= units_per_meter;
+    }
+    if let Some(social_force) = &mut self.social_force {
+      social_force.strength *= units_per_meter;
+      social_force.range *= units_per_meter;
+    }
+    if let Some(leader_following) = &mut self.leader_following {
+      leader_following.options.distance *= units_per_meter;
+    }
+    if let Some(yielding) = &mut self.yielding {
+      yielding.speed *= units_per_meter;
+      yielding.standing_distance *= units_per_meter;
+    }
+    if let Some(standing_height) = &mut self.standing_height {
+      *standing_height *= units_per_meter;
+    }
+    self
+  }
+}
+
+#[cfg(test)]
+#[path = "unit_scale_test.rs"]
+mod test;
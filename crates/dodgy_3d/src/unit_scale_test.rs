@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::{
+  Agent, AgentParameters, AvoidanceOptions, MarginCurve, Simulator,
+  SimulatorMargin, Tolerances, UnitScale, Yielding,
+};
+
+#[test]
+fn scales_absolute_tolerances_only() {
+  let tolerances =
+    Tolerances::default().scaled_to_units(UnitScale::Centimeters);
+  let default_tolerances = Tolerances::default();
+  assert_eq!(tolerances.tangent_epsilon, default_tolerances.tangent_epsilon);
+  assert_eq!(tolerances.touching_epsilon, default_tolerances.touching_epsilon);
+  assert_eq!(
+    tolerances.normalization_epsilon,
+    default_tolerances.normalization_epsilon * 10000.0
+  );
+  assert_eq!(
+    tolerances.solver_epsilon,
+    default_tolerances.solver_epsilon * 100.0
+  );
+}
+
+#[test]
+fn scales_distances_and_speeds_of_parameters() {
+  let parameters = AgentParameters::builder()
+    .goal_point(Vec3::new(1.0, 2.0, 3.0))
+    .max_speed(1.5)
+    .obstacle_margin(SimulatorMargin::Distance(0.25))
+    .yielding(Yielding {
+      speed: 0.5,
+      look_ahead_time: 2.0,
+      standing_distance: 1.0,
+    })
+    .standing_height(1.8)
+    .build()
+    .scaled_to_units(UnitScale::Millimeters);
+
+  assert_eq!(parameters.goal_point, Vec3::new(1000.0, 2000.0, 3000.0));
+  assert_eq!(parameters.max_speed, 1500.0);
+  assert_eq!(parameters.obstacle_margin, SimulatorMargin::Distance(250.0));
+  assert_eq!(
+    parameters.yielding,
+    Some(Yielding {
+      speed: 500.0,
+      look_ahead_time: 2.0,
+      standing_distance: 1000.0,
+    })
+  );
+  assert_eq!(parameters.standing_height, Some(1800.0));
+  assert_eq!(parameters.time_horizon, 3.0);
+}
+
+#[test]
+fn scales_both_coordinates_of_margin_curves() {
+  let curve = MarginCurve { points: vec![(0.5, 0.1), (2.0, 0.4)] };
+  let scaled_curve = MarginCurve { points: vec![(50.0, 10.0), (200.0, 40.0)] };
+  for (margin, scaled_margin) in [
+    (
+      SimulatorMargin::SpeedCurve(curve.clone()),
+      SimulatorMargin::SpeedCurve(scaled_curve.clone()),
+    ),
+    (
+      SimulatorMargin::ObstacleDistanceCurve(curve.clone()),
+      SimulatorMargin::ObstacleDistanceCurve(scaled_curve.clone()),
+    ),
+  ] {
+    let parameters = AgentParameters::builder()
+      .obstacle_margin(margin)
+      .build()
+      .scaled_to_units(UnitScale::Centimeters);
+    assert_eq!(parameters.obstacle_margin, scaled_margin);
+  }
+}
+
+#[test]
+fn avoidance_is_independent_of_units() {
+  let agent =
+    Agent::builder().radius(0.3).velocity(Vec3::new(1.4, 0.0, 0.0)).build();
+  let neighbour = Agent::builder()
+    .position(Vec3::new(3.0, 0.0, 0.2))
+    .radius(0.3)
+    .velocity(Vec3::new(-1.4, 0.0, 0.0))
+    .build();
+  let velocity_in = |scale: UnitScale| {
+    let units_per_meter = scale.units_per_meter();
+    agent.clone().scaled_to_units(scale).compute_avoiding_velocity(
+      &[Cow::Owned(neighbour.clone().scaled_to_units(scale))],
+      &[],
+      Vec3::new(1.4, 0.0, 0.0) * units_per_meter,
+      /* max_speed= */ 1.4 * units_per_meter,
+      /* time_step= */ 0.1,
+      &AvoidanceOptions::pedestrian().scaled_to_units(scale),
+    ) / units_per_meter
+  };
+
+  let velocity_in_meters = velocity_in(UnitScale::Meters);
+  let velocity_in_centimeters = velocity_in(UnitScale::Centimeters);
+  assert_ne!(velocity_in_meters, Vec3::new(1.4, 0.0, 0.0));
+  assert!(
+    velocity_in_meters.distance(velocity_in_centimeters) < 1e-4,
+    "{velocity_in_meters} {velocity_in_centimeters}"
+  );
+}
+
+#[test]
+fn simulation_is_independent_of_units() {
+  let run = |scale: UnitScale| {
+    let mut simulator = Simulator::new();
+    simulator.set_unit_scale(scale);
+    for (position, goal_point) in [
+      (Vec3::new(0.0, 0.0, 0.0), Vec3::new(6.0, 0.0, 0.0)),
+      (Vec3::new(6.0, 0.0, 0.2), Vec3::new(0.0, 0.0, 0.2)),
+    ] {
+      simulator.add_agent(
+        Agent::builder()
+          .position(position)
+          .radius(0.3)
+          .build()
+          .scaled_to_units(scale),
+        AgentParameters::builder()
+          .goal_point(goal_point)
+          .max_speed(1.4)
+          .standing_height(1.8)
+          .build()
+          .scaled_to_units(scale),
+      );
+    }
+    for _ in 0..30 {
+      simulator.step(0.1);
+    }
+    [0, 1].map(|agent_index| {
+      simulator.get_agent(agent_index).position / scale.units_per_meter()
+    })
+  };
+
+  let mut simulator = Simulator::new();
+  simulator.set_unit_scale(UnitScale::Centimeters);
+  assert_eq!(
+    simulator.get_tolerances(),
+    &Tolerances::default().scaled_to_units(UnitScale::Centimeters)
+  );
+
+  let positions_in_meters = run(UnitScale::Meters);
+  let positions_in_centimeters = run(UnitScale::Centimeters);
+  assert_ne!(positions_in_meters[0].z, 0.0);
+  for (in_meters, in_centimeters) in
+    positions_in_meters.into_iter().zip(positions_in_centimeters)
+  {
+    assert!(
+      in_meters.distance(in_centimeters) < 1e-3,
+      "{in_meters} {in_centimeters}"
+    );
+  }
+}
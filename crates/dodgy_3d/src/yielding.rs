@@ -0,0 +1,79 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::Agent;
+
+/// Parameters for standing agents to step out of the way of moving agents.
+/// Standing agents normally only move once a moving agent is about to bump
+/// into them, so the moving agent has to detour around the crowd. Agents that
+/// yield instead sidestep out of the path of agents heading towards them, so
+/// standing crowds part for moving agents.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Yielding {
+  /// How fast to sidestep.
+  pub speed: f32,
+  /// How far ahead (in seconds) to look for moving agents whose path passes
+  /// through this agent.
+  pub look_ahead_time: f32,
+  /// How close the agent must be to its goal to be considered standing. Agents
+  /// still heading for their goals never yield. Agents that have stepped aside
+  /// keep yielding as long as they stay this close to their goal, so this
+  /// should be larger than the distance needed to step out of the way.
+  pub standing_distance: f32,
+}
+
+/// Computes the velocity for `agent` to step out of the path of any
+/// `neighbours` moving towards it. Returns None if no neighbour's path passes
+/// through the agent within the look ahead time.
+pub(crate) fn get_sidestep_velocity(
+  agent: &Agent,
+  neighbours: &[Cow<'_, Agent>],
+  yielding: &Yielding,
+) -> Option<Vec3> {
+  const MIN_SPEED: f32 = 1e-3;
+
+  let mut sidestep = Vec3::ZERO;
+  let mut in_path = false;
+  for neighbour in neighbours.iter() {
+    let speed = neighbour.velocity.length();
+    if speed < MIN_SPEED {
+      continue;
+    }
+    let direction = neighbour.velocity / speed;
+    let offset = agent.position - neighbour.position;
+    let distance_along_path = offset.dot(direction);
+    if distance_along_path <= 0.0
+      || distance_along_path > speed * yielding.look_ahead_time
+    {
+      continue;
+    }
+    let offset_from_path = offset - direction * distance_along_path;
+    let path_radius = agent.radius + neighbour.radius;
+    let distance_from_path = offset_from_path.length();
+    if distance_from_path >= path_radius {
+      continue;
+    }
+
+    in_path = true;
+    // Step towards whichever side of the path the agent is already on.
+    // Agents right in the middle of a horizontal path step to its right.
+    let side = offset_from_path.try_normalize().unwrap_or_else(|| {
+      direction
+        .cross(Vec3::Y)
+        .try_normalize()
+        .unwrap_or_else(|| direction.any_orthonormal_vector())
+    });
+    // Dodge the neighbours most in the way the most.
+    sidestep += side * (1.0 - distance_from_path / path_radius);
+  }
+
+  if !in_path {
+    return None;
+  }
+  Some(sidestep.normalize_or_zero() * yielding.speed)
+}
+
+#[cfg(test)]
+#[path = "yielding_test.rs"]
+mod test;
@@ -0,0 +1,55 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use crate::Agent;
+
+use super::*;
+
+fn agent(position: Vec3, velocity: Vec3) -> Agent {
+  Agent { position, velocity, radius: 0.5, avoidance_responsibility: 1.0 }
+}
+
+fn yielding() -> Yielding {
+  Yielding { speed: 1.0, look_ahead_time: 2.0, standing_distance: 2.0 }
+}
+
+#[test]
+fn sidesteps_out_of_path() {
+  let standing = agent(Vec3::new(0.0, 0.0, 0.2), Vec3::ZERO);
+  let sidestep = get_sidestep_velocity(
+    &standing,
+    &[Cow::Owned(agent(Vec3::new(-3.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)))],
+    &yielding(),
+  );
+  assert_eq!(sidestep, Some(Vec3::Z));
+
+  // Right in the middle of the path, the agent steps to the path's right.
+  let standing = agent(Vec3::ZERO, Vec3::ZERO);
+  let sidestep = get_sidestep_velocity(
+    &standing,
+    &[Cow::Owned(agent(Vec3::new(-3.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)))],
+    &yielding(),
+  );
+  assert_eq!(sidestep, Some(Vec3::Z));
+}
+
+#[test]
+fn ignores_agents_not_heading_through() {
+  let standing = agent(Vec3::ZERO, Vec3::ZERO);
+  for neighbour in [
+    // Moving away.
+    agent(Vec3::new(-3.0, 0.0, 0.0), Vec3::new(-2.0, 0.0, 0.0)),
+    // Passing by.
+    agent(Vec3::new(-3.0, 0.0, 1.5), Vec3::new(2.0, 0.0, 0.0)),
+    // Too far away to arrive within the look ahead time.
+    agent(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)),
+    // Standing still.
+    agent(Vec3::new(-1.0, 0.0, 0.0), Vec3::ZERO),
+  ] {
+    assert_eq!(
+      get_sidestep_velocity(&standing, &[Cow::Owned(neighbour)], &yielding()),
+      None
+    );
+  }
+}